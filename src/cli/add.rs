@@ -1,10 +1,23 @@
-use crate::{Result, CobraError};
+use crate::{Result, CobraError, DependencySource, RegistryClient};
+use crate::cli::install::{self, InstallOptions};
 use crate::core::config::CobraConfig;
+use crate::core::lockfile::LOCKFILE_NAME;
+use crate::core::requirements::parse_package_arg;
+use crate::core::resolver::{select_release, ResolutionStrategy};
+use crate::core::vcs;
+use crate::core::version::{self, VersionSpec};
+use crate::registry::packagecloud::PrivateRegistries;
+use crate::utils::fs::atomic_write;
 use colored::Colorize;
+use std::future::Future;
 use std::path::Path;
 
-pub async fn execute(packages: Vec<String>) -> Result<()> {
-    if packages.is_empty() {
+/// `path` adds a local project directory that is built into a wheel on install,
+/// as does a package given as `./dir` or `git+<url>[@rev]`; `exact` pins each
+/// package to the newest release matching its spec. With `install`, the
+/// project is installed straight away and a failed install undoes the edit.
+pub async fn execute(packages: Vec<String>, dev: bool, path: Option<String>, exact: bool, install: Option<InstallOptions>) -> Result<()> {
+    if packages.is_empty() && path.is_none() {
         return Err(CobraError::Config("No packages specified".to_string()));
     }
     
@@ -19,29 +32,184 @@ pub async fn execute(packages: Vec<String>) -> Result<()> {
     
     let mut config = CobraConfig::load(config_path).await?;
     
-    for package in &packages {
-        let (name, version) = parse_package_spec(package)?;
-        config.add_dependency(&name, &version);
-        println!("{} Added {} {}", "✓".green(), name.cyan(), version.dimmed());
+    let mut sources: Vec<DependencySource> = path.into_iter().map(|path| DependencySource::Path { path }).collect();
+    let mut registry_packages = Vec::new();
+    for package in packages {
+        match parse_source_spec(&package) {
+            Some(source) => sources.push(source),
+            None => registry_packages.push(package),
+        }
     }
     
-    config.save(config_path).await?;
+    for source in sources {
+        let name = source_project_name(&source).await?;
+        if dev {
+            config.add_dev_dependency(&name, "*");
+        } else {
+            config.add_dependency(&name, "*");
+        }
+        println!("{} Added {} {}", "✓".green(), name.cyan(), format!("({})", source).dimmed());
+        config.add_source(&name, source);
+    }
+    
+    let client = config.registry_client();
+    let registries = config.private_registries();
+    add_registry_packages(&mut config, &client, &registries, &registry_packages, dev, exact).await?;
     
-    println!("\n{} Run {} to install the new packages", 
-        "💡".bright_yellow(),
-        "cobra install".cyan()
-    );
+    let Some(options) = install else {
+        config.save(config_path).await?;
+        println!("\n{} Run {} to install the new packages", 
+            "💡".bright_yellow(),
+            "cobra install".cyan()
+        );
+        return Ok(());
+    };
     
+    let lock_path = Path::new(LOCKFILE_NAME);
+    let result = with_rollback(&[config_path, lock_path], async {
+        config.save(config_path).await?;
+        println!();
+        install::execute(options).await
+    }).await;
+    if result.is_err() {
+        println!("{} Install failed; restored cobra.toml and {}", "↩".yellow(), LOCKFILE_NAME);
+    }
+    result
+}
+
+/// Run `work`, putting the files at `paths` back as they were, or removing
+/// ones that didn't exist, if it fails. Each file is replaced whole, so an
+/// interrupted rollback never leaves it half written.
+pub async fn with_rollback<T>(paths: &[&Path], work: impl Future<Output = Result<T>>) -> Result<T> {
+    let mut snapshots = Vec::new();
+    for path in paths {
+        let contents = match tokio::fs::read(path).await {
+            Ok(contents) => Some(contents),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        snapshots.push((*path, contents));
+    }
+
+    let result = work.await;
+    if result.is_err() {
+        for (path, contents) in snapshots {
+            match contents {
+                Some(contents) => atomic_write(path, &contents).await?,
+                None => {
+                    let _ = tokio::fs::remove_file(path).await;
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Add `name[@spec]` / `name==version` entries, or any other requirement,
+/// to the config. A bare name is
+/// written as a caret pin of its newest release; with `exact`, every spec is
+/// resolved and written as an `==` pin to the chosen release. Packages served
+/// by a `registries` repository are looked up there instead of on PyPI.
+pub async fn add_registry_packages(
+    config: &mut CobraConfig,
+    client: &RegistryClient,
+    registries: &PrivateRegistries,
+    packages: &[String],
+    dev: bool,
+    exact: bool,
+) -> Result<()> {
+    for package in packages {
+        let dependency = parse_package_arg(package)?;
+        let (name, spec) = (dependency.name, Some(dependency.version_spec).filter(|spec| spec != "*"));
+        let version = match (spec, exact) {
+            (spec, true) => format!("=={}", resolve_latest(client, registries, &name, spec.as_deref().unwrap_or("*")).await?),
+            (Some(spec), false) => spec,
+            (None, false) => format!("^{}", resolve_latest(client, registries, &name, "*").await?),
+        };
+        if dev {
+            config.add_dev_dependency(&name, &version);
+            println!("{} Added {} {} {}", "✓".green(), name.cyan(), version.dimmed(), "(dev)".dimmed());
+        } else {
+            config.add_dependency(&name, &version);
+            println!("{} Added {} {}", "✓".green(), name.cyan(), version.dimmed());
+        }
+    }
     Ok(())
 }
 
-fn parse_package_spec(spec: &str) -> Result<(String, String)> {
-    if let Some((name, version)) = spec.split_once('@') {
-        Ok((name.to_string(), version.to_string()))
-    } else if let Some((name, version)) = spec.split_once("==") {
-        Ok((name.to_string(), format!("=={}", version)))
-    } else {
-        // No version specified, use latest
-        Ok((spec.to_string(), "*".to_string()))
+/// Newest installable release of `name` matching `version_spec`; one
+/// release-list request, which also fails for packages that don't exist
+async fn resolve_latest(
+    client: &RegistryClient,
+    registries: &PrivateRegistries,
+    name: &str,
+    version_spec: &str,
+) -> Result<String> {
+    if let Some(package) = registries.get_package(name, version_spec).await? {
+        if !version::satisfies(&package.version, version_spec) {
+            return Err(CobraError::ResolutionFailed(format!(
+                "{} {} from a private registry does not match '{}'", name, package.version, version_spec
+            )));
+        }
+        return Ok(package.version);
+    }
+
+    let spec = VersionSpec::parse(version_spec)?;
+    let releases = client.get_releases(name).await?;
+    let (release, _) = select_release(&releases, &spec, None, None, ResolutionStrategy::Highest)
+        .ok_or_else(|| CobraError::ResolutionFailed(format!(
+            "No release of {} matches '{}'", name, version_spec
+        )))?;
+    Ok(release.version.clone())
+}
+
+/// A local directory (`./libs/mylib`, `/abs/path`) or `git+<url>[@rev]` given
+/// in place of a package name
+pub fn parse_source_spec(spec: &str) -> Option<DependencySource> {
+    if let Some(source) = vcs::parse_git_spec(spec) {
+        return Some(source);
+    }
+    let is_path = spec == "." || spec == ".."
+        || ["./", "../", ".\\", "..\\"].iter().any(|prefix| spec.starts_with(prefix))
+        || Path::new(spec).is_absolute();
+    is_path.then(|| DependencySource::Path { path: spec.to_string() })
+}
+
+/// Project name of a path or git source; git repositories are cloned to read it
+async fn source_project_name(source: &DependencySource) -> Result<String> {
+    match source {
+        DependencySource::Path { path } => local_project_name(Path::new(path)),
+        DependencySource::Git { git, rev } => {
+            let checkout = tempfile::tempdir()?;
+            vcs::checkout(git, rev.as_deref(), checkout.path()).await?;
+            pyproject_name(checkout.path())
+                .or_else(|| {
+                    let repo = git.trim_end_matches('/').rsplit(['/', ':']).next()?;
+                    Some(repo.trim_end_matches(".git").to_string()).filter(|name| !name.is_empty())
+                })
+                .ok_or_else(|| CobraError::InvalidInput(format!("Cannot determine project name for {}", git)))
+        }
+        DependencySource::Registry => Err(CobraError::InvalidInput("Not a path or git source".to_string())),
     }
 }
+
+/// `name` from `pyproject.toml`'s `[project]` table in `dir`
+fn pyproject_name(dir: &Path) -> Option<String> {
+    std::fs::read_to_string(dir.join("pyproject.toml"))
+        .ok()
+        .and_then(|contents| toml::from_str::<toml::Value>(&contents).ok())
+        .and_then(|doc| doc.get("project")?.get("name")?.as_str().map(str::to_string))
+}
+
+/// Project name from `pyproject.toml`'s `[project]` table, else the directory name
+fn local_project_name(dir: &Path) -> Result<String> {
+    if !dir.is_dir() {
+        return Err(CobraError::InvalidInput(format!("{} is not a directory", dir.display())));
+    }
+
+    pyproject_name(dir)
+        .or_else(|| {
+            dir.canonicalize().ok()?.file_name().map(|n| n.to_string_lossy().to_string())
+        })
+        .ok_or_else(|| CobraError::InvalidInput(format!("Cannot determine project name for {}", dir.display())))
+}
@@ -0,0 +1,79 @@
+use crate::Result;
+use crate::core::cache::MultiLevelCache;
+use crate::core::installer::format_bytes;
+use crate::core::store::WheelStore;
+use crate::core::wheel_cache::WheelCache;
+use colored::Colorize;
+
+/// Which cache entries `cobra cache clear` removes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClearScope {
+    /// Everything, including hit/miss statistics
+    #[default]
+    All,
+    /// Resolver metadata only
+    Metadata,
+    /// Downloaded package archives only, including the wheel cache
+    Blobs,
+}
+
+impl ClearScope {
+    pub fn from_flags(metadata_only: bool, blobs_only: bool) -> Self {
+        match (metadata_only, blobs_only) {
+            (true, _) => ClearScope::Metadata,
+            (false, true) => ClearScope::Blobs,
+            (false, false) => ClearScope::All,
+        }
+    }
+}
+
+pub async fn clear(scope: ClearScope) -> Result<()> {
+    let cache = MultiLevelCache::new().await?;
+
+    match scope {
+        ClearScope::All => {
+            cache.clear().await?;
+            WheelCache::open_default()?.prune(None).await?;
+            println!("{} Cleared the package cache", "✓".green());
+        }
+        ClearScope::Metadata => {
+            let removed = cache.clear_metadata().await?;
+            println!("{} Cleared {} cached metadata entries, kept package archives", "✓".green(), removed);
+        }
+        ClearScope::Blobs => {
+            // Archives cached in the database by earlier versions go too
+            let removed = cache.clear_blobs().await? + WheelCache::open_default()?.prune(None).await?.0;
+            println!("{} Cleared {} cached package archives, kept metadata", "✓".green(), removed);
+        }
+    }
+
+    Ok(())
+}
+
+/// Remove wheels from the store that no project links any more
+pub async fn prune() -> Result<()> {
+    let (removed, freed) = WheelStore::open_default()?.prune().await?;
+    println!("{} Removed {} unused stored wheels, freeing {}", "✓".green(), removed, format_bytes(freed));
+    Ok(())
+}
+
+/// Print the archives in the wheel cache with their sizes
+pub async fn list_wheels() -> Result<()> {
+    let cache = WheelCache::open_default()?;
+    let wheels = cache.list().await?;
+    for wheel in &wheels {
+        println!("  {} {}", wheel.filename.cyan(), format_bytes(wheel.size).dimmed());
+    }
+    let total: u64 = wheels.iter().map(|w| w.size).sum();
+    println!("{} archives, {} in {}", wheels.len(), format_bytes(total), cache.root().display());
+    Ok(())
+}
+
+/// Remove archives from the wheel cache, only those unused for
+/// `older_than_days` when given
+pub async fn prune_wheels(older_than_days: Option<u64>) -> Result<()> {
+    let max_age = older_than_days.map(|days| std::time::Duration::from_secs(days * 24 * 60 * 60));
+    let (removed, freed) = WheelCache::open_default()?.prune(max_age).await?;
+    println!("{} Removed {} cached archives, freeing {}", "✓".green(), removed, format_bytes(freed));
+    Ok(())
+}
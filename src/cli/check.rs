@@ -1,12 +1,20 @@
-use crate::{Result, CobraError};
-use crate::core::{config::CobraConfig, package_manager::LocalPackageManager};
-use crate::registry::client::RegistryClient;
+use crate::{Result, CobraError, Dependency, normalize_name};
+use crate::core::{cache::MultiLevelCache, config::{CobraConfig, DependencySelection}, package_manager::{InstalledPackage, LocalPackageManager}};
+use crate::cli::install::{self, InstallOptions};
+use crate::core::markers::MarkerEnvironment;
+use crate::core::record::FileProblem;
+use crate::core::version;
+use crate::core::resolver::{find_cycles, DependencyCycle, DependencyResolver};
+use crate::cli::index::open_local_index;
 use colored::Colorize;
 use std::path::Path;
 use std::sync::Arc;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 
-pub async fn execute() -> Result<()> {
+/// `dev` treats `[dev-dependencies]` as part of the expected environment;
+/// `fix` repairs missing, conflicting and corrupted packages, or with
+/// `dry_run` only lists the repairs
+pub async fn execute(dev: bool, fix: bool, dry_run: bool) -> Result<()> {
     let config_path = Path::new("cobra.toml");
     if !config_path.exists() {
         return Err(CobraError::Config(
@@ -15,9 +23,10 @@ pub async fn execute() -> Result<()> {
     }
 
     let config = CobraConfig::load(config_path).await?;
+    let settings = config.settings()?;
     
     // Initialize package manager
-    let install_dir = std::env::current_dir()?.join(config.get_install_dir());
+    let install_dir = config.install_dir()?;
     let package_manager = Arc::new(LocalPackageManager::new(install_dir));
     
     println!("Checking package dependencies and conflicts...");
@@ -25,29 +34,25 @@ pub async fn execute() -> Result<()> {
     
     // Get installed packages
     let installed_packages = package_manager.list_installed().await?;
-    let configured_deps = config.get_dependencies_list();
+    // Dependencies whose markers exclude this platform are not expected here
+    let environment = MarkerEnvironment::detect(&config.tool.cobra.python_version).await;
+    let configured_deps: Vec<Dependency> = config.select_dependencies(DependencySelection::from_flags(dev, false))
+        .into_iter()
+        .filter(|d| environment.includes(d.markers.as_deref()))
+        .collect();
     
     let mut issues_found = 0;
     
-    // Check 1: Missing packages (in config but not installed)
-    let installed_names: HashSet<String> = installed_packages.iter()
-        .map(|p| p.name.clone())
-        .collect();
+    let problems = find_problems(&package_manager, &configured_deps, &installed_packages).await?;
     
-    let mut missing_packages = Vec::new();
-    for dep in &configured_deps {
-        if !installed_names.contains(&dep.name) {
-            missing_packages.push(&dep.name);
-        }
-    }
-    
-    if !missing_packages.is_empty() {
+    // Check 1: Missing packages (in config but not installed)
+    if !problems.missing.is_empty() {
         println!("{} Missing packages:", "!".yellow().bold());
-        for pkg in &missing_packages {
+        for pkg in &problems.missing {
             println!("  {} {}", "•".yellow(), pkg.red());
         }
-        println!("  Run 'cobra install' to install missing packages\n");
-        issues_found += missing_packages.len();
+        println!("  Run 'cobra check --fix' to install missing packages\n");
+        issues_found += problems.missing.len();
     }
     
     // Check 2: Extra packages (installed but not in config)
@@ -72,57 +77,61 @@ pub async fn execute() -> Result<()> {
     }
     
     // Check 3: Version conflicts
-    let client = RegistryClient::new();
-    let mut version_conflicts = Vec::new();
-    
-    for dep in &configured_deps {
-        if let Some(installed_pkg) = installed_packages.iter().find(|p| p.name == dep.name) {
-            if !version_matches(&installed_pkg.version, &dep.version_spec) {
-                version_conflicts.push((dep, installed_pkg));
-            }
-        }
-    }
-    
-    if !version_conflicts.is_empty() {
+    if !problems.conflicts.is_empty() {
         println!("{} Version conflicts:", "!".red().bold());
-        for (dep, installed) in &version_conflicts {
+        for (name, required, installed) in &problems.conflicts {
             println!("  {} {} (required: {}, installed: {})", 
                 "•".red(), 
-                dep.name.cyan(),
-                dep.version_spec.green(),
-                installed.version.red()
+                name.cyan(),
+                required.green(),
+                installed.red()
             );
         }
-        println!("  Run 'cobra update' to resolve version conflicts\n");
-        issues_found += version_conflicts.len();
+        println!("  Run 'cobra check --fix' to install the required versions\n");
+        issues_found += problems.conflicts.len();
     }
     
     // Check 4: Dependency integrity (check if package files exist)
-    let mut corrupted_packages = Vec::new();
-    for pkg in &installed_packages {
-        if !pkg.install_path.exists() {
-            corrupted_packages.push(&pkg.name);
-        }
-    }
-    
-    if !corrupted_packages.is_empty() {
+    if !problems.corrupted.is_empty() || !problems.vanished.is_empty() {
         println!("{} Corrupted packages (files missing):", "!".red().bold());
-        for pkg in &corrupted_packages {
-            println!("  {} {}", "•".red(), pkg.red());
+        for (pkg, missing, total) in &problems.corrupted {
+            println!("  {} {} ({} of {} files missing)", "•".red(), pkg.red(), missing, total);
+        }
+        for pkg in &problems.vanished {
+            println!("  {} {} (install directory missing)", "•".red(), pkg.red());
         }
-        println!("  Run 'cobra install' to repair corrupted packages\n");
-        issues_found += corrupted_packages.len();
+        println!("  Run 'cobra check --fix' to repair corrupted packages\n");
+        issues_found += problems.corrupted.len() + problems.vanished.len();
     }
     
-    // Check 5: Circular dependencies (basic check)
-    let circular_deps = check_circular_dependencies(&configured_deps, &client).await?;
-    if !circular_deps.is_empty() {
-        println!("{} Potential circular dependencies:", "!".yellow().bold());
-        for cycle in &circular_deps {
-            println!("  {} {}", "•".yellow(), cycle.join(" -> ").cyan());
+    // Check 5: Circular dependencies
+    let cache = Arc::new(MultiLevelCache::new().await?);
+    let resolver = DependencyResolver::new(Arc::new(config.registry_client()), Some(cache))
+        .with_local_index(open_local_index()?)
+        .with_registries(config.private_registries())
+        .with_overrides(config.get_overrides()?)
+        .with_all_extras(true)
+        .with_timeout(Some(config.resolve_timeout()))
+        .with_metadata_ttl(config.metadata_cache_ttl())
+        .with_settings(&settings);
+    let registry_deps = config.registry_dependencies(DependencySelection::from_flags(dev, false));
+    let cycles = check_circular_dependencies(&registry_deps, &resolver).await?;
+    let (hard_cycles, conditional_cycles): (Vec<_>, Vec<_>) = cycles.iter().partition(|c| !c.conditional);
+    if !hard_cycles.is_empty() {
+        println!("{} Circular dependencies:", "!".red().bold());
+        for cycle in &hard_cycles {
+            println!("  {} {}", "•".red(), cycle.chain.join(" -> ").cyan());
         }
         println!("  Review dependency specifications\n");
-        issues_found += circular_deps.len();
+        issues_found += hard_cycles.len();
+    }
+    if !conditional_cycles.is_empty() {
+        // Common on PyPI (e.g. a package's test extra depending back on it), so not counted as issues
+        println!("{} Cycles through extras or environment markers:", "i".blue().bold());
+        for cycle in &conditional_cycles {
+            println!("  {} {}", "•".blue(), cycle.chain.join(" -> ").dimmed());
+        }
+        println!();
     }
     
     // Summary
@@ -135,36 +144,154 @@ pub async fn execute() -> Result<()> {
             "!".yellow().bold(), 
             issues_found.to_string().red().bold()
         );
-        println!("Run the suggested commands to resolve these issues.");
+        if !fix {
+            println!("Run the suggested commands to resolve these issues.");
+        }
     }
     
+    if fix {
+        repair(&problems, &package_manager, &configured_deps, dev, dry_run).await?;
+    }
     Ok(())
 }
 
-fn version_matches(installed_version: &str, version_spec: &str) -> bool {
-    // Simple version matching - in production, use proper semver parsing
-    if version_spec == "*" {
-        return true;
+/// What `cobra check --fix` repairs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnvironmentProblems {
+    /// Configured but not installed
+    pub missing: Vec<String>,
+    /// `(name, required spec, installed version)` of installs the spec excludes
+    pub conflicts: Vec<(String, String, String)>,
+    /// `(name, missing files, recorded files)` of installs with some files gone
+    pub corrupted: Vec<(String, usize, usize)>,
+    /// Registered packages whose files, or install directory when no files
+    /// were recorded, are all gone
+    pub vanished: Vec<String>,
+}
+
+impl EnvironmentProblems {
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
-    
-    if version_spec.starts_with("==") {
-        return installed_version == &version_spec[2..];
+
+    pub fn len(&self) -> usize {
+        self.missing.len() + self.conflicts.len() + self.corrupted.len() + self.vanished.len()
     }
-    
-    if version_spec.starts_with(">=") {
-        // Simplified comparison - in production, use proper version comparison
-        return true; // For now, assume it matches
+
+    /// Every affected package, for error messages
+    fn names(&self) -> Vec<&str> {
+        self.missing.iter()
+            .chain(self.conflicts.iter().map(|(name, _, _)| name))
+            .chain(self.corrupted.iter().map(|(name, _, _)| name))
+            .chain(&self.vanished)
+            .map(String::as_str)
+            .collect()
     }
-    
-    // Default: exact match
-    installed_version == version_spec
 }
 
-async fn check_circular_dependencies(
-    _deps: &[crate::Dependency], 
-    _client: &RegistryClient
-) -> Result<Vec<Vec<String>>> {
-    // Simplified circular dependency check
-    // In production, this would build a dependency graph and detect cycles
-    Ok(Vec::new())
+/// Compare the `installed` packages with the `configured` dependencies and
+/// with their recorded files
+pub async fn find_problems(
+    package_manager: &LocalPackageManager,
+    configured: &[Dependency],
+    installed: &[InstalledPackage],
+) -> Result<EnvironmentProblems> {
+    let mut problems = EnvironmentProblems::default();
+    let find = |name: &str| installed.iter().find(|p| normalize_name(&p.name) == normalize_name(name));
+    for dep in configured {
+        match find(&dep.name) {
+            None => problems.missing.push(dep.name.clone()),
+            Some(installed) if !version::satisfies(&installed.version, &dep.version_spec) => {
+                problems.conflicts.push((dep.name.clone(), dep.version_spec.clone(), installed.version.clone()));
+            }
+            Some(_) => {}
+        }
+    }
+
+    // Packages registered before files were recorded only have their directory checked
+    for pkg in installed {
+        if pkg.files.is_empty() {
+            if !pkg.install_path.exists() {
+                problems.vanished.push(pkg.name.clone());
+            }
+            continue;
+        }
+        let missing = package_manager.verify_package(pkg).await?
+            .into_iter()
+            .filter(|problem| matches!(problem, FileProblem::Missing(_)))
+            .count();
+        if missing == pkg.files.len() {
+            problems.vanished.push(pkg.name.clone());
+        } else if missing > 0 {
+            problems.corrupted.push((pkg.name.clone(), missing, pkg.files.len()));
+        }
+    }
+    problems.vanished.sort();
+    problems.corrupted.sort();
+    Ok(problems)
+}
+
+/// Drop registry entries of vanished packages, then let `cobra install`
+/// install missing and vanished packages, replace conflicting versions and
+/// reinstall corrupted packages. Fails if any problem is left afterwards.
+async fn repair(
+    problems: &EnvironmentProblems,
+    package_manager: &LocalPackageManager,
+    configured: &[Dependency],
+    dev: bool,
+    dry_run: bool,
+) -> Result<()> {
+    if problems.is_empty() {
+        return Ok(());
+    }
+    println!("\n{} Repairs:", "🔧".bright_blue());
+    for name in &problems.vanished {
+        println!("  {} Forget {}, whose files are gone, and install it again", "•".blue(), name.cyan());
+    }
+    for name in &problems.missing {
+        println!("  {} Install {}", "•".blue(), name.cyan());
+    }
+    for (name, required, installed) in &problems.conflicts {
+        println!("  {} Replace {} {} with a version matching {}", "•".blue(), name.cyan(), installed.red(), required.green());
+    }
+    for (name, missing, total) in &problems.corrupted {
+        println!("  {} Reinstall {} ({} of {} files missing)", "•".blue(), name.cyan(), missing, total);
+    }
+    if dry_run {
+        println!("\n{} Dry run: nothing was changed", "💡".bright_yellow());
+        return Ok(());
+    }
+
+    for name in &problems.vanished {
+        package_manager.unregister_package(name).await?;
+    }
+    let corrupted: Vec<String> = problems.corrupted.iter().map(|(name, _, _)| name.clone()).collect();
+    install::execute(InstallOptions {
+        dependencies: DependencySelection::from_flags(dev, false),
+        force_reinstall: (!corrupted.is_empty()).then_some(corrupted),
+        ..InstallOptions::default()
+    }).await?;
+
+    let installed = package_manager.list_installed().await?;
+    let remaining = find_problems(package_manager, configured, &installed).await?;
+    println!("{} Repaired {} of {} problems",
+        if remaining.is_empty() { "✓".green().bold() } else { "!".yellow().bold() },
+        problems.len().saturating_sub(remaining.len()),
+        problems.len()
+    );
+    if !remaining.is_empty() {
+        return Err(CobraError::InstallationFailed(format!(
+            "{} problems remain after repair: {}", remaining.len(), remaining.names().join(", ")
+        )));
+    }
+    Ok(())
+}
+
+/// Resolve the full dependency closure (reusing cached metadata) and report its cycles
+pub async fn check_circular_dependencies(
+    deps: &[Dependency],
+    resolver: &DependencyResolver,
+) -> Result<Vec<DependencyCycle>> {
+    let packages = resolver.resolve_unordered(deps).await?;
+    Ok(find_cycles(&packages))
 }
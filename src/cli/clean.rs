@@ -0,0 +1,51 @@
+use crate::{Result, CobraError};
+use crate::core::{config::CobraConfig, package_manager::LocalPackageManager};
+use colored::Colorize;
+use std::path::Path;
+
+/// Remove files no registered package owns and registry entries whose files are gone
+pub async fn execute(dry_run: bool) -> Result<()> {
+    let config_path = Path::new("cobra.toml");
+    if !config_path.exists() {
+        return Err(CobraError::Config(
+            "No cobra.toml found. Run 'cobra init' to create one.".to_string()
+        ));
+    }
+
+    let config = CobraConfig::load(config_path).await?;
+    let install_dir = config.install_dir()?;
+    let package_manager = LocalPackageManager::new(install_dir);
+
+    let plan = package_manager.plan_clean().await?;
+    if plan.is_empty() {
+        println!("{} Install directory matches the registry", "✓".green().bold());
+        return Ok(());
+    }
+
+    for orphan in &plan.orphans {
+        println!("  {} {} {}", "-".red(), orphan.display(), "(not in the registry)".dimmed());
+    }
+    for name in &plan.missing {
+        println!("  {} {} {}", "-".red(), name.cyan(), "(files are gone)".dimmed());
+    }
+
+    if dry_run {
+        println!("\n{} Dry run: would remove {} orphaned paths and {} broken registry entries",
+            "💡".bright_yellow(),
+            plan.orphans.len(),
+            plan.missing.len()
+        );
+        return Ok(());
+    }
+
+    package_manager.clean(&plan).await?;
+    if !plan.missing.is_empty() {
+        package_manager.create_pth_file().await?;
+    }
+    println!("\n{} Removed {} orphaned paths and {} broken registry entries",
+        "✓".green().bold(),
+        plan.orphans.len(),
+        plan.missing.len()
+    );
+    Ok(())
+}
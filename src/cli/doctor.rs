@@ -0,0 +1,163 @@
+use crate::Result;
+use crate::core::cache::MultiLevelCache;
+use crate::core::config::CobraConfig;
+use crate::core::installer::format_bytes;
+use crate::core::package_manager::{user_pth_file, LocalPackageManager, PthStatus};
+use crate::core::python::PythonEnvironment;
+use crate::registry::packagecloud::PACKAGECLOUD_URL;
+use crate::utils::credentials;
+use crate::utils::fs::{dir_size, get_cache_dir, is_writable};
+use colored::Colorize;
+use std::path::{Path, PathBuf};
+
+/// Environment details reported by `cobra doctor`
+#[derive(Debug)]
+pub struct Diagnostics {
+    pub python: std::result::Result<PythonEnvironment, String>,
+    pub config_found: bool,
+    pub install_dir: PathBuf,
+    pub install_dir_writable: bool,
+    pub pth_file: Option<PathBuf>,
+    pub pth_status: std::result::Result<PthStatus, String>,
+    pub cache_dir: PathBuf,
+    pub cache_size: u64,
+    /// `(hits, misses)` across runs; `None` when the cache could not be opened
+    pub cache_lookups: Option<(u64, u64)>,
+    pub index_url: String,
+    /// `(name, "repo at url", has a token)` per configured private registry
+    pub registries: Vec<(String, String, bool)>,
+}
+
+impl Diagnostics {
+    /// Inspect the environment `config` would install into
+    pub async fn collect(config: &CobraConfig, config_found: bool) -> Result<Self> {
+        let python = PythonEnvironment::detect().await.map_err(|e| e.to_string());
+        let install_dir = config.install_dir()?;
+        let package_manager = LocalPackageManager::new(install_dir.clone());
+
+        let pth_file = user_pth_file().await.ok();
+        let pth_status = match &pth_file {
+            Some(pth_file) => package_manager.pth_status(pth_file).await.map_err(|e| e.to_string()),
+            None => Err("user site-packages could not be determined".to_string()),
+        };
+
+        let cache_dir = get_cache_dir()?;
+        let cache_size = dir_size(&cache_dir).await.unwrap_or(0);
+        let cache_lookups = match MultiLevelCache::open(&cache_dir.join("packages")).await {
+            Ok(cache) => Some(cache.lookups().await),
+            Err(_) => None,
+        };
+
+        let mut registries: Vec<(String, String, bool)> = config.tool.cobra.registries.iter()
+            .map(|(name, registry)| {
+                let url = registry.url.as_deref().unwrap_or(PACKAGECLOUD_URL).trim_end_matches('/');
+                let has_token = registry.token.is_some() || credentials::lookup_token(url).is_some();
+                (name.clone(), format!("{} at {}", registry.repo, url), has_token)
+            })
+            .collect();
+        registries.sort();
+
+        Ok(Self {
+            python,
+            config_found,
+            install_dir_writable: is_writable(&install_dir),
+            install_dir,
+            pth_file,
+            pth_status,
+            cache_dir,
+            cache_size,
+            cache_lookups,
+            index_url: config.registry_client().base_url().to_string(),
+            registries,
+        })
+    }
+
+    /// Problems likely to break installs, worded as advice
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if let Err(e) = &self.python {
+            problems.push(format!("No usable Python interpreter: {}", e));
+        }
+        if !self.config_found {
+            problems.push("No cobra.toml in the current directory; run 'cobra init' (defaults shown)".to_string());
+        }
+        if !self.install_dir_writable {
+            problems.push(format!("Install directory {} is not writable", self.install_dir.display()));
+        }
+        match &self.pth_status {
+            Ok(PthStatus::Missing(entries)) => problems.push(format!(
+                ".pth file does not list {}; run 'cobra install' to rewrite it",
+                entries.iter().map(|e| e.display().to_string()).collect::<Vec<_>>().join(", ")
+            )),
+            Err(e) => problems.push(format!("Cannot check the .pth file: {}", e)),
+            Ok(_) => {}
+        }
+        if self.cache_lookups.is_none() {
+            problems.push(format!("Cache at {} could not be opened; is another cobra running?", self.cache_dir.display()));
+        }
+        problems
+    }
+
+    fn print(&self) {
+        let row = |label: &str, value: String| println!("{:<16}{}", format!("{}:", label).bold(), value);
+
+        row("cobra", format!("{} ({}-{})", env!("CARGO_PKG_VERSION"), std::env::consts::OS, std::env::consts::ARCH));
+        match &self.python {
+            Ok(python) => {
+                row("Python", format!("{} ({})", python.python_path.display(), python.version));
+                row("site-packages", python.site_packages.display().to_string());
+            }
+            Err(_) => row("Python", "not found".red().to_string()),
+        }
+        row("Install dir", format!("{}{}",
+            self.install_dir.display(),
+            if self.install_dir_writable { "" } else { " (not writable)" }
+        ));
+        let pth = match &self.pth_status {
+            Ok(PthStatus::NotNeeded) => "not needed".to_string(),
+            Ok(PthStatus::Complete) => "ok".green().to_string(),
+            Ok(PthStatus::Missing(entries)) => format!("missing {} entries", entries.len()).yellow().to_string(),
+            Err(_) => "unknown".yellow().to_string(),
+        };
+        match &self.pth_file {
+            Some(file) => row(".pth file", format!("{} ({})", file.display(), pth)),
+            None => row(".pth file", pth),
+        }
+        row("Cache", format!("{} ({})", self.cache_dir.display(), format_bytes(self.cache_size)));
+        let hit_rate = match self.cache_lookups {
+            Some((0, 0)) => "no lookups yet".to_string(),
+            Some((hits, misses)) => format!("{:.1}% of {} lookups", hits as f64 * 100.0 / (hits + misses) as f64, hits + misses),
+            None => "unavailable".to_string(),
+        };
+        row("Cache hit rate", hit_rate);
+        row("Index", self.index_url.clone());
+        for (name, location, has_token) in &self.registries {
+            row("Registry", format!("{}: {}{}", name, location, if *has_token { "" } else { " (no token)" }));
+        }
+    }
+}
+
+/// Print environment details for bug reports and flag common problems
+pub async fn execute() -> Result<()> {
+    let config_path = Path::new("cobra.toml");
+    let config_found = config_path.exists();
+    let config = if config_found {
+        CobraConfig::load(config_path).await?
+    } else {
+        CobraConfig::default()
+    };
+
+    let diagnostics = Diagnostics::collect(&config, config_found).await?;
+    diagnostics.print();
+
+    let problems = diagnostics.problems();
+    if problems.is_empty() {
+        println!("\n{} No problems found", "✓".green().bold());
+    } else {
+        println!("\n{} {} problems found:", "⚠".yellow(), problems.len());
+        for problem in &problems {
+            println!("  {} {}", "•".yellow(), problem);
+        }
+    }
+    Ok(())
+}
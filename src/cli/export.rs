@@ -0,0 +1,118 @@
+use crate::{Result, CobraError, normalize_name};
+use crate::core::{cache::MultiLevelCache, config::{CobraConfig, DependencySelection}, resolver::{DependencyResolver, ResolutionStrategy}};
+use crate::core::lockfile::{LockedPackage, Lockfile, LOCKFILE_NAME};
+use crate::cli::index::open_local_index;
+use crate::utils::fs::atomic_write;
+use crate::utils::hash::HashAlgo;
+use colored::Colorize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// What `cobra export` writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// Pinned requirements.txt, optionally with `--hash` lines
+    #[default]
+    Requirements,
+    /// constraints.txt for `pip install -c`
+    Constraints,
+    /// A PEP 621 `[project]` table with pinned `dependencies`
+    Pyproject,
+}
+
+/// Write the resolved packages in `format`, taken from cobra.lock or, when
+/// there is none, from a fresh resolution of cobra.toml
+pub async fn execute(format: ExportFormat, hashes: bool, output: Option<PathBuf>) -> Result<()> {
+    let config_path = Path::new("cobra.toml");
+    if !config_path.exists() {
+        return Err(CobraError::Config(
+            "No cobra.toml found. Run 'cobra init' to create one.".to_string()
+        ));
+    }
+    let config = CobraConfig::load(config_path).await?;
+
+    let lock_path = Path::new(LOCKFILE_NAME);
+    let (packages, origin) = if lock_path.exists() {
+        (Lockfile::load(lock_path).await?.packages, LOCKFILE_NAME)
+    } else {
+        let settings = config.settings()?;
+        let cache = Arc::new(MultiLevelCache::new().await?);
+        let resolver = DependencyResolver::new(Arc::new(config.registry_client()), Some(cache))
+            .with_local_index(open_local_index()?)
+            .with_registries(config.private_registries())
+            .with_overrides(config.get_overrides()?)
+            .with_timeout(Some(config.resolve_timeout()))
+            .with_metadata_ttl(config.metadata_cache_ttl())
+            .with_settings(&settings);
+        let resolved = resolver.resolve(&config.registry_dependencies(DependencySelection::Main)).await?;
+        (Lockfile::from_packages(&resolved, ResolutionStrategy::default()).packages, "cobra.toml")
+    };
+
+    let contents = render_export(&packages, format, hashes, origin)?;
+    match output {
+        Some(path) => {
+            atomic_write(&path, contents.as_bytes()).await?;
+            println!("{} Exported {} packages to {}",
+                "✓".green(),
+                packages.len().to_string().bold(),
+                path.display().to_string().cyan()
+            );
+        }
+        None => print!("{}", contents),
+    }
+    Ok(())
+}
+
+/// Render locked `packages` as `format`, sorted by name, with a header naming
+/// `origin`. Each package's environment marker is kept. `hashes` adds pip's
+/// `--hash=sha256:` lines and needs a sha256 digest for every package.
+pub fn render_export(packages: &[LockedPackage], format: ExportFormat, hashes: bool, origin: &str) -> Result<String> {
+    if hashes && format != ExportFormat::Requirements {
+        return Err(CobraError::InvalidInput(
+            "--hashes only applies to --format requirements".to_string()
+        ));
+    }
+    let mut packages: Vec<&LockedPackage> = packages.iter().collect();
+    packages.sort_by_key(|p| normalize_name(&p.name));
+
+    // Requirements files space out the `;` as pip-compile does
+    let requirement = |package: &LockedPackage, separator: &str| match &package.markers {
+        Some(markers) => format!("{}=={}{}{}", package.name, package.version, separator, markers),
+        None => format!("{}=={}", package.name, package.version),
+    };
+
+    let mut contents = format!("# Generated by cobra export from {}\n", origin);
+    match format {
+        ExportFormat::Requirements | ExportFormat::Constraints => {
+            for package in packages {
+                contents.push_str(&requirement(package, " ; "));
+                if hashes {
+                    contents.push_str(&format!(" \\\n    --hash=sha256:{}", sha256_of(package)?));
+                }
+                contents.push('\n');
+            }
+        }
+        ExportFormat::Pyproject => {
+            contents.push_str("[project]\ndependencies = [\n");
+            for package in packages {
+                let quoted = toml::Value::String(requirement(package, "; ")).to_string();
+                contents.push_str(&format!("    {},\n", quoted));
+            }
+            contents.push_str("]\n");
+        }
+    }
+    Ok(contents)
+}
+
+/// The package's sha256 digest, which pip's hash-checking mode needs
+fn sha256_of(package: &LockedPackage) -> Result<&str> {
+    let missing = || CobraError::InvalidInput(format!(
+        "--hashes needs a sha256 digest for every package, but {} {} has none",
+        package.name, package.version
+    ));
+    let hash = package.hash.as_deref().ok_or_else(missing)?;
+    match HashAlgo::of_digest(hash)? {
+        (HashAlgo::Sha256, digest) => Ok(digest),
+        _ => Err(missing()),
+    }
+}
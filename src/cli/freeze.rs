@@ -1,9 +1,9 @@
 use crate::{Result, CobraError};
 use crate::core::{config::CobraConfig, package_manager::LocalPackageManager};
+use crate::utils::fs::atomic_write;
 use colored::Colorize;
 use std::path::Path;
 use std::sync::Arc;
-use tokio::fs;
 
 pub async fn execute(output_file: Option<String>) -> Result<()> {
     let config_path = Path::new("cobra.toml");
@@ -16,7 +16,7 @@ pub async fn execute(output_file: Option<String>) -> Result<()> {
     let config = CobraConfig::load(config_path).await?;
     
     // Initialize package manager
-    let install_dir = std::env::current_dir()?.join(config.get_install_dir());
+    let install_dir = config.install_dir()?;
     let package_manager = Arc::new(LocalPackageManager::new(install_dir));
     
     // Get installed packages
@@ -27,24 +27,18 @@ pub async fn execute(output_file: Option<String>) -> Result<()> {
         return Ok(());
     }
     
-    // Generate requirements content
-    let mut requirements_content = String::new();
-    requirements_content.push_str("# Generated by Cobra Package Manager\n");
-    requirements_content.push_str(&format!("# Frozen on {}\n", chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC")));
-    requirements_content.push_str("\n");
-    
-    // Sort packages alphabetically for consistent output
+    // Sort case-insensitively like pip freeze
     let mut sorted_packages = installed_packages;
-    sorted_packages.sort_by(|a, b| a.name.cmp(&b.name));
+    sorted_packages.sort_by_key(|p| p.name.to_lowercase());
     
-    for package in &sorted_packages {
-        requirements_content.push_str(&format!("{}=={}\n", package.name, package.version));
-    }
+    let requirements_content = freeze_requirements(
+        sorted_packages.iter().map(|p| (p.name.as_str(), p.version.as_str()))
+    );
     
     // Output to file or stdout
     match output_file {
         Some(file_path) => {
-            fs::write(&file_path, &requirements_content).await?;
+            atomic_write(Path::new(&file_path), requirements_content.as_bytes()).await?;
             println!("Requirements frozen to {}", file_path.cyan());
             println!("Total: {} packages", sorted_packages.len().to_string().bold());
         }
@@ -57,6 +51,14 @@ pub async fn execute(output_file: Option<String>) -> Result<()> {
     Ok(())
 }
 
+/// `name==version` lines in pip freeze format
+pub fn freeze_requirements<'a>(packages: impl IntoIterator<Item = (&'a str, &'a str)>) -> String {
+    packages
+        .into_iter()
+        .map(|(name, version)| format!("{}=={}\n", name, version))
+        .collect()
+}
+
 pub async fn execute_with_format(
     output_file: Option<String>, 
     format: Option<String>
@@ -82,7 +84,7 @@ async fn execute_poetry_format(output_file: Option<String>) -> Result<()> {
     }
 
     let config = CobraConfig::load(config_path).await?;
-    let install_dir = std::env::current_dir()?.join(config.get_install_dir());
+    let install_dir = config.install_dir()?;
     let package_manager = Arc::new(LocalPackageManager::new(install_dir));
     let installed_packages = package_manager.list_installed().await?;
     
@@ -104,7 +106,7 @@ async fn execute_poetry_format(output_file: Option<String>) -> Result<()> {
     
     match output_file {
         Some(file_path) => {
-            fs::write(&file_path, &content).await?;
+            atomic_write(Path::new(&file_path), content.as_bytes()).await?;
             println!("Poetry dependencies frozen to {}", file_path.cyan());
         }
         None => {
@@ -124,7 +126,7 @@ async fn execute_pipenv_format(output_file: Option<String>) -> Result<()> {
     }
 
     let config = CobraConfig::load(config_path).await?;
-    let install_dir = std::env::current_dir()?.join(config.get_install_dir());
+    let install_dir = config.install_dir()?;
     let package_manager = Arc::new(LocalPackageManager::new(install_dir));
     let installed_packages = package_manager.list_installed().await?;
     
@@ -153,7 +155,7 @@ async fn execute_pipenv_format(output_file: Option<String>) -> Result<()> {
     
     match output_file {
         Some(file_path) => {
-            fs::write(&file_path, &content).await?;
+            atomic_write(Path::new(&file_path), content.as_bytes()).await?;
             println!("Pipenv dependencies frozen to {}", file_path.cyan());
         }
         None => {
@@ -0,0 +1,39 @@
+use crate::{Result, CobraError};
+use crate::core::{config::CobraConfig, requirements::parse_requirements_file};
+use colored::Colorize;
+use std::path::Path;
+
+pub async fn execute(requirements: String, dev: bool) -> Result<()> {
+    let config_path = Path::new("cobra.toml");
+    if !config_path.exists() {
+        return Err(CobraError::Config(
+            "cobra.toml not found. Run 'cobra init' first.".to_string()
+        ));
+    }
+
+    let dependencies = parse_requirements_file(Path::new(&requirements)).await?;
+    if dependencies.is_empty() {
+        println!("No requirements found in {}", requirements.cyan());
+        return Ok(());
+    }
+
+    let mut config = CobraConfig::load(config_path).await?;
+    for dep in &dependencies {
+        if dev {
+            config.add_dev_dependency(&dep.name, &dep.version_spec);
+        } else {
+            config.add_dependency(&dep.name, &dep.version_spec);
+        }
+        println!("{} Imported {} {}", "✓".green(), dep.name.cyan(), dep.version_spec.dimmed());
+    }
+    config.save(config_path).await?;
+
+    println!("\n{} Imported {} requirements from {}",
+        "✓".green().bold(),
+        dependencies.len(),
+        requirements.cyan()
+    );
+    println!("{} Run {} to install them", "💡".bright_yellow(), "cobra install".cyan());
+
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use crate::{Result, CobraError};
+use crate::core::config::CobraConfig;
+use crate::core::index::LocalIndex;
+use colored::Colorize;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Mirror the version lists and requirements of `packages` into the local index
+pub async fn sync(packages: Vec<String>) -> Result<()> {
+    if packages.is_empty() {
+        return Err(CobraError::Config("No packages specified".to_string()));
+    }
+
+    // Respect the project's request limit and timeouts when run inside one
+    let config_path = Path::new("cobra.toml");
+    let config = if config_path.exists() {
+        CobraConfig::load(config_path).await?
+    } else {
+        CobraConfig::default()
+    };
+
+    let index = LocalIndex::open_default()?;
+    let client = config.registry_client();
+    let parallel = config.settings()?.parallel_downloads;
+    for name in &packages {
+        println!("{} Indexing {}...", "🔍".bright_blue(), name.cyan());
+        let releases = index.sync(&client, name, parallel).await?;
+        println!("{} Indexed {} releases of {}", "✓".green(), releases, name.cyan());
+    }
+    Ok(())
+}
+
+/// The local index, if any package was ever synced into it
+pub fn open_local_index() -> Result<Option<Arc<LocalIndex>>> {
+    let index = LocalIndex::open_default()?;
+    Ok(index.exists().then(|| Arc::new(index)))
+}
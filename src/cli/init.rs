@@ -18,8 +18,10 @@ description = "A Python project managed by Cobra"
 [tool.cobra]
 python-version = "3.11"
 parallel-downloads = 16
+parallel-installs = 16
 cache-enabled = true
 install-dir = ".cobra_packages"  # Local package directory
+resolve-timeout-secs = 120  # Give up on a slow index after this long
 "#;
 
 pub async fn execute(path: &str) -> Result<()> {
@@ -1,65 +1,667 @@
-use crate::{Result, CobraError};
-use crate::core::{config::CobraConfig, installer::Installer, resolver::DependencyResolver, cache::MultiLevelCache, package_manager::LocalPackageManager};
-use crate::registry::client::RegistryClient;
+use crate::{Result, CobraError, Dependency, DependencySource, Package, normalize_name};
+use crate::core::build::{self, BuildFrontend, WheelBuilder};
+use crate::core::record::DirectUrl;
+use crate::core::vcs;
+use crate::core::config::HooksConfig;
+use crate::core::hooks::{run_hooks, HookContext, HookStage};
+use crate::core::imports::{top_level_modules, verify_imports, ImportCheck};
+use crate::core::markers::MarkerEnvironment;
+use crate::core::python::PythonEnvironment;
+use crate::utils::fs::{atomic_write, ensure_writable, get_cache_dir, get_download_dir};
+use crate::core::{config::{CobraConfig, DependencySelection}, installer::{InstallReport, Installer, format_bytes}, resolver::{DependencyResolver, ResolutionStrategy}, cache::MultiLevelCache, layout::InstallLayout, package_manager::LocalPackageManager, store::WheelStore, wheel_cache::WheelCache, find_links::FindLinks};
+use crate::core::lockfile::{self, Lockfile, LOCKFILE_NAME};
+use crate::core::requirements::{parse_requirement_args, parse_requirements_file};
+use crate::cli::status::lockfile_drift;
+use crate::cli::update::VersionDiff;
+use crate::core::package_manager::InstalledPackage;
+use crate::core::version::satisfies;
 use crate::utils::progress::ProgressTracker;
+use crate::cli::index::open_local_index;
+use chrono::{DateTime, Utc};
 use colored::Colorize;
-use std::path::Path;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
 
-pub async fn execute(no_cache: bool) -> Result<()> {
+/// Flags accepted by `cobra install`
+#[derive(Debug, Clone, Default)]
+pub struct InstallOptions {
+    pub no_cache: bool,
+    pub exclude_newer: Option<DateTime<Utc>>,
+    pub resolution: ResolutionStrategy,
+    pub frozen: bool,
+    pub dependencies: DependencySelection,
+    pub offline: bool,
+    pub no_verify: bool,
+    /// Overrides `max-download-rate` from cobra.toml
+    pub max_rate: Option<u64>,
+    /// Packages to reinstall even if already installed; an empty list means all
+    pub force_reinstall: Option<Vec<String>>,
+    /// Packages to re-resolve from fresh metadata and reinstall, ignoring
+    /// cobra.lock; an empty list means all
+    pub upgrade: Option<Vec<String>>,
+    /// Vendor packages into this directory instead of the project's install directory
+    pub target: Option<PathBuf>,
+    /// Skip the check for enough free disk space before downloading
+    pub no_space_check: bool,
+    /// Skip `[tool.cobra.hooks]`
+    pub no_hooks: bool,
+    /// Overrides `verify-imports` from cobra.toml
+    pub verify_imports: Option<ImportCheck>,
+    /// Directories of wheels the resolver picks from before any index, and
+    /// of archives named as on the index, used before downloading
+    pub find_links: Vec<PathBuf>,
+    /// Resolve only from `find_links` and wheel files named in `packages`
+    pub no_index: bool,
+    /// Uninstall this run's packages again if any package fails
+    pub atomic: bool,
+    /// Requirements such as `requests==2.31.0`, or paths of wheel files, to
+    /// install instead of cobra.toml's
+    pub packages: Vec<String>,
+    /// pip requirements files to install instead of cobra.toml's dependencies
+    pub requirements: Vec<PathBuf>,
+    /// Also add `packages` and `requirements` to cobra.toml's dependencies
+    /// once they are installed, when there is a cobra.toml
+    pub save: bool,
+    /// Write an [`InstallReport`] as JSON here; `-` prints it to stdout alone
+    pub report: Option<PathBuf>,
+    /// Print the per-package timing table, not just the totals
+    pub timings: bool,
+    /// Fired on Ctrl-C to stop the install, keeping only finished packages
+    pub cancel: CancellationToken,
+}
+
+pub async fn execute(options: InstallOptions) -> Result<()> {
     let start = Instant::now();
     
+    // `--report -` owns stdout, so every other line is dropped
+    let report_to_stdout = options.report.as_ref().is_some_and(|path| path.as_os_str() == "-");
+    let progress = Arc::new(if report_to_stdout { ProgressTracker::hidden() } else { ProgressTracker::new() });
+    
+    // Packages named on the command line or in requirements files replace
+    // cobra.toml's dependencies, so they don't need one
+    let ad_hoc = !options.packages.is_empty() || !options.requirements.is_empty();
+    if options.save && !ad_hoc {
+        return Err(CobraError::InvalidInput(
+            "--save needs packages or a requirements file to add to cobra.toml".to_string()
+        ));
+    }
+    
     // Load configuration
     let config_path = Path::new("cobra.toml");
-    if !config_path.exists() {
+    let config = if config_path.exists() {
+        progress.println(&format!("{} Loading configuration...", "⚡".bright_yellow()));
+        CobraConfig::load(config_path).await?
+    } else if ad_hoc {
+        CobraConfig::default()
+    } else {
         return Err(CobraError::Config(
-            "cobra.toml not found. Run 'cobra init' first.".to_string()
+            "cobra.toml not found. Run 'cobra init' first, or name packages or a requirements file with -r".to_string()
         ));
+    };
+    let settings = config.settings()?;
+    let save_to = options.save.then_some(config_path).filter(|path| path.exists());
+    if options.save && save_to.is_none() {
+        progress.println(&format!("{} No cobra.toml here, so --save has nothing to update", "⚠".yellow()));
     }
     
-    println!("{} Loading configuration...", "⚡".bright_yellow());
-    let config = CobraConfig::load(config_path).await?;
+    // Wheel files named on the command line are offered to the resolver
+    // alongside the --find-links directories and pinned to their version
+    let mut find_links = FindLinks::scan(&options.find_links)?;
+    let mut packages = Vec::new();
+    for arg in &options.packages {
+        if arg.ends_with(".whl") {
+            let wheel = find_links.add_file(Path::new(arg))?;
+            packages.push(format!("{}=={}", wheel.filename.name, wheel.filename.version));
+        } else {
+            packages.push(arg.clone());
+        }
+    }
+    if !find_links.is_empty() {
+        progress.println(&format!("{} Found {} local wheels", "✓".green(), find_links.wheels().len()));
+    }
+    if options.no_index && options.find_links.is_empty() && find_links.is_empty() {
+        return Err(CobraError::InvalidInput(
+            "--no-index needs --find-links directories or wheel files to install from".to_string()
+        ));
+    }
     
-    println!("{} Found {} dependencies", "✓".green(), config.dependencies.len());
+    let (mut dependencies_list, source_dependencies) = if ad_hoc {
+        let dependencies = requested_dependencies(&packages, &options.requirements).await?;
+        progress.println(&format!("{} Found {} requirements", "✓".green(), dependencies.len()));
+        (dependencies, Vec::new())
+    } else {
+        let dependencies = config.registry_dependencies(options.dependencies);
+        match options.dependencies {
+            DependencySelection::Main => {
+                progress.println(&format!("{} Found {} dependencies", "✓".green(), dependencies.len()));
+            }
+            DependencySelection::WithDev => {
+                // Dev entries also listed as main dependencies are only counted once
+                let dev = dependencies.len().saturating_sub(config.registry_dependencies(DependencySelection::Main).len());
+                progress.println(&format!("{} Found {} dependencies (including {} dev)", 
+                    "✓".green(), 
+                    dependencies.len(),
+                    dev
+                ));
+            }
+            DependencySelection::OnlyDev => {
+                progress.println(&format!("{} Found {} dev dependencies", "✓".green(), dependencies.len()));
+            }
+        }
+        (dependencies, config.source_dependencies(options.dependencies))
+    };
     
     // Initialize components
-    let cache = if no_cache {
+    // A frozen install must reproduce the lockfile exactly
+    let lock_path = Path::new(LOCKFILE_NAME);
+    if ad_hoc && options.frozen {
+        return Err(CobraError::Config(format!(
+            "--frozen installs {}; it can't be combined with packages or requirements files", LOCKFILE_NAME
+        )));
+    }
+    let frozen_lock = if options.frozen {
+        if !lock_path.exists() {
+            return Err(CobraError::Config(
+                format!("{} not found. Run 'cobra install' without --frozen first.", LOCKFILE_NAME)
+            ));
+        }
+        let lockfile = Lockfile::load(lock_path).await?;
+        if lockfile.resolution != options.resolution {
+            return Err(CobraError::Config(format!(
+                "{} was produced with --resolution {} but --resolution {} was requested",
+                LOCKFILE_NAME, lockfile.resolution, options.resolution
+            )));
+        }
+        Some(lockfile)
+    } else {
         None
+    };
+    
+    if options.offline && options.no_cache {
+        return Err(CobraError::Config("--offline needs the package cache; drop --no-cache".to_string()));
+    }
+    if options.no_verify {
+        progress.println(&format!("{} Skipping sha256 verification of package archives", "⚠".yellow()));
+    }
+    let max_rate = options.max_rate.or(config.tool.cobra.max_download_rate);
+    if let Some(rate) = max_rate {
+        progress.println(&format!("{} Limiting downloads to {}/s", "🐢".bright_blue(), format_bytes(rate)));
+    }
+    if options.offline {
+        progress.println(&format!("{} Offline mode: using cached metadata and packages only", "✈".bright_blue()));
+    }
+    
+    let package_manager = Arc::new(package_manager_for(&config, options.target.as_deref())?.with_quiet(report_to_stdout));
+    ensure_writable(package_manager.layout().base_dir())?;
+    if options.target.is_some() {
+        progress.println(&format!("{} Installing into {}", "📁".bright_blue(), package_manager.layout().base_dir().display()));
+    }
+    
+    // A current lock that is fully installed leaves nothing to resolve or
+    // download, so the network isn't touched at all
+    let overrides = config.get_overrides()?;
+    let environment = MarkerEnvironment::detect(&config.tool.cobra.python_version).await;
+    let plain = !ad_hoc && source_dependencies.is_empty() && options.report.is_none()
+        && options.force_reinstall.is_none() && options.upgrade.is_none();
+    if plain && lock_path.exists() {
+        let lockfile = match &frozen_lock {
+            Some(lockfile) => lockfile.clone(),
+            None => Lockfile::load(lock_path).await?,
+        };
+        let input_hash = lockfile::input_hash(&dependencies_list, &overrides, options.resolution, options.exclude_newer.as_ref(), environment.python().as_ref());
+        let installed = package_manager.list_present().await?;
+        if already_up_to_date(&lockfile, &input_hash, &dependencies_list, &installed, &environment) {
+            progress.println(&format!("{} Already up to date ({:.2}ms)", "✓".green(), start.elapsed().as_secs_f64() * 1000.0));
+            return Ok(());
+        }
+    }
+    
+    let (cache, wheels, store) = if options.no_cache {
+        (None, None, None)
     } else {
-        Some(Arc::new(MultiLevelCache::new().await?))
+        (
+            Some(Arc::new(MultiLevelCache::new().await?)),
+            Some(Arc::new(WheelCache::open_default()?)),
+            Some(Arc::new(WheelStore::open_default()?)),
+        )
     };
     
-    let client = Arc::new(RegistryClient::new());
-    let progress = Arc::new(ProgressTracker::new());
+    let client = Arc::new(config.registry_client());
     
-    // Initialize package manager with install directory from config
-    let install_dir = std::env::current_dir()?.join(config.get_install_dir());
-    let package_manager = Arc::new(LocalPackageManager::new(install_dir));
+    let hooks = if options.no_hooks { HooksConfig::default() } else { config.tool.cobra.hooks.clone() };
+    let mut hook_context = HookContext {
+        install_dir: package_manager.layout().base_dir().to_path_buf(),
+        packages: Vec::new(),
+    };
+    run_hooks(HookStage::PreInstall, &hooks.pre_install, &hook_context, &progress).await?;
     
     // Resolve dependencies
-    println!("{} Resolving dependency graph...", "🔍".bright_blue());
-    let resolver = DependencyResolver::new(client.clone(), cache.clone());
-    let dependencies_list = config.get_dependencies_list();
-    let resolved = resolver.resolve(&dependencies_list).await?;
+    progress.println(&format!("{} Resolving dependency graph...", "🔍".bright_blue()));
+    if let Some(cutoff) = &options.exclude_newer {
+        progress.println(&format!("{} Excluding releases uploaded after {}", "⏱".bright_blue(), cutoff.to_rfc3339()));
+    }
+    let mut resolver = DependencyResolver::new(client.clone(), cache.clone())
+        .with_local_index(open_local_index()?)
+        .with_registries(config.private_registries())
+        .with_exclude_newer(options.exclude_newer)
+        .with_python_version(environment.python())
+        .with_strategy(options.resolution)
+        .with_overrides(overrides.clone())
+        .with_offline(options.offline)
+        .with_find_links(Some(Arc::new(find_links)))
+        .with_no_index(options.no_index)
+        .with_timeout(Some(config.resolve_timeout()))
+        .with_metadata_ttl(if options.upgrade.is_some() { Duration::ZERO } else { config.metadata_cache_ttl() })
+        .with_settings(&settings);
+    if let Some(lockfile) = &frozen_lock {
+        resolver = resolver.with_pins(lockfile.pins());
+    }
+
+    // Local and git projects are built first so their own requirements get resolved
+    let mut local_builds = build_source_dependencies(&source_dependencies, &package_manager, config.tool.cobra.build_frontend, &progress).await?;
+    for build in &local_builds {
+        dependencies_list.extend(build.package.dependencies.iter().cloned());
+    }
+    let input_hash = lockfile::input_hash(
+        &dependencies_list,
+        &overrides,
+        options.resolution,
+        options.exclude_newer.as_ref(),
+        environment.python().as_ref(),
+    );
+    
+    // A lock resolved from these exact inputs is installed as-is; one-off
+    // requirements neither use nor replace the project's lock
+    let existing_lock = match frozen_lock {
+        Some(lockfile) => Some(lockfile),
+        None if lock_path.exists() && !ad_hoc => Lockfile::load(lock_path).await.ok(),
+        None => None,
+    };
+    // Upgrading named packages keeps every other package at its locked version
+    if let Some(names) = options.upgrade.as_ref().filter(|names| !names.is_empty())
+        && let Some(lockfile) = &existing_lock {
+        let upgraded: HashSet<String> = names.iter().map(|name| normalize_name(name)).collect();
+        resolver = resolver.with_pins(lockfile.pins().into_iter()
+            .filter(|(name, _)| !upgraded.contains(&normalize_name(name)))
+            .collect());
+    }
+    let locked_packages = existing_lock
+        .as_ref()
+        .filter(|_| options.upgrade.is_none())
+        .and_then(|lockfile| lockfile.installable_packages(&input_hash));
+    let up_to_date = locked_packages.is_some();
+    let resolved = match locked_packages {
+        Some(packages) => {
+            progress.println(&format!("{} {} is up to date, skipping resolution", "🔒".bright_blue(), LOCKFILE_NAME));
+            packages
+        }
+        None => cancellable(resolver.resolve(&dependencies_list), &options.cancel).await?,
+    };
+    
+    for skipped in resolver.skipped_dependencies() {
+        progress.println(&format!("{} Skipping optional {}: {}", "⚠".yellow(), skipped.package.cyan(), skipped.reason));
+    }
+    for package in resolved.iter().filter(|p| p.yanked) {
+        progress.println(&format!("{} Installing yanked release {} {}: {}",
+            "⚠".yellow(),
+            package.name.cyan(),
+            package.version.red(),
+            package.yanked_reason.as_deref().unwrap_or("no reason given")
+        ));
+    }
+    
+    let overridden = resolver.overridden_requirements();
+    if !overridden.is_empty() {
+        progress.println(&format!("{} Overrides replaced {} requirements:", "⚠".yellow(), overridden.len()));
+        for requirement in &overridden {
+            progress.println(&format!("  {} {} {} -> {} (required by {})",
+                "•".yellow(),
+                requirement.package.cyan(),
+                requirement.requested.red(),
+                requirement.forced.green(),
+                requirement.parent.as_deref().unwrap_or("cobra.toml")
+            ));
+        }
+    }
+    
+    // Show how a fresh resolution moves away from the previous lock
+    if let Some(previous) = existing_lock.as_ref().filter(|_| !up_to_date) {
+        let diff = VersionDiff::between(
+            previous.packages.iter().map(|p| (p.name.as_str(), p.version.as_str())),
+            resolved.iter().map(|p| (p.name.as_str(), p.version.as_str())),
+        );
+        if diff.has_changes() {
+            progress.println(&format!("{} Versions changed from {}:", "📋".bright_blue(), LOCKFILE_NAME));
+            diff.print();
+        }
+    }
+    
+    if !options.frozen && !up_to_date && !ad_hoc {
+        Lockfile::from_packages(&resolved, options.resolution)
+            .with_overrides(&overrides)
+            .with_input_hash(input_hash)
+            .save(lock_path)
+            .await?;
+        progress.println(&format!("{} Wrote {}", "🔒".bright_blue(), LOCKFILE_NAME));
+    }
+    
+    // The lock covers every platform; install only what applies to this one
+    let total = resolved.len();
+    let resolved: Vec<Package> = resolved.into_iter()
+        .filter(|p| environment.includes(p.markers.as_deref()))
+        .collect();
+    if resolved.len() < total {
+        progress.println(&format!("{} Skipping {} packages whose markers do not match this platform",
+            "⏭".bright_blue(),
+            total - resolved.len()
+        ));
+    }
+    
+    // Forced and upgraded packages are replaced even at their installed version
+    let candidates: Vec<&Package> = resolved.iter().chain(local_builds.iter().map(|b| &b.package)).collect();
+    let mut reinstall = HashSet::new();
+    for requested in [&options.force_reinstall, &options.upgrade].into_iter().flatten() {
+        reinstall.extend(select_reinstalls(requested, &candidates)?);
+    }
+    for build in &mut local_builds {
+        if reinstall.contains(&normalize_name(&build.package.name)) {
+            build.up_to_date = false;
+        }
+    }
     
     let resolve_time = start.elapsed();
-    println!("{} Resolved {} packages in {:.2}ms", 
+    progress.println(&format!("{} Resolved {} packages in {:.2}ms", 
         "✓".green(), 
         resolved.len(),
         resolve_time.as_secs_f64() * 1000.0
-    );
+    ));
     
     // Install packages in parallel
-    println!("{} Installing packages...", "📦".bright_blue());
-    let installer = Installer::new(client, cache, progress.clone(), package_manager);
-    installer.install_parallel(resolved).await?;
+    progress.println(&format!("{} Installing packages...", "📦".bright_blue()));
+    let installer = Installer::new(client, wheels, progress.clone(), package_manager.clone())
+        .with_offline(options.offline || options.no_index)
+        .with_find_links(options.find_links.clone())
+        .with_verify(!options.no_verify)
+        .with_settings(&settings)
+        .with_max_download_rate(max_rate)
+        .with_download_dir(get_download_dir()?)
+        .with_build_frontend(config.tool.cobra.build_frontend)
+        .with_post_install_checks(config.verify_commands())
+        .with_reinstall(reinstall)
+        .with_store(store)
+        .with_space_check(!options.no_space_check)
+        .with_atomic(options.atomic)
+        .with_timings(options.timings)
+        .with_cancellation(options.cancel.clone());
+    let resolved_names: Vec<String> = resolved.iter().map(|p| format!("{}=={}", p.name, p.version)).collect();
+    let stats = installer.install_parallel(resolved).await?;
+    if stats.cached_packages + stats.downloaded_packages > 0 {
+        progress.println(&format!("{} {}", "💾".bright_blue(), stats.summary()));
+        progress.println(&format!("{} Installed size: {}", "💽".bright_blue(), format_bytes(stats.installed_bytes)));
+    }
+    for build in &local_builds {
+        if options.cancel.is_cancelled() {
+            return Err(CobraError::Cancelled);
+        }
+        if build.up_to_date {
+            progress.println(&format!("⏭️  Skipping {} {} (source unchanged)", build.package.name, build.package.version));
+            continue;
+        }
+        installer.install_built_wheel(&build.wheel, &build.package, &build.source_hash, &build.origin).await?;
+        progress.println(&format!("{} Installed {} {} ({})", "✓".green(), build.package.name.cyan(), build.package.version, build.source));
+    }
+    
+    let import_check = options.verify_imports.unwrap_or(config.tool.cobra.verify_imports);
+    if import_check != ImportCheck::Off {
+        let installed: HashSet<String> = resolved_names.iter()
+            .filter_map(|pin| pin.split_once("==").map(|(name, _)| normalize_name(name)))
+            .chain(local_builds.iter().map(|b| normalize_name(&b.package.name)))
+            .collect();
+        verify_package_imports(&package_manager, &installed, import_check, settings.parallel_installs, &progress).await?;
+    }
+    
+    hook_context.packages = resolved_names.into_iter()
+        .chain(local_builds.iter().map(|b| format!("{}=={}", b.package.name, b.package.version)))
+        .collect();
+    run_hooks(HookStage::PostInstall, &hooks.post_install, &hook_context, &progress).await?;
+    
+    if let Some(path) = &options.report {
+        let hit_rate = match &cache {
+            Some(cache) => cache.hit_rate().await,
+            None => 0.0,
+        };
+        let mut report = serde_json::to_string_pretty(&InstallReport::new(&stats, hit_rate))
+            .map_err(|e| CobraError::Config(format!("Failed to serialize install report: {}", e)))?;
+        report.push('\n');
+        if report_to_stdout {
+            std::io::Write::write_all(&mut std::io::stdout(), report.as_bytes())?;
+        } else {
+            atomic_write(path, report.as_bytes()).await?;
+            progress.println(&format!("{} Wrote install report to {}", "📝".bright_blue(), path.display()));
+        }
+    }
+    
+    if let Some(path) = save_to {
+        let mut saved = config.clone();
+        for dependency in &dependencies_list {
+            let spec = saved_spec(dependency, &hook_context.packages);
+            saved.add_dependency(&dependency.name, &spec);
+            progress.println(&format!("{} Added {} {} to cobra.toml", "✓".green(), dependency.name.cyan(), spec.dimmed()));
+        }
+        saved.save(path).await?;
+    }
     
     let total_time = start.elapsed();
-    println!("\n{} Installation complete in {:.2}s", 
+    progress.println(&format!("\n{} Installation complete in {:.2}s", 
         "✓".green().bold(),
         total_time.as_secs_f64()
-    );
+    ));
     
     Ok(())
 }
+
+/// The cobra.toml spec `--save` writes for `dependency`: its own, or for a
+/// bare name a caret range on the version among `installed` `name==version`
+/// pins, as `cobra add` writes
+pub fn saved_spec(dependency: &Dependency, installed: &[String]) -> String {
+    if dependency.version_spec != "*" {
+        return dependency.version_spec.clone();
+    }
+    installed.iter()
+        .filter_map(|pin| pin.split_once("=="))
+        .find(|(name, _)| normalize_name(name) == normalize_name(&dependency.name))
+        .map_or_else(|| "*".to_string(), |(_, version)| format!("^{}", version))
+}
+
+/// The requirements named as arguments followed by those of each
+/// requirements file, in order
+async fn requested_dependencies(packages: &[String], files: &[PathBuf]) -> Result<Vec<Dependency>> {
+    let mut dependencies = parse_requirement_args(packages)?;
+    for file in files {
+        dependencies.extend(parse_requirements_file(file).await?);
+    }
+    if dependencies.is_empty() {
+        return Err(CobraError::Config("No requirements found to install".to_string()));
+    }
+    Ok(dependencies)
+}
+
+/// Import the top-level modules of the `installed` packages, failing the
+/// install on a broken import unless `check` only warns
+async fn verify_package_imports(package_manager: &LocalPackageManager, installed: &HashSet<String>, check: ImportCheck, limit: usize, progress: &ProgressTracker) -> Result<()> {
+    let layout = package_manager.layout();
+    let targets: Vec<(String, String)> = package_manager.list_installed().await?
+        .into_iter()
+        .filter(|p| installed.contains(&normalize_name(&p.name)))
+        .flat_map(|p| {
+            let modules = top_level_modules(&p.files, &layout.site_packages(&p.name));
+            modules.into_iter().map(move |module| (p.name.clone(), module))
+        })
+        .collect();
+    progress.println(&format!("{} Verifying {} imports...", "🔎".bright_blue(), targets.len()));
+
+    let python = PythonEnvironment::detect().await?.python_path;
+    let python_path = package_manager.import_path().await?;
+    let failures = verify_imports(&python, &python_path, &targets, limit).await;
+    if failures.is_empty() {
+        progress.println(&format!("{} All imports succeeded", "✓".green()));
+        return Ok(());
+    }
+
+    let marker = if check == ImportCheck::Warn { "⚠".yellow() } else { "✗".red() };
+    for failure in &failures {
+        progress.println(&format!("  {} {}: import {} failed", marker, failure.package.cyan(), failure.module));
+        for line in failure.traceback.lines() {
+            progress.println(&format!("    {}", line.dimmed()));
+        }
+    }
+    if check == ImportCheck::Warn {
+        return Ok(());
+    }
+    Err(CobraError::InstallationFailed(format!(
+        "{} of {} imports failed; pass --verify-imports=warn to install anyway", failures.len(), targets.len()
+    )))
+}
+
+/// Whether an install would change nothing: `lockfile` was resolved from
+/// `input_hash`, everything it locks for this platform is installed at the
+/// locked version, and so is every requirement in `dependencies` that applies
+/// here at a version its spec accepts
+pub fn already_up_to_date(
+    lockfile: &Lockfile,
+    input_hash: &str,
+    dependencies: &[Dependency],
+    installed: &[InstalledPackage],
+    environment: &MarkerEnvironment,
+) -> bool {
+    if lockfile.installable_packages(input_hash).is_none() {
+        return false;
+    }
+    let drift = lockfile_drift(lockfile, installed, environment);
+    if !drift.install.is_empty() || !drift.reinstall.is_empty() {
+        return false;
+    }
+    dependencies.iter()
+        .filter(|dependency| environment.includes(dependency.markers.as_deref()))
+        .all(|dependency| installed.iter().any(|package| {
+            normalize_name(&package.name) == normalize_name(&dependency.name)
+                && satisfies(&package.version, &dependency.version_spec)
+        }))
+}
+
+/// The package manager for `--target <dir>`, relative to the current
+/// directory, or for the project's install directory from cobra.toml
+pub fn package_manager_for(config: &CobraConfig, target: Option<&Path>) -> Result<LocalPackageManager> {
+    Ok(match target {
+        Some(target) => LocalPackageManager::with_layout(InstallLayout::target(std::env::current_dir()?.join(target))),
+        None => LocalPackageManager::new(config.install_dir()?),
+    })
+}
+
+/// Normalized names of the packages `--force-reinstall` or `--upgrade`
+/// applies to; an empty `requested` list selects every candidate
+pub fn select_reinstalls(requested: &[String], candidates: &[&Package]) -> Result<HashSet<String>> {
+    let available: HashSet<String> = candidates.iter().map(|p| normalize_name(&p.name)).collect();
+    if requested.is_empty() {
+        return Ok(available);
+    }
+    let mut selected = HashSet::new();
+    for name in requested {
+        let key = normalize_name(name);
+        if !available.contains(&key) {
+            return Err(CobraError::InvalidInput(format!(
+                "Cannot reinstall {}: it is not part of the resolved dependencies", name
+            )));
+        }
+        selected.insert(key);
+    }
+    Ok(selected)
+}
+
+/// Run `work` until it completes or `cancel` fires. Dropping it cancels
+/// in-flight requests; only use it for work that leaves nothing on disk.
+async fn cancellable<T>(work: impl Future<Output = Result<T>>, cancel: &CancellationToken) -> Result<T> {
+    tokio::select! {
+        result = work => result,
+        _ = cancel.cancelled() => Err(CobraError::Cancelled),
+    }
+}
+
+/// A path or git dependency and the wheel built from its current sources
+struct LocalBuild {
+    source: DependencySource,
+    /// Recorded in the installed `direct_url.json`
+    origin: DirectUrl,
+    source_hash: String,
+    wheel: PathBuf,
+    package: Package,
+    /// The installed copy was built from identical sources
+    up_to_date: bool,
+}
+
+/// Build each path or git dependency into a wheel, reusing the wheel cached
+/// for an unchanged source hash. Git repositories are cloned fresh each time.
+async fn build_source_dependencies(
+    dependencies: &[Dependency],
+    package_manager: &LocalPackageManager,
+    frontend: BuildFrontend,
+    progress: &ProgressTracker,
+) -> Result<Vec<LocalBuild>> {
+    if dependencies.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let installed = package_manager.list_installed().await?;
+    let builder = WheelBuilder::new(PythonEnvironment::detect().await?.python_path).with_frontend(frontend);
+    let builds_dir = get_cache_dir()?.join("builds");
+
+    let mut builds = Vec::new();
+    for dependency in dependencies {
+        let name = &dependency.name;
+        let (dir, origin, _checkout) = match &dependency.source {
+            DependencySource::Path { path } => {
+                let dir = PathBuf::from(path);
+                let origin = DirectUrl::Dir(std::path::absolute(&dir)?);
+                (dir, origin, None)
+            }
+            DependencySource::Git { git, rev } => {
+                progress.println(&format!("{} Cloning {} from {}", "📥".bright_blue(), name.cyan(), git));
+                let checkout = tempfile::tempdir()?;
+                vcs::checkout(git, rev.as_deref(), checkout.path()).await?;
+                let origin = DirectUrl::Git {
+                    url: git.clone(),
+                    requested_revision: rev.clone(),
+                    commit_id: vcs::head_commit(checkout.path()).await?,
+                };
+                (checkout.path().to_path_buf(), origin, Some(checkout))
+            }
+            DependencySource::Registry => continue,
+        };
+        let source_hash = build::source_hash(&dir)?;
+        let out_dir = builds_dir.join(format!("{}-{}", normalize_name(name), &source_hash[..16]));
+
+        let cached = std::fs::read_dir(&out_dir).ok().and_then(|entries| {
+            entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .find(|p| p.extension().is_some_and(|ext| ext == "whl"))
+        });
+        let wheel = match cached {
+            Some(wheel) => wheel,
+            None => {
+                progress.println(&format!("{} Building {} from {}", "🔨".bright_blue(), name.cyan(), dir.display()));
+                builder.build(&dir, &out_dir).await?
+            }
+        };
+
+        let package = build::wheel_metadata(&wheel)?.to_package(&wheel);
+        let up_to_date = installed.iter().any(|p| {
+            normalize_name(&p.name) == normalize_name(&package.name)
+                && p.source_hash.as_deref() == Some(source_hash.as_str())
+        });
+        builds.push(LocalBuild { source: dependency.source.clone(), origin, source_hash, wheel, package, up_to_date });
+    }
+    Ok(builds)
+}
@@ -1,10 +1,12 @@
 use crate::{Result, CobraError};
-use crate::core::{config::CobraConfig, package_manager::LocalPackageManager};
+use crate::cli::install::package_manager_for;
+use crate::core::{config::CobraConfig, installer::format_bytes};
 use colored::Colorize;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-pub async fn execute() -> Result<()> {
+/// List the packages of the project, or of a `--target` directory
+pub async fn execute(target: Option<PathBuf>) -> Result<()> {
     let config_path = Path::new("cobra.toml");
     if !config_path.exists() {
         return Err(CobraError::Config(
@@ -14,9 +16,7 @@ pub async fn execute() -> Result<()> {
 
     let config = CobraConfig::load(config_path).await?;
     
-    // Initialize package manager
-    let install_dir = std::env::current_dir()?.join(config.get_install_dir());
-    let package_manager = Arc::new(LocalPackageManager::new(install_dir));
+    let package_manager = Arc::new(package_manager_for(&config, target.as_deref())?);
     
     // Get installed packages
     let installed_packages = package_manager.list_installed().await?;
@@ -34,16 +34,19 @@ pub async fn execute() -> Result<()> {
         let name_colored = package.name.cyan();
         let version_colored = package.version.green();
         let install_time = package.installed_at.format("%Y-%m-%d %H:%M:%S");
+        let size = package.size_on_disk.map(format_bytes).unwrap_or_else(|| "size unknown".to_string());
         
-        println!("{} {} (installed: {})", 
+        println!("{} {} {} (installed: {})", 
             name_colored, 
             version_colored,
+            size.yellow(),
             install_time.to_string().dimmed()
         );
     }
     
+    let total_size: u64 = installed_packages.iter().filter_map(|p| p.size_on_disk).sum();
     println!("{}", "─".repeat(50));
-    println!("Total: {} packages", installed_packages.len().to_string().bold());
+    println!("Total: {} packages, {}", installed_packages.len().to_string().bold(), format_bytes(total_size).bold());
     
     Ok(())
 }
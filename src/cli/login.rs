@@ -0,0 +1,28 @@
+use crate::{Result, CobraError};
+use crate::utils::credentials;
+use colored::Colorize;
+use std::io::{BufRead, IsTerminal};
+
+/// Prompt for a token and save it in the OS credential store for `registry_url`
+pub async fn execute(registry_url: String) -> Result<()> {
+    let token = read_token(&registry_url)?;
+    if token.is_empty() {
+        return Err(CobraError::InvalidInput("No token entered".to_string()));
+    }
+
+    credentials::store_token(&registry_url, &token)?;
+    println!("{} Saved token for {}", "✓".green(), registry_url.cyan());
+    Ok(())
+}
+
+/// Read without echo from a terminal, or one line from piped stdin
+fn read_token(registry_url: &str) -> Result<String> {
+    let token = if std::io::stdin().is_terminal() {
+        rpassword::prompt_password(format!("Token for {}: ", registry_url))?
+    } else {
+        let mut line = String::new();
+        std::io::stdin().lock().read_line(&mut line)?;
+        line
+    };
+    Ok(token.trim().to_string())
+}
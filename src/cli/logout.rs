@@ -0,0 +1,13 @@
+use crate::Result;
+use crate::utils::credentials;
+use colored::Colorize;
+
+/// Remove the token saved for `registry_url` from the OS credential store
+pub async fn execute(registry_url: String) -> Result<()> {
+    if credentials::delete_token(&registry_url)? {
+        println!("{} Removed token for {}", "✓".green(), registry_url.cyan());
+    } else {
+        println!("{} No token saved for {}", "i".blue(), registry_url.cyan());
+    }
+    Ok(())
+}
@@ -1,11 +1,25 @@
 pub mod add;
+pub mod cache;
 pub mod check;
+pub mod clean;
+pub mod doctor;
+pub mod export;
 pub mod freeze;
+pub mod import;
+pub mod index;
 pub mod init;
 pub mod install;
 pub mod list;
+pub mod login;
+pub mod logout;
 pub mod remove;
+pub mod run;
 pub mod search;
 pub mod show;
+pub mod status;
+pub mod sync;
+pub mod tree;
 pub mod uninstall;
 pub mod update;
+pub mod verify;
+pub mod why;
@@ -0,0 +1,75 @@
+use crate::{Result, CobraError};
+use crate::core::config::CobraConfig;
+use crate::core::package_manager::LocalPackageManager;
+use crate::core::python::PythonEnvironment;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Run `command` with the project's packages importable and their scripts on
+/// `PATH`. Where the platform allows, the command replaces cobra, keeping its
+/// stdio, signals and exit code; elsewhere its exit code is returned.
+pub async fn execute(command: Vec<String>) -> Result<u8> {
+    let config_path = Path::new("cobra.toml");
+    if !config_path.exists() {
+        return Err(CobraError::Config(
+            "No cobra.toml found. Run 'cobra init' to create one.".to_string()
+        ));
+    }
+
+    let config = CobraConfig::load(config_path).await?;
+    let install_dir = config.install_dir()?;
+    let command = prepare(&command, &LocalPackageManager::new(install_dir)).await?;
+    run(command)
+}
+
+/// `args` as a command that sees the packages of `package_manager`; a bare
+/// `python` or `python3` runs the detected interpreter
+pub async fn prepare(args: &[String], package_manager: &LocalPackageManager) -> Result<Command> {
+    let (program, args) = args.split_first()
+        .ok_or_else(|| CobraError::InvalidInput("No command given".to_string()))?;
+    let program = match program.as_str() {
+        "python" | "python3" => PythonEnvironment::detect().await?.python_path.into_os_string(),
+        _ => OsString::from(program),
+    };
+
+    let layout = package_manager.layout();
+    let installed: Vec<String> = package_manager.list_installed().await?
+        .into_iter()
+        .map(|p| p.name)
+        .collect();
+    let mut command = Command::new(program);
+    command.args(args)
+        .env("PYTHONPATH", prepend_paths(layout.pth_entries(&installed), "PYTHONPATH")?);
+    if layout.scripts_dir().is_dir() {
+        command.env("PATH", prepend_paths(vec![layout.scripts_dir().to_path_buf()], "PATH")?);
+    }
+    Ok(command)
+}
+
+/// `paths` followed by the entries already in `variable`
+fn prepend_paths(mut paths: Vec<PathBuf>, variable: &str) -> Result<OsString> {
+    if let Some(existing) = std::env::var_os(variable) {
+        paths.extend(std::env::split_paths(&existing));
+    }
+    std::env::join_paths(paths)
+        .map_err(|e| CobraError::InvalidInput(format!("Invalid {}: {}", variable, e)))
+}
+
+fn not_started(command: &Command, e: std::io::Error) -> CobraError {
+    CobraError::InvalidInput(format!("Failed to run {}: {}", command.get_program().to_string_lossy(), e))
+}
+
+#[cfg(unix)]
+fn run(mut command: Command) -> Result<u8> {
+    use std::os::unix::process::CommandExt;
+    // Only returns if the command could not be started
+    let e = command.exec();
+    Err(not_started(&command, e))
+}
+
+#[cfg(not(unix))]
+fn run(mut command: Command) -> Result<u8> {
+    let status = command.status().map_err(|e| not_started(&command, e))?;
+    Ok(status.code().map_or(1, |code| code as u8))
+}
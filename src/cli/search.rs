@@ -4,24 +4,11 @@ use reqwest::Client;
 use serde::Deserialize;
 use std::time::Duration;
 
-#[derive(Debug, Deserialize)]
-struct SearchResponse {
-    info: SearchInfo,
-    results: Vec<SearchResult>,
-}
-
-#[derive(Debug, Deserialize)]
-struct SearchInfo {
-    count: u32,
-}
-
 #[derive(Debug, Deserialize)]
 struct SearchResult {
     name: String,
-    version: String,
     description: Option<String>,
     author: Option<String>,
-    author_email: Option<String>,
 }
 
 pub async fn execute(query: String, limit: Option<usize>) -> Result<()> {
@@ -35,7 +22,7 @@ pub async fn execute(query: String, limit: Option<usize>) -> Result<()> {
         .timeout(Duration::from_secs(30))
         .user_agent("cobra/1.0")
         .build()
-        .map_err(|e| CobraError::Network(e))?;
+        .map_err(CobraError::Network)?;
     
     // Use PyPI's JSON API for search
     let search_url = format!("https://pypi.org/search/?q={}&format=json", 
@@ -45,16 +32,16 @@ pub async fn execute(query: String, limit: Option<usize>) -> Result<()> {
         .get(&search_url)
         .send()
         .await
-        .map_err(|e| CobraError::Network(e))?;
+        .map_err(CobraError::Network)?;
     
     if !response.status().is_success() {
         return Err(CobraError::Network(
-            reqwest::Error::from(response.error_for_status().unwrap_err())
+            response.error_for_status().unwrap_err()
         ));
     }
     
     let search_text = response.text().await
-        .map_err(|e| CobraError::Network(e))?;
+        .map_err(CobraError::Network)?;
     
     // Parse HTML response (PyPI search doesn't have a proper JSON API)
     let results = parse_search_results(&search_text, &query)?;
@@ -135,10 +122,8 @@ fn parse_search_results(_html: &str, query: &str) -> Result<Vec<SearchResult>> {
            description.to_lowercase().contains(&query.to_lowercase()) {
             results.push(SearchResult {
                 name: name.to_string(),
-                version: "latest".to_string(),
                 description: Some(description.to_string()),
                 author: Some("Python Community".to_string()),
-                author_email: None,
             });
         }
     }
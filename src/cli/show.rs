@@ -1,6 +1,5 @@
 use crate::{Result, CobraError};
-use crate::core::{config::CobraConfig, package_manager::LocalPackageManager};
-use crate::registry::client::RegistryClient;
+use crate::core::{config::CobraConfig, installer::format_bytes, package_manager::LocalPackageManager};
 use colored::Colorize;
 use std::path::Path;
 use std::sync::Arc;
@@ -16,7 +15,7 @@ pub async fn execute(package_name: String) -> Result<()> {
     let config = CobraConfig::load(config_path).await?;
     
     // Initialize package manager
-    let install_dir = std::env::current_dir()?.join(config.get_install_dir());
+    let install_dir = config.install_dir()?;
     let package_manager = Arc::new(LocalPackageManager::new(install_dir));
     
     // Check if package is installed locally
@@ -24,7 +23,7 @@ pub async fn execute(package_name: String) -> Result<()> {
     let local_package = installed_packages.iter().find(|p| p.name == package_name);
     
     // Get package info from PyPI
-    let client = RegistryClient::new();
+    let client = config.registry_client();
     let package_info = client.get_package_info(&package_name, "*").await?;
     
     // Display package information
@@ -34,27 +33,23 @@ pub async fn execute(package_name: String) -> Result<()> {
     println!("{}: {}", "Name".bold(), package_info.name.cyan());
     println!("{}: {}", "Version".bold(), package_info.version.green());
     
-    if let Some(description) = &package_info.description {
-        if !description.is_empty() {
-            println!("{}: {}", "Description".bold(), description);
-        }
+    if let Some(description) = &package_info.description
+        && !description.is_empty() {
+        println!("{}: {}", "Description".bold(), description);
     }
     
-    if let Some(author) = &package_info.author {
-        if !author.is_empty() {
-            println!("{}: {}", "Author".bold(), author);
-        }
+    if let Some(author) = &package_info.author
+        && !author.is_empty() {
+        println!("{}: {}", "Author".bold(), author);
     }
     
-    if let Some(homepage) = &package_info.homepage {
-        if !homepage.is_empty() {
-            println!("{}: {}", "Homepage".bold(), homepage.blue().underline());
-        }
+    if let Some(homepage) = &package_info.homepage
+        && !homepage.is_empty() {
+        println!("{}: {}", "Homepage".bold(), homepage.blue().underline());
     }
     
     if let Some(size) = package_info.size {
-        let size_mb = size as f64 / 1024.0 / 1024.0;
-        println!("{}: {:.2} MB", "Size".bold(), size_mb);
+        println!("{}: {}", "Download Size".bold(), format_bytes(size));
     }
     
     // Installation status
@@ -66,6 +61,9 @@ pub async fn execute(package_name: String) -> Result<()> {
             format!("({})", local_pkg.installed_at.format("%Y-%m-%d %H:%M:%S")).dimmed()
         );
         println!("{}: {}", "Install Path".bold(), local_pkg.install_path.display());
+        if let Some(size) = local_pkg.size_on_disk {
+            println!("{}: {}", "Installed Size".bold(), format_bytes(size));
+        }
         
         if local_pkg.version != package_info.version {
             println!("{}: {} -> {}", 
@@ -0,0 +1,70 @@
+use crate::{Result, CobraError, Package, normalize_name};
+use crate::core::{config::{CobraConfig, DependencySelection}, package_manager::{InstalledPackage, LocalPackageManager}};
+use crate::core::lockfile::{Lockfile, LOCKFILE_NAME};
+use crate::core::markers::MarkerEnvironment;
+use crate::cli::sync::{plan_sync, SyncPlan};
+use colored::Colorize;
+use std::path::Path;
+
+/// Compare the installed packages with `cobra.lock` without touching the
+/// network. Locked packages for other platforms are ignored. Pass the
+/// packages from `list_present`, so ones whose files were deleted count as missing.
+pub fn lockfile_drift(lockfile: &Lockfile, installed: &[InstalledPackage], environment: &MarkerEnvironment) -> SyncPlan {
+    let locked: Vec<Package> = lockfile.packages.iter()
+        .filter(|p| environment.includes(p.markers.as_deref()))
+        .map(Package::from)
+        .collect();
+    plan_sync(&locked, installed)
+}
+
+/// One warning line per drifted package, missing first, then mismatched, then extra
+pub fn drift_warnings(drift: &SyncPlan) -> Vec<String> {
+    let missing = drift.install.iter()
+        .map(|p| format!("{} {} is locked but not installed", p.name, p.version));
+    let mismatched = drift.reinstall.iter()
+        .map(|(current, locked)| format!("{} {} is installed but {} is locked", locked.name, current.version, locked.version));
+    let extra = drift.remove.iter()
+        .map(|p| format!("{} {} is installed but not locked", p.name, p.version));
+    missing.chain(mismatched).chain(extra).collect()
+}
+
+pub async fn execute() -> Result<()> {
+    let config_path = Path::new("cobra.toml");
+    if !config_path.exists() {
+        return Err(CobraError::Config(
+            "No cobra.toml found. Run 'cobra init' to create one.".to_string()
+        ));
+    }
+    let lock_path = Path::new(LOCKFILE_NAME);
+    if !lock_path.exists() {
+        return Err(CobraError::Config(
+            format!("{} not found. Run 'cobra install' first.", LOCKFILE_NAME)
+        ));
+    }
+
+    let config = CobraConfig::load(config_path).await?;
+    let lockfile = Lockfile::load(lock_path).await?;
+    let install_dir = config.install_dir()?;
+    let package_manager = LocalPackageManager::new(install_dir);
+    let installed = package_manager.list_present().await?;
+    let environment = MarkerEnvironment::detect(&config.tool.cobra.python_version).await;
+
+    // Path and git dependencies are built locally and never appear in the lock
+    let mut drift = lockfile_drift(&lockfile, &installed, &environment);
+    let local: Vec<String> = config.source_dependencies(DependencySelection::WithDev).iter()
+        .map(|d| normalize_name(&d.name))
+        .collect();
+    drift.remove.retain(|p| !local.contains(&normalize_name(&p.name)));
+
+    if drift.is_empty() {
+        println!("{} Environment matches {}", "✓".green().bold(), LOCKFILE_NAME);
+        return Ok(());
+    }
+
+    println!("{} Environment has drifted from {}:", "⚠".yellow(), LOCKFILE_NAME);
+    for warning in drift_warnings(&drift) {
+        println!("  {} {}", "•".yellow(), warning);
+    }
+    println!("\n{} Run 'cobra sync' to restore the locked environment", "💡".bright_yellow());
+    Ok(())
+}
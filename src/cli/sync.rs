@@ -0,0 +1,169 @@
+use crate::{Result, CobraError, Package, normalize_name};
+use crate::core::{cache::MultiLevelCache, config::{CobraConfig, DependencySelection}, installer::Installer, resolver::DependencyResolver, wheel_cache::WheelCache};
+use crate::core::markers::MarkerEnvironment;
+use crate::core::package_manager::{InstalledPackage, LocalPackageManager};
+use crate::core::store::WheelStore;
+use crate::utils::progress::ProgressTracker;
+use crate::cli::index::open_local_index;
+use crate::utils::fs::{ensure_writable, get_download_dir};
+use colored::Colorize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Changes needed to make the installed set match the resolved configuration
+#[derive(Debug, Clone, Default)]
+pub struct SyncPlan {
+    /// Resolved but not installed
+    pub install: Vec<Package>,
+    /// Installed but not part of the resolution
+    pub remove: Vec<InstalledPackage>,
+    /// Installed with a different version than resolved: `(installed, target)`
+    pub reinstall: Vec<(InstalledPackage, Package)>,
+}
+
+impl SyncPlan {
+    pub fn is_empty(&self) -> bool {
+        self.install.is_empty() && self.remove.is_empty() && self.reinstall.is_empty()
+    }
+}
+
+/// Diff resolved packages against installed ones, matching by normalized name
+pub fn plan_sync(resolved: &[Package], installed: &[InstalledPackage]) -> SyncPlan {
+    let installed_by_name: HashMap<String, &InstalledPackage> = installed.iter()
+        .map(|p| (normalize_name(&p.name), p))
+        .collect();
+    let resolved_by_name: HashMap<String, &Package> = resolved.iter()
+        .map(|p| (normalize_name(&p.name), p))
+        .collect();
+
+    let mut plan = SyncPlan::default();
+    for package in resolved {
+        match installed_by_name.get(&normalize_name(&package.name)) {
+            None => plan.install.push(package.clone()),
+            Some(current) if current.version != package.version => {
+                plan.reinstall.push(((*current).clone(), package.clone()));
+            }
+            Some(_) => {}
+        }
+    }
+    for package in installed {
+        if !resolved_by_name.contains_key(&normalize_name(&package.name)) {
+            plan.remove.push(package.clone());
+        }
+    }
+
+    plan.install.sort_by(|a, b| a.name.cmp(&b.name));
+    plan.remove.sort_by(|a, b| a.name.cmp(&b.name));
+    plan.reinstall.sort_by(|a, b| a.1.name.cmp(&b.1.name));
+    plan
+}
+
+pub async fn execute(dry_run: bool, dependencies: DependencySelection) -> Result<()> {
+    let config_path = Path::new("cobra.toml");
+    if !config_path.exists() {
+        return Err(CobraError::Config(
+            "No cobra.toml found. Run 'cobra init' to create one.".to_string()
+        ));
+    }
+
+    let config = CobraConfig::load(config_path).await?;
+    let settings = config.settings()?;
+    let install_dir = config.install_dir()?;
+    if !dry_run {
+        ensure_writable(&install_dir)?;
+    }
+    let package_manager = Arc::new(LocalPackageManager::new(install_dir));
+
+    let cache = Arc::new(MultiLevelCache::new().await?);
+    let client = Arc::new(config.registry_client());
+
+    println!("{} Resolving dependency graph...", "🔍".bright_blue());
+    let environment = MarkerEnvironment::detect(&config.tool.cobra.python_version).await;
+    let resolver = DependencyResolver::new(client.clone(), Some(cache.clone()))
+        .with_local_index(open_local_index()?)
+        .with_python_version(environment.python())
+        .with_registries(config.private_registries())
+        .with_overrides(config.get_overrides()?)
+        .with_timeout(Some(config.resolve_timeout()))
+        .with_metadata_ttl(config.metadata_cache_ttl())
+        .with_settings(&settings);
+    let resolved: Vec<Package> = resolver.resolve(&config.registry_dependencies(dependencies)).await?
+        .into_iter()
+        .filter(|p| environment.includes(p.markers.as_deref()))
+        .collect();
+    for skipped in resolver.skipped_dependencies() {
+        println!("{} Skipping optional {}: {}", "⚠".yellow(), skipped.package.cyan(), skipped.reason);
+    }
+    for package in resolved.iter().filter(|p| p.yanked) {
+        println!("{} Installing yanked release {} {}: {}",
+            "⚠".yellow(),
+            package.name.cyan(),
+            package.version.red(),
+            package.yanked_reason.as_deref().unwrap_or("no reason given")
+        );
+    }
+    let installed = package_manager.list_installed().await?;
+
+    // Path and git dependencies are built by `cobra install`; never treat them as extras
+    let mut plan = plan_sync(&resolved, &installed);
+    let local: Vec<String> = config.source_dependencies(dependencies).iter()
+        .map(|d| normalize_name(&d.name))
+        .collect();
+    plan.remove.retain(|p| !local.contains(&normalize_name(&p.name)));
+    if plan.is_empty() {
+        println!("{} Environment already matches cobra.toml", "✓".green().bold());
+        return Ok(());
+    }
+
+    for package in &plan.install {
+        println!("  {} {} {}", "+".green(), package.name.cyan(), package.version.green());
+    }
+    for (current, target) in &plan.reinstall {
+        println!("  {} {} {} -> {}", "~".yellow(), target.name.cyan(), current.version.red(), target.version.green());
+    }
+    for package in &plan.remove {
+        println!("  {} {} {}", "-".red(), package.name.cyan(), package.version.red());
+    }
+
+    if dry_run {
+        println!("\n{} Dry run: would install {}, reinstall {}, remove {}",
+            "💡".bright_yellow(),
+            plan.install.len(),
+            plan.reinstall.len(),
+            plan.remove.len()
+        );
+        return Ok(());
+    }
+
+    // The installer replaces outdated versions once their new version is
+    // staged; extras are only removed once everything else is in
+    let to_install: Vec<Package> = plan.install.iter().cloned()
+        .chain(plan.reinstall.iter().map(|(_, target)| target.clone()))
+        .collect();
+    if !to_install.is_empty() {
+        let progress = Arc::new(ProgressTracker::new());
+        let installer = Installer::new(client, Some(Arc::new(WheelCache::open_default()?)), progress, package_manager.clone())
+            .with_settings(&settings)
+            .with_max_download_rate(config.tool.cobra.max_download_rate)
+            .with_download_dir(get_download_dir()?)
+            .with_build_frontend(config.tool.cobra.build_frontend)
+            .with_post_install_checks(config.verify_commands())
+            .with_store(Some(Arc::new(WheelStore::open_default()?)));
+        installer.install_parallel(to_install).await?;
+    }
+    if !plan.remove.is_empty() {
+        for package in &plan.remove {
+            package_manager.uninstall(&package.name).await?;
+        }
+        package_manager.create_pth_file().await?;
+    }
+
+    println!("\n{} Synced: installed {}, reinstalled {}, removed {}",
+        "✓".green().bold(),
+        plan.install.len(),
+        plan.reinstall.len(),
+        plan.remove.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use crate::{Result, CobraError, Package, normalize_name};
+use crate::core::{cache::MultiLevelCache, config::{CobraConfig, DependencySelection}, resolver::DependencyResolver};
+use crate::cli::index::open_local_index;
+use colored::Colorize;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+
+pub async fn execute() -> Result<()> {
+    let config_path = Path::new("cobra.toml");
+    if !config_path.exists() {
+        return Err(CobraError::Config(
+            "No cobra.toml found. Run 'cobra init' to create one.".to_string()
+        ));
+    }
+
+    let config = CobraConfig::load(config_path).await?;
+    let settings = config.settings()?;
+    let overrides = config.get_overrides()?;
+
+    let cache = Arc::new(MultiLevelCache::new().await?);
+    let client = Arc::new(config.registry_client());
+    let resolver = DependencyResolver::new(client, Some(cache))
+        .with_local_index(open_local_index()?)
+        .with_registries(config.private_registries())
+        .with_overrides(overrides.clone())
+        .with_timeout(Some(config.resolve_timeout()))
+        .with_metadata_ttl(config.metadata_cache_ttl())
+        .with_settings(&settings);
+
+    let roots = config.registry_dependencies(DependencySelection::Main);
+    let resolved = resolver.resolve(&roots).await?;
+
+    let packages: HashMap<String, &Package> = resolved.iter()
+        .map(|p| (normalize_name(&p.name), p))
+        .collect();
+
+    let mut root_names: Vec<&str> = roots.iter().map(|d| d.name.as_str()).collect();
+    root_names.sort();
+
+    for name in root_names {
+        let mut seen = HashSet::new();
+        print_node(name, &packages, &overrides, "", None, &mut seen);
+    }
+
+    Ok(())
+}
+
+/// Print `name` and its dependencies; `branch` is `Some(is_last)` for non-root nodes
+fn print_node(
+    name: &str,
+    packages: &HashMap<String, &Package>,
+    overrides: &HashMap<String, String>,
+    prefix: &str,
+    branch: Option<bool>,
+    seen: &mut HashSet<String>,
+) {
+    let key = normalize_name(name);
+    let connector = match branch {
+        None => "",
+        Some(true) => "└── ",
+        Some(false) => "├── ",
+    };
+
+    let Some(package) = packages.get(&key) else {
+        println!("{}{}{} {}", prefix, connector, name.cyan(), "(unresolved)".dimmed());
+        return;
+    };
+
+    let suffix = if overrides.contains_key(&key) {
+        format!(" {}", "(override)".yellow())
+    } else {
+        String::new()
+    };
+
+    // Cycles and shared subtrees are printed once per root
+    let repeated = !seen.insert(key);
+    println!("{}{}{} {}{}{}",
+        prefix,
+        connector,
+        package.name.cyan(),
+        package.version.green(),
+        suffix,
+        if repeated { " (*)".dimmed().to_string() } else { String::new() }
+    );
+    if repeated {
+        return;
+    }
+
+    let child_prefix = match branch {
+        None => prefix.to_string(),
+        Some(true) => format!("{}    ", prefix),
+        Some(false) => format!("{}│   ", prefix),
+    };
+
+    let count = package.dependencies.len();
+    for (i, dep) in package.dependencies.iter().enumerate() {
+        print_node(&dep.name, packages, overrides, &child_prefix, Some(i + 1 == count), seen);
+    }
+}
@@ -1,9 +1,10 @@
-use crate::{Result, CobraError};
+use crate::{normalize_name, Result, CobraError};
 use crate::core::{config::CobraConfig, package_manager::LocalPackageManager};
+use crate::core::lockfile::{Lockfile, LOCKFILE_NAME};
 use colored::Colorize;
+use std::collections::HashSet;
 use std::path::Path;
 use std::sync::Arc;
-use tokio::fs;
 
 pub async fn execute(packages: Vec<String>) -> Result<()> {
     if packages.is_empty() {
@@ -20,16 +21,18 @@ pub async fn execute(packages: Vec<String>) -> Result<()> {
     let config = CobraConfig::load(config_path).await?;
     
     // Initialize package manager
-    let install_dir = std::env::current_dir()?.join(config.get_install_dir());
+    let install_dir = config.install_dir()?;
     let package_manager = Arc::new(LocalPackageManager::new(install_dir));
     
+    warn_about_dependents(&package_manager, &packages).await?;
+
     println!("Uninstalling packages...");
     
     let mut uninstalled_count = 0;
     let mut not_found_count = 0;
     
     for package_name in &packages {
-        match uninstall_single_package(&package_manager, package_name).await {
+        match package_manager.uninstall(package_name).await {
             Ok(was_installed) => {
                 if was_installed {
                     println!("  {} {}", "✓".green(), format!("Uninstalled {}", package_name).cyan());
@@ -47,10 +50,9 @@ pub async fn execute(packages: Vec<String>) -> Result<()> {
     }
     
     // Update .pth file after uninstallation
-    if uninstalled_count > 0 {
-        if let Err(e) = package_manager.create_pth_file().await {
-            println!("Warning: Failed to update Python path file: {}", e);
-        }
+    if uninstalled_count > 0
+        && let Err(e) = package_manager.create_pth_file().await {
+        println!("Warning: Failed to update Python path file: {}", e);
     }
     
     // Summary
@@ -70,34 +72,29 @@ pub async fn execute(packages: Vec<String>) -> Result<()> {
     Ok(())
 }
 
-async fn uninstall_single_package(
-    package_manager: &LocalPackageManager, 
-    package_name: &str
-) -> Result<bool> {
-    // Check if package is installed
-    let installed_packages = package_manager.list_installed().await?;
-    let package = installed_packages.iter().find(|p| p.name == package_name);
-    
-    if let Some(pkg) = package {
-        // Remove package directory
-        if pkg.install_path.exists() {
-            fs::remove_dir_all(&pkg.install_path).await?;
-        }
-        
-        // Remove dist-info directory if it exists
-        let dist_info_path = pkg.install_path.parent()
-            .unwrap()
-            .join(format!("{}-{}.dist-info", pkg.name, pkg.version));
-        
-        if dist_info_path.exists() {
-            fs::remove_dir_all(&dist_info_path).await?;
+/// Warn about installed packages, kept by this uninstall, that cobra.lock
+/// says still require one being removed
+async fn warn_about_dependents(package_manager: &LocalPackageManager, packages: &[String]) -> Result<()> {
+    let lock_path = Path::new(LOCKFILE_NAME);
+    if !lock_path.exists() {
+        return Ok(());
+    }
+    let lockfile = Lockfile::load(lock_path).await?;
+    let removing: HashSet<String> = packages.iter().map(|p| normalize_name(p)).collect();
+    let kept: HashSet<String> = package_manager.list_installed().await?
+        .iter()
+        .map(|p| normalize_name(&p.name))
+        .filter(|name| !removing.contains(name))
+        .collect();
+
+    for package in packages {
+        let dependents: Vec<&str> = lockfile.dependents(package)
+            .into_iter()
+            .filter(|dependent| kept.contains(&normalize_name(dependent)))
+            .collect();
+        if !dependents.is_empty() {
+            println!("  {} {} is required by {}", "⚠".yellow(), package.cyan(), dependents.join(", "));
         }
-        
-        // Remove from registry
-        package_manager.unregister_package(package_name).await?;
-        
-        Ok(true)
-    } else {
-        Ok(false)
     }
+    Ok(())
 }
@@ -1,12 +1,319 @@
-use crate::{Result, CobraError};
-use crate::core::{config::CobraConfig, resolver::DependencyResolver, installer::Installer, cache::MultiLevelCache, package_manager::LocalPackageManager};
-use crate::registry::client::RegistryClient;
+use crate::{Result, CobraError, Dependency, DependencySource, Package, normalize_name};
+use crate::core::{config::{CobraConfig, DependencySelection}, resolver::{select_release, DependencyResolver, ResolutionStrategy}, installer::Installer, cache::MultiLevelCache, package_manager::LocalPackageManager, store::WheelStore, wheel_cache::WheelCache};
+use crate::core::package_manager::InstalledPackage;
+use crate::core::markers::MarkerEnvironment;
+use crate::core::version::{self, Version, VersionSpec};
+use crate::registry::backend::RegistryBackend;
+use crate::registry::packagecloud::PrivateRegistries;
 use crate::utils::progress::ProgressTracker;
+use crate::utils::fs::{ensure_writable, get_download_dir};
 use colored::Colorize;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{IsTerminal, Write};
 use std::path::Path;
 use std::sync::Arc;
 
-pub async fn execute(package: Option<String>) -> Result<()> {
+/// How one package's version differs between two package sets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionChange {
+    Upgraded { from: String, to: String },
+    Downgraded { from: String, to: String },
+    Added(String),
+    Removed(String),
+    Unchanged(String),
+}
+
+impl VersionChange {
+    /// Risk tier of an upgrade, `None` for any other change
+    pub fn bump(&self) -> Option<Bump> {
+        match self {
+            VersionChange::Upgraded { from, to } => Some(Bump::between(from, to)),
+            _ => None,
+        }
+    }
+}
+
+/// Semver-style risk of an upgrade, from safest to riskiest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Bump {
+    Patch,
+    Minor,
+    Major,
+}
+
+impl Bump {
+    /// Classify by the first release component that changes. As in semver,
+    /// a minor change of a `0.x` version counts as major. Versions that
+    /// aren't PEP 440 can't be classified and count as major.
+    pub fn between(from: &str, to: &str) -> Bump {
+        let (Ok(from), Ok(to)) = (Version::parse(from), Version::parse(to)) else {
+            return Bump::Major;
+        };
+        let segment = |v: &Version, i: usize| v.release.get(i).copied().unwrap_or(0);
+        let major_changed = from.epoch != to.epoch || segment(&from, 0) != segment(&to, 0);
+        let minor_changed = segment(&from, 1) != segment(&to, 1);
+        if major_changed || (minor_changed && segment(&from, 0) == 0) {
+            Bump::Major
+        } else if minor_changed {
+            Bump::Minor
+        } else {
+            Bump::Patch
+        }
+    }
+
+    /// Requirement admitting upgrades of `from` up to this tier: `~from` for
+    /// patches, `^from` for minors. Non-PEP 440 versions stay where they are.
+    pub fn range_from(self, from: &str) -> String {
+        if Version::parse(from).is_err() {
+            return format!("=={}", from);
+        }
+        match self {
+            Bump::Patch => format!("~{}", from),
+            Bump::Minor => format!("^{}", from),
+            Bump::Major => "*".to_string(),
+        }
+    }
+
+    fn heading(self) -> &'static str {
+        match self {
+            Bump::Major => "Major (breaking)",
+            Bump::Minor => "Minor (features)",
+            Bump::Patch => "Patch (safe)",
+        }
+    }
+}
+
+/// Per-package version changes, sorted by normalized name
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VersionDiff {
+    pub changes: Vec<(String, VersionChange)>,
+}
+
+impl VersionDiff {
+    /// Compare `(name, version)` pairs before and after, matching names by
+    /// their normalized form and versions by PEP 440 ordering
+    pub fn between<'a>(
+        before: impl IntoIterator<Item = (&'a str, &'a str)>,
+        after: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Self {
+        type Entry<'a> = Option<(&'a str, &'a str)>;
+        let mut sides: BTreeMap<String, (Entry, Entry)> = BTreeMap::new();
+        for (name, version) in before {
+            sides.entry(normalize_name(name)).or_default().0 = Some((name, version));
+        }
+        for (name, version) in after {
+            sides.entry(normalize_name(name)).or_default().1 = Some((name, version));
+        }
+
+        let changes = sides.into_values()
+            .map(|sides| match sides {
+                (Some((_, from)), Some((name, to))) => {
+                    let (from, to) = (from.to_string(), to.to_string());
+                    let change = match compare_versions(&from, &to) {
+                        Ordering::Less => VersionChange::Upgraded { from, to },
+                        Ordering::Greater => VersionChange::Downgraded { from, to },
+                        Ordering::Equal => VersionChange::Unchanged(to),
+                    };
+                    (name.to_string(), change)
+                }
+                (None, Some((name, to))) => (name.to_string(), VersionChange::Added(to.to_string())),
+                (Some((name, from)), None) => (name.to_string(), VersionChange::Removed(from.to_string())),
+                (None, None) => unreachable!("every entry has at least one side"),
+            })
+            .collect();
+        Self { changes }
+    }
+
+    /// Diff from what is installed to a new resolution
+    pub fn from_installed(installed: &[InstalledPackage], resolved: &[Package]) -> Self {
+        Self::between(
+            installed.iter().map(|p| (p.name.as_str(), p.version.as_str())),
+            resolved.iter().map(|p| (p.name.as_str(), p.version.as_str())),
+        )
+    }
+
+    pub fn has_changes(&self) -> bool {
+        self.changes.iter().any(|(_, change)| !matches!(change, VersionChange::Unchanged(_)))
+    }
+
+    pub fn has_downgrades(&self) -> bool {
+        self.changes.iter().any(|(_, change)| matches!(change, VersionChange::Downgraded { .. }))
+    }
+
+    /// Names of upgraded packages in one risk tier
+    pub fn upgrades(&self, bump: Bump) -> Vec<&str> {
+        self.changes.iter()
+            .filter(|(_, change)| change.bump() == Some(bump))
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// `name -> current version` of every upgrade riskier than `max`
+    pub fn held_back(&self, max: Bump) -> HashMap<String, String> {
+        self.changes.iter()
+            .filter_map(|(name, change)| match change {
+                VersionChange::Upgraded { from, .. } if change.bump() > Some(max) => {
+                    Some((name.clone(), from.clone()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// One colored line per changed package, then a count of unchanged ones
+    pub fn print(&self) {
+        for (name, change) in &self.changes {
+            print_change(name, change);
+        }
+        self.print_unchanged();
+    }
+
+    /// Like `print`, with upgrades grouped from riskiest to safest tier
+    pub fn print_grouped(&self) {
+        for bump in [Bump::Major, Bump::Minor, Bump::Patch] {
+            let group: Vec<_> = self.changes.iter().filter(|(_, change)| change.bump() == Some(bump)).collect();
+            if group.is_empty() {
+                continue;
+            }
+            println!("  {}:", bump.heading().bold());
+            for (name, change) in group {
+                print!("  ");
+                print_change(name, change);
+            }
+        }
+
+        let others: Vec<_> = self.changes.iter()
+            .filter(|(_, change)| !matches!(change, VersionChange::Upgraded { .. } | VersionChange::Unchanged(_)))
+            .collect();
+        if !others.is_empty() {
+            println!("  {}:", "Other changes".bold());
+            for (name, change) in others {
+                print!("  ");
+                print_change(name, change);
+            }
+        }
+        self.print_unchanged();
+    }
+
+    fn print_unchanged(&self) {
+        let unchanged = self.changes.iter().filter(|(_, change)| matches!(change, VersionChange::Unchanged(_))).count();
+        if unchanged > 0 {
+            println!("  {} {} unchanged", "=".dimmed(), unchanged);
+        }
+    }
+}
+
+fn print_change(name: &str, change: &VersionChange) {
+    match change {
+        VersionChange::Upgraded { from, to } => {
+            println!("  {} {} {} → {}", "↑".green(), name.cyan(), from.dimmed(), to.green());
+        }
+        VersionChange::Downgraded { from, to } => {
+            println!("  {} {} {} → {}", "↓".yellow(), name.cyan(), from.dimmed(), to.yellow());
+        }
+        VersionChange::Added(version) => println!("  {} {} {}", "+".green(), name.cyan(), version.green()),
+        VersionChange::Removed(version) => println!("  {} {} {}", "-".red(), name.cyan(), version.red()),
+        VersionChange::Unchanged(_) => {}
+    }
+}
+
+/// PEP 440 order, falling back to string order for legacy versions
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    match (Version::parse(a), Version::parse(b)) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+/// What one configured dependency could be upgraded to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AvailableUpgrade {
+    pub name: String,
+    pub spec: String,
+    pub installed: Option<String>,
+    /// Newest release the configured spec admits
+    pub wanted: Option<String>,
+    /// Newest release of all
+    pub latest: Option<String>,
+}
+
+impl AvailableUpgrade {
+    /// Whether only changing the spec would reach the newest release
+    pub fn latest_outside_spec(&self) -> bool {
+        self.latest.is_some() && self.latest != self.wanted
+    }
+}
+
+/// The installed, newest admitted and newest version of every root, in
+/// order. Prereleases, yanked releases and releases this `python` can't run
+/// are left out, as the resolver would leave them out.
+pub async fn available_upgrades(
+    client: &dyn RegistryBackend,
+    registries: &PrivateRegistries,
+    roots: &[Dependency],
+    installed: &[InstalledPackage],
+    python: Option<&Version>,
+) -> Result<Vec<AvailableUpgrade>> {
+    let any = VersionSpec::parse("*")?;
+    let lookups = roots.iter().map(|root| async {
+        let (wanted, latest) = match registries.get_package(&root.name, "*").await? {
+            // PackageCloud only reports its newest release
+            Some(package) => (
+                version::satisfies(&package.version, &root.version_spec).then(|| package.version.clone()),
+                Some(package.version),
+            ),
+            None => {
+                let releases = client.get_releases(&root.name).await?;
+                let newest = |spec: &VersionSpec| select_release(&releases, spec, None, python, ResolutionStrategy::Highest)
+                    .map(|(release, _)| release.version.clone());
+                let wanted = VersionSpec::parse(&root.version_spec).ok().and_then(|spec| newest(&spec));
+                (wanted, newest(&any))
+            }
+        };
+        Ok::<_, CobraError>(AvailableUpgrade {
+            name: root.name.clone(),
+            spec: root.version_spec.clone(),
+            installed: installed.iter()
+                .find(|p| normalize_name(&p.name) == normalize_name(&root.name))
+                .map(|p| p.version.clone()),
+            wanted,
+            latest,
+        })
+    });
+    futures::future::try_join_all(lookups).await
+}
+
+/// One row per dependency: installed, newest within the spec, newest overall
+fn print_upgrades(upgrades: &[AvailableUpgrade]) {
+    let width = |column: &dyn Fn(&AvailableUpgrade) -> usize, heading: &str| {
+        upgrades.iter().map(column).chain([heading.len()]).max().unwrap_or(0)
+    };
+    let shown = |version: &Option<String>| version.clone().unwrap_or_else(|| "-".to_string());
+    let name_width = width(&|u| u.name.len(), "Package");
+    let spec_width = width(&|u| u.spec.len(), "Spec");
+    let installed_width = width(&|u| shown(&u.installed).len(), "Installed");
+    let wanted_width = width(&|u| shown(&u.wanted).len(), "Wanted");
+
+    println!("  {:name_width$}  {:spec_width$}  {:installed_width$}  {:wanted_width$}  {}",
+        "Package".bold(), "Spec".bold(), "Installed".bold(), "Wanted".bold(), "Latest".bold());
+    for upgrade in upgrades {
+        let installed = shown(&upgrade.installed);
+        let wanted = shown(&upgrade.wanted);
+        let latest = shown(&upgrade.latest);
+        // Wanted is reachable with `cobra update`; latest needs a new spec
+        let wanted = if upgrade.wanted.is_some() && upgrade.wanted != upgrade.installed { wanted.green() } else { wanted.normal() };
+        let latest = if upgrade.latest_outside_spec() { latest.yellow() } else { latest.normal() };
+        println!("  {:name_width$}  {:spec_width$}  {:installed_width$}  {:wanted_width$}  {}",
+            upgrade.name.cyan(), upgrade.spec.dimmed(), installed, wanted, latest);
+    }
+}
+
+/// `dry_run` stops after showing what is available and the diff; `latest`
+/// also shows the diff of moving every spec to its newest release; `yes`
+/// installs downgrades without asking; `max_bump` holds back upgrades
+/// riskier than that tier
+pub async fn execute(package: Option<String>, dry_run: bool, latest: bool, yes: bool, max_bump: Option<Bump>) -> Result<()> {
     let config_path = Path::new("cobra.toml");
     if !config_path.exists() {
         return Err(CobraError::Config(
@@ -15,71 +322,190 @@ pub async fn execute(package: Option<String>) -> Result<()> {
     }
     
     let config = CobraConfig::load(config_path).await?;
+    let settings = config.settings()?;
     
-    match package {
+    let cache = Arc::new(MultiLevelCache::new().await?);
+    let client = Arc::new(config.registry_client());
+    let install_dir = config.install_dir()?;
+    ensure_writable(&install_dir)?;
+    let package_manager = Arc::new(LocalPackageManager::new(install_dir));
+    
+    let roots = match &package {
         Some(pkg_name) => {
             println!("{} Updating {}...", "⚡".bright_yellow(), pkg_name.cyan());
-            update_single_package(&config, &pkg_name).await?;
+            vec![single_dependency(&config, pkg_name)?]
         }
         None => {
             println!("{} Updating all packages...", "⚡".bright_yellow());
-            update_all_packages(&config).await?;
+            config.registry_dependencies(DependencySelection::Main)
         }
-    }
-    
-    Ok(())
-}
-
-async fn update_single_package(config: &CobraConfig, package_name: &str) -> Result<()> {
-    let cache = Arc::new(MultiLevelCache::new().await?);
-    let client = Arc::new(RegistryClient::new());
-    let progress = Arc::new(ProgressTracker::new());
-    
-    // Initialize package manager
-    let install_dir = std::env::current_dir()?.join(config.get_install_dir());
-    let package_manager = Arc::new(LocalPackageManager::new(install_dir));
-    
-    // Find the package in dependencies
-    let version_spec = config.dependencies.get(package_name)
-        .ok_or_else(|| CobraError::PackageNotFound(package_name.to_string()))?;
-    
-    println!("{} Checking for updates...", "🔍".bright_blue());
-    
-    let dep = crate::Dependency {
-        name: package_name.to_string(),
-        version_spec: version_spec.clone(),
     };
+    println!("{} Resolving latest versions...", "🔍".bright_blue());
+    let installed = package_manager.list_installed().await?;
+    let registries = config.private_registries();
+    let python = MarkerEnvironment::detect(&config.tool.cobra.python_version).await.python();
+    let make_resolver = |overrides| {
+        DependencyResolver::new(client.clone(), Some(cache.clone()))
+            .with_registries(registries.clone())
+            .with_python_version(python.clone())
+            .with_timeout(Some(config.resolve_timeout()))
+            .with_metadata_ttl(config.metadata_cache_ttl())
+            .with_settings(&settings)
+            .with_overrides(overrides)
+    };
+    let upgrades = if dry_run {
+        let upgrades = available_upgrades(client.as_ref(), &registries, &roots, &installed, python.as_ref()).await?;
+        println!("{} Available upgrades:", "📋".bright_blue());
+        print_upgrades(&upgrades);
+        upgrades
+    } else {
+        Vec::new()
+    };
+    let (resolved, diff) = resolve_within(&make_resolver, &roots, &installed, max_bump).await?;
+
+    if !diff.has_changes() {
+        println!("{} Everything is up to date", "✓".green().bold());
+    } else {
+        println!("{} Planned changes:", "📋".bright_blue());
+        diff.print_grouped();
+    }
     
-    let resolver = DependencyResolver::new(client.clone(), Some(cache.clone()));
-    let resolved = resolver.resolve(&[dep]).await?;
+    if dry_run {
+        if latest {
+            print_latest_changes(&make_resolver, &roots, &upgrades, &installed).await?;
+        }
+        println!("\n{} Dry run: nothing was installed", "💡".bright_yellow());
+        return Ok(());
+    }
+    if !diff.has_changes() {
+        return Ok(());
+    }
+    if !confirm_downgrades(&diff, yes)? {
+        println!("{} Update cancelled", "✗".red());
+        return Ok(());
+    }
     
-    let installer = Installer::new(client, Some(cache), progress, package_manager);
+    println!("{} Installing {} packages...", "📦".bright_blue(), resolved.len());
+    let installer = Installer::new(client, Some(Arc::new(WheelCache::open_default()?)), Arc::new(ProgressTracker::new()), package_manager)
+        .with_settings(&settings)
+        .with_max_download_rate(config.tool.cobra.max_download_rate)
+        .with_download_dir(get_download_dir()?)
+        .with_build_frontend(config.tool.cobra.build_frontend)
+        .with_post_install_checks(config.verify_commands())
+        .with_store(Some(Arc::new(WheelStore::open_default()?)));
     installer.install_parallel(resolved).await?;
     
-    println!("{} {} updated successfully", "✓".green(), package_name.cyan());
+    match package {
+        Some(pkg_name) => println!("{} {} updated successfully", "✓".green(), pkg_name.cyan()),
+        None => println!("{} All packages updated successfully", "✓".green().bold()),
+    }
     Ok(())
 }
 
-async fn update_all_packages(config: &CobraConfig) -> Result<()> {
-    let cache = Arc::new(MultiLevelCache::new().await?);
-    let client = Arc::new(RegistryClient::new());
-    let progress = Arc::new(ProgressTracker::new());
-    
-    // Initialize package manager
-    let install_dir = std::env::current_dir()?.join(config.get_install_dir());
-    let package_manager = Arc::new(LocalPackageManager::new(install_dir));
-    
-    println!("{} Resolving latest versions...", "🔍".bright_blue());
-    
-    let dependencies_list = config.get_dependencies_list();
-    let resolver = DependencyResolver::new(client.clone(), Some(cache.clone()));
-    let resolved = resolver.resolve(&dependencies_list).await?;
-    
-    println!("{} Installing {} packages...", "📦".bright_blue(), resolved.len());
-    
-    let installer = Installer::new(client, Some(cache), progress, package_manager);
-    installer.install_parallel(resolved).await?;
-    
-    println!("{} All packages updated successfully", "✓".green().bold());
+/// Resolve `roots` and diff the result against `installed`. With `max_bump`,
+/// packages upgraded beyond that tier are overridden to the tier's range of
+/// their installed version, and resolution repeats until every upgrade fits.
+pub async fn resolve_within(
+    make_resolver: impl Fn(HashMap<String, String>) -> DependencyResolver,
+    roots: &[Dependency],
+    installed: &[InstalledPackage],
+    max_bump: Option<Bump>,
+) -> Result<(Vec<Package>, VersionDiff)> {
+    // Every round narrows at least one override, so this terminates
+    let mut overrides: HashMap<String, String> = HashMap::new();
+    loop {
+        let resolved = make_resolver(overrides.clone()).resolve(roots).await?;
+
+        // Update never uninstalls, so only packages of the new resolution are compared
+        let current: Vec<InstalledPackage> = installed.iter()
+            .filter(|p| resolved.iter().any(|r| normalize_name(&r.name) == normalize_name(&p.name)))
+            .cloned()
+            .collect();
+        let diff = VersionDiff::from_installed(&current, &resolved);
+
+        let Some(max) = max_bump else {
+            return Ok((resolved, diff));
+        };
+        let held = diff.held_back(max);
+        if held.is_empty() {
+            return Ok((resolved, diff));
+        }
+        for (name, version) in held {
+            // Still too risky within the range (e.g. `~1` admits 1.1): pin exactly
+            let key = normalize_name(&name);
+            let spec = if overrides.contains_key(&key) {
+                format!("=={}", version)
+            } else {
+                max.range_from(&version)
+            };
+            println!("{} Holding back {} ({})", "⏸".yellow(), name.cyan(), spec.dimmed());
+            overrides.insert(key, spec);
+        }
+    }
+}
+
+/// Show what resolving with each root's spec moved to its newest release
+/// (`^latest`) would change
+async fn print_latest_changes(
+    make_resolver: impl Fn(HashMap<String, String>) -> DependencyResolver,
+    roots: &[Dependency],
+    upgrades: &[AvailableUpgrade],
+    installed: &[InstalledPackage],
+) -> Result<()> {
+    let moved = latest_roots(roots, upgrades);
+    if moved.iter().zip(roots).all(|(moved, root)| moved.version_spec == root.version_spec) {
+        println!("{} Every spec already admits the newest release", "✓".green());
+        return Ok(());
+    }
+    let (_, diff) = resolve_within(make_resolver, &moved, installed, None).await?;
+    println!("{} Moving specs to the newest major would change:", "📋".bright_blue());
+    for (root, moved) in roots.iter().zip(&moved).filter(|(root, moved)| root.version_spec != moved.version_spec) {
+        println!("  {} {} {} → {}", "✎".yellow(), root.name.cyan(), root.version_spec.dimmed(), moved.version_spec.yellow());
+    }
+    diff.print_grouped();
     Ok(())
 }
+
+/// `roots` with every spec that excludes the newest release replaced by a
+/// caret range on it
+pub fn latest_roots(roots: &[Dependency], upgrades: &[AvailableUpgrade]) -> Vec<Dependency> {
+    roots.iter()
+        .map(|root| {
+            let mut root = root.clone();
+            let newest = upgrades.iter()
+                .find(|u| normalize_name(&u.name) == normalize_name(&root.name))
+                .filter(|u| u.latest_outside_spec())
+                .and_then(|u| u.latest.as_deref());
+            if let Some(newest) = newest {
+                root.version_spec = Bump::Minor.range_from(newest);
+            }
+            root
+        })
+        .collect()
+}
+
+/// Ask before downgrading when someone is at the terminal; `yes` skips asking
+fn confirm_downgrades(diff: &VersionDiff, yes: bool) -> Result<bool> {
+    if yes || !diff.has_downgrades() || !std::io::stdin().is_terminal() {
+        return Ok(true);
+    }
+    print!("{} Some packages would be downgraded. Continue? [y/N] ", "?".yellow().bold());
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes"))
+}
+
+/// The configured requirement of `package_name`
+fn single_dependency(config: &CobraConfig, package_name: &str) -> Result<Dependency> {
+    let version_spec = config.get_dependency(package_name)
+        .ok_or_else(|| CobraError::PackageNotFound(package_name.to_string()))?;
+    Ok(Dependency {
+        name: package_name.to_string(),
+        version_spec,
+        markers: None,
+        source: DependencySource::Registry,
+        extras: Vec::new(),
+        optional: false,
+    })
+}
@@ -0,0 +1,118 @@
+use crate::{Result, CobraError, Installer, Package, ProgressTracker, normalize_name};
+use crate::core::{config::CobraConfig, package_manager::LocalPackageManager};
+use crate::core::lockfile::{Lockfile, LOCKFILE_NAME};
+use crate::core::record::FileProblem;
+use crate::core::store::WheelStore;
+use crate::core::wheel_cache::WheelCache;
+use crate::utils::fs::get_download_dir;
+use colored::Colorize;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Re-hash installed files against the RECORD captured at install time.
+/// With `fix`, corrupted packages are reinstalled from `cobra.lock`.
+pub async fn execute(fix: bool) -> Result<()> {
+    let config_path = Path::new("cobra.toml");
+    if !config_path.exists() {
+        return Err(CobraError::Config(
+            "No cobra.toml found. Run 'cobra init' to create one.".to_string()
+        ));
+    }
+
+    let config = CobraConfig::load(config_path).await?;
+    let install_dir = config.install_dir()?;
+    let package_manager = Arc::new(LocalPackageManager::new(install_dir));
+    let mut installed = package_manager.list_installed().await?;
+    installed.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut corrupted: Vec<(String, Vec<FileProblem>)> = Vec::new();
+    let mut unrecorded = 0;
+    for package in &installed {
+        if package.files.is_empty() {
+            unrecorded += 1;
+            continue;
+        }
+        let mut problems = package_manager.verify_package(package).await?;
+        if !problems.is_empty() {
+            problems.sort();
+            corrupted.push((package.name.clone(), problems));
+        }
+    }
+
+    let checked = installed.len() - unrecorded;
+    if unrecorded > 0 {
+        println!("{} {} packages were installed without a RECORD and can't be verified",
+            "i".blue().bold(), unrecorded);
+    }
+    if corrupted.is_empty() {
+        println!("{} All files of {} packages are intact", "✓".green().bold(), checked);
+        return Ok(());
+    }
+
+    for (name, problems) in &corrupted {
+        println!("{} {}:", "✗".red().bold(), name.cyan());
+        for problem in problems {
+            println!("  {} {}", "•".red(), problem);
+        }
+    }
+
+    if !fix {
+        println!("\n{} Run 'cobra verify --fix' to reinstall corrupted packages", "💡".bright_yellow());
+        return Err(CobraError::InstallationFailed(
+            format!("{} of {} packages have missing or modified files", corrupted.len(), checked)
+        ));
+    }
+    reinstall(&config, package_manager, &corrupted).await
+}
+
+/// Reinstall corrupted packages at the versions `cobra.lock` records
+async fn reinstall(
+    config: &CobraConfig,
+    package_manager: Arc<LocalPackageManager>,
+    corrupted: &[(String, Vec<FileProblem>)],
+) -> Result<()> {
+    let lock_path = Path::new(LOCKFILE_NAME);
+    if !lock_path.exists() {
+        return Err(CobraError::Config(
+            format!("{} not found. Run 'cobra install' to reinstall corrupted packages.", LOCKFILE_NAME)
+        ));
+    }
+    let lockfile = Lockfile::load(lock_path).await?;
+    let settings = config.settings()?;
+
+    let mut packages: Vec<Package> = Vec::new();
+    let mut unlocked = Vec::new();
+    for (name, _) in corrupted {
+        match lockfile.packages.iter().find(|p| normalize_name(&p.name) == normalize_name(name)) {
+            Some(locked) => packages.push(Package::from(locked)),
+            None => unlocked.push(name.as_str()),
+        }
+    }
+    if !unlocked.is_empty() {
+        return Err(CobraError::Config(format!(
+            "{} not in {}; run 'cobra install' to reinstall", unlocked.join(", "), LOCKFILE_NAME
+        )));
+    }
+
+    println!("\n{} Reinstalling {} packages...", "🔧".bright_blue(), packages.len());
+    let (wheels, store) = if config.tool.cobra.cache_enabled {
+        (Some(Arc::new(WheelCache::open_default()?)), Some(Arc::new(WheelStore::open_default()?)))
+    } else {
+        (None, None)
+    };
+    // Damage to hardlinked files reaches the store, so its entries are rechecked
+    let reinstall = packages.iter().map(|p| normalize_name(&p.name)).collect();
+    Installer::new(Arc::new(config.registry_client()), wheels, Arc::new(ProgressTracker::new()), package_manager)
+        .with_settings(&settings)
+        .with_max_download_rate(config.tool.cobra.max_download_rate)
+        .with_download_dir(get_download_dir()?)
+        .with_build_frontend(config.tool.cobra.build_frontend)
+        .with_post_install_checks(config.verify_commands())
+        .with_reinstall(reinstall)
+        .with_store(store)
+        .install_parallel(packages)
+        .await?;
+
+    println!("{} Reinstalled {} corrupted packages", "✓".green().bold(), corrupted.len());
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use crate::{Result, CobraError};
+use crate::core::{cache::MultiLevelCache, config::{CobraConfig, DependencySelection}, resolver::{DependencyResolver, PathStep}};
+use crate::cli::index::open_local_index;
+use colored::Colorize;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Print every chain of requirements from cobra.toml down to `package`
+pub async fn execute(package: String, dev: bool) -> Result<()> {
+    let config_path = Path::new("cobra.toml");
+    if !config_path.exists() {
+        return Err(CobraError::Config(
+            "No cobra.toml found. Run 'cobra init' to create one.".to_string()
+        ));
+    }
+
+    let config = CobraConfig::load(config_path).await?;
+    let settings = config.settings()?;
+    let cache = Arc::new(MultiLevelCache::new().await?);
+    let client = Arc::new(config.registry_client());
+    let resolver = DependencyResolver::new(client, Some(cache))
+        .with_local_index(open_local_index()?)
+        .with_registries(config.private_registries())
+        .with_overrides(config.get_overrides()?)
+        .with_timeout(Some(config.resolve_timeout()))
+        .with_metadata_ttl(config.metadata_cache_ttl())
+        .with_settings(&settings);
+
+    let graph = resolver.resolve_graph(&config.registry_dependencies(DependencySelection::from_flags(dev, false))).await?;
+    let Some(target) = graph.package(&package) else {
+        println!("{} {} is not a dependency of this project", "✗".red(), package.cyan());
+        return Ok(());
+    };
+
+    let paths = graph.paths_to(&package);
+    println!("{} {} is required by {} path{}:",
+        target.name.cyan(),
+        target.version.green(),
+        paths.len(),
+        if paths.len() == 1 { "" } else { "s" }
+    );
+    for path in &paths {
+        println!("  {}", format_path(path));
+    }
+    Ok(())
+}
+
+/// `app 1.0 -> lib 2.0 (extra == "x")`, with each requirement's marker after its package
+pub fn format_path(path: &[PathStep]) -> String {
+    path.iter()
+        .map(|step| match &step.markers {
+            Some(marker) => format!("{} {} ({})", step.package.name, step.package.version, marker),
+            None => format!("{} {}", step.package.name, step.package.version),
+        })
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
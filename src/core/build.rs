@@ -0,0 +1,374 @@
+use crate::{Result, CobraError, Dependency, Package};
+use crate::registry::client::parse_dependency;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+use walkdir::WalkDir;
+
+/// Backend used when `pyproject.toml` has no `[build-system]` table (PEP 517)
+const DEFAULT_BACKEND: &str = "setuptools.build_meta:__legacy__";
+const DEFAULT_REQUIRES: [&str; 2] = ["setuptools>=40.8.0", "wheel"];
+
+/// Calls the PEP 517 hooks of a backend and prints the built wheel's filename.
+/// Extra requirements from `get_requires_for_build_wheel` are installed first.
+const BUILD_DRIVER: &str = r#"
+import importlib, json, os, subprocess, sys
+source, out_dir, backend_spec, backend_path = sys.argv[1], sys.argv[2], sys.argv[3], json.loads(sys.argv[4])
+os.chdir(source)
+for entry in reversed(backend_path):
+    sys.path.insert(0, os.path.join(source, entry))
+module, _, attr = backend_spec.partition(":")
+backend = importlib.import_module(module)
+for part in filter(None, attr.split(".")):
+    backend = getattr(backend, part)
+hook = getattr(backend, "get_requires_for_build_wheel", None)
+extra = hook() if hook else []
+if extra:
+    subprocess.check_call([sys.executable, "-m", "pip", "install", "--quiet", *extra], stdout=sys.stderr)
+print(backend.build_wheel(out_dir))
+"#;
+
+/// The `[build-system]` table of a project
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildSystem {
+    pub requires: Vec<String>,
+    pub backend: String,
+    pub backend_path: Vec<String>,
+}
+
+impl BuildSystem {
+    /// Read `pyproject.toml` in `source`, falling back to the legacy setuptools backend
+    pub fn load(source: &Path) -> Result<Self> {
+        let pyproject = source.join("pyproject.toml");
+        let table = match std::fs::read_to_string(&pyproject) {
+            Ok(contents) => {
+                let document: toml::Value = toml::from_str(&contents).map_err(|e| {
+                    CobraError::Config(format!("Failed to parse {}: {}", pyproject.display(), e))
+                })?;
+                document.get("build-system").cloned()
+            }
+            Err(_) => None,
+        };
+
+        let strings = |value: Option<&toml::Value>| -> Vec<String> {
+            value
+                .and_then(|v| v.as_array())
+                .map(|items| items.iter().filter_map(|i| i.as_str().map(str::to_string)).collect())
+                .unwrap_or_default()
+        };
+
+        Ok(match table {
+            Some(table) => Self {
+                requires: strings(table.get("requires")),
+                backend: table
+                    .get("build-backend")
+                    .and_then(|b| b.as_str())
+                    .unwrap_or(DEFAULT_BACKEND)
+                    .to_string(),
+                backend_path: strings(table.get("backend-path")),
+            },
+            None => Self {
+                requires: DEFAULT_REQUIRES.iter().map(|r| r.to_string()).collect(),
+                backend: DEFAULT_BACKEND.to_string(),
+                backend_path: Vec::new(),
+            },
+        })
+    }
+}
+
+/// Tool that drives a project's PEP 517 backend, `build-frontend` under `[tool.cobra]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BuildFrontend {
+    /// Cobra calls the backend hooks itself
+    #[default]
+    Cobra,
+    /// `python -m pip wheel --no-deps`
+    Pip,
+    /// `python -m build --wheel`, which needs the `build` package
+    Build,
+}
+
+impl BuildFrontend {
+    pub fn is_default(&self) -> bool {
+        *self == BuildFrontend::default()
+    }
+}
+
+/// Builds local project directories into wheels through their PEP 517 backend
+pub struct WheelBuilder {
+    python: PathBuf,
+    isolated: bool,
+    frontend: BuildFrontend,
+}
+
+impl WheelBuilder {
+    pub fn new(python: PathBuf) -> Self {
+        Self { python, isolated: true, frontend: BuildFrontend::default() }
+    }
+
+    /// Build inside a fresh virtual environment holding only the build requirements
+    pub fn with_isolation(mut self, isolated: bool) -> Self {
+        self.isolated = isolated;
+        self
+    }
+
+    /// Hand the build to pip or `build` instead of calling the backend directly
+    pub fn with_frontend(mut self, frontend: BuildFrontend) -> Self {
+        self.frontend = frontend;
+        self
+    }
+
+    /// Build `source` into a wheel placed in `out_dir`, returning the wheel's path
+    pub async fn build(&self, source: &Path, out_dir: &Path) -> Result<PathBuf> {
+        let source = source.canonicalize().map_err(|e| {
+            CobraError::InstallationFailed(format!("Cannot build {}: {}", source.display(), e))
+        })?;
+        tokio::fs::create_dir_all(out_dir).await?;
+        if self.frontend != BuildFrontend::Cobra {
+            return self.build_with_frontend(&source, out_dir).await;
+        }
+        let build_system = BuildSystem::load(&source)?;
+
+        // Keep the environment alive until the build finishes
+        let env_dir = tempfile::tempdir()?;
+        let python = if self.isolated {
+            self.create_build_env(env_dir.path(), &build_system.requires).await?
+        } else {
+            self.python.clone()
+        };
+
+        let backend_path = serde_json::to_string(&build_system.backend_path)
+            .map_err(|e| CobraError::InstallationFailed(e.to_string()))?;
+        let output = Command::new(&python)
+            .arg("-c")
+            .arg(BUILD_DRIVER)
+            .arg(&source)
+            .arg(out_dir)
+            .arg(&build_system.backend)
+            .arg(backend_path)
+            .output()
+            .await
+            .map_err(|e| CobraError::PythonEnv(format!("Failed to run build backend: {}", e)))?;
+        if !output.status.success() {
+            return Err(CobraError::InstallationFailed(format!(
+                "Building {} failed:\n{}",
+                source.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let filename = stdout.lines().last().unwrap_or_default().trim();
+        let wheel = out_dir.join(filename);
+        if filename.is_empty() || !wheel.is_file() {
+            return Err(CobraError::InstallationFailed(format!(
+                "Build backend for {} did not produce a wheel", source.display()
+            )));
+        }
+        Ok(wheel)
+    }
+
+    /// Run pip or `build`, which isolate the build themselves unless told not to
+    async fn build_with_frontend(&self, source: &Path, out_dir: &Path) -> Result<PathBuf> {
+        // A fresh directory, so the only wheel in it is the one just built
+        let staging = tempfile::tempdir_in(out_dir)?;
+        let mut command = Command::new(&self.python);
+        if self.frontend == BuildFrontend::Pip {
+            command.args(["-m", "pip", "wheel", "--no-deps", "--quiet", "--wheel-dir"]).arg(staging.path());
+            if !self.isolated {
+                command.arg("--no-build-isolation");
+            }
+        } else {
+            command.args(["-m", "build", "--wheel", "--outdir"]).arg(staging.path());
+            if !self.isolated {
+                command.arg("--no-isolation");
+            }
+        }
+        run(command.arg(source), &format!("build {}", source.display())).await?;
+
+        let built = std::fs::read_dir(staging.path())?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .find(|p| p.extension().is_some_and(|ext| ext == "whl"))
+            .ok_or_else(|| CobraError::InstallationFailed(format!(
+                "Build frontend for {} did not produce a wheel", source.display()
+            )))?;
+        let wheel = out_dir.join(built.file_name().unwrap_or_default());
+        tokio::fs::rename(&built, &wheel).await?;
+        Ok(wheel)
+    }
+
+    /// Create a virtual environment in `dir` with `requires` installed, returning its interpreter
+    async fn create_build_env(&self, dir: &Path, requires: &[String]) -> Result<PathBuf> {
+        let mut venv = Command::new(&self.python);
+        venv.arg("-m").arg("venv");
+        if requires.is_empty() {
+            venv.arg("--without-pip");
+        }
+        run(venv.arg(dir), "create build environment").await?;
+
+        let python = if cfg!(windows) {
+            dir.join("Scripts").join("python.exe")
+        } else {
+            dir.join("bin").join("python")
+        };
+        if !requires.is_empty() {
+            let mut pip = Command::new(&python);
+            pip.args(["-m", "pip", "install", "--quiet"]).args(requires);
+            run(&mut pip, "install build requirements").await?;
+        }
+        Ok(python)
+    }
+}
+
+async fn run(command: &mut Command, action: &str) -> Result<()> {
+    let output = command
+        .output()
+        .await
+        .map_err(|e| CobraError::PythonEnv(format!("Failed to {}: {}", action, e)))?;
+    if !output.status.success() {
+        return Err(CobraError::InstallationFailed(format!(
+            "Failed to {}:\n{}",
+            action,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+/// Whether a distribution filename or URL names a source archive rather than a wheel
+pub fn is_sdist(filename: &str) -> bool {
+    let filename = filename.split(['?', '#']).next().unwrap_or_default();
+    [".tar.gz", ".tgz", ".zip"].iter().any(|ext| filename.ends_with(ext))
+}
+
+/// Unpack the sdist `archive`, whose original name is `filename`, into `dest`
+/// and return the project directory, the single top-level directory sdists wrap it in
+pub fn unpack_sdist(archive: &Path, filename: &str, dest: &Path) -> Result<PathBuf> {
+    let unpack_error = |e: &dyn std::fmt::Display| CobraError::Archive(format!("Failed to unpack {}: {}", filename, e));
+    let file = std::fs::File::open(archive)?;
+    if filename.split(['?', '#']).next().unwrap_or_default().ends_with(".zip") {
+        zip::ZipArchive::new(file).and_then(|mut zip| zip.extract(dest)).map_err(|e| unpack_error(&e))?;
+    } else {
+        // `unpack` refuses entries that would land outside `dest`
+        tar::Archive::new(flate2::read::GzDecoder::new(file)).unpack(dest).map_err(|e| unpack_error(&e))?;
+    }
+
+    let entries: Vec<PathBuf> = std::fs::read_dir(dest)?.filter_map(|e| e.ok().map(|e| e.path())).collect();
+    Ok(match entries.as_slice() {
+        [single] if single.is_dir() => single.clone(),
+        _ => dest.to_path_buf(),
+    })
+}
+
+/// Hash of every source file in a project directory, used to detect when a
+/// local dependency must be rebuilt. Build outputs and VCS data are ignored.
+pub fn source_hash(dir: &Path) -> Result<String> {
+    let ignored = |name: &str| {
+        matches!(name, ".git" | ".hg" | "build" | "dist" | "__pycache__" | ".venv" | ".cobra_packages")
+            || name.ends_with(".egg-info")
+    };
+
+    let mut hasher = blake3::Hasher::new();
+    let walker = WalkDir::new(dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !ignored(&entry.file_name().to_string_lossy()));
+    for entry in walker {
+        let entry = entry.map_err(|e| CobraError::Io(e.into()))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(dir).unwrap_or(entry.path());
+        hasher.update(relative.to_string_lossy().as_bytes());
+        hasher.update(&[0]);
+        hasher.update(&std::fs::read(entry.path())?);
+    }
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Core metadata read from a wheel's `.dist-info/METADATA`
+#[derive(Debug, Clone, PartialEq)]
+pub struct WheelMetadata {
+    pub name: String,
+    pub version: String,
+    pub requires: Vec<Dependency>,
+    pub requires_python: Option<String>,
+}
+
+impl WheelMetadata {
+    /// Package record for a locally built wheel
+    pub fn to_package(&self, wheel: &Path) -> Package {
+        Package {
+            name: self.name.clone(),
+            version: self.version.clone(),
+            dependencies: self.requires.clone(),
+            download_url: format!("file://{}", wheel.display()),
+            hash: None,
+            size: std::fs::metadata(wheel).ok().map(|m| m.len()),
+            description: None,
+            author: None,
+            homepage: None,
+            markers: None,
+            yanked: false,
+            yanked_reason: None,
+            requires_python: self.requires_python.clone(),
+        }
+    }
+}
+
+pub fn wheel_metadata(wheel: &Path) -> Result<WheelMetadata> {
+    read_wheel_metadata(std::fs::File::open(wheel)?, &wheel.display().to_string())
+}
+
+/// Core metadata of the wheel archive in `reader`, called `label` in errors
+pub fn read_wheel_metadata(reader: impl std::io::Read + std::io::Seek, label: &str) -> Result<WheelMetadata> {
+    let metadata = find_wheel_metadata(reader, label)?
+        .ok_or_else(|| CobraError::Archive(format!("{} has no METADATA", label)))?;
+    if metadata.name.is_empty() || metadata.version.is_empty() {
+        return Err(CobraError::Archive(format!("{} has incomplete METADATA", label)));
+    }
+    Ok(metadata)
+}
+
+/// The METADATA headers of the wheel archive in `reader`, which may lack a
+/// name or version; `None` for an archive without METADATA
+pub fn find_wheel_metadata(reader: impl std::io::Read + std::io::Seek, label: &str) -> Result<Option<WheelMetadata>> {
+    let mut archive = zip::ZipArchive::new(reader)
+        .map_err(|e| CobraError::Archive(format!("Failed to read {}: {}", label, e)))?;
+
+    let Some(metadata_name) = archive
+        .file_names()
+        .find(|name| name.ends_with(".dist-info/METADATA") && name.matches('/').count() == 1)
+        .map(str::to_string) else {
+        return Ok(None);
+    };
+    let mut contents = String::new();
+    archive
+        .by_name(&metadata_name)
+        .map_err(|e| CobraError::Archive(e.to_string()))?
+        .read_to_string(&mut contents)?;
+
+    Ok(Some(parse_core_metadata(&contents)))
+}
+
+/// Parse the headers of a METADATA file, also served alone as a PEP 658 `.metadata`
+pub fn parse_core_metadata(contents: &str) -> WheelMetadata {
+    let mut metadata = WheelMetadata { name: String::new(), version: String::new(), requires: Vec::new(), requires_python: None };
+    // Headers end at the first blank line; the description may follow
+    for line in contents.lines().take_while(|line| !line.is_empty()) {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key {
+            "Name" => metadata.name = value.to_string(),
+            "Version" => metadata.version = value.to_string(),
+            "Requires-Dist" => metadata.requires.extend(parse_dependency(value)),
+            "Requires-Python" => metadata.requires_python = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    metadata
+}
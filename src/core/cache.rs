@@ -6,8 +6,22 @@ use bloomfilter::Bloom;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use crate::utils::fs::get_cache_dir;
 
+/// Key prefix of cached resolver metadata, cheap to fetch again
+pub const METADATA_PREFIX: &str = "metadata:";
+/// Key prefix of cached package archives, expensive to download again
+pub const BLOB_PREFIX: &str = "package:";
+
+/// Which cache level answered a lookup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheTier {
+    Memory,
+    Disk,
+}
+
 /// Multi-level cache: Memory -> Disk -> Network
 pub struct MultiLevelCache {
     memory: Arc<RwLock<LruCache<String, Bytes>>>,
@@ -15,18 +29,41 @@ pub struct MultiLevelCache {
     bloom: Arc<RwLock<Bloom<String>>>,
     hits: Arc<RwLock<u64>>,
     misses: Arc<RwLock<u64>>,
+    /// Hit/miss counters carried across runs, saved when the cache is dropped
+    stats: sled::Tree,
+    /// Unix time each entry was last written, for entries that expire
+    written: sled::Tree,
+    dir: PathBuf,
 }
 
 impl MultiLevelCache {
     pub async fn new() -> Result<Self> {
         let cache_dir = get_cache_dir()?;
-        let db_path = cache_dir.join("packages");
-        
-        let disk = sled::open(&db_path)
-            .map_err(|e| CobraError::Cache(format!("Failed to open disk cache: {}", e)))?;
+        Self::open(&cache_dir.join("packages")).await
+    }
+
+    /// Open a cache whose disk level lives at `db_path`
+    pub async fn open(db_path: &Path) -> Result<Self> {
+        let disk = open_disk(db_path).await?;
         
         // Initialize bloom filter for fast negative lookups
-        let bloom = Bloom::new_for_fp_rate(10000, 0.01);
+        let mut bloom = Bloom::new_for_fp_rate(10000, 0.01);
+        // Entries written by earlier runs must pass the filter too
+        for key in disk.iter().keys().flatten() {
+            bloom.set(&String::from_utf8_lossy(&key).into_owned());
+        }
+        
+        let stats = disk.open_tree("stats")
+            .map_err(|e| CobraError::Cache(format!("Failed to open cache statistics: {}", e)))?;
+        let counter = |name: &str| -> u64 {
+            stats.get(name).ok().flatten()
+                .and_then(|value| value.as_ref().try_into().ok())
+                .map(u64::from_be_bytes)
+                .unwrap_or(0)
+        };
+        let (hits, misses) = (counter("hits"), counter("misses"));
+        let written = disk.open_tree("written")
+            .map_err(|e| CobraError::Cache(format!("Failed to open cache timestamps: {}", e)))?;
         
         Ok(Self {
             memory: Arc::new(RwLock::new(
@@ -34,12 +71,25 @@ impl MultiLevelCache {
             )),
             disk,
             bloom: Arc::new(RwLock::new(bloom)),
-            hits: Arc::new(RwLock::new(0)),
-            misses: Arc::new(RwLock::new(0)),
+            hits: Arc::new(RwLock::new(hits)),
+            misses: Arc::new(RwLock::new(misses)),
+            stats,
+            written,
+            dir: db_path.to_path_buf(),
         })
     }
 
+    /// Directory holding the disk level
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
     pub async fn get(&self, key: &str) -> Option<Bytes> {
+        self.get_with_tier(key).await.map(|(data, _)| data)
+    }
+
+    /// Look up `key`, reporting whether it came from memory or disk
+    pub async fn get_with_tier(&self, key: &str) -> Option<(Bytes, CacheTier)> {
         // Check bloom filter first (fastest)
         {
             let bloom = self.bloom.read().await;
@@ -54,7 +104,7 @@ impl MultiLevelCache {
             let mut memory = self.memory.write().await;
             if let Some(data) = memory.get(key) {
                 *self.hits.write().await += 1;
-                return Some(data.clone());
+                return Some((data.clone(), CacheTier::Memory));
             }
         }
 
@@ -65,7 +115,7 @@ impl MultiLevelCache {
                 // Promote to memory cache
                 self.memory.write().await.put(key.to_string(), bytes.clone());
                 *self.hits.write().await += 1;
-                Some(bytes)
+                Some((bytes, CacheTier::Disk))
             }
             _ => {
                 *self.misses.write().await += 1;
@@ -74,6 +124,20 @@ impl MultiLevelCache {
         }
     }
 
+    /// Like `get`, but entries written more than `max_age` ago, or before
+    /// write times were recorded, count as misses
+    pub async fn get_fresh(&self, key: &str, max_age: Duration) -> Option<Bytes> {
+        let written = self.written.get(key).ok().flatten()
+            .and_then(|value| value.as_ref().try_into().ok())
+            .map(u64::from_be_bytes);
+        let fresh = written.is_some_and(|written| unix_now().saturating_sub(written) < max_age.as_secs());
+        if !fresh {
+            *self.misses.write().await += 1;
+            return None;
+        }
+        self.get(key).await
+    }
+
     pub async fn put(&self, key: String, data: Bytes) -> Result<()> {
         // Add to bloom filter
         self.bloom.write().await.set(&key);
@@ -84,20 +148,75 @@ impl MultiLevelCache {
         // Add to disk cache
         self.disk.insert(key.as_bytes(), data.as_ref())
             .map_err(|e| CobraError::Cache(format!("Failed to write to disk cache: {}", e)))?;
+        self.written.insert(key.as_bytes(), &unix_now().to_be_bytes()[..])
+            .map_err(|e| CobraError::Cache(format!("Failed to write to disk cache: {}", e)))?;
         
         Ok(())
     }
 
+    /// Drop one entry from memory and disk
+    pub async fn remove(&self, key: &str) -> Result<()> {
+        self.memory.write().await.pop(key);
+        self.disk.remove(key)
+            .map_err(|e| CobraError::Cache(format!("Failed to remove from disk cache: {}", e)))?;
+        self.written.remove(key)
+            .map_err(|e| CobraError::Cache(format!("Failed to remove from disk cache: {}", e)))?;
+        Ok(())
+    }
+
     pub async fn clear(&self) -> Result<()> {
         self.memory.write().await.clear();
         self.disk.clear()
             .map_err(|e| CobraError::Cache(format!("Failed to clear disk cache: {}", e)))?;
+        self.written.clear()
+            .map_err(|e| CobraError::Cache(format!("Failed to clear disk cache: {}", e)))?;
         *self.bloom.write().await = Bloom::new_for_fp_rate(10000, 0.01);
         *self.hits.write().await = 0;
         *self.misses.write().await = 0;
+        self.stats.clear()
+            .map_err(|e| CobraError::Cache(format!("Failed to clear cache statistics: {}", e)))?;
         Ok(())
     }
 
+    /// Drop cached resolver metadata, keeping package archives and stats
+    pub async fn clear_metadata(&self) -> Result<usize> {
+        self.clear_prefix(METADATA_PREFIX).await
+    }
+
+    /// Drop cached package archives, keeping resolver metadata and stats
+    pub async fn clear_blobs(&self) -> Result<usize> {
+        self.clear_prefix(BLOB_PREFIX).await
+    }
+
+    /// Remove every entry whose key starts with `prefix`, returning how many
+    /// disk entries were removed. The bloom filter keeps the stale keys, which
+    /// only costs a lookup that then misses.
+    async fn clear_prefix(&self, prefix: &str) -> Result<usize> {
+        {
+            let mut memory = self.memory.write().await;
+            let keys: Vec<String> = memory.iter()
+                .filter(|(key, _)| key.starts_with(prefix))
+                .map(|(key, _)| key.clone())
+                .collect();
+            for key in keys {
+                memory.pop(&key);
+            }
+        }
+
+        let mut removed = 0;
+        for key in self.disk.scan_prefix(prefix).keys() {
+            let key = key.map_err(|e| CobraError::Cache(format!("Failed to scan disk cache: {}", e)))?;
+            self.disk.remove(&key)
+                .map_err(|e| CobraError::Cache(format!("Failed to remove from disk cache: {}", e)))?;
+            self.written.remove(&key)
+                .map_err(|e| CobraError::Cache(format!("Failed to remove from disk cache: {}", e)))?;
+            removed += 1;
+        }
+        self.disk.flush_async().await
+            .map_err(|e| CobraError::Cache(format!("Failed to flush disk cache: {}", e)))?;
+        Ok(removed)
+    }
+
     pub async fn hit_rate(&self) -> f64 {
         let hits = *self.hits.read().await;
         let misses = *self.misses.read().await;
@@ -108,4 +227,50 @@ impl MultiLevelCache {
             hits as f64 / total as f64
         }
     }
+
+    /// Lookups answered and missed so far, including earlier runs
+    pub async fn lookups(&self) -> (u64, u64) {
+        (*self.hits.read().await, *self.misses.read().await)
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Open the sled database, waiting briefly for a handle dropped moments ago:
+/// its last writes can hold the file lock after the drop returns
+async fn open_disk(db_path: &Path) -> Result<Db> {
+    let mut attempts = 0;
+    loop {
+        match sled::open(db_path) {
+            Ok(disk) => return Ok(disk),
+            Err(sled::Error::Io(_)) if attempts < 20 && lock_held(db_path) => {
+                attempts += 1;
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            }
+            Err(e) => return Err(CobraError::Cache(format!("Failed to open disk cache: {}", e))),
+        }
+    }
+}
+
+/// Whether another handle holds the lock sled takes on `<db_path>/db`.
+/// sled only reports a held lock as text, so the lock itself is tried.
+fn lock_held(db_path: &Path) -> bool {
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(db_path.join("db"))
+        .is_ok_and(|file| matches!(file.try_lock(), Err(std::fs::TryLockError::WouldBlock)))
+}
+
+impl Drop for MultiLevelCache {
+    fn drop(&mut self) {
+        let (Ok(hits), Ok(misses)) = (self.hits.try_read(), self.misses.try_read()) else {
+            return;
+        };
+        let _ = self.stats.insert("hits", &hits.to_be_bytes()[..]);
+        let _ = self.stats.insert("misses", &misses.to_be_bytes()[..]);
+        let _ = self.stats.flush();
+    }
 }
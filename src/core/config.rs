@@ -1,20 +1,326 @@
-use crate::{Result, CobraError, Dependency};
+use crate::{Result, CobraError, Dependency, DependencySource, normalize_name};
+use crate::core::build::BuildFrontend;
+use crate::core::imports::ImportCheck;
+use crate::core::markers;
+use crate::core::version::{Version, VersionSpec};
+use crate::registry::client::{HttpTimeouts, RegistryClient, RetryPolicy};
+use crate::registry::backend::RegistryBackend;
+use crate::registry::packagecloud::{PackageCloudRegistry, PrivateRegistries, PACKAGECLOUD_URL};
+use crate::registry::simple::SimpleIndex;
+use crate::utils::fs::expand_path;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::Path;
+use std::sync::Arc;
+use std::path::{Path, PathBuf};
 use tokio::fs;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct CobraConfig {
     pub project: ProjectInfo,
     #[serde(default)]
-    pub dependencies: HashMap<String, String>,
+    pub dependencies: HashMap<String, DependencySpec>,
     #[serde(default, rename = "dev-dependencies")]
-    pub dev_dependencies: HashMap<String, String>,
+    pub dev_dependencies: HashMap<String, DependencySpec>,
+    /// Versions forced for a package no matter which parent requires it
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub overrides: HashMap<String, String>,
+    /// Where a dependency comes from when it is not fetched from the index
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub sources: HashMap<String, DependencySource>,
     #[serde(default)]
     pub tool: ToolConfig,
 }
 
+/// A dependency entry: `"^2.31"` or a table such as
+/// `{ version = "^2.31", extras = ["socks"], optional = true, index = "private" }`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum DependencySpec {
+    Version(String),
+    Detailed(DetailedDependency),
+    /// `[dependencies.'sys_platform == "win32"']`: packages keyed by name that
+    /// are only needed where the marker holds
+    Group(HashMap<String, DependencySpec>),
+}
+
+/// Unknown keys are rejected so a table of packages is read as a marker group
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct DetailedDependency {
+    #[serde(default = "any_version")]
+    pub version: String,
+    /// PEP 508 environment marker; the dependency is skipped where it is false
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub markers: Option<String>,
+    /// Extras of the package to install along with it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extras: Vec<String>,
+    /// Warn and carry on instead of failing when the package cannot be resolved
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub optional: bool,
+    /// `[tool.cobra.registries]` entry the package is fetched from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<String>,
+}
+
+fn any_version() -> String {
+    "*".to_string()
+}
+
+impl DependencySpec {
+    /// The version requirement; a marker group has none of its own
+    pub fn version(&self) -> &str {
+        match self {
+            DependencySpec::Version(version) => version,
+            DependencySpec::Detailed(detailed) => &detailed.version,
+            DependencySpec::Group(_) => "*",
+        }
+    }
+
+    pub fn markers(&self) -> Option<&str> {
+        match self {
+            DependencySpec::Detailed(detailed) => detailed.markers.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub fn extras(&self) -> &[String] {
+        match self {
+            DependencySpec::Detailed(detailed) => &detailed.extras,
+            _ => &[],
+        }
+    }
+
+    pub fn is_optional(&self) -> bool {
+        matches!(self, DependencySpec::Detailed(detailed) if detailed.optional)
+    }
+
+    pub fn index(&self) -> Option<&str> {
+        match self {
+            DependencySpec::Detailed(detailed) => detailed.index.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Replace the version requirement, keeping any other options
+    pub fn set_version(&mut self, version: &str) {
+        match self {
+            DependencySpec::Version(current) => *current = version.to_string(),
+            DependencySpec::Detailed(detailed) => detailed.version = version.to_string(),
+            DependencySpec::Group(_) => {}
+        }
+    }
+
+    /// The dependency named `name`, limited to where `group`'s marker holds
+    fn to_dependency(&self, name: &str, group: Option<&str>) -> Dependency {
+        Dependency {
+            name: name.to_string(),
+            version_spec: self.version().to_string(),
+            markers: markers::and_markers(group, self.markers()),
+            source: DependencySource::Registry,
+            extras: self.extras().to_vec(),
+            optional: self.is_optional(),
+        }
+    }
+}
+
+/// `(name, spec, group marker)` of every package in a dependency table, by
+/// name, with the packages of marker groups taken out of their group
+fn table_entries(table: &HashMap<String, DependencySpec>) -> Vec<(&String, &DependencySpec, Option<&str>)> {
+    let mut entries = Vec::new();
+    for (key, spec) in table {
+        match spec {
+            DependencySpec::Group(group) => {
+                entries.extend(group.iter().map(|(name, spec)| (name, spec, Some(key.as_str()))));
+            }
+            _ => entries.push((key, spec, None)),
+        }
+    }
+    entries.sort_by(|a, b| a.0.cmp(b.0).then(a.2.cmp(&b.2)));
+    entries
+}
+
+/// The entry for `name`, at the top of `table` or inside a marker group
+fn table_entry_mut<'a>(table: &'a mut HashMap<String, DependencySpec>, name: &str) -> Option<&'a mut DependencySpec> {
+    if table.get(name).is_some_and(|spec| !matches!(spec, DependencySpec::Group(_))) {
+        return table.get_mut(name);
+    }
+    table.values_mut().find_map(|spec| match spec {
+        DependencySpec::Group(group) => group.get_mut(name),
+        _ => None,
+    })
+}
+
+impl From<&str> for DependencySpec {
+    fn from(version: &str) -> Self {
+        DependencySpec::Version(version.to_string())
+    }
+}
+
+/// A semantic problem in cobra.toml and the key it concerns
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigProblem {
+    /// Dotted table name, e.g. `tool.cobra`
+    pub table: String,
+    pub key: String,
+    pub message: String,
+}
+
+impl ConfigProblem {
+    fn new(table: &str, key: &str, message: impl Into<String>) -> Self {
+        Self { table: table.to_string(), key: key.to_string(), message: message.into() }
+    }
+
+    /// Prefix the line of the offending key in `contents` when it can be found
+    pub fn located(&self, contents: &str) -> String {
+        match key_line(contents, &self.table, &self.key) {
+            Some(line) => format!("line {}: {}", line, self),
+            None => self.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}", self.table, self.message)
+    }
+}
+
+/// 1-based line and column of byte `offset` in `contents`
+fn line_column(contents: &str, offset: usize) -> (usize, usize) {
+    let before = &contents[..offset.min(contents.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    (line, column)
+}
+
+/// 1-based line defining `key` directly under the `[table]` header, or the
+/// `[table.key]` header of a subtable
+fn key_line(contents: &str, table: &str, key: &str) -> Option<usize> {
+    let unquote = |s: &str| {
+        let s = s.trim();
+        ['"', '\''].iter()
+            .find_map(|q| s.strip_prefix(*q).and_then(|s| s.strip_suffix(*q)))
+            .unwrap_or(s)
+            .to_string()
+    };
+    let mut current = String::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix('[') {
+            let header = header.trim_start_matches('[');
+            let header = header.rsplit_once(']').map_or(header, |(header, _)| header).trim_end_matches(']');
+            current = header_keys(header).iter().map(|key| unquote(key)).collect::<Vec<_>>().join(".");
+            if current == format!("{}.{}", table, key) {
+                return Some(index + 1);
+            }
+        } else if let Some((name, _)) = line.split_once('=')
+            && current == table
+            && unquote(name) == key {
+            return Some(index + 1);
+        }
+    }
+    None
+}
+
+/// Dotted keys of a table header, leaving dots inside quoted keys alone
+fn header_keys(header: &str) -> Vec<&str> {
+    let mut keys = Vec::new();
+    let (mut start, mut quote) = (0, None);
+    for (i, c) in header.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(open)) if c == open => quote = None,
+            ('.', None) => {
+                keys.push(&header[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    keys.push(&header[start..]);
+    keys
+}
+
+/// Pairs of keys naming the same package once normalized, e.g. `Requests` and `requests`
+fn same_package_keys<'a>(keys: impl Iterator<Item = &'a String>) -> Vec<(String, String)> {
+    let mut keys: Vec<&String> = keys.collect();
+    keys.sort();
+    let mut seen: HashMap<String, &String> = HashMap::new();
+    let mut pairs = Vec::new();
+    for key in keys {
+        match seen.get(&normalize_name(key)) {
+            Some(first) => pairs.push(((*first).clone(), key.clone())),
+            None => {
+                seen.insert(normalize_name(key), key);
+            }
+        }
+    }
+    pairs
+}
+
+/// API a `[tool.cobra.registries]` entry speaks, `api` in its table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RegistryApi {
+    /// PackageCloud's REST API, for the `repo` at `url`
+    #[default]
+    PackageCloud,
+    /// A PEP 503 simple index rooted at `url`
+    Simple,
+}
+
+impl RegistryApi {
+    pub fn is_default(&self) -> bool {
+        *self == RegistryApi::default()
+    }
+}
+
+/// A `[tool.cobra.registries.<name>]` PackageCloud repository or simple index
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RegistryConfig {
+    #[serde(default, skip_serializing_if = "RegistryApi::is_default")]
+    pub api: RegistryApi,
+    /// PackageCloud `user/repo`; unused by simple indexes
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub repo: String,
+    /// API token for private repositories; prefer `cobra login <url>`, which
+    /// keeps it in the OS credential store instead
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Package names served only by this repository; `*` is a wildcard
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub packages: Vec<String>,
+    /// Also try this repository, before PyPI, for packages no pattern claims
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub default: bool,
+    /// Base URL of a self-hosted PackageCloud, or the root of a simple index
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// Which dependency tables take part in an operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DependencySelection {
+    /// `[dependencies]` only
+    #[default]
+    Main,
+    /// `[dependencies]` plus `[dev-dependencies]`
+    WithDev,
+    /// `[dev-dependencies]` only
+    OnlyDev,
+}
+
+impl DependencySelection {
+    pub fn from_flags(dev: bool, only_dev: bool) -> Self {
+        match (dev, only_dev) {
+            (_, true) => DependencySelection::OnlyDev,
+            (true, false) => DependencySelection::WithDev,
+            (false, false) => DependencySelection::Main,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ProjectInfo {
     pub name: String,
@@ -33,12 +339,85 @@ pub struct ToolConfig {
 pub struct CobraToolConfig {
     #[serde(default = "default_python_version", rename = "python-version")]
     pub python_version: String,
+    /// Maximum concurrent registry requests (metadata and archives)
     #[serde(default = "default_parallel_downloads", rename = "parallel-downloads")]
     pub parallel_downloads: usize,
+    /// Maximum packages extracted at once
+    #[serde(default = "default_parallel_installs", rename = "parallel-installs")]
+    pub parallel_installs: usize,
+    /// Concurrent Range requests per large archive, sharing `parallel-downloads`
+    #[serde(default = "default_download_segments", rename = "download-segments")]
+    pub download_segments: usize,
+    /// Bytes written per step while a download is throttled by `max-download-rate`
+    #[serde(default = "default_chunk_size", rename = "chunk-size")]
+    pub chunk_size: usize,
+    /// Most bytes one wheel may unpack to; larger archives are refused as decompression bombs
+    #[serde(default = "default_max_unpacked_size", rename = "max-unpacked-size")]
+    pub max_unpacked_size: u64,
     #[serde(default = "default_cache_enabled", rename = "cache-enabled")]
     pub cache_enabled: bool,
     #[serde(default = "default_install_dir", rename = "install-dir")]
     pub install_dir: String,
+    /// Combined download throughput cap in bytes per second; unset means unlimited
+    #[serde(default, rename = "max-download-rate", skip_serializing_if = "Option::is_none")]
+    pub max_download_rate: Option<u64>,
+    /// Deadline for the whole dependency resolution phase
+    #[serde(default = "default_resolve_timeout_secs", rename = "resolve-timeout-secs")]
+    pub resolve_timeout_secs: u64,
+    /// Seconds cached package metadata is trusted before it is fetched again
+    #[serde(default = "default_metadata_cache_ttl", rename = "metadata-cache-ttl")]
+    pub metadata_cache_ttl: u64,
+    /// Deadline for establishing a registry connection
+    #[serde(default = "default_connect_timeout_secs", rename = "connect-timeout-secs")]
+    pub connect_timeout_secs: u64,
+    /// Deadline for each metadata request, kept short so dead indexes fail fast
+    #[serde(default = "default_metadata_timeout_secs", rename = "metadata-timeout-secs")]
+    pub metadata_timeout_secs: u64,
+    /// Deadline for each archive download; unset means none
+    #[serde(default, rename = "download-timeout-secs", skip_serializing_if = "Option::is_none")]
+    pub download_timeout_secs: Option<u64>,
+    /// Retries of a registry request failing with a connection error, timeout or 5xx
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    /// Backoff before the first retry, doubled for every further one
+    #[serde(default = "default_retry_base_delay_ms", rename = "retry-base-delay-ms")]
+    pub retry_base_delay_ms: u64,
+    /// File hosts tried in order when an archive's own URL fails, e.g.
+    /// `["https://mirror.corp/pypi"]`; the archive's path is kept
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mirrors: Vec<String>,
+    /// How packages without a wheel are built: "cobra", "pip" or "build"
+    #[serde(default, rename = "build-frontend", skip_serializing_if = "BuildFrontend::is_default")]
+    pub build_frontend: BuildFrontend,
+    /// `[tool.cobra.verify]`: shell command run after a package installs
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub verify: HashMap<String, String>,
+    /// Import every installed package's top-level modules after `cobra install`
+    #[serde(default, rename = "verify-imports", skip_serializing_if = "ImportCheck::is_default")]
+    pub verify_imports: ImportCheck,
+    /// `[tool.cobra.registries]`: private PackageCloud repositories by name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub registries: HashMap<String, RegistryConfig>,
+    /// `[tool.cobra.hooks]`: shell commands run around `cobra install`
+    #[serde(default, skip_serializing_if = "HooksConfig::is_empty")]
+    pub hooks: HooksConfig,
+}
+
+/// Commands `cobra install` runs in order, failing the install on a nonzero exit
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct HooksConfig {
+    /// Run before resolving dependencies
+    #[serde(default, rename = "pre-install", skip_serializing_if = "Vec::is_empty")]
+    pub pre_install: Vec<String>,
+    /// Run after every package installed successfully
+    #[serde(default, rename = "post-install", skip_serializing_if = "Vec::is_empty")]
+    pub post_install: Vec<String>,
+}
+
+impl HooksConfig {
+    pub fn is_empty(&self) -> bool {
+        self.pre_install.is_empty() && self.post_install.is_empty()
+    }
 }
 
 impl Default for CobraToolConfig {
@@ -46,8 +425,36 @@ impl Default for CobraToolConfig {
         Self {
             python_version: default_python_version(),
             parallel_downloads: default_parallel_downloads(),
+            parallel_installs: default_parallel_installs(),
+            download_segments: default_download_segments(),
+            chunk_size: default_chunk_size(),
+            max_unpacked_size: default_max_unpacked_size(),
             cache_enabled: default_cache_enabled(),
             install_dir: default_install_dir(),
+            max_download_rate: None,
+            resolve_timeout_secs: default_resolve_timeout_secs(),
+            metadata_cache_ttl: default_metadata_cache_ttl(),
+            connect_timeout_secs: default_connect_timeout_secs(),
+            metadata_timeout_secs: default_metadata_timeout_secs(),
+            download_timeout_secs: None,
+            retries: default_retries(),
+            retry_base_delay_ms: default_retry_base_delay_ms(),
+            mirrors: Vec::new(),
+            build_frontend: BuildFrontend::default(),
+            verify: HashMap::new(),
+            verify_imports: ImportCheck::default(),
+            registries: HashMap::new(),
+            hooks: HooksConfig::default(),
+        }
+    }
+}
+
+impl CobraToolConfig {
+    fn http_timeouts(&self) -> HttpTimeouts {
+        HttpTimeouts {
+            connect: std::time::Duration::from_secs(self.connect_timeout_secs),
+            metadata: std::time::Duration::from_secs(self.metadata_timeout_secs),
+            download: self.download_timeout_secs.map(std::time::Duration::from_secs),
         }
     }
 }
@@ -57,7 +464,23 @@ fn default_python_version() -> String {
 }
 
 fn default_parallel_downloads() -> usize {
-    16
+    crate::constants::MAX_CONCURRENT_DOWNLOADS
+}
+
+fn default_parallel_installs() -> usize {
+    crate::constants::MAX_CONCURRENT_INSTALLS
+}
+
+fn default_download_segments() -> usize {
+    crate::constants::DOWNLOAD_SEGMENTS
+}
+
+fn default_chunk_size() -> usize {
+    crate::constants::CHUNK_SIZE
+}
+
+fn default_max_unpacked_size() -> u64 {
+    crate::constants::MAX_UNPACKED_SIZE
 }
 
 fn default_cache_enabled() -> bool {
@@ -68,12 +491,216 @@ fn default_install_dir() -> String {
     ".cobra_packages".to_string()
 }
 
+fn default_resolve_timeout_secs() -> u64 {
+    crate::constants::RESOLVE_TIMEOUT.as_secs()
+}
+
+fn default_metadata_cache_ttl() -> u64 {
+    crate::constants::METADATA_CACHE_TTL.as_secs()
+}
+
+fn default_connect_timeout_secs() -> u64 {
+    crate::constants::CONNECT_TIMEOUT.as_secs()
+}
+
+fn default_metadata_timeout_secs() -> u64 {
+    crate::constants::METADATA_TIMEOUT.as_secs()
+}
+
+fn default_retries() -> u32 {
+    crate::constants::MAX_RETRIES
+}
+
+fn default_retry_base_delay_ms() -> u64 {
+    crate::constants::RETRY_BASE_DELAY.as_millis() as u64
+}
+
 impl CobraConfig {
+    /// Parse and validate `path`. Syntax errors carry their line and column;
+    /// semantic problems are all reported together, located where possible.
     pub async fn load(path: &Path) -> Result<Self> {
         let contents = fs::read_to_string(path).await?;
-        let config: CobraConfig = toml::from_str(&contents)
-            .map_err(|e| CobraError::Config(format!("Failed to parse cobra.toml: {}", e)))?;
-        Ok(config)
+        let config: CobraConfig = toml::from_str(&contents).map_err(|e| {
+            let (line, column) = e.span().map_or((1, 1), |span| line_column(&contents, span.start));
+            CobraError::Config(format!("{}:{}:{}: {}", path.display(), line, column, e.message().trim_end()))
+        })?;
+
+        let problems = config.problems();
+        if problems.is_empty() {
+            return Ok(config);
+        }
+        Err(CobraError::InvalidConfig {
+            path: path.display().to_string(),
+            problems: problems.iter().map(|problem| problem.located(&contents)).collect(),
+        })
+    }
+
+    /// Reject configurations that parse but cannot be applied consistently
+    pub fn validate(&self) -> Result<()> {
+        let problems = self.problems();
+        if problems.is_empty() {
+            return Ok(());
+        }
+        Err(CobraError::InvalidConfig {
+            path: "cobra.toml".to_string(),
+            problems: problems.iter().map(ToString::to_string).collect(),
+        })
+    }
+
+    /// Every semantic problem in this configuration, not just the first
+    pub fn problems(&self) -> Vec<ConfigProblem> {
+        let mut problems = Vec::new();
+        if self.project.name.trim().is_empty() {
+            problems.push(ConfigProblem::new("project", "name", "name must not be empty"));
+        }
+        if Version::parse(&self.project.version).is_err() {
+            problems.push(ConfigProblem::new("project", "version", format!(
+                "version '{}' is not a valid PEP 440 version", self.project.version
+            )));
+        }
+        for (table, names) in [("dependencies", &self.dependencies), ("dev-dependencies", &self.dev_dependencies)] {
+            let mut groups: Vec<&String> = names.iter()
+                .filter(|(_, spec)| matches!(spec, DependencySpec::Group(_)))
+                .map(|(marker, _)| marker)
+                .collect();
+            groups.sort();
+            for marker in groups {
+                if markers::applies_on_some_platform(marker).is_err() {
+                    problems.push(ConfigProblem::new(table, marker, format!(
+                        "'{}' is neither a package with a version nor a valid PEP 508 marker", marker
+                    )));
+                }
+            }
+
+            let entries = table_entries(names);
+            for (name, spec, group) in &entries {
+                let table = match group {
+                    Some(marker) => format!("{}.{}", table, marker),
+                    None => table.to_string(),
+                };
+                if matches!(spec, DependencySpec::Group(_)) {
+                    problems.push(ConfigProblem::new(&table, name, format!(
+                        "{} is a marker group inside a marker group; combine the markers with 'and' instead", name
+                    )));
+                    continue;
+                }
+                if VersionSpec::parse(spec.version()).is_err() {
+                    problems.push(ConfigProblem::new(&table, name, format!(
+                        "{} has an invalid version requirement '{}'", name, spec.version()
+                    )));
+                }
+                if let Some(index) = spec.index()
+                    && !self.tool.cobra.registries.contains_key(index) {
+                    problems.push(ConfigProblem::new(&table, name, format!(
+                        "{} uses index '{}', which is not in [tool.cobra.registries]", name, index
+                    )));
+                }
+            }
+            // One entry per package, so a marker group cannot shadow another entry
+            for (first, second) in same_package_keys(entries.iter().map(|(name, _, _)| *name)) {
+                let message = if first == second {
+                    format!("{} is listed more than once; give each entry's marker to a single entry", first)
+                } else {
+                    format!("'{}' and '{}' name the same package", first, second)
+                };
+                problems.push(ConfigProblem::new(table, &second, message));
+            }
+        }
+        for (first, second) in same_package_keys(self.overrides.keys()) {
+            problems.push(ConfigProblem::new("overrides", &second, format!(
+                "Conflicting overrides for {}: '{} = \"{}\"' and '{} = \"{}\"'",
+                normalize_name(&first), first, self.overrides[&first], second, self.overrides[&second]
+            )));
+        }
+
+        let cobra = &self.tool.cobra;
+        for (key, value) in [
+            ("parallel-downloads", cobra.parallel_downloads),
+            ("parallel-installs", cobra.parallel_installs),
+        ] {
+            if let Err(message) = check_parallelism(key, value) {
+                problems.push(ConfigProblem::new("tool.cobra", key, message));
+            }
+        }
+        for (key, value) in [
+            ("download-segments", cobra.download_segments),
+            ("chunk-size", cobra.chunk_size),
+        ] {
+            if value == 0 {
+                problems.push(ConfigProblem::new("tool.cobra", key, format!("{} must be at least 1", key)));
+            }
+        }
+        if cobra.max_unpacked_size == 0 {
+            problems.push(ConfigProblem::new("tool.cobra", "max-unpacked-size", "max-unpacked-size must be at least 1".to_string()));
+        }
+        for (key, value) in [
+            ("connect-timeout-secs", Some(cobra.connect_timeout_secs)),
+            ("metadata-timeout-secs", Some(cobra.metadata_timeout_secs)),
+            ("download-timeout-secs", cobra.download_timeout_secs),
+        ] {
+            if value == Some(0) {
+                problems.push(ConfigProblem::new("tool.cobra", key, format!("{} must be at least 1 second", key)));
+            }
+        }
+        if cobra.max_download_rate == Some(0) {
+            problems.push(ConfigProblem::new(
+                "tool.cobra", "max-download-rate", "max-download-rate must be at least 1 byte per second"
+            ));
+        }
+        for mirror in &cobra.mirrors {
+            let valid = reqwest::Url::parse(mirror)
+                .is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.has_host());
+            if !valid {
+                problems.push(ConfigProblem::new("tool.cobra", "mirrors", format!(
+                    "mirror '{}' is not an http(s) URL", mirror
+                )));
+            }
+        }
+        let mut names: Vec<&String> = cobra.registries.keys().collect();
+        names.sort();
+        for name in names {
+            let registry = &cobra.registries[name];
+            let table = format!("tool.cobra.registries.{}", name);
+            match registry.api {
+                RegistryApi::PackageCloud if registry.repo.is_empty() => problems.push(ConfigProblem::new(
+                    &table, "repo", "a PackageCloud registry needs a repo such as \"user/repo\""
+                )),
+                RegistryApi::Simple if registry.url.is_none() => problems.push(ConfigProblem::new(
+                    &table, "url", "a simple index needs the url of its root, e.g. \"https://host/simple\""
+                )),
+                _ => {}
+            }
+        }
+        let mut defaults: Vec<&String> = cobra.registries.iter()
+            .filter(|(_, registry)| registry.default)
+            .map(|(name, _)| name)
+            .collect();
+        defaults.sort();
+        if defaults.len() > 1 {
+            for name in &defaults[1..] {
+                problems.push(ConfigProblem::new(&format!("tool.cobra.registries.{}", name), "default", format!(
+                    "only one registry can be the default, found {}", defaults.len()
+                )));
+            }
+        }
+        problems
+    }
+
+    /// Overrides keyed by normalized package name; spellings of the same
+    /// package that normalize together (`Foo_Bar` / `foo-bar`) are a conflict
+    pub fn get_overrides(&self) -> Result<HashMap<String, String>> {
+        let mut overrides: HashMap<String, (String, String)> = HashMap::new();
+        for (name, spec) in &self.overrides {
+            let normalized = normalize_name(name);
+            if let Some((other_name, other_spec)) = overrides.get(&normalized) {
+                return Err(CobraError::Config(format!(
+                    "Conflicting overrides for {}: '{} = \"{}\"' and '{} = \"{}\"'",
+                    normalized, other_name, other_spec, name, spec
+                )));
+            }
+            overrides.insert(normalized, (name.clone(), spec.clone()));
+        }
+        Ok(overrides.into_iter().map(|(name, (_, spec))| (name, spec)).collect())
     }
 
     pub async fn save(&self, path: &Path) -> Result<()> {
@@ -84,31 +711,248 @@ impl CobraConfig {
     }
 
     pub fn add_dependency(&mut self, name: &str, version: &str) {
-        self.dependencies.insert(name.to_string(), version.to_string());
+        upsert(&mut self.dependencies, name, version);
     }
 
+    pub fn add_dev_dependency(&mut self, name: &str, version: &str) {
+        upsert(&mut self.dev_dependencies, name, version);
+    }
+
+    /// Remove `name` from `[dependencies]` or the marker group listing it
     pub fn remove_dependency(&mut self, name: &str) -> bool {
-        self.dependencies.remove(name).is_some()
+        if table_entry_mut(&mut self.dev_dependencies, name).is_none() {
+            self.sources.remove(name);
+        }
+        if self.dependencies.get(name).is_some_and(|spec| !matches!(spec, DependencySpec::Group(_))) {
+            return self.dependencies.remove(name).is_some();
+        }
+        let mut removed = false;
+        self.dependencies.retain(|_, spec| {
+            if let DependencySpec::Group(group) = spec {
+                removed |= group.remove(name).is_some();
+                return !group.is_empty();
+            }
+            true
+        });
+        removed
     }
 
     pub fn get_dependency(&self, name: &str) -> Option<String> {
-        self.dependencies.get(name).cloned()
+        table_entries(&self.dependencies).into_iter()
+            .find(|(entry, _, _)| *entry == name)
+            .map(|(_, spec, _)| spec.version().to_string())
     }
 
     /// Convert HashMap dependencies to Vec<Dependency> for processing
     pub fn get_dependencies_list(&self) -> Vec<Dependency> {
-        self.dependencies
-            .iter()
-            .map(|(name, version_spec)| Dependency {
-                name: name.clone(),
-                version_spec: version_spec.clone(),
+        table_entries(&self.dependencies)
+            .into_iter()
+            .map(|(name, spec, group)| spec.to_dependency(name, group))
+            .collect()
+    }
+
+    /// Dependencies for the given selection, with their `[sources]` entry; a
+    /// package listed in both tables keeps its `[dependencies]` spec
+    pub fn select_dependencies(&self, selection: DependencySelection) -> Vec<Dependency> {
+        let to_list = |table: &HashMap<String, DependencySpec>| -> Vec<Dependency> {
+            table_entries(table)
+                .into_iter()
+                .map(|(name, spec, group)| spec.to_dependency(name, group))
+                .collect()
+        };
+
+        let selected = match selection {
+            DependencySelection::Main => self.get_dependencies_list(),
+            DependencySelection::OnlyDev => to_list(&self.dev_dependencies),
+            DependencySelection::WithDev => {
+                let mut deps = self.get_dependencies_list();
+                let main: Vec<String> = deps.iter().map(|d| d.name.clone()).collect();
+                deps.extend(
+                    to_list(&self.dev_dependencies)
+                        .into_iter()
+                        .filter(|d| !main.contains(&d.name)),
+                );
+                deps
+            }
+        };
+        selected.into_iter()
+            .map(|mut d| {
+                d.source = self.sources.get(&d.name).cloned().unwrap_or_default();
+                d
             })
             .collect()
     }
 
-    /// Get install directory path
-    pub fn get_install_dir(&self) -> String {
-        self.tool.cobra.install_dir.clone()
+    /// Record where `name` is built from instead of the package index
+    pub fn add_source(&mut self, name: &str, source: DependencySource) {
+        self.sources.insert(name.to_string(), source);
+    }
+
+    /// Record `name` as built from the project directory at `path`
+    pub fn add_path_source(&mut self, name: &str, path: &str) {
+        self.add_source(name, DependencySource::Path { path: path.to_string() });
+    }
+
+    /// Selected dependencies that are fetched from the package index
+    pub fn registry_dependencies(&self, selection: DependencySelection) -> Vec<Dependency> {
+        self.select_dependencies(selection)
+            .into_iter()
+            .filter(|d| d.source.is_registry())
+            .collect()
+    }
+
+    /// Selected dependencies built from a local directory or git repository, by name
+    pub fn source_dependencies(&self, selection: DependencySelection) -> Vec<Dependency> {
+        let mut deps: Vec<Dependency> = self.select_dependencies(selection)
+            .into_iter()
+            .filter(|d| !d.source.is_registry())
+            .collect();
+        deps.sort_by(|a, b| a.name.cmp(&b.name));
+        deps
+    }
+
+    /// Where packages are installed: `COBRA_INSTALL_DIR` when set, otherwise
+    /// `install-dir`, with `~` and environment variables expanded
+    pub fn install_dir(&self) -> Result<PathBuf> {
+        self.resolve_install_dir(&std::env::current_dir()?, |name| std::env::var(name).ok())
+    }
+
+    /// [`Self::install_dir`] with the working directory relative paths are
+    /// taken from and the environment injected
+    pub fn resolve_install_dir(&self, cwd: &Path, env: impl Fn(&str) -> Option<String>) -> Result<PathBuf> {
+        let configured = env("COBRA_INSTALL_DIR")
+            .filter(|value| !value.trim().is_empty())
+            .unwrap_or_else(|| self.tool.cobra.install_dir.clone());
+        Ok(cwd.join(expand_path(&configured, env)?))
+    }
+
+    /// Post-install verification commands keyed by normalized package name
+    pub fn verify_commands(&self) -> HashMap<String, String> {
+        self.tool.cobra.verify.iter()
+            .map(|(name, command)| (normalize_name(name), command.clone()))
+            .collect()
+    }
+
+    /// Deadline for resolving the dependency graph
+    pub fn resolve_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.tool.cobra.resolve_timeout_secs)
+    }
+
+    /// How long cached package metadata stays valid
+    pub fn metadata_cache_ttl(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.tool.cobra.metadata_cache_ttl)
+    }
+
+    /// Registry request deadlines from `[tool.cobra]`
+    pub fn http_timeouts(&self) -> HttpTimeouts {
+        self.tool.cobra.http_timeouts()
+    }
+
+    /// Concurrency and I/O sizes from `[tool.cobra]`, overridden by the
+    /// `COBRA_PARALLEL`, `COBRA_PARALLEL_DOWNLOADS` and `COBRA_PARALLEL_INSTALLS`
+    /// environment variables
+    pub fn settings(&self) -> Result<Settings> {
+        Settings::from_config(&self.tool.cobra, |name| std::env::var(name).ok())
+    }
+
+    /// Registry request retries from `[tool.cobra]`
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            retries: self.tool.cobra.retries,
+            base_delay: std::time::Duration::from_millis(self.tool.cobra.retry_base_delay_ms),
+        }
+    }
+
+    /// The `[tool.cobra.registries]` repositories, in name order so overlapping
+    /// patterns resolve the same way every run
+    pub fn private_registries(&self) -> Arc<PrivateRegistries> {
+        let mut names: Vec<&String> = self.tool.cobra.registries.keys().collect();
+        names.sort();
+        let registries = names.into_iter().fold(PrivateRegistries::new(), |registries, name| {
+            let config = &self.tool.cobra.registries[name];
+            let url = config.url.as_deref().unwrap_or(PACKAGECLOUD_URL).trim_end_matches('/');
+            let backend: Arc<dyn RegistryBackend> = match config.api {
+                RegistryApi::PackageCloud => Arc::new(
+                    PackageCloudRegistry::with_custom_url(url.to_string(), config.token.clone()).with_repo(&config.repo)
+                ),
+                RegistryApi::Simple => Arc::new(SimpleIndex::new(url.to_string(), config.token.clone())),
+            };
+            // Dependencies naming this registry as their `index` are claimed by it too
+            let mut packages = config.packages.clone();
+            packages.extend(
+                table_entries(&self.dependencies).into_iter().chain(table_entries(&self.dev_dependencies))
+                    .filter(|(_, spec, _)| spec.index() == Some(name.as_str()))
+                    .map(|(dependency, _, _)| dependency.clone()),
+            );
+            registries.with_backend(backend, &packages, config.default)
+        });
+        Arc::new(registries)
+    }
+
+    /// A pypi.org client with this project's timeouts and retries
+    pub fn registry_client(&self) -> RegistryClient {
+        RegistryClient::new()
+            .with_timeouts(self.http_timeouts())
+            .with_retry(self.retry_policy())
+    }
+}
+
+/// Runtime limits the installer and resolver are sized with, and the
+/// mirrors downloads fall back to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub parallel_downloads: usize,
+    pub parallel_installs: usize,
+    pub download_segments: usize,
+    pub chunk_size: usize,
+    pub max_unpacked_size: u64,
+    pub timeouts: HttpTimeouts,
+    pub mirrors: Vec<String>,
+}
+
+impl Settings {
+    /// `config` with environment overrides read through `env`. `COBRA_PARALLEL`
+    /// sets both limits; the specific variables win over it.
+    pub fn from_config(config: &CobraToolConfig, env: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let parallel = |name: &str| -> Result<Option<usize>> {
+            let Some(value) = env(name) else {
+                return Ok(None);
+            };
+            let parsed = value.trim().parse::<usize>()
+                .map_err(|_| format!("{} must be a number, got '{}'", name, value))
+                .and_then(|n| check_parallelism(name, n).map(|_| n));
+            parsed.map(Some).map_err(CobraError::Config)
+        };
+        let both = parallel("COBRA_PARALLEL")?;
+
+        Ok(Self {
+            parallel_downloads: parallel("COBRA_PARALLEL_DOWNLOADS")?.or(both).unwrap_or(config.parallel_downloads),
+            parallel_installs: parallel("COBRA_PARALLEL_INSTALLS")?.or(both).unwrap_or(config.parallel_installs),
+            download_segments: config.download_segments,
+            chunk_size: config.chunk_size,
+            max_unpacked_size: config.max_unpacked_size,
+            timeouts: config.http_timeouts(),
+            mirrors: config.mirrors.clone(),
+        })
+    }
+}
+
+fn check_parallelism(key: &str, value: usize) -> std::result::Result<(), String> {
+    let max = crate::constants::MAX_PARALLELISM;
+    if (1..=max).contains(&value) {
+        return Ok(());
+    }
+    Err(format!("{} must be between 1 and {}, got {}", key, max, value))
+}
+
+/// Set a dependency's version, keeping markers, other options and the marker
+/// group of an existing entry
+fn upsert(table: &mut HashMap<String, DependencySpec>, name: &str, version: &str) {
+    match table_entry_mut(table, name) {
+        Some(spec) => spec.set_version(version),
+        None => {
+            table.insert(name.to_string(), DependencySpec::from(version));
+        }
     }
 }
 
@@ -122,6 +966,8 @@ impl Default for CobraConfig {
             },
             dependencies: HashMap::new(),
             dev_dependencies: HashMap::new(),
+            overrides: HashMap::new(),
+            sources: HashMap::new(),
             tool: ToolConfig::default(),
         }
     }
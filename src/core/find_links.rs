@@ -0,0 +1,216 @@
+use crate::{Result, CobraError, Package, Dependency, normalize_name};
+use crate::core::build::wheel_metadata;
+use crate::core::resolver::{supports_python, ResolutionStrategy};
+use crate::core::version::{self, Version};
+use crate::utils::fs::{file_url, file_url_path};
+use crate::utils::hash::sha256_hex;
+use std::path::{Path, PathBuf};
+
+/// The parts of a wheel filename, `{name}-{version}(-{build})?-{python}-{abi}-{platform}.whl`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WheelFilename {
+    pub name: String,
+    pub version: String,
+    pub build: Option<String>,
+    pub python_tags: Vec<String>,
+    pub abi_tags: Vec<String>,
+    pub platform_tags: Vec<String>,
+}
+
+impl WheelFilename {
+    /// `None` for anything but a wheel filename
+    pub fn parse(filename: &str) -> Option<Self> {
+        let stem = filename.strip_suffix(".whl")?;
+        let parts: Vec<&str> = stem.split('-').collect();
+        let (name, version, build, tags) = match parts.as_slice() {
+            [name, version, tags @ ..] if tags.len() == 3 => (name, version, None, tags),
+            [name, version, build, tags @ ..] if tags.len() == 3 => (name, version, Some(build.to_string()), tags),
+            _ => return None,
+        };
+        if name.is_empty() || version.is_empty() {
+            return None;
+        }
+        let split = |tag: &str| tag.split('.').map(str::to_string).collect();
+        Some(Self {
+            name: name.to_string(),
+            version: version.to_string(),
+            build,
+            python_tags: split(tags[0]),
+            abi_tags: split(tags[1]),
+            platform_tags: split(tags[2]),
+        })
+    }
+
+    /// Whether the wheel runs on CPython `python` (any Python 3 when unknown)
+    /// on this operating system and architecture
+    pub fn is_compatible(&self, python: Option<&Version>) -> bool {
+        let (major, minor) = python
+            .map(|python| (python.release.first().copied().unwrap_or(3), python.release.get(1).copied()))
+            .unwrap_or((3, None));
+        let abi3 = self.abi_tags.iter().any(|abi| abi == "abi3");
+        let python_ok = self.python_tags.iter().any(|tag| {
+            let (exact, digits) = match (tag.strip_prefix("py"), tag.strip_prefix("cp")) {
+                (Some(digits), _) => (false, digits),
+                // Extension modules are built for one minor version, unless
+                // they only use the stable ABI
+                (_, Some(digits)) => (!abi3, digits),
+                _ => return false,
+            };
+            let Some(tag_major) = digits.get(..1).and_then(|d| d.parse::<u64>().ok()) else {
+                return false;
+            };
+            let tag_minor = digits.get(1..).filter(|d| !d.is_empty()).and_then(|d| d.parse::<u64>().ok());
+            tag_major == major && match (tag_minor, minor) {
+                (Some(tag_minor), Some(minor)) if exact => tag_minor == minor,
+                (Some(tag_minor), Some(minor)) => tag_minor <= minor,
+                _ => true,
+            }
+        });
+        python_ok && self.platform_tags.iter().any(|platform| platform == "any" || runs_here(platform))
+    }
+}
+
+/// Whether a platform tag such as `manylinux_2_17_x86_64` or `win_amd64`
+/// names this operating system and architecture
+fn runs_here(platform: &str) -> bool {
+    let os = match std::env::consts::OS {
+        "linux" => ["linux", "manylinux", "musllinux"].iter().any(|os| platform.starts_with(os)),
+        "macos" => platform.starts_with("macosx"),
+        "windows" => platform.starts_with("win"),
+        _ => false,
+    };
+    let arch = match std::env::consts::ARCH {
+        "x86_64" => platform.ends_with("x86_64") || platform.ends_with("amd64") || platform.ends_with("intel")
+            || platform.ends_with("universal2"),
+        "aarch64" => platform.ends_with("aarch64") || platform.ends_with("arm64") || platform.ends_with("universal2"),
+        "x86" => platform.ends_with("i686") || platform == "win32",
+        arch => platform.ends_with(arch),
+    };
+    os && arch
+}
+
+/// A wheel found in a `--find-links` directory or named on the command line
+#[derive(Debug, Clone)]
+pub struct LocalWheel {
+    pub path: PathBuf,
+    pub filename: WheelFilename,
+    /// Computed from the file, since no index vouches for it
+    pub sha256: String,
+    pub size: u64,
+    pub requires: Vec<Dependency>,
+    pub requires_python: Option<String>,
+}
+
+impl LocalWheel {
+    /// Read a wheel's METADATA and digest; `None` if `path` isn't named like a wheel
+    pub fn read(path: &Path) -> Result<Option<Self>> {
+        let Some(filename) = path.file_name().and_then(|name| WheelFilename::parse(&name.to_string_lossy())) else {
+            return Ok(None);
+        };
+        let data = std::fs::read(path)?;
+        let metadata = wheel_metadata(path)?;
+        Ok(Some(Self {
+            path: std::path::absolute(path)?,
+            filename,
+            sha256: sha256_hex(&data),
+            size: data.len() as u64,
+            requires: metadata.requires,
+            requires_python: metadata.requires_python,
+        }))
+    }
+
+    /// The package the resolver installs from this wheel
+    pub fn to_package(&self) -> Package {
+        Package {
+            name: self.filename.name.clone(),
+            version: self.filename.version.clone(),
+            dependencies: self.requires.clone(),
+            download_url: file_url(&self.path),
+            hash: Some(self.sha256.clone()),
+            size: Some(self.size),
+            description: None,
+            author: None,
+            homepage: None,
+            markers: None,
+            yanked: false,
+            yanked_reason: None,
+            requires_python: self.requires_python.clone(),
+        }
+    }
+}
+
+/// Wheels the resolver picks from before consulting any index, for
+/// air-gapped machines that ship a directory of downloaded wheels
+#[derive(Debug, Clone, Default)]
+pub struct FindLinks {
+    wheels: Vec<LocalWheel>,
+}
+
+impl FindLinks {
+    /// The wheels in each of `dirs`, which need not exist
+    pub fn scan(dirs: &[PathBuf]) -> Result<Self> {
+        let mut wheels = Vec::new();
+        for dir in dirs {
+            let entries = match std::fs::read_dir(dir) {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            let mut paths: Vec<PathBuf> = entries
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<std::io::Result<_>>()?;
+            paths.sort();
+            for path in paths.iter().filter(|path| path.is_file()) {
+                wheels.extend(LocalWheel::read(path)?);
+            }
+        }
+        Ok(Self { wheels })
+    }
+
+    /// Also offer the wheel at `path`, named on the command line
+    pub fn add_file(&mut self, path: &Path) -> Result<&LocalWheel> {
+        let wheel = LocalWheel::read(path)?.ok_or_else(|| CobraError::InvalidInput(format!(
+            "{} is not named like a wheel ({{name}}-{{version}}-{{python}}-{{abi}}-{{platform}}.whl)", path.display()
+        )))?;
+        self.wheels.push(wheel);
+        Ok(&self.wheels[self.wheels.len() - 1])
+    }
+
+    pub fn wheels(&self) -> &[LocalWheel] {
+        &self.wheels
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.wheels.is_empty()
+    }
+
+    /// The newest (or, with `Lowest`, oldest) wheel of `name` matching
+    /// `version_spec` that runs here, as a package with its local sha256
+    pub fn select(&self, name: &str, version_spec: &str, python: Option<&Version>, strategy: ResolutionStrategy) -> Option<Package> {
+        let name = normalize_name(name);
+        let candidates = self.wheels.iter()
+            .filter(|wheel| normalize_name(&wheel.filename.name) == name)
+            .filter(|wheel| version::satisfies(&wheel.filename.version, version_spec))
+            .filter(|wheel| wheel.filename.is_compatible(python))
+            .filter(|wheel| python.is_none_or(|python| supports_python(wheel.requires_python.as_deref(), python)))
+            .filter_map(|wheel| Version::parse(&wheel.filename.version).ok().map(|version| (version, wheel)));
+        let selected = match strategy {
+            ResolutionStrategy::Highest => candidates.max_by(|a, b| a.0.cmp(&b.0)),
+            ResolutionStrategy::Lowest => candidates.min_by(|a, b| a.0.cmp(&b.0)),
+        };
+        selected.map(|(_, wheel)| wheel.to_package())
+    }
+}
+
+/// The local file a `file://` package URL points at; `None` for other URLs
+/// or a file that is gone. Its digest is checked like any other archive's.
+pub async fn read_local_archive(package: &Package) -> Result<Option<bytes::Bytes>> {
+    let Some(path) = file_url_path(&package.download_url) else {
+        return Ok(None);
+    };
+    match tokio::fs::read(&path).await {
+        Ok(data) => Ok(Some(bytes::Bytes::from(data))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
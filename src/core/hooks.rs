@@ -0,0 +1,84 @@
+//! `[tool.cobra.hooks]` commands run before and after `cobra install`
+
+use crate::{Result, CobraError};
+use crate::utils::progress::ProgressTracker;
+use std::fmt;
+use std::path::PathBuf;
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookStage {
+    PreInstall,
+    PostInstall,
+}
+
+impl fmt::Display for HookStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            HookStage::PreInstall => "pre-install",
+            HookStage::PostInstall => "post-install",
+        })
+    }
+}
+
+/// What a hook learns about the install through its environment
+#[derive(Debug, Clone, Default)]
+pub struct HookContext {
+    /// `COBRA_INSTALL_DIR`
+    pub install_dir: PathBuf,
+    /// `COBRA_PACKAGES`, space-separated `name==version`; empty before resolution
+    pub packages: Vec<String>,
+}
+
+/// Run `commands` one after another through the platform shell, streaming
+/// their output above the progress bars. The first nonzero exit stops the
+/// rest and fails with `InstallationFailed`.
+pub async fn run_hooks(stage: HookStage, commands: &[String], context: &HookContext, progress: &ProgressTracker) -> Result<()> {
+    for command in commands {
+        progress.println(&format!("🪝 Running {} hook: {}", stage, command));
+        let mut child = shell(command)
+            .env("COBRA_HOOK", stage.to_string())
+            .env("COBRA_INSTALL_DIR", &context.install_dir)
+            .env("COBRA_PACKAGES", context.packages.join(" "))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| CobraError::InstallationFailed(format!("Could not run {} hook `{}`: {}", stage, command, e)))?;
+
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+        let (status, _, _) = tokio::join!(child.wait(), stream_lines(stdout, progress), stream_lines(stderr, progress));
+        let status = status?;
+        if !status.success() {
+            return Err(CobraError::InstallationFailed(format!(
+                "{} hook `{}` failed: {}", stage, command, status
+            )));
+        }
+    }
+    Ok(())
+}
+
+async fn stream_lines(output: impl AsyncRead + Unpin, progress: &ProgressTracker) {
+    let mut lines = BufReader::new(output).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        progress.println(&format!("  │ {}", line));
+    }
+}
+
+/// `command` run by `sh -c`, or `cmd /C` on Windows
+pub fn shell(command: &str) -> Command {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell.arg(command);
+    shell
+}
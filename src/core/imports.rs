@@ -0,0 +1,143 @@
+//! Import smoke test run after `cobra install --verify-imports`
+
+use crate::core::record::RecordEntry;
+use serde::{Deserialize, Serialize};
+use std::ffi::OsStr;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::process::Command;
+use tokio::sync::Semaphore;
+
+/// `verify-imports` under `[tool.cobra]`: `true`, `false` or `"warn"`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(try_from = "ImportCheckValue", into = "ImportCheckValue")]
+pub enum ImportCheck {
+    #[default]
+    Off,
+    /// A module that fails to import fails the install
+    Fail,
+    /// Failures are reported but the install still succeeds
+    Warn,
+}
+
+impl ImportCheck {
+    pub fn is_default(&self) -> bool {
+        *self == ImportCheck::default()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ImportCheckValue {
+    Enabled(bool),
+    Mode(String),
+}
+
+impl TryFrom<ImportCheckValue> for ImportCheck {
+    type Error = String;
+
+    fn try_from(value: ImportCheckValue) -> Result<Self, Self::Error> {
+        match value {
+            ImportCheckValue::Enabled(true) => Ok(ImportCheck::Fail),
+            ImportCheckValue::Enabled(false) => Ok(ImportCheck::Off),
+            ImportCheckValue::Mode(mode) => match mode.as_str() {
+                "warn" => Ok(ImportCheck::Warn),
+                "fail" => Ok(ImportCheck::Fail),
+                "off" => Ok(ImportCheck::Off),
+                _ => Err(format!("verify-imports must be true, false or \"warn\", got \"{}\"", mode)),
+            },
+        }
+    }
+}
+
+impl From<ImportCheck> for ImportCheckValue {
+    fn from(check: ImportCheck) -> Self {
+        match check {
+            ImportCheck::Off => ImportCheckValue::Enabled(false),
+            ImportCheck::Fail => ImportCheckValue::Enabled(true),
+            ImportCheck::Warn => ImportCheckValue::Mode("warn".to_string()),
+        }
+    }
+}
+
+/// A module of an installed package that could not be imported
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportFailure {
+    pub package: String,
+    pub module: String,
+    /// Python's traceback, or why the interpreter could not be started
+    pub traceback: String,
+}
+
+/// Top-level modules a package provides: the lines of its dist-info
+/// `top_level.txt` when it has one, otherwise the first component of each
+/// RECORD path that Python could import
+pub fn top_level_modules(files: &[RecordEntry], site_packages: &Path) -> Vec<String> {
+    let dist_info = files.iter()
+        .find_map(|f| f.path.strip_suffix("/RECORD").filter(|dir| dir.ends_with(".dist-info")));
+    let listed = dist_info
+        .and_then(|dir| std::fs::read_to_string(site_packages.join(dir).join("top_level.txt")).ok());
+
+    let mut modules: Vec<String> = match listed {
+        Some(text) => text.lines()
+            .map(|line| line.trim().replace('/', "."))
+            .filter(|module| module.split('.').all(is_identifier))
+            .collect(),
+        None => files.iter().filter_map(|f| record_module(&f.path)).collect(),
+    };
+    modules.sort();
+    modules.dedup();
+    modules
+}
+
+fn record_module(path: &str) -> Option<String> {
+    let (first, rest) = match path.split_once('/') {
+        Some((dir, rest)) => (dir, Some(rest)),
+        None => (path, None),
+    };
+    let module = match rest {
+        // Packages, including namespace packages without `__init__.py`
+        Some(_) => first,
+        // `six.py`, or an extension such as `_cffi_backend.cpython-311-x86_64-linux-gnu.so`
+        None if first.ends_with(".py") => first.strip_suffix(".py")?,
+        None if first.ends_with(".so") || first.ends_with(".pyd") => first.split('.').next()?,
+        None => return None,
+    };
+    (is_identifier(module) && module != "__pycache__").then(|| module.to_string())
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Run `python -c "import <module>"` for every `(package, module)` pair,
+/// at most `limit` at a time, returning the ones that failed
+pub async fn verify_imports(python: &Path, python_path: &OsStr, targets: &[(String, String)], limit: usize) -> Vec<ImportFailure> {
+    let semaphore = Arc::new(Semaphore::new(limit.max(1)));
+    let runs = targets.iter().map(|(package, module)| {
+        let semaphore = semaphore.clone();
+        async move {
+            let _permit = semaphore.acquire().await.expect("semaphore is never closed");
+            let traceback = try_import(python, python_path, module).await.err()?;
+            Some(ImportFailure { package: package.clone(), module: module.clone(), traceback })
+        }
+    });
+    futures::future::join_all(runs).await.into_iter().flatten().collect()
+}
+
+async fn try_import(python: &Path, python_path: &OsStr, module: &str) -> std::result::Result<(), String> {
+    let output = Command::new(python)
+        .arg("-c")
+        .arg(format!("import {}", module))
+        .env("PYTHONPATH", python_path)
+        .output()
+        .await
+        .map_err(|e| format!("could not run {}: {}", python.display(), e))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr).trim_end().to_string();
+    Err(if stderr.is_empty() { output.status.to_string() } else { stderr })
+}
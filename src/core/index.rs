@@ -0,0 +1,158 @@
+use crate::{Result, CobraError, Package, Dependency, normalize_name};
+use crate::core::resolver::{select_release, ResolutionStrategy};
+use crate::core::version::{Version, VersionSpec};
+use crate::registry::client::{select_artifact, Release, ReleaseFile, RegistryClient};
+use crate::utils::fs::{atomic_write, get_cache_dir};
+use chrono::{DateTime, Utc};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Every release of one project with its requirements, as stored in the index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedProject {
+    pub name: String,
+    pub synced_at: DateTime<Utc>,
+    /// Oldest version first
+    pub releases: Vec<IndexedRelease>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedRelease {
+    pub version: String,
+    pub dependencies: Vec<Dependency>,
+    pub files: Vec<ReleaseFile>,
+}
+
+/// Local mirror of version lists and dependency metadata (never archives)
+/// for packages added with `cobra index sync`. The resolver answers lookups
+/// for these packages without contacting the registry.
+pub struct LocalIndex {
+    dir: PathBuf,
+    /// Projects read so far; `None` remembers a package isn't indexed
+    loaded: Mutex<HashMap<String, Option<Arc<IndexedProject>>>>,
+}
+
+impl LocalIndex {
+    /// The index in cobra's cache directory
+    pub fn open_default() -> Result<Self> {
+        Ok(Self::open(get_cache_dir()?.join("index")))
+    }
+
+    pub fn open(dir: PathBuf) -> Self {
+        Self { dir, loaded: Mutex::new(HashMap::new()) }
+    }
+
+    /// Whether anything was ever synced into the index
+    pub fn exists(&self) -> bool {
+        self.dir.is_dir()
+    }
+
+    fn project_path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.msgpack", normalize_name(name)))
+    }
+
+    /// Download the release list of `name` and the requirements of every
+    /// release, `parallel` requests at a time. Returns the number of releases.
+    pub async fn sync(&self, client: &RegistryClient, name: &str, parallel: usize) -> Result<usize> {
+        let releases = client.get_releases(name).await?;
+        let indexed: Vec<Option<IndexedRelease>> = stream::iter(releases.into_iter().filter(|r| !r.files.is_empty()))
+            .map(|release| async move {
+                match client.get_package_version(name, &release.version).await {
+                    Ok(package) => Ok(Some(IndexedRelease {
+                        version: release.version,
+                        dependencies: package.dependencies,
+                        files: release.files,
+                    })),
+                    // Legacy releases without a metadata page can't be resolved anyway
+                    Err(CobraError::PackageNotFound(_)) => Ok(None),
+                    Err(e) => Err(e),
+                }
+            })
+            .buffered(parallel.max(1))
+            .try_collect()
+            .await?;
+
+        let project = IndexedProject {
+            name: name.to_string(),
+            synced_at: Utc::now(),
+            releases: indexed.into_iter().flatten().collect(),
+        };
+        let data = rmp_serde::to_vec_named(&project)
+            .map_err(|e| CobraError::Cache(format!("Failed to encode index entry for {}: {}", name, e)))?;
+        atomic_write(&self.project_path(name), &data).await?;
+
+        let count = project.releases.len();
+        self.loaded.lock().unwrap().insert(normalize_name(name), Some(Arc::new(project)));
+        Ok(count)
+    }
+
+    /// The indexed project, `None` if `name` was never synced
+    pub async fn project(&self, name: &str) -> Result<Option<Arc<IndexedProject>>> {
+        let key = normalize_name(name);
+        if let Some(project) = self.loaded.lock().unwrap().get(&key) {
+            return Ok(project.clone());
+        }
+
+        let project = match tokio::fs::read(self.project_path(name)).await {
+            Ok(data) => Some(Arc::new(decode(name, &data)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        self.loaded.lock().unwrap().insert(key, project.clone());
+        Ok(project)
+    }
+
+    /// Pick a release of an indexed package the way the resolver would.
+    /// `None` when the package isn't indexed, no indexed release matches, or
+    /// the spec isn't PEP 440, so the caller can fall back to the registry.
+    pub async fn select(
+        &self,
+        name: &str,
+        version_spec: &str,
+        cutoff: Option<&DateTime<Utc>>,
+        python: Option<&Version>,
+        strategy: ResolutionStrategy,
+    ) -> Result<Option<Package>> {
+        let Some(project) = self.project(name).await? else {
+            return Ok(None);
+        };
+        let Ok(spec) = VersionSpec::parse(version_spec) else {
+            return Ok(None);
+        };
+
+        let releases: Vec<Release> = project.releases.iter()
+            .map(|r| Release { version: r.version.clone(), files: r.files.clone() })
+            .collect();
+        let Some((release, files)) = select_release(&releases, &spec, cutoff, python, strategy) else {
+            return Ok(None);
+        };
+        let indexed = project.releases.iter().find(|r| r.version == release.version);
+        let (Some(indexed), Some(artifact)) = (indexed, select_artifact(&files)) else {
+            return Ok(None);
+        };
+
+        Ok(Some(Package {
+            name: name.to_string(),
+            version: release.version.clone(),
+            dependencies: indexed.dependencies.clone(),
+            download_url: artifact.url.clone(),
+            hash: artifact.sha256.clone(),
+            size: artifact.size,
+            description: None,
+            author: None,
+            homepage: None,
+            markers: None,
+            yanked: artifact.yanked,
+            yanked_reason: artifact.yanked_reason.clone(),
+            requires_python: artifact.requires_python.clone(),
+        }))
+    }
+}
+
+fn decode(name: &str, data: &[u8]) -> Result<IndexedProject> {
+    rmp_serde::from_slice(data)
+        .map_err(|e| CobraError::Cache(format!("Corrupt index entry for {}: {}; run 'cobra index sync {}'", name, e, name)))
+}
@@ -1,199 +1,1915 @@
-use crate::{Result, CobraError, Package, constants::*};
-use crate::core::cache::MultiLevelCache;
-use crate::core::package_manager::LocalPackageManager;
-use crate::registry::client::RegistryClient;
+use crate::{Result, CobraError, Package, normalize_name, constants::*};
+use crate::core::build::{self, BuildFrontend, WheelBuilder};
+use crate::core::config::Settings;
+use crate::core::hooks;
+use crate::core::layout::{InstallLayout, LayoutKind};
+use crate::core::package_manager::{InstalledPackage, LocalPackageManager};
+use crate::core::python::PythonEnvironment;
+use crate::core::record::{format_record, is_record_file, DirectUrl, RecordEntry};
+use crate::core::store::{StoredWheel, WheelStore};
+use crate::core::version::Version;
+use crate::core::find_links::read_local_archive;
+use crate::core::wheel_cache::{artifact_filename, find_link, WheelCache};
+use crate::registry::backend::RegistryBackend;
+use crate::registry::client::{mirror_url, version_from_filename};
+use crate::utils::fs::{check_free_space, move_into_place, remove_stale_dirs, remove_stale_files, same_filesystem};
+use crate::utils::hash::{sha256_hex, HashAlgo};
 use crate::utils::progress::ProgressTracker;
+use crate::utils::throttle::RateLimiter;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use std::path::Path;
-use tokio::sync::Semaphore;
+use std::path::{Component, Path, PathBuf};
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
 use tokio::fs;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
 use futures::stream::StreamExt;
 use zip::ZipArchive;
 use memmap2::MmapOptions;
-use std::io::Cursor;
 use rayon::prelude::*;
+use std::io::Cursor;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// Where an installed package's archive bytes came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageSource {
+    /// The wheel cache or a `--find-links` directory
+    Disk,
+    Network,
+    /// Linked from an already extracted wheel in the wheel store
+    Store,
+}
+
+/// Result of installing a single package
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InstallOutcome {
+    pub name: String,
+    pub version: String,
+    pub source: PackageSource,
+    pub bytes: u64,
+    /// The `[tool.cobra] mirrors` entry the archive came from when its own
+    /// URL failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mirror: Option<String>,
+    /// The archive's index URL
+    pub url: String,
+    /// Digest of the archive bytes installed
+    pub sha256: String,
+    /// Time spent getting the archive, whether from disk or the network
+    pub download_ms: u64,
+    /// Time spent extracting, building or linking it into place
+    pub extract_ms: u64,
+    /// The site-packages directory the package was installed into
+    pub install_path: PathBuf,
+}
+
+/// Totals of cache-served versus downloaded archives for one install run
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct InstallStats {
+    pub cached_packages: usize,
+    pub downloaded_packages: usize,
+    pub skipped_packages: usize,
+    /// Bytes served from cache, i.e. not downloaded
+    pub bytes_saved: u64,
+    pub bytes_downloaded: u64,
+    /// Disk space taken by the packages installed in this run
+    pub installed_bytes: u64,
+    /// Archives downloaded from a mirror after their own URL failed
+    pub mirror_fallbacks: usize,
+    /// Wall-clock time from the first download to the last package in place
+    pub elapsed_ms: u64,
+    pub packages: Vec<InstallOutcome>,
+}
+
+impl InstallStats {
+    pub fn record(&mut self, outcome: InstallOutcome) {
+        match outcome.source {
+            PackageSource::Network => {
+                self.downloaded_packages += 1;
+                self.bytes_downloaded += outcome.bytes;
+            }
+            PackageSource::Disk | PackageSource::Store => {
+                self.cached_packages += 1;
+                self.bytes_saved += outcome.bytes;
+            }
+        }
+        if outcome.mirror.is_some() {
+            self.mirror_fallbacks += 1;
+        }
+        self.packages.push(outcome);
+    }
+
+    /// One-line summary such as "Saved 142 MB via cache (served 38 packages
+    /// from cache, downloaded 4)", noting downloads that fell back to a mirror
+    pub fn summary(&self) -> String {
+        let fallbacks = match self.mirror_fallbacks {
+            0 => String::new(),
+            n => format!(", {} from a fallback mirror", n),
+        };
+        format!(
+            "Saved {} via cache (served {} packages from cache, downloaded {}{})",
+            format_bytes(self.bytes_saved),
+            self.cached_packages,
+            self.downloaded_packages,
+            fallbacks
+        )
+    }
+
+    /// Totals and overall speed such as "Installed 12 packages (42 MB, 38 MB
+    /// downloaded) in 3.10s, 13.5 MB/s". With `table`, preceded by one row per
+    /// package, slowest first, with its size, source, download and extract
+    /// times and effective download speed.
+    pub fn timings(&self, table: bool) -> String {
+        let mut out = String::new();
+        if table {
+            let mut packages: Vec<&InstallOutcome> = self.packages.iter().collect();
+            packages.sort_by_key(|p| std::cmp::Reverse(p.download_ms + p.extract_ms));
+            let labels: Vec<String> = packages.iter().map(|p| format!("{} {}", p.name, p.version)).collect();
+            let width = labels.iter().map(String::len).max().unwrap_or(0).max("Package".len());
+            out.push_str(&format!(
+                "{:<width$}  {:>8}  {:<7}  {:>8}  {:>8}  {:>9}\n",
+                "Package", "Size", "Source", "Download", "Extract", "MB/s", width = width
+            ));
+            for (package, label) in packages.iter().zip(&labels) {
+                let source = match package.source {
+                    PackageSource::Network => "network",
+                    PackageSource::Disk | PackageSource::Store => "cache",
+                };
+                out.push_str(&format!(
+                    "{:<width$}  {:>8}  {:<7}  {:>7.2}s  {:>7.2}s  {:>9}\n",
+                    label,
+                    format_bytes(package.bytes),
+                    source,
+                    package.download_ms as f64 / 1000.0,
+                    package.extract_ms as f64 / 1000.0,
+                    megabytes_per_sec(package.bytes, package.download_ms),
+                    width = width
+                ));
+            }
+        }
+        let bytes: u64 = self.packages.iter().map(|p| p.bytes).sum();
+        out.push_str(&format!(
+            "Installed {} packages ({}, {} downloaded) in {:.2}s, {} MB/s",
+            self.packages.len(),
+            format_bytes(bytes),
+            format_bytes(self.bytes_downloaded),
+            self.elapsed_ms as f64 / 1000.0,
+            megabytes_per_sec(bytes, self.elapsed_ms)
+        ));
+        out
+    }
+}
+
+/// `bytes` over `ms` in MB/s to one decimal, or `-` when no time was measured
+fn megabytes_per_sec(bytes: u64, ms: u64) -> String {
+    if ms == 0 {
+        return "-".to_string();
+    }
+    format!("{:.1}", bytes as f64 / (1024.0 * 1024.0) / (ms as f64 / 1000.0))
+}
+
+/// What one `cobra install` run did, as written by `--report`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstallReport {
+    pub packages: Vec<ReportedPackage>,
+    pub totals: ReportTotals,
+}
+
+/// One installed package in an [`InstallReport`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportedPackage {
+    pub name: String,
+    pub version: String,
+    pub url: String,
+    pub sha256: String,
+    /// Archive size in bytes
+    pub size: u64,
+    /// Served from the wheel cache, a `--find-links` directory or the wheel store
+    pub cache_hit: bool,
+    pub source: PackageSource,
+    pub download_ms: u64,
+    pub extract_ms: u64,
+    pub install_path: PathBuf,
+}
+
+/// Run-wide counts in an [`InstallReport`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportTotals {
+    pub installed_packages: usize,
+    pub cached_packages: usize,
+    pub downloaded_packages: usize,
+    /// Already installed at the resolved version
+    pub skipped_packages: usize,
+    pub bytes_downloaded: u64,
+    pub bytes_saved: u64,
+    pub installed_bytes: u64,
+    /// Wall-clock milliseconds spent installing
+    #[serde(default)]
+    pub elapsed_ms: u64,
+    /// Share of package metadata lookups served by the metadata cache
+    pub cache_hit_rate: f64,
+}
+
+impl InstallReport {
+    /// Report `stats` along with the metadata cache's hit rate
+    pub fn new(stats: &InstallStats, cache_hit_rate: f64) -> Self {
+        let packages = stats.packages.iter()
+            .map(|outcome| ReportedPackage {
+                name: outcome.name.clone(),
+                version: outcome.version.clone(),
+                url: outcome.url.clone(),
+                sha256: outcome.sha256.clone(),
+                size: outcome.bytes,
+                cache_hit: outcome.source != PackageSource::Network,
+                source: outcome.source,
+                download_ms: outcome.download_ms,
+                extract_ms: outcome.extract_ms,
+                install_path: outcome.install_path.clone(),
+            })
+            .collect();
+        Self {
+            packages,
+            totals: ReportTotals {
+                installed_packages: stats.packages.len(),
+                cached_packages: stats.cached_packages,
+                downloaded_packages: stats.downloaded_packages,
+                skipped_packages: stats.skipped_packages,
+                bytes_downloaded: stats.bytes_downloaded,
+                bytes_saved: stats.bytes_saved,
+                installed_bytes: stats.installed_bytes,
+                elapsed_ms: stats.elapsed_ms,
+                cache_hit_rate,
+            },
+        }
+    }
+}
+
+/// Human readable byte count using binary units
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.0} {}", value, UNITS[unit])
+    }
+}
+
+/// Disk space an install needs, estimated from its archives' sizes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SpaceEstimate {
+    /// The archives themselves, where they are downloaded and cached
+    pub archives: u64,
+    /// Their contents once extracted
+    pub extracted: u64,
+}
+
+impl SpaceEstimate {
+    /// Packages of unknown size count as empty
+    pub fn for_packages(packages: &[Package]) -> Self {
+        let archives: u64 = packages.iter().filter_map(|p| p.size).sum();
+        Self { archives, extracted: archives.saturating_mul(EXTRACTION_FACTOR) }
+    }
+}
 
 pub struct Installer {
-    client: Arc<RegistryClient>,
-    cache: Option<Arc<MultiLevelCache>>,
+    client: Arc<dyn RegistryBackend>,
+    wheels: Option<Arc<WheelCache>>,
+    find_links: Vec<PathBuf>,
     progress: Arc<ProgressTracker>,
     package_manager: Arc<LocalPackageManager>,
+    offline: bool,
+    verify: bool,
+    parallel_downloads: usize,
+    parallel_installs: usize,
+    max_download_rate: Option<u64>,
+    post_install_checks: HashMap<String, String>,
+    download_dir: Option<PathBuf>,
+    download_segments: usize,
+    segment_min_size: u64,
+    chunk_size: usize,
+    max_unpacked_size: u64,
+    mirrors: Vec<String>,
+    build_frontend: BuildFrontend,
+    reinstall: HashSet<String>,
+    store: Option<Arc<WheelStore>>,
+    space_check: bool,
+    atomic: bool,
+    timings: bool,
+    cancel: CancellationToken,
 }
 
 impl Installer {
     pub fn new(
-        client: Arc<RegistryClient>,
-        cache: Option<Arc<MultiLevelCache>>,
+        client: Arc<dyn RegistryBackend>,
+        wheels: Option<Arc<WheelCache>>,
         progress: Arc<ProgressTracker>,
         package_manager: Arc<LocalPackageManager>,
     ) -> Self {
         Self {
             client,
-            cache,
+            wheels,
+            find_links: Vec::new(),
             progress,
             package_manager,
+            offline: false,
+            verify: true,
+            parallel_downloads: MAX_CONCURRENT_DOWNLOADS,
+            parallel_installs: MAX_CONCURRENT_INSTALLS,
+            max_download_rate: None,
+            post_install_checks: HashMap::new(),
+            download_dir: None,
+            download_segments: DOWNLOAD_SEGMENTS,
+            segment_min_size: SEGMENTED_DOWNLOAD_MIN_SIZE,
+            chunk_size: CHUNK_SIZE,
+            max_unpacked_size: MAX_UNPACKED_SIZE,
+            mirrors: Vec::new(),
+            build_frontend: BuildFrontend::default(),
+            reinstall: HashSet::new(),
+            store: None,
+            space_check: true,
+            atomic: false,
+            timings: false,
+            cancel: CancellationToken::new(),
         }
     }
 
+    /// Stop installing once `cancel` fires: downloads are abandoned, packages
+    /// already being extracted finish and stay registered, and the install
+    /// fails with `Cancelled`
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Install every package or none: when one fails, the rest are stopped
+    /// and those installed by this call are uninstalled again
+    pub fn with_atomic(mut self, atomic: bool) -> Self {
+        self.atomic = atomic;
+        self
+    }
+
+    /// Print a row per package, with its size, source and timings, above
+    /// the totals printed after every install
+    pub fn with_timings(mut self, timings: bool) -> Self {
+        self.timings = timings;
+        self
+    }
+
+    /// Directories of archives, named as on the index, to install from
+    /// before downloading
+    pub fn with_find_links(mut self, dirs: Vec<PathBuf>) -> Self {
+        self.find_links = dirs;
+        self
+    }
+
+    /// Install only packages whose archives are already cached, never downloading
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Concurrency limits, download segments, chunk size, unpacked size
+    /// limit and mirrors from `settings`
+    pub fn with_settings(mut self, settings: &Settings) -> Self {
+        self.parallel_downloads = settings.parallel_downloads;
+        self.parallel_installs = settings.parallel_installs;
+        self.download_segments = settings.download_segments;
+        self.chunk_size = settings.chunk_size;
+        self.max_unpacked_size = settings.max_unpacked_size;
+        self.mirrors = settings.mirrors.clone();
+        self
+    }
+
+    /// File hosts tried in order, with the same path, when an archive's own
+    /// URL answers with an error or can't be reached
+    pub fn with_mirrors(mut self, mirrors: Vec<String>) -> Self {
+        self.mirrors = mirrors;
+        self
+    }
+
+    /// Download at most `limit` archives at once
+    pub fn with_parallel_downloads(mut self, limit: usize) -> Self {
+        self.parallel_downloads = limit;
+        self
+    }
+
+    /// Install at most `limit` packages at once
+    pub fn with_parallel_installs(mut self, limit: usize) -> Self {
+        self.parallel_installs = limit;
+        self
+    }
+
+    /// Refuse any wheel that would unpack to more than `bytes`
+    pub fn with_max_unpacked_size(mut self, bytes: u64) -> Self {
+        self.max_unpacked_size = bytes;
+        self
+    }
+
+    /// Cap the combined throughput of all downloads, in bytes per second
+    pub fn with_max_download_rate(mut self, bytes_per_sec: Option<u64>) -> Self {
+        self.max_download_rate = bytes_per_sec;
+        self
+    }
+
+    /// Shell commands, keyed by normalized package name, that must succeed
+    /// after the package installs
+    pub fn with_post_install_checks(mut self, checks: HashMap<String, String>) -> Self {
+        self.post_install_checks = checks;
+        self
+    }
+
+    /// Keep partial downloads in `dir` so a later run can resume them;
+    /// without one they only survive retries within a run
+    pub fn with_download_dir(mut self, dir: PathBuf) -> Self {
+        self.download_dir = Some(dir);
+        self
+    }
+
+    /// Fetch archives in up to `segments` concurrent Range requests; 1 disables it
+    pub fn with_download_segments(mut self, segments: usize) -> Self {
+        self.download_segments = segments;
+        self
+    }
+
+    /// Only archives larger than `bytes` are downloaded in segments
+    pub fn with_segment_min_size(mut self, bytes: u64) -> Self {
+        self.segment_min_size = bytes;
+        self
+    }
+
+    /// Tool that builds packages published only as an sdist
+    pub fn with_build_frontend(mut self, frontend: BuildFrontend) -> Self {
+        self.build_frontend = frontend;
+        self
+    }
+
+    /// Replace these packages (normalized names) even when the installed
+    /// version already matches
+    pub fn with_reinstall(mut self, packages: HashSet<String>) -> Self {
+        self.reinstall = packages;
+        self
+    }
+
+    /// Extract wheels once into `store` and link their files into the
+    /// install directory instead of extracting them for every project
+    pub fn with_store(mut self, store: Option<Arc<WheelStore>>) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Refuse to start when the download, cache, store or install directory's
+    /// filesystem lacks the space the install is estimated to need
+    pub fn with_space_check(mut self, check: bool) -> Self {
+        self.space_check = check;
+        self
+    }
+
+    /// Check every archive, cached or downloaded, against its recorded sha256
+    pub fn with_verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Bytes each location written to during an install needs. Wheels in the
+    /// store are extracted there, and only take space again in the install
+    /// directory when they must be copied across filesystems.
+    fn space_needs(&self, download_dir: &Path, estimate: SpaceEstimate) -> Vec<(PathBuf, u64)> {
+        let install_dir = self.package_manager.layout().base_dir().to_path_buf();
+        let mut needs = vec![(download_dir.to_path_buf(), estimate.archives)];
+        if let Some(wheels) = &self.wheels {
+            needs.push((wheels.root().to_path_buf(), estimate.archives));
+        }
+        match &self.store {
+            Some(store) => {
+                needs.push((store.root().to_path_buf(), estimate.extracted));
+                if !same_filesystem(store.root(), &install_dir) {
+                    needs.push((install_dir, estimate.extracted));
+                }
+            }
+            None => needs.push((install_dir, estimate.extracted)),
+        }
+        needs
+    }
+
     /// Install packages in parallel with streaming downloads
-    pub async fn install_parallel(&self, packages: Vec<Package>) -> Result<()> {
+    pub async fn install_parallel(&self, packages: Vec<Package>) -> Result<InstallStats> {
+        let mut stats = InstallStats::default();
         if packages.is_empty() {
-            return Ok(());
+            return Ok(stats);
         }
 
         // Ensure installation directory exists
         self.package_manager.ensure_install_dir().await?;
+        let staging = self.package_manager.layout().staging_dir();
+        if remove_stale_dirs(&staging, STALE_STAGING_AGE).await? > 0 {
+            let _ = fs::remove_dir(&staging).await;
+        }
+        let bars = FinishOnDrop(&self.progress);
 
-        // Filter out already installed packages
+        // Filter out already installed packages; other versions and forced
+        // reinstalls are removed once their replacement is staged, so no
+        // stale files survive and a failed download leaves them installed
+        let installed: HashMap<String, _> = self.package_manager.list_installed().await?
+            .into_iter()
+            .map(|p| (normalize_name(&p.name), p))
+            .collect();
         let mut packages_to_install = Vec::new();
         let mut skipped_count = 0;
+        let mut to_replace = Vec::new();
 
         for package in packages {
-            if self.package_manager.is_package_installed(&package.name, &package.version).await? {
-                println!("⏭️  Skipping {} {} (already installed)", package.name, package.version);
-                skipped_count += 1;
-            } else {
-                packages_to_install.push(package);
+            let key = normalize_name(&package.name);
+            match installed.get(&key) {
+                Some(current) if current.version == package.version && !self.reinstall.contains(&key)
+                    && self.package_manager.is_package_installed(&current.name, &package.version).await? => {
+                    self.progress.println(&format!("⏭️  Skipping {} {} (already installed)", package.name, package.version));
+                    skipped_count += 1;
+                    continue;
+                }
+                Some(current) => {
+                    if current.version != package.version {
+                        self.progress.println(&format!("🔄 Replacing {} {} with {}", package.name, current.version, package.version));
+                    }
+                    to_replace.push(current.name.clone());
+                }
+                None => {}
             }
+            packages_to_install.push(package);
         }
 
         if packages_to_install.is_empty() {
-            println!("✅ All {} packages are already installed!", skipped_count);
-            return Ok(());
+            self.progress.println(&format!("✅ All {} packages are already installed!", skipped_count));
+            stats.skipped_packages = skipped_count;
+            return Ok(stats);
         }
 
         if skipped_count > 0 {
-            println!("📦 Installing {} new packages ({} already installed)", 
-                packages_to_install.len(), skipped_count);
+            self.progress.println(&format!("📦 Installing {} new packages ({} already installed)", 
+                packages_to_install.len(), skipped_count));
+        }
+        let estimate = SpaceEstimate::for_packages(&packages_to_install);
+        if estimate.archives > 0 {
+            self.progress.println(&format!("📦 Total download size: {}", format_bytes(estimate.archives)));
+        }
+
+        let scratch;
+        let download_dir = match &self.download_dir {
+            Some(dir) => {
+                remove_stale_files(dir, STALE_DOWNLOAD_AGE).await?;
+                dir.clone()
+            }
+            None => {
+                scratch = tempfile::tempdir()?;
+                scratch.path().to_path_buf()
+            }
+        };
+        fs::create_dir_all(&download_dir).await?;
+        if self.space_check {
+            check_free_space(&self.space_needs(&download_dir, estimate))?;
+        }
+
+        let backup = if self.atomic && !to_replace.is_empty() {
+            Some(staging_dir(&staging, "replaced-").await?)
+        } else {
+            None
+        };
+        // An atomic install keeps them aside until every package is in, so
+        // a failure can put them back
+        let mut replacing = HashMap::new();
+        let mut replaced = Vec::new();
+        for name in to_replace {
+            let Some(backup) = &backup else {
+                replacing.insert(normalize_name(&name), name);
+                continue;
+            };
+            let kept = backup.path().join(normalize_name(&name));
+            match self.package_manager.set_aside(&name, &kept).await {
+                Ok(Some(previous)) => replaced.push((previous, kept)),
+                Ok(None) => {}
+                Err(e) => {
+                    self.roll_back(&[], replaced).await?;
+                    return Err(e);
+                }
+            }
         }
 
         // Semaphore to limit concurrent operations
-        let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_INSTALLS));
+        let semaphore = Arc::new(Semaphore::new(self.parallel_installs));
+        let context = Arc::new(InstallContext {
+            client: Arc::clone(&self.client),
+            wheels: self.wheels.clone(),
+            find_links: self.find_links.clone(),
+            progress: Arc::clone(&self.progress),
+            package_manager: Arc::clone(&self.package_manager),
+            downloads: Semaphore::new(self.parallel_downloads),
+            rate_limit: self.max_download_rate.map(RateLimiter::new),
+            offline: self.offline,
+            verify: self.verify,
+            download_dir,
+            download_segments: self.download_segments,
+            segment_min_size: self.segment_min_size,
+            chunk_size: self.chunk_size,
+            max_unpacked_size: self.max_unpacked_size,
+            mirrors: self.mirrors.clone(),
+            build_frontend: self.build_frontend,
+            reinstall: self.reinstall.clone(),
+            replacing,
+            store: self.store.clone(),
+            // An atomic install also stops the remaining packages once one fails
+            cancel: self.cancel.child_token(),
+        });
         
-        let tasks: Vec<_> = packages_to_install.into_iter().map(|pkg| {
+        let overall = self.progress.add_overall(packages_to_install.len());
+        let started = Instant::now();
+
+        // Cancellation is left to the tasks rather than aborting them, since
+        // an aborted task would leave its blocking extraction running unregistered
+        let mut tasks = JoinSet::new();
+        for pkg in packages_to_install {
             let sem = Arc::clone(&semaphore);
-            let client = Arc::clone(&self.client);
-            let cache = self.cache.clone();
-            let progress = Arc::clone(&self.progress);
-            let package_manager = Arc::clone(&self.package_manager);
+            let context = Arc::clone(&context);
             
-            tokio::spawn(async move {
+            tasks.spawn(async move {
                 let _permit = sem.acquire().await.unwrap();
-                Self::install_single(pkg, client, cache, progress, package_manager).await
-            })
-        }).collect();
+                let name = pkg.name.clone();
+                (name, context.install_single(pkg).await)
+            });
+        }
 
         // Wait for all installations to complete
-        let results = futures::future::join_all(tasks).await;
-        
-        for result in results {
-            result.map_err(|e| CobraError::InstallationFailed(e.to_string()))??;
+        stats.skipped_packages = skipped_count;
+        let mut cancelled = false;
+        let mut failure = None;
+        while let Some(result) = tasks.join_next().await {
+            let (name, result) = result.map_err(|e| CobraError::InstallationFailed(e.to_string()))?;
+            match result {
+                Ok(outcome) => {
+                    overall.inc(1);
+                    stats.record(outcome);
+                }
+                Err(CobraError::Cancelled) => cancelled |= self.cancel.is_cancelled(),
+                // The rest are stopped and drained rather than dropped, so
+                // extractions under way finish and are registered first
+                Err(e) => {
+                    context.cancel.cancel();
+                    failure.get_or_insert((name, e));
+                }
+            }
+        }
+        drop(bars);
+        stats.elapsed_ms = started.elapsed().as_millis() as u64;
+        let installed: Vec<String> = stats.packages.iter().map(|p| p.name.clone()).collect();
+        if let Some((package, error)) = failure {
+            if !self.atomic {
+                self.package_manager.create_pth_file().await?;
+                return Err(error);
+            }
+            let rolled_back = self.roll_back(&installed, replaced).await?;
+            return Err(CobraError::RolledBack { package, reason: error.to_string(), rolled_back });
+        }
+        if cancelled {
+            // Previous versions whose replacement never made it go back
+            for (previous, kept) in replaced {
+                if !installed.contains(&previous.name) {
+                    self.package_manager.restore(previous, &kept).await?;
+                }
+            }
+            // Only fully installed packages were registered; keep Python's
+            // path in step with them
+            self.package_manager.create_pth_file().await?;
+            return Err(CobraError::Cancelled);
+        }
+        if self.progress.downloads() > 0 {
+            self.progress.println(&format!("📥 {}", self.progress.summary()));
         }
 
+        // Checks run once every package is in place so they can import
+        // dependencies; they are independent of each other
+        if let Err(e) = self.run_post_install_checks(&installed).await {
+            if self.atomic {
+                self.roll_back(&installed, replaced).await?;
+            }
+            return Err(e);
+        }
+        let registry = self.package_manager.load_registry().await?;
+        stats.installed_bytes = installed.iter()
+            .filter_map(|name| registry.packages.get(name)?.size_on_disk)
+            .sum();
+
         // Create .pth file to make packages discoverable by Python
         self.package_manager.create_pth_file().await?;
+        self.progress.println(&format!("⏱️  {}", stats.timings(self.timings)));
+
+        Ok(stats)
+    }
+
+    /// Uninstall `packages`, installed by a failed atomic install, and put
+    /// back the previous versions it set aside, returning the ones removed
+    async fn roll_back(&self, packages: &[String], replaced: Vec<(InstalledPackage, PathBuf)>) -> Result<Vec<String>> {
+        let mut rolled_back = Vec::new();
+        for name in packages {
+            if self.package_manager.uninstall(name).await? {
+                self.progress.println(&format!("↩️  Rolled back {}", name));
+                rolled_back.push(name.clone());
+            }
+        }
+        for (previous, kept) in replaced {
+            self.progress.println(&format!("↩️  Restored {} {}", previous.name, previous.version));
+            self.package_manager.restore(previous, &kept).await?;
+        }
+        self.package_manager.create_pth_file().await?;
+        Ok(rolled_back)
+    }
+
+    /// Install a wheel built from a local project or repository, recording
+    /// its source hash and, in `direct_url.json`, where it came from
+    pub async fn install_built_wheel(&self, wheel: &Path, package: &Package, source_hash: &str, origin: &DirectUrl) -> Result<()> {
+        self.package_manager.ensure_install_dir().await?;
+        let files = Self::extract_package_mmap(wheel, package, &self.package_manager, Some(origin), self.max_unpacked_size, None).await?;
+        self.package_manager.register_package_files(package, Some(source_hash), files).await?;
+        self.run_post_install_checks(std::slice::from_ref(&package.name)).await?;
+        self.package_manager.create_pth_file().await
+    }
 
+    /// Run the configured checks of `packages` concurrently. Packages whose
+    /// check fails are unregistered so they count as not installed.
+    async fn run_post_install_checks(&self, packages: &[String]) -> Result<()> {
+        let checks: Vec<(&String, &String)> = packages.iter()
+            .filter_map(|name| Some((name, self.post_install_checks.get(&normalize_name(name))?)))
+            .collect();
+        if checks.is_empty() {
+            return Ok(());
+        }
+
+        let python_path = self.package_manager.import_path().await?;
+
+        let runs = checks.iter().map(|(_, command)| run_check(command, &python_path));
+        let results = futures::future::join_all(runs).await;
+
+        let mut failures = Vec::new();
+        for ((name, _), result) in checks.iter().zip(results) {
+            match result {
+                Ok(()) => self.progress.println(&format!("✅ Verified {}", name)),
+                Err(reason) => {
+                    // An atomic install uninstalls it along with the rest
+                    if !self.atomic {
+                        self.package_manager.unregister_package(name).await?;
+                    }
+                    failures.push(format!("{} ({})", name, reason));
+                }
+            }
+        }
+        if !failures.is_empty() {
+            return Err(CobraError::InstallationFailed(format!(
+                "post-install verification failed for {}", failures.join(", ")
+            )));
+        }
         Ok(())
     }
 
-    async fn install_single(
-        package: Package,
-        client: Arc<RegistryClient>,
-        cache: Option<Arc<MultiLevelCache>>,
-        progress: Arc<ProgressTracker>,
-        package_manager: Arc<LocalPackageManager>,
-    ) -> Result<()> {
-        // Check cache first
-        let cache_key = format!("package:{}:{}", package.name, package.version);
+    /// Extract a wheel and, per PEP 376, rewrite its RECORD to list every file
+    /// written and add an INSTALLER marker. Returns the RECORD entries.
+    async fn extract_package_mmap(
+        archive_path: &Path,
+        package: &Package,
+        package_manager: &LocalPackageManager,
+        direct_url: Option<&DirectUrl>,
+        max_unpacked_size: u64,
+        replacing: Option<&str>,
+    ) -> Result<Vec<RecordEntry>> {
+        // Let the install layout decide where this package's files go
+        let layout = package_manager.layout();
+        let site_packages = layout.site_packages(&package.name);
+
+        // The wheel is unpacked out of sight and only moved into place once
+        // complete, so a failure or crash mid-way leaves no torn package
+        let staging_root = layout.staging_dir();
+        let staging = staging_dir(&staging_root, &format!("{}-{}-", package.name, package.version)).await?;
+        let stage = staging.path().to_path_buf();
+        let extracted = async {
+            let (archive_path, into) = (archive_path.to_path_buf(), stage.clone());
+            let extracted = blocking(move || extract_wheel_within(&archive_path, &into, max_unpacked_size)).await?;
+            // The version this replaces only goes once the new one is staged
+            if let Some(name) = replacing {
+                package_manager.uninstall(name).await?;
+            }
+            fs::create_dir_all(&site_packages).await?;
+            let dest = site_packages.clone();
+            blocking(move || move_into_place(&stage, &dest)).await?;
+            Ok::<_, CobraError>(extracted)
+        }.await;
+        drop(staging);
+        // Left in place while another package is still being staged
+        let _ = fs::remove_dir(&staging_root).await;
+
+        place_extracted(site_packages, layout, &package.name, extracted?, direct_url).await
+    }
+}
+
+/// Files of a wheel as written to disk, without its RECORD
+#[derive(Debug, Clone)]
+pub struct ExtractedWheel {
+    pub record: Vec<RecordEntry>,
+    /// The wheel's `.dist-info` directory, if it has one
+    pub dist_info: Option<String>,
+}
+
+/// Write the files of the wheel at `archive_path` into `dest`, skipping its
+/// RECORD, which no longer matches once files are installed. Regular files
+/// are spread across threads, each reading the mapped archive through its
+/// own `ZipArchive`; RECORD lists them in archive order either way.
+pub fn extract_wheel(archive_path: &Path, dest: &Path) -> Result<ExtractedWheel> {
+    extract_wheel_within(archive_path, dest, MAX_UNPACKED_SIZE)
+}
+
+/// `extract_wheel`, refusing an archive whose entries declare more than
+/// `max_size` bytes in all before anything is written, and any entry that
+/// inflates past its declared size while being written
+pub fn extract_wheel_within(archive_path: &Path, dest: &Path, max_size: u64) -> Result<ExtractedWheel> {
+    // Use memory-mapped file for faster extraction
+    let file = std::fs::File::open(archive_path)
+        .map_err(|e| CobraError::Archive(format!("Failed to open archive: {}", e)))?;
+    
+    let mmap = unsafe { 
+        MmapOptions::new().map(&file)
+            .map_err(|e| CobraError::Archive(format!("Failed to mmap file: {}", e)))?
+    };
+    let mut archive = open_archive(&mmap)?;
+
+    let mut declared: u64 = 0;
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i)
+            .map_err(|e| CobraError::Archive(format!("Failed to read file: {}", e)))?;
+        declared = declared.saturating_add(file.size());
+    }
+    if declared > max_size {
+        return Err(CobraError::Archive(format!(
+            "Refusing to extract {}: it unpacks to {} bytes, over the {} byte limit (max-unpacked-size)",
+            archive_path.display(), declared, max_size
+        )));
+    }
+
+    // Directories and symlinks are handled here; regular files only have
+    // their parent directories created so the threads never race on them
+    let mut links = Vec::new();
+    let mut files = Vec::new();
+    let mut dist_info = None;
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)
+            .map_err(|e| CobraError::Archive(format!("Failed to read file: {}", e)))?;
+        let relative = enclosed_path(file.name())?;
+        let name = relative.to_string_lossy().replace('\\', "/");
+        if dist_info.is_none()
+            && let Some((dir, _)) = name.split_once('/')
+            && dir.ends_with(".dist-info") {
+            dist_info = Some(dir.to_string());
+        }
         
-        let package_data = if let Some(cache) = &cache {
-            if let Some(data) = cache.get(&cache_key).await {
-                data
-            } else {
-                // Download package
-                let data = Self::download_package(&package, &client, &progress).await?;
-                let _ = cache.put(cache_key, data.clone()).await;
-                data
+        // Symlinks store their target as the entry's contents; create them
+        // once every regular file exists so Windows can fall back to copies
+        if file.unix_mode().is_some_and(|mode| mode & S_IFMT == S_IFLNK) {
+            let mut target = String::new();
+            let declared = file.size();
+            std::io::Read::read_to_string(&mut std::io::Read::take(&mut file, declared), &mut target)?;
+            let resolved = link_target(&relative, &target)?;
+            links.push((relative, PathBuf::from(target), resolved));
+            continue;
+        }
+
+        let outpath = dest.join(&relative);
+
+        // Explicit directory entries are created even when empty, as pip does
+        if file.is_dir() {
+            std::fs::create_dir_all(&outpath)?;
+            continue;
+        }
+
+        if file.is_file() {
+            if let Some(parent) = outpath.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            // Replaced by a RECORD of what was actually written
+            if !is_record_file(&name) {
+                files.push((i, outpath, name));
+            }
+        }
+    }
+
+    let chunk_size = files.len().div_ceil(rayon::current_num_threads()).max(1);
+    let mut record: Vec<RecordEntry> = files.par_chunks(chunk_size)
+        .map(|chunk| extract_files(&mmap, chunk))
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .flatten()
+        .collect();
+
+    for (entry, target, resolved) in links {
+        let outpath = dest.join(&entry);
+        if let Some(parent) = outpath.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        if outpath.symlink_metadata().is_ok() {
+            std::fs::remove_file(&outpath)?;
+        }
+        create_link(&target, &dest.join(resolved), &outpath)?;
+        record.push(RecordEntry { path: entry.to_string_lossy().replace('\\', "/"), sha256: None, size: None });
+    }
+
+    Ok(ExtractedWheel { record, dist_info })
+}
+
+/// A fresh directory under `root`. Another install removes `root` once its
+/// own staging directory is gone, so it is recreated if that happens in between.
+async fn staging_dir(root: &Path, prefix: &str) -> Result<tempfile::TempDir> {
+    loop {
+        fs::create_dir_all(root).await?;
+        match tempfile::Builder::new().prefix(prefix).tempdir_in(root) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            result => return Ok(result?),
+        }
+    }
+}
+
+fn open_archive(data: &[u8]) -> Result<ZipArchive<Cursor<&[u8]>>> {
+    ZipArchive::new(Cursor::new(data))
+        .map_err(|e| CobraError::Archive(format!("Failed to read archive: {}", e)))
+}
+
+/// Write the archive entries at the given indices to their paths, returning
+/// their RECORD entries under the given names
+fn extract_files(data: &[u8], files: &[(usize, PathBuf, String)]) -> Result<Vec<RecordEntry>> {
+    let mut archive = open_archive(data)?;
+    let mut record = Vec::with_capacity(files.len());
+    for (index, outpath, name) in files {
+        let mut file = archive.by_index(*index)
+            .map_err(|e| CobraError::Archive(format!("Failed to read file: {}", e)))?;
+        let mut outfile = RecordingWriter::new(std::fs::File::create(outpath)?);
+        // One byte past the declared size is enough to tell an entry lies about it
+        let declared = file.size();
+        let written = std::io::copy(&mut std::io::Read::take(&mut file, declared + 1), &mut outfile)?;
+        if written > declared {
+            drop(outfile);
+            std::fs::remove_file(outpath)?;
+            return Err(CobraError::Archive(format!(
+                "Refusing to extract {}: it inflates past its declared {} bytes", name, declared
+            )));
+        }
+        record.push(outfile.entry(name));
+        // Like pip, only the executable bits of the archived mode are kept
+        if file.unix_mode().is_some_and(|mode| mode & 0o111 != 0) {
+            make_executable(outpath)?;
+        }
+    }
+    Ok(record)
+}
+
+/// Where an archive entry goes, relative to the directory it is extracted
+/// into, with `.` and `..` resolved. Backslashes count as separators, as in
+/// archives built on Windows. Absolute names, drive letters and `..` climbing
+/// out of the directory are refused.
+fn enclosed_path(name: &str) -> Result<PathBuf> {
+    let refuse = || CobraError::Archive(format!(
+        "Refusing to extract {} outside the install directory", name
+    ));
+    let unified = name.replace('\\', "/");
+    if unified.contains('\0') || unified.starts_with('/') {
+        return Err(refuse());
+    }
+
+    let mut relative = PathBuf::new();
+    for (i, part) in unified.split('/').enumerate() {
+        match part {
+            "" | "." => {}
+            ".." => {
+                if !relative.pop() {
+                    return Err(refuse());
+                }
+            }
+            _ if i == 0 && part.len() >= 2 && part.as_bytes()[0].is_ascii_alphabetic() && part.as_bytes()[1] == b':' => {
+                return Err(refuse());
             }
+            _ => relative.push(part),
+        }
+    }
+    Ok(relative)
+}
+
+/// Run blocking filesystem work on tokio's blocking pool, keeping the
+/// runtime free to drive downloads and progress bars
+async fn blocking<T: Send + 'static>(work: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|e| CobraError::InstallationFailed(e.to_string()))?
+}
+
+/// Finish installing files extracted or linked into `site_packages`: move
+/// `.data` trees where the layout wants them, then write the dist-info
+/// metadata. Returns the RECORD entries.
+async fn place_extracted(
+    site_packages: PathBuf,
+    layout: &InstallLayout,
+    package_name: &str,
+    mut extracted: ExtractedWheel,
+    direct_url: Option<&DirectUrl>,
+) -> Result<Vec<RecordEntry>> {
+    let interpreter = if extracted.record.iter().any(|entry| matches!(data_entry(&entry.path), Some(("scripts", _)))) {
+        Some(script_interpreter(layout).await)
+    } else {
+        None
+    };
+    let (layout, package_name, direct_url) = (layout.clone(), package_name.to_string(), direct_url.cloned());
+    blocking(move || {
+        spread_data_dirs(&site_packages, &layout, &package_name, &mut extracted.record, interpreter.as_deref())?;
+        finish_dist_info(&site_packages, extracted, direct_url.as_ref())
+    }).await
+}
+
+/// Scheme and path of a `<name>-<version>.data/<scheme>/<path>` entry
+fn data_entry(path: &str) -> Option<(&str, &str)> {
+    let (dir, rest) = path.split_once('/')?;
+    if !dir.ends_with(".data") {
+        return None;
+    }
+    rest.split_once('/')
+}
+
+/// What `#!python` script shebangs become: the venv's own interpreter, or
+/// the detected one
+async fn script_interpreter(layout: &InstallLayout) -> String {
+    if layout.kind() == LayoutKind::Venv {
+        let python = if cfg!(windows) { "python.exe" } else { "python" };
+        return layout.scripts_dir().join(python).to_string_lossy().into_owned();
+    }
+    match PythonEnvironment::detect().await {
+        Ok(environment) => environment.python_path.to_string_lossy().into_owned(),
+        Err(_) => "/usr/bin/env python3".to_string(),
+    }
+}
+
+/// Move the `.data/<scheme>/` trees of a wheel out of site-packages: scripts
+/// to the scripts directory with `#!python` rewritten to `interpreter`, data
+/// and headers to the layout's directories, purelib and platlib next to the
+/// other modules. RECORD paths follow the files, relative to site-packages.
+fn spread_data_dirs(
+    site_packages: &Path,
+    layout: &InstallLayout,
+    package_name: &str,
+    record: &mut [RecordEntry],
+    interpreter: Option<&str>,
+) -> Result<()> {
+    let place = |path: &str| -> Option<(&'static str, PathBuf)> {
+        let (scheme, rest) = data_entry(path)?;
+        Some(match scheme {
+            "purelib" => ("purelib", site_packages.join(rest)),
+            "platlib" => ("platlib", site_packages.join(rest)),
+            "scripts" => ("scripts", layout.scripts_dir().join(rest)),
+            "data" => ("data", layout.data_dir().join(rest)),
+            "headers" => ("headers", layout.headers_dir(package_name).join(rest)),
+            _ => return None,
+        })
+    };
+    let mut data_dirs = std::collections::BTreeSet::new();
+    for entry in record.iter_mut() {
+        let Some((scheme, target)) = place(&entry.path) else { continue };
+        let source = site_packages.join(&entry.path);
+        if let Some((dir, _)) = entry.path.split_once('/') {
+            data_dirs.insert(site_packages.join(dir));
+        }
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        if target.symlink_metadata().is_ok() {
+            std::fs::remove_file(&target)?;
+        }
+
+        let link = std::fs::read_link(&source).ok();
+        let script = match (scheme, interpreter, &link) {
+            ("scripts", Some(interpreter), None) => rewrite_shebang(&std::fs::read(&source)?, interpreter),
+            _ => None,
+        };
+        if let Some(link) = link {
+            // Re-aimed from the link's new place at where its target ends up,
+            // which may itself be a moved `.data` entry
+            let resolved = link_target(Path::new(&entry.path), &link.to_string_lossy())?;
+            let resolved = resolved.to_string_lossy().replace('\\', "/");
+            let destination = place(&resolved).map_or_else(|| site_packages.join(&resolved), |(_, placed)| placed);
+            std::fs::remove_file(&source)?;
+            let parent = target.parent().unwrap_or(site_packages);
+            create_link(Path::new(&relative_path(parent, &destination)), &destination, &target)?;
+        } else if let Some(script) = script {
+            // Written anew rather than through a link into the wheel store
+            std::fs::remove_file(&source)?;
+            let mut outfile = RecordingWriter::new(std::fs::File::create(&target)?);
+            std::io::Write::write_all(&mut outfile, &script)?;
+            let written = outfile.entry(&entry.path);
+            entry.sha256 = written.sha256;
+            entry.size = written.size;
         } else {
-            Self::download_package(&package, &client, &progress).await?
+            std::fs::rename(&source, &target)?;
+        }
+        // A link's target keeps its own mode
+        if scheme == "scripts" && !target.is_symlink() {
+            make_executable(&target)?;
+        }
+        entry.path = relative_path(site_packages, &target);
+    }
+
+    // Schemes cobra doesn't know stay behind; the emptied directories go
+    for dir in data_dirs {
+        let mut emptied: Vec<PathBuf> = walkdir::WalkDir::new(&dir)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_dir())
+            .map(|entry| entry.into_path())
+            .collect();
+        emptied.reverse();
+        for dir in emptied {
+            let _ = std::fs::remove_dir(dir);
+        }
+    }
+    Ok(())
+}
+
+/// Replace a `#!python` or `#!pythonw` first line with `#!<interpreter>`
+fn rewrite_shebang(script: &[u8], interpreter: &str) -> Option<Vec<u8>> {
+    if !script.starts_with(b"#!python") {
+        return None;
+    }
+    let body = script.iter().position(|&b| b == b'\n').map_or(script.len(), |i| i + 1);
+    let mut rewritten = format!("#!{}\n", interpreter).into_bytes();
+    rewritten.extend_from_slice(&script[body..]);
+    Some(rewritten)
+}
+
+/// `to` relative to the directory `from`, like the `../bin/tool` RECORD
+/// paths of scripts installed outside site-packages
+fn relative_path(from: &Path, to: &Path) -> String {
+    let from: Vec<Component> = from.components().collect();
+    let to: Vec<Component> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    std::iter::repeat_n("..".to_string(), from.len() - common)
+        .chain(to[common..].iter().map(|part| part.as_os_str().to_string_lossy().into_owned()))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = std::fs::metadata(path)?.permissions();
+    permissions.set_mode(permissions.mode() | 0o111);
+    std::fs::set_permissions(path, permissions)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// Add the INSTALLER marker (and `direct_url.json`) to an installed wheel's
+/// `.dist-info` and write a RECORD listing every file. Returns the RECORD entries.
+fn finish_dist_info(site_packages: &Path, extracted: ExtractedWheel, direct_url: Option<&DirectUrl>) -> Result<Vec<RecordEntry>> {
+    let ExtractedWheel { mut record, dist_info } = extracted;
+    let Some(dist_info) = dist_info else {
+        return Ok(record);
+    };
+    let mut metadata = vec![("INSTALLER", "cobra\n".to_string())];
+    if let Some(direct_url) = direct_url {
+        metadata.push(("direct_url.json", direct_url.to_json()));
+    }
+    for (name, contents) in metadata {
+        let path = format!("{}/{}", dist_info, name);
+        let outpath = site_packages.join(&path);
+        // A file linked from the wheel store must not be written through
+        if outpath.symlink_metadata().is_ok() {
+            std::fs::remove_file(&outpath)?;
+        }
+        let mut outfile = RecordingWriter::new(std::fs::File::create(outpath)?);
+        std::io::Write::write_all(&mut outfile, contents.as_bytes())?;
+        record.retain(|entry| entry.path != path);
+        record.push(outfile.entry(&path));
+    }
+    let record_path = format!("{}/RECORD", dist_info);
+    record.push(RecordEntry { path: record_path.clone(), sha256: None, size: None });
+    std::fs::write(site_packages.join(&record_path), format_record(&record))?;
+
+    Ok(record)
+}
+
+/// Writes through to a file, hashing and counting the bytes for RECORD
+struct RecordingWriter {
+    file: std::fs::File,
+    hasher: sha2::Sha256,
+    size: u64,
+}
+
+impl RecordingWriter {
+    fn new(file: std::fs::File) -> Self {
+        Self { file, hasher: sha2::Digest::new(), size: 0 }
+    }
+
+    /// The RECORD entry of everything written, under `path`
+    fn entry(self, path: &str) -> RecordEntry {
+        let digest = sha2::Digest::finalize(self.hasher);
+        RecordEntry {
+            path: path.to_string(),
+            sha256: Some(digest.iter().map(|b| format!("{:02x}", b)).collect()),
+            size: Some(self.size),
+        }
+    }
+}
+
+impl std::io::Write for RecordingWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.file.write(buf)?;
+        sha2::Digest::update(&mut self.hasher, &buf[..written]);
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Clears the progress bars when dropped, so installs that fail or are
+/// cancelled leave no stray bars behind
+struct FinishOnDrop<'a>(&'a ProgressTracker);
+
+impl Drop for FinishOnDrop<'_> {
+    fn drop(&mut self) {
+        self.0.finish_all();
+    }
+}
+
+/// State shared by the install tasks of one `install_parallel` run
+struct InstallContext {
+    client: Arc<dyn RegistryBackend>,
+    wheels: Option<Arc<WheelCache>>,
+    find_links: Vec<PathBuf>,
+    progress: Arc<ProgressTracker>,
+    package_manager: Arc<LocalPackageManager>,
+    /// Caps concurrent archive downloads independently of installs
+    downloads: Semaphore,
+    rate_limit: Option<RateLimiter>,
+    offline: bool,
+    verify: bool,
+    /// Where `.part` files of unfinished downloads live
+    download_dir: PathBuf,
+    download_segments: usize,
+    segment_min_size: u64,
+    chunk_size: usize,
+    max_unpacked_size: u64,
+    mirrors: Vec<String>,
+    build_frontend: BuildFrontend,
+    reinstall: HashSet<String>,
+    /// Registered names of the installed versions these packages replace,
+    /// by normalized name
+    replacing: HashMap<String, String>,
+    store: Option<Arc<WheelStore>>,
+    cancel: CancellationToken,
+}
+
+impl InstallContext {
+    async fn install_single(&self, package: Package) -> Result<InstallOutcome> {
+        if self.cancel.is_cancelled() {
+            return Err(CobraError::Cancelled);
+        }
+
+        let install_path = self.package_manager.layout().site_packages(&package.name);
+
+        // Stored wheels are linked without reading the archive at all
+        if let Some(stored) = self.stored_wheel(&package).await? {
+            let linking = Instant::now();
+            self.link_stored(&package, &stored).await?;
+            return Ok(InstallOutcome {
+                bytes: package.size.unwrap_or(0),
+                url: package.download_url,
+                sha256: stored.sha256,
+                download_ms: 0,
+                extract_ms: linking.elapsed().as_millis() as u64,
+                install_path,
+                name: package.name,
+                version: package.version,
+                source: PackageSource::Store,
+                mirror: None,
+            });
+        }
+
+        // Dropping an unfinished fetch removes its temp archive; a resumable
+        // `.part` file is kept for the next run
+        let fetching = Instant::now();
+        let (archive, package_data, source, mirror) = tokio::select! {
+            biased;
+            fetched = self.fetch(&package) => fetched?,
+            _ = self.cancel.cancelled() => return Err(CobraError::Cancelled),
         };
+        let download_ms = fetching.elapsed().as_millis() as u64;
 
-        // Extract package (skip hash verification for now)
-        let temp_path = std::env::temp_dir().join(format!("{}.whl", package.name));
-        fs::write(&temp_path, &package_data).await?;
-        Self::extract_package_mmap(&temp_path, &package.name, &package_manager).await?;
-        fs::remove_file(&temp_path).await?;
+        let extracting = Instant::now();
+        let sha256 = sha256_hex(&package_data);
+        if build::is_sdist(&package.download_url) {
+            let (_build_dir, wheel) = self.build_sdist(&package, &archive).await?;
+            let files = Installer::extract_package_mmap(&wheel, &package, &self.package_manager, None, self.max_unpacked_size, self.replaced_by(&package)).await?;
+            self.package_manager.register_package_files(&package, None, files).await?;
+        } else if let Some(store) = &self.store {
+            // Keyed by the bytes actually installed, which --no-verify may not have checked
+            let (store, sha256, path, max_size) = (store.clone(), sha256.clone(), archive.to_path_buf(), self.max_unpacked_size);
+            let stored = blocking(move || store.insert(&sha256, |dest| {
+                let extracted = extract_wheel_within(&path, dest, max_size)?;
+                Ok((extracted.record, extracted.dist_info))
+            })).await?;
+            self.link_stored(&package, &stored).await?;
+        } else {
+            let files = Installer::extract_package_mmap(&archive, &package, &self.package_manager, None, self.max_unpacked_size, self.replaced_by(&package)).await?;
+            self.package_manager.register_package_files(&package, None, files).await?;
+        }
+        drop(archive);
 
-        // Register the installed package
-        package_manager.register_package(&package).await?;
+        Ok(InstallOutcome {
+            name: package.name,
+            version: package.version,
+            source,
+            bytes: package_data.len() as u64,
+            mirror,
+            url: package.download_url,
+            sha256,
+            download_ms,
+            extract_ms: extracting.elapsed().as_millis() as u64,
+            install_path,
+        })
+    }
 
+    /// The archive of `package` from the cache or the index, with its bytes,
+    /// where they came from and the mirror that served them, if any
+    async fn fetch(&self, package: &Package) -> Result<(tempfile::TempPath, bytes::Bytes, PackageSource, Option<String>)> {
+        let mut local = match &self.wheels {
+            Some(wheels) => wheels.get(package).await?,
+            None => None,
+        };
+        let cached = local.is_some();
+        // A poisoned cache entry is rejected just like a tampered download
+        // and evicted so a retry downloads again
+        if self.verify && let Some(data) = &local && let Err(e) = verify_archive(package, data) {
+            if let Some(wheels) = &self.wheels {
+                wheels.remove(package).await?;
+            }
+            return Err(e);
+        }
+        // Only find-links archives matching the index's sha256 are taken
+        for dir in &self.find_links {
+            if local.is_some() {
+                break;
+            }
+            local = find_link(dir, package).await?;
+        }
+        // Wheels resolved from local files are read where they lie
+        if local.is_none() {
+            local = read_local_archive(package).await?;
+        }
+
+        if let Some(data) = local {
+            if self.verify {
+                verify_archive(package, &data)?;
+            }
+            if let Err(e) = check_wheel_identity(package, &data) {
+                // A cached archive of another project must not be served again
+                if cached && let Some(wheels) = &self.wheels {
+                    wheels.remove(package).await?;
+                }
+                return Err(e);
+            }
+            let archive = self.unique_archive(package)?;
+            fs::write(&archive, &data).await?;
+            Ok((archive, data, PackageSource::Disk, None))
+        } else if self.offline {
+            Err(CobraError::Offline(match (&self.wheels, self.find_links.is_empty()) {
+                (None, true) => "the package cache is disabled".to_string(),
+                _ => format!("{} is not in the cache", artifact_filename(&package.download_url)),
+            }))
+        } else {
+            // Downloads are verified before they are moved into place, and
+            // only archives matching the index's digest are cached
+            let (archive, data, mirror) = self.download(package).await?;
+            check_wheel_identity(package, &data)?;
+            if let Some(wheels) = &self.wheels {
+                let _ = wheels.insert(package, &data).await;
+            }
+            Ok((archive, data, PackageSource::Network, mirror))
+        }
+    }
+
+    /// The store entry of a wheel with a known sha256. Entries of packages
+    /// being force-reinstalled are checked first, since damage to hardlinked
+    /// files reaches the store; a damaged entry is dropped and extracted again.
+    async fn stored_wheel(&self, package: &Package) -> Result<Option<StoredWheel>> {
+        let (Some(store), Some(hash)) = (&self.store, &package.hash) else {
+            return Ok(None);
+        };
+        if build::is_sdist(&package.download_url) {
+            return Ok(None);
+        }
+        let Some(stored) = store.get(hash) else {
+            return Ok(None);
+        };
+        if self.reinstall.contains(&normalize_name(&package.name)) && !store.is_intact(&stored).await? {
+            store.remove(hash)?;
+            return Ok(None);
+        }
+        Ok(Some(stored))
+    }
+
+    /// Registered name of the installed version `package` replaces
+    fn replaced_by(&self, package: &Package) -> Option<&str> {
+        self.replacing.get(&normalize_name(&package.name)).map(String::as_str)
+    }
+
+    /// Link a stored wheel into the package's site-packages and register it
+    /// as a user of the store entry, removing the version it replaces first
+    async fn link_stored(&self, package: &Package, stored: &StoredWheel) -> Result<()> {
+        if let Some(name) = self.replaced_by(package) {
+            self.package_manager.uninstall(name).await?;
+        }
+        let site_packages = self.package_manager.layout().site_packages(&package.name);
+        fs::create_dir_all(&site_packages).await?;
+        let (wheel, dest) = (stored.clone(), site_packages.clone());
+        let mode = blocking(move || wheel.link_into(&dest)).await?;
+        tracing::debug!("linked {} {} from the wheel store ({:?})", package.name, package.version, mode);
+
+        let extracted = ExtractedWheel { record: stored.record.clone(), dist_info: stored.dist_info.clone() };
+        let files = place_extracted(site_packages, self.package_manager.layout(), &package.name, extracted, None).await?;
+        self.package_manager.register_stored_package(package, files, &stored.sha256).await?;
+        if let Some(store) = &self.store {
+            store.add_user(&stored.sha256, self.package_manager.registry_path())?;
+        }
         Ok(())
     }
 
-    async fn download_package(
-        package: &Package,
-        client: &RegistryClient,
-        progress: &ProgressTracker,
-    ) -> Result<bytes::Bytes> {
-        let size = package.size.unwrap_or(0);
-        let pb = progress.add_download(&package.name, size).await;
+    /// Unpack a downloaded sdist and build it into a wheel with the detected
+    /// Python, returning the wheel and the directory that holds it
+    async fn build_sdist(&self, package: &Package, archive: &Path) -> Result<(tempfile::TempDir, PathBuf)> {
+        let build_dir = tempfile::tempdir()?;
+        let filename = artifact_filename(&package.download_url).to_string();
+        let (archive, source) = (archive.to_path_buf(), build_dir.path().join("source"));
+        let project = tokio::task::spawn_blocking(move || build::unpack_sdist(&archive, &filename, &source))
+            .await
+            .map_err(|e| CobraError::InstallationFailed(e.to_string()))??;
 
-        let response = client.download_package(&package.download_url).await?;
-        let mut stream = response.bytes_stream();
-        let mut buffer = Vec::new();
+        let python = PythonEnvironment::detect().await?.python_path;
+        let wheel = WheelBuilder::new(python)
+            .with_frontend(self.build_frontend)
+            .build(&project, &build_dir.path().join("dist"))
+            .await
+            .map_err(|e| match e {
+                CobraError::InstallationFailed(reason) => CobraError::InstallationFailed(format!(
+                    "{} {} has no wheel and its sdist failed to build: {}", package.name, package.version, reason
+                )),
+                other => other,
+            })?;
+        Ok((build_dir, wheel))
+    }
 
-        while let Some(chunk) = stream.next().await {
-            let chunk = chunk.map_err(|e| CobraError::Network(e))?;
-            buffer.extend_from_slice(&chunk);
-            pb.inc(chunk.len() as u64);
+    /// Stream an archive into `<name>-<version>.whl.part`, in parallel
+    /// segments when it is large, retrying dropped connections from where
+    /// they stopped and moving on to the next mirror when a host fails. The
+    /// finished file is checked against the package's size and digest before
+    /// it moves to a unique `.whl`. Returns the mirror used, if any.
+    async fn download(&self, package: &Package) -> Result<(tempfile::TempPath, bytes::Bytes, Option<String>)> {
+        let _permit = self.downloads.acquire().await
+            .map_err(|e| CobraError::InstallationFailed(e.to_string()))?;
+        let stem = format!("{}-{}", package.name, package.version);
+
+        // While another task or process downloads the same archive into the
+        // shared `.part`, this one uses a private file that is never resumed
+        let lock = self.lock_download(&stem)?;
+        let shared_part = self.download_dir.join(format!("{}.whl.part", stem));
+        let private_part;
+        let part = if lock.is_some() {
+            shared_part.as_path()
+        } else {
+            private_part = tempfile::Builder::new()
+                .prefix(&format!("{}-", stem))
+                .suffix(".whl.part")
+                .tempfile_in(&self.download_dir)?
+                .into_temp_path();
+            &*private_part
+        };
+        let pb = self.progress.add_download(&package.name, package.size.unwrap_or(0)).await;
+
+        // Each archive fails over on its own, so one missing file doesn't
+        // send the rest to a slower mirror. The digest check below makes
+        // mirrors interchangeable, so a partial file is resumed from the next.
+        let sources = self.archive_sources(package);
+        let mut mirror = None;
+        for (i, (url, source)) in sources.iter().enumerate() {
+            match self.fetch_from(package, url, part, &pb).await {
+                Ok(()) => {
+                    mirror = source.clone();
+                    break;
+                }
+                Err(e) if i + 1 < sources.len() && is_host_failure(&e) => {
+                    tracing::debug!("{} failed ({}), trying the next mirror", url, e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        if let Some(mirror) = &mirror {
+            tracing::debug!("{} {} was served by mirror {}", package.name, package.version, mirror);
         }
 
+        let data = bytes::Bytes::from(fs::read(part).await?);
+        let check = match package.size {
+            Some(expected) if data.len() as u64 != expected => Err(CobraError::InstallationFailed(format!(
+                "{} {}: downloaded {} bytes, expected {}", package.name, package.version, data.len(), expected
+            ))),
+            _ if self.verify => verify_archive(package, &data),
+            _ => Ok(()),
+        };
+        if let Err(e) = check {
+            // Resuming a bad file would only reproduce it
+            let _ = fs::remove_file(part).await;
+            return Err(e);
+        }
+
+        let archive = self.unique_archive(package)?;
+        fs::rename(part, &archive).await?;
+        drop(lock);
         pb.finish_with_message(format!("✓ {}", package.name));
-        Ok(bytes::Bytes::from(buffer))
+        self.progress.record_download(data.len() as u64);
+        Ok((archive, data, mirror))
     }
 
-    async fn extract_package_mmap(archive_path: &Path, _package_name: &str, package_manager: &LocalPackageManager) -> Result<()> {
-        // Use the package manager's installation directory
-        let site_packages = package_manager.get_install_dir();
-        
-        // Ensure the site-packages directory exists
-        fs::create_dir_all(&site_packages).await?;
+    /// URLs to fetch `package` from in order: its own, then the same path on
+    /// each mirror, with the mirror each one belongs to
+    fn archive_sources(&self, package: &Package) -> Vec<(String, Option<String>)> {
+        let mirrored = self.mirrors.iter().filter_map(|mirror| {
+            Some((mirror_url(&package.download_url, mirror)?, Some(mirror.clone())))
+        });
+        std::iter::once((package.download_url.clone(), None)).chain(mirrored).collect()
+    }
 
-        // Use memory-mapped file for faster extraction
-        let file = std::fs::File::open(archive_path)
-            .map_err(|e| CobraError::Archive(format!("Failed to open archive: {}", e)))?;
-        
-        let mmap = unsafe { 
-            MmapOptions::new().map(&file)
-                .map_err(|e| CobraError::Archive(format!("Failed to mmap file: {}", e)))?
+    /// Fetch `url` into `part`, in parallel segments when the archive is large
+    async fn fetch_from(&self, package: &Package, url: &str, part: &Path, pb: &indicatif::ProgressBar) -> Result<()> {
+        // Large archives use connections the download limit leaves idle; a
+        // partial file from an earlier run is resumed instead
+        let resuming = fs::metadata(part).await.is_ok_and(|m| m.len() > 0);
+        let mut segmented = false;
+        if let Some(size) = package.size.filter(|&size| size > self.segment_min_size && !resuming) {
+            let spare = self.spare_connections();
+            if !spare.is_empty() {
+                let fetched = tokio::select! {
+                    fetched = self.fetch_segmented(url, size, spare.len() + 1, part, pb) => fetched,
+                    _ = self.cancel.cancelled() => {
+                        // A preallocated file can't be resumed by a later run
+                        let _ = std::fs::remove_file(part);
+                        return Err(CobraError::Cancelled);
+                    }
+                };
+                match fetched {
+                    Ok(done) => segmented = done,
+                    Err(e) => {
+                        tracing::debug!("segmented download of {} failed, using one stream: {}", package.name, e);
+                        let _ = fs::remove_file(part).await;
+                    }
+                }
+            }
+        }
+
+        if segmented {
+            return Ok(());
+        }
+        self.fetch_resuming(package, url, part, pb).await
+    }
+
+    /// Exclusive hold on `<stem>.whl.lock`, released when dropped or when the
+    /// process dies; `None` while someone else holds it
+    fn lock_download(&self, stem: &str) -> Result<Option<std::fs::File>> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(self.download_dir.join(format!("{}.whl.lock", stem)))?;
+        match file.try_lock() {
+            Ok(()) => Ok(Some(file)),
+            Err(std::fs::TryLockError::WouldBlock) => Ok(None),
+            Err(std::fs::TryLockError::Error(e)) => Err(e.into()),
+        }
+    }
+
+    /// A fresh `.whl` path in the download directory, removed when dropped
+    fn unique_archive(&self, package: &Package) -> Result<tempfile::TempPath> {
+        let file = tempfile::Builder::new()
+            .prefix(&format!("{}-{}-", package.name, package.version))
+            .suffix(".whl")
+            .tempfile_in(&self.download_dir)?;
+        Ok(file.into_temp_path())
+    }
+
+    /// Stream into `part` in one connection, resuming after dropped ones
+    async fn fetch_resuming(&self, package: &Package, url: &str, part: &Path, pb: &indicatif::ProgressBar) -> Result<()> {
+        let mut attempt = 1;
+        loop {
+            match self.fetch_into(url, part, pb).await {
+                Ok(()) => return Ok(()),
+                Err(CobraError::Network(e)) if attempt < DOWNLOAD_ATTEMPTS => {
+                    pb.set_message(format!("{} (retrying: {})", package.name, e));
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// One download attempt appending to `part`. Asks for the remaining bytes
+    /// when `part` holds some and starts over when the server sends it all.
+    async fn fetch_into(&self, url: &str, part: &Path, pb: &indicatif::ProgressBar) -> Result<()> {
+        let mut offset = fs::metadata(part).await.map(|m| m.len()).unwrap_or(0);
+        let response = match self.client.download_package(url, offset).await {
+            // e.g. 416 for a partial file left by a different upload
+            Err(CobraError::InstallationFailed(_)) if offset > 0 => {
+                offset = 0;
+                self.client.download_package(url, 0).await?
+            }
+            result => result?,
         };
 
-        let cursor = Cursor::new(&mmap[..]);
-        let mut archive = ZipArchive::new(cursor)
-            .map_err(|e| CobraError::Archive(format!("Failed to read archive: {}", e)))?;
+        let resumed = offset > 0 && response.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+        let accepts_ranges = resumed || response.headers()
+            .get(reqwest::header::ACCEPT_RANGES)
+            .is_some_and(|value| value.as_bytes().eq_ignore_ascii_case(b"bytes"));
+        let mut file = if resumed {
+            fs::OpenOptions::new().append(true).open(part).await?
+        } else {
+            fs::File::create(part).await?
+        };
+        pb.set_position(if resumed { offset } else { 0 });
 
-        // Extract files in parallel using rayon
-        let indices: Vec<usize> = (0..archive.len()).collect();
-        
-        // Note: We need to extract sequentially due to ZipArchive borrowing rules
-        // But we can still optimize with buffering
-        for i in indices {
-            let mut file = archive.by_index(i)
-                .map_err(|e| CobraError::Archive(format!("Failed to read file: {}", e)))?;
-            
-            if file.is_file() {
-                let outpath = site_packages.join(file.name());
-                
-                if let Some(parent) = outpath.parent() {
-                    std::fs::create_dir_all(parent)?;
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    file.flush().await?;
+                    // Without range support the next attempt starts over anyway
+                    if !accepts_ranges {
+                        drop(file);
+                        let _ = fs::remove_file(part).await;
+                    }
+                    return Err(CobraError::Network(e));
                 }
+            };
+            self.write_chunk(&mut file, &chunk, pb).await?;
+        }
+        file.flush().await?;
+        Ok(())
+    }
+
+    /// Download permits for up to `download_segments - 1` extra connections,
+    /// taking only those no other download is waiting for
+    fn spare_connections(&self) -> Vec<SemaphorePermit<'_>> {
+        (1..self.download_segments)
+            .map_while(|_| self.downloads.try_acquire().ok())
+            .collect()
+    }
 
-                let mut outfile = std::fs::File::create(&outpath)?;
-                std::io::copy(&mut file, &mut outfile)?;
+    /// Fetch `size` bytes as `segments` concurrent Range requests written into
+    /// a preallocated `part`. `Ok(false)` when the server doesn't honor ranges.
+    async fn fetch_segmented(
+        &self,
+        url: &str,
+        size: u64,
+        segments: usize,
+        part: &Path,
+        pb: &indicatif::ProgressBar,
+    ) -> Result<bool> {
+        let len = size.div_ceil(segments as u64);
+        let ranges: Vec<(u64, u64)> = (0..segments as u64)
+            .map(|i| i * len)
+            .take_while(|&start| start < size)
+            .map(|start| (start, (start + len).min(size) - 1))
+            .collect();
+
+        // The first segment doubles as the probe for range support
+        let (first_start, first_end) = ranges[0];
+        let first = self.client.download_range(url, first_start, first_end).await?;
+        if first.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+            return Ok(false);
+        }
+        fs::File::create(part).await?.set_len(size).await?;
+        pb.set_position(0);
+
+        let rest = ranges[1..].iter().map(|&(start, end)| async move {
+            let response = self.client.download_range(url, start, end).await?;
+            self.write_segment(response, part, start, end, pb).await
+        });
+        futures::future::try_join(
+            self.write_segment(first, part, first_start, first_end, pb),
+            futures::future::try_join_all(rest),
+        ).await?;
+        Ok(true)
+    }
+
+    /// Write a segment's response at `start` of `part`, checking it covered `start..=end`
+    async fn write_segment(
+        &self,
+        response: reqwest::Response,
+        part: &Path,
+        start: u64,
+        end: u64,
+        pb: &indicatif::ProgressBar,
+    ) -> Result<()> {
+        if response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+            return Err(CobraError::InstallationFailed(format!(
+                "bytes {}-{} were answered with {}", start, end, response.status()
+            )));
+        }
+        let mut file = fs::OpenOptions::new().write(true).open(part).await?;
+        file.seek(std::io::SeekFrom::Start(start)).await?;
+
+        let mut written = 0;
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            if written + chunk.len() as u64 > end - start + 1 {
+                return Err(CobraError::InstallationFailed(format!("bytes {}-{} overran the segment", start, end)));
             }
+            self.write_chunk(&mut file, &chunk, pb).await?;
+            written += chunk.len() as u64;
         }
+        file.flush().await?;
+        if written != end - start + 1 {
+            return Err(CobraError::InstallationFailed(format!(
+                "bytes {}-{} ended after {} bytes", start, end, written
+            )));
+        }
+        Ok(())
+    }
 
+    /// Append `chunk` to `file` within the download rate limit
+    async fn write_chunk(&self, file: &mut fs::File, chunk: &[u8], pb: &indicatif::ProgressBar) -> Result<()> {
+        match &self.rate_limit {
+            // Reading slower applies TCP backpressure to the connection;
+            // small slices keep the progress bar moving while throttled
+            Some(limiter) => {
+                for slice in chunk.chunks(self.chunk_size) {
+                    limiter.acquire(slice.len()).await;
+                    file.write_all(slice).await?;
+                    pb.inc(slice.len() as u64);
+                }
+            }
+            None => {
+                file.write_all(chunk).await?;
+                pb.inc(chunk.len() as u64);
+            }
+        }
         Ok(())
     }
 }
+
+/// Attempts per archive download; later attempts resume the partial file
+const DOWNLOAD_ATTEMPTS: usize = 3;
+
+/// Whether `error` means the host couldn't serve the archive, i.e. an error
+/// status or a connection failure, rather than a local problem
+fn is_host_failure(error: &CobraError) -> bool {
+    matches!(
+        error,
+        CobraError::Network(_) | CobraError::RetriesExhausted { .. } | CobraError::InstallationFailed(_)
+    )
+}
+
+/// Run a post-install check through the platform shell, describing why it failed
+async fn run_check(command: &str, python_path: &std::ffi::OsStr) -> std::result::Result<(), String> {
+    let output = hooks::shell(command)
+        .env("PYTHONPATH", python_path)
+        .output()
+        .await
+        .map_err(|e| format!("could not run `{}`: {}", command, e))?;
+
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    match stderr.lines().rev().find(|line| !line.trim().is_empty()) {
+        Some(last) => Err(format!("`{}` {}: {}", command, output.status, last.trim())),
+        None => Err(format!("`{}` {}", command, output.status)),
+    }
+}
+
+/// Compare an archive with the digest recorded for the package, using the
+/// algorithm the digest is given in
+fn verify_archive(package: &Package, data: &[u8]) -> Result<()> {
+    let Some(recorded) = &package.hash else {
+        return Err(CobraError::InstallationFailed(format!(
+            "{} {} has no sha256 digest to verify against; pass --no-verify to install it anyway",
+            package.name, package.version
+        )));
+    };
+
+    let (algorithm, expected) = HashAlgo::of_digest(recorded).map_err(|e| CobraError::InstallationFailed(format!(
+        "{} {}: {}", package.name, package.version, e
+    )))?;
+    let computed = algorithm.digest(data);
+    if !computed.eq_ignore_ascii_case(expected) {
+        return Err(CobraError::HashMismatch {
+            package: format!("{} {}", package.name, package.version),
+            algorithm,
+            expected: expected.to_string(),
+            computed,
+        });
+    }
+    Ok(())
+}
+
+/// Refuse a wheel whose METADATA, or filename, names another project or
+/// version than `package`, e.g. bytes from a poisoned cache or a mistyped
+/// URL. Sdists, and headers a wheel's METADATA lacks, are not checked.
+fn check_wheel_identity(package: &Package, data: &[u8]) -> Result<()> {
+    if build::is_sdist(&package.download_url) {
+        return Ok(());
+    }
+    let same_version = |version: &str| match (Version::parse(version), Version::parse(&package.version)) {
+        (Ok(found), Ok(expected)) => found == expected,
+        _ => version == package.version,
+    };
+    let mismatch = |found: String| CobraError::InstallationFailed(format!(
+        "{} was resolved as {} {} but {}",
+        artifact_filename(&package.download_url), package.name, package.version, found
+    ));
+
+    // Only trust the version segment when the name segment is the escaped
+    // project name; hand-built filenames that keep dashes can't be split
+    let filename = artifact_filename(&package.download_url);
+    let conforming = filename.split('-').next().is_some_and(|name| normalize_name(name) == normalize_name(&package.name));
+    if conforming
+        && let Some((version, _)) = version_from_filename(&package.name, filename)
+        && !same_version(&version) {
+        return Err(mismatch(format!("its filename carries version {}", version)));
+    }
+
+    let Some(metadata) = build::find_wheel_metadata(Cursor::new(data), filename)? else {
+        return Ok(());
+    };
+    let other_name = !metadata.name.is_empty() && normalize_name(&metadata.name) != normalize_name(&package.name);
+    let other_version = !metadata.version.is_empty() && !same_version(&metadata.version);
+    if other_name || other_version {
+        return Err(mismatch(format!("its METADATA is for {} {}", metadata.name, metadata.version)));
+    }
+    Ok(())
+}
+
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+
+/// Resolve a symlink entry's target relative to the archive root, rejecting
+/// targets that are absolute or climb out of the extraction directory
+fn link_target(entry: &Path, target: &str) -> Result<PathBuf> {
+    let unsafe_link = || CobraError::Archive(format!(
+        "Refusing symlink {} -> {} pointing outside the package", entry.display(), target
+    ));
+
+    let mut resolved = PathBuf::new();
+    let base = entry.parent().unwrap_or(Path::new(""));
+    for component in base.components().chain(Path::new(target).components()) {
+        match component {
+            Component::Normal(part) => resolved.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !resolved.pop() {
+                    return Err(unsafe_link());
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return Err(unsafe_link()),
+        }
+    }
+    Ok(resolved)
+}
+
+#[cfg(unix)]
+fn create_link(target: &Path, _resolved: &Path, link: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(target, link)?;
+    Ok(())
+}
+
+/// Symlinks need extra privileges on Windows, so install a copy of the target
+#[cfg(not(unix))]
+fn create_link(_target: &Path, resolved: &Path, link: &Path) -> Result<()> {
+    if resolved.is_dir() {
+        return Err(CobraError::Archive(format!(
+            "Cannot install directory symlink {} on this platform", link.display()
+        )));
+    }
+    std::fs::copy(resolved, link)?;
+    Ok(())
+}
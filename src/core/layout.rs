@@ -0,0 +1,167 @@
+use std::path::{Component, Path, PathBuf};
+
+/// How installed packages are arranged on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutKind {
+    /// Every package extracted into one shared site-packages directory
+    FlatSitePackages,
+    /// Each package extracted into its own `<root>/<name>` directory
+    PerPackage,
+    /// A Python virtual environment (`lib/pythonX.Y/site-packages`, `bin`)
+    Venv,
+    /// A standalone vendoring directory (`--target`)
+    Target,
+}
+
+/// Central place deciding where site-packages, scripts, dist-info and data files go
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstallLayout {
+    kind: LayoutKind,
+    base: PathBuf,
+    site_packages: PathBuf,
+    scripts: PathBuf,
+    data: PathBuf,
+}
+
+impl InstallLayout {
+    /// Shared site-packages directory, Cobra's default `.cobra_packages` layout
+    pub fn flat(root: PathBuf) -> Self {
+        Self {
+            kind: LayoutKind::FlatSitePackages,
+            site_packages: root.clone(),
+            scripts: root.join("bin"),
+            data: root.join("data"),
+            base: root,
+        }
+    }
+
+    /// One directory per package under `root`
+    pub fn per_package(root: PathBuf) -> Self {
+        Self {
+            kind: LayoutKind::PerPackage,
+            site_packages: root.clone(),
+            scripts: root.join("bin"),
+            data: root.join("data"),
+            base: root,
+        }
+    }
+
+    /// Standard virtual environment layout for the given `major.minor` Python version
+    pub fn venv(root: PathBuf, python_version: &str) -> Self {
+        let (site_packages, scripts) = if cfg!(windows) {
+            (root.join("Lib").join("site-packages"), root.join("Scripts"))
+        } else {
+            (
+                root.join("lib").join(format!("python{}", python_version)).join("site-packages"),
+                root.join("bin"),
+            )
+        };
+
+        Self {
+            kind: LayoutKind::Venv,
+            site_packages,
+            scripts,
+            data: root.clone(),
+            base: root,
+        }
+    }
+
+    /// Flat vendoring directory that is never added to Python's path
+    pub fn target(dir: PathBuf) -> Self {
+        Self {
+            kind: LayoutKind::Target,
+            site_packages: dir.clone(),
+            scripts: dir.join("bin"),
+            data: dir.clone(),
+            base: dir,
+        }
+    }
+
+    /// Relocate every path of this layout under `root`, like pip's `--root`
+    pub fn with_root(self, root: &Path) -> Self {
+        let rebase = |path: PathBuf| {
+            let relative: PathBuf = path
+                .components()
+                .filter(|c| matches!(c, Component::Normal(_) | Component::CurDir | Component::ParentDir))
+                .collect();
+            root.join(relative)
+        };
+
+        Self {
+            kind: self.kind,
+            base: rebase(self.base),
+            site_packages: rebase(self.site_packages),
+            scripts: rebase(self.scripts),
+            data: rebase(self.data),
+        }
+    }
+
+    pub fn kind(&self) -> LayoutKind {
+        self.kind
+    }
+
+    /// Directory holding Cobra's own bookkeeping (registry file)
+    pub fn base_dir(&self) -> &Path {
+        &self.base
+    }
+
+    /// Directory a package's wheel contents are extracted into
+    pub fn site_packages(&self, package: &str) -> PathBuf {
+        match self.kind {
+            LayoutKind::PerPackage => self.site_packages.join(package),
+            _ => self.site_packages.clone(),
+        }
+    }
+
+    /// Directory that owns a package's files, recorded as its install path
+    pub fn package_dir(&self, package: &str) -> PathBuf {
+        self.site_packages.join(package)
+    }
+
+    /// `<name>-<version>.dist-info` directory using the wheel's normalized name
+    pub fn dist_info_dir(&self, package: &str, version: &str) -> PathBuf {
+        self.site_packages(package)
+            .join(format!("{}-{}.dist-info", wheel_name(package), version))
+    }
+
+    /// Where wheels are extracted before their files are moved into site-packages
+    pub fn staging_dir(&self) -> PathBuf {
+        self.base.join(".staging")
+    }
+
+    /// Where entry-point and `.data/scripts` executables are written
+    pub fn scripts_dir(&self) -> &Path {
+        &self.scripts
+    }
+
+    /// Where `.data/data` files are written
+    pub fn data_dir(&self) -> &Path {
+        &self.data
+    }
+
+    /// Where a package's `.data/headers` C headers are written
+    pub fn headers_dir(&self, package: &str) -> PathBuf {
+        self.data.join("include").join(package)
+    }
+
+    /// Directories that must be listed in a `.pth` file for Python to find the packages
+    pub fn pth_entries(&self, packages: &[String]) -> Vec<PathBuf> {
+        match self.kind {
+            LayoutKind::FlatSitePackages => vec![self.site_packages.clone()],
+            LayoutKind::PerPackage => {
+                let mut entries: Vec<PathBuf> = packages.iter().map(|p| self.site_packages(p)).collect();
+                entries.sort();
+                entries.dedup();
+                entries
+            }
+            // A venv's interpreter already sees its site-packages, and a
+            // target directory is meant to be shipped, not imported in place
+            LayoutKind::Venv | LayoutKind::Target => Vec::new(),
+        }
+    }
+}
+
+/// Escape a project name the way wheel filenames and dist-info directories do
+pub fn wheel_name(name: &str) -> String {
+    name.replace(['-', '.'], "_")
+}
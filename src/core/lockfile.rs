@@ -0,0 +1,196 @@
+use crate::{Result, CobraError, Dependency, DependencySource, Package, normalize_name};
+use crate::core::resolver::ResolutionStrategy;
+use crate::core::version::Version;
+use crate::utils::fs::atomic_write;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use tokio::fs;
+
+pub const LOCKFILE_NAME: &str = "cobra.lock";
+const LOCKFILE_VERSION: u32 = 1;
+
+/// Exact set of packages produced by a resolution, written to `cobra.lock`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Lockfile {
+    pub version: u32,
+    #[serde(default)]
+    pub resolution: ResolutionStrategy,
+    /// Hash of the inputs this lock was resolved from, see [`input_hash`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub overrides: BTreeMap<String, String>,
+    #[serde(default, rename = "package")]
+    pub packages: Vec<LockedPackage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LockedPackage {
+    pub name: String,
+    pub version: String,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<String>,
+    /// Environment marker under which the package is installed; absent means everywhere
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub markers: Option<String>,
+    /// Version was forced by an `[overrides]` entry
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub overridden: bool,
+    /// The locked release was yanked from its index
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub yanked: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub yanked_reason: Option<String>,
+}
+
+impl Lockfile {
+    /// Build a lockfile from resolved packages, sorted by name for stable diffs
+    pub fn from_packages(packages: &[Package], resolution: ResolutionStrategy) -> Self {
+        let mut packages: Vec<LockedPackage> = packages.iter().map(LockedPackage::from).collect();
+        packages.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.version.cmp(&b.version)));
+
+        Self {
+            version: LOCKFILE_VERSION,
+            resolution,
+            input_hash: None,
+            overrides: BTreeMap::new(),
+            packages,
+        }
+    }
+
+    /// Record the `[overrides]` that shaped this resolution and flag affected packages
+    pub fn with_overrides(mut self, overrides: &HashMap<String, String>) -> Self {
+        self.overrides = overrides.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        for package in &mut self.packages {
+            package.overridden = overrides.contains_key(&normalize_name(&package.name));
+        }
+        self
+    }
+
+    /// Record the hash of the inputs the packages were resolved from
+    pub fn with_input_hash(mut self, input_hash: String) -> Self {
+        self.input_hash = Some(input_hash);
+        self
+    }
+
+    /// The locked packages, ready to install without resolving, when the lock
+    /// was produced from exactly `input_hash` and pins every package to an
+    /// exact version and download URL
+    pub fn installable_packages(&self, input_hash: &str) -> Option<Vec<Package>> {
+        if self.input_hash.as_deref() != Some(input_hash) {
+            return None;
+        }
+        let complete = self.packages.iter().all(|p| !p.url.is_empty() && Version::parse(&p.version).is_ok());
+        complete.then(|| self.packages.iter().map(Package::from).collect())
+    }
+
+    pub async fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path).await?;
+        let lockfile: Lockfile = toml::from_str(&contents)
+            .map_err(|e| CobraError::Config(format!("Failed to parse {}: {}", LOCKFILE_NAME, e)))?;
+        Ok(lockfile)
+    }
+
+    pub async fn save(&self, path: &Path) -> Result<()> {
+        let contents = toml::to_string_pretty(self)
+            .map_err(|e| CobraError::Config(format!("Failed to serialize lockfile: {}", e)))?;
+        let contents = format!("# This file is generated by Cobra. Do not edit by hand.\n\n{}", contents);
+        atomic_write(path, contents.as_bytes()).await
+    }
+
+    /// Locked `name -> version` map keyed by normalized name, used to pin
+    /// resolution for `--frozen`
+    pub fn pins(&self) -> HashMap<String, String> {
+        self.packages
+            .iter()
+            .map(|p| (normalize_name(&p.name), p.version.clone()))
+            .collect()
+    }
+
+    /// Names of the locked packages that require `name`
+    pub fn dependents(&self, name: &str) -> Vec<&str> {
+        let name = normalize_name(name);
+        self.packages
+            .iter()
+            .filter(|p| p.dependencies.iter().any(|d| normalize_name(d) == name))
+            .map(|p| p.name.as_str())
+            .collect()
+    }
+}
+
+/// Hash of everything that shapes a resolution: the root requirements (in any
+/// order), overrides, strategy, upload cutoff and the Python version releases
+/// were picked for
+pub fn input_hash(
+    dependencies: &[Dependency],
+    overrides: &HashMap<String, String>,
+    resolution: ResolutionStrategy,
+    exclude_newer: Option<&DateTime<Utc>>,
+    python: Option<&Version>,
+) -> String {
+    let mut lines: Vec<String> = dependencies
+        .iter()
+        .map(|d| {
+            let extras = if d.extras.is_empty() { String::new() } else { format!("[{}]", d.extras.join(",")) };
+            format!("dependency {}{} {} ; {}", normalize_name(&d.name), extras, d.version_spec, d.markers.as_deref().unwrap_or(""))
+        })
+        .collect();
+    lines.extend(overrides.iter().map(|(name, spec)| format!("override {} {}", name, spec)));
+    lines.sort();
+    lines.push(format!("resolution {}", resolution));
+    if let Some(cutoff) = exclude_newer {
+        lines.push(format!("exclude-newer {}", cutoff.to_rfc3339()));
+    }
+    if let Some(python) = python {
+        lines.push(format!("python {}", python));
+    }
+    blake3::hash(lines.join("\n").as_bytes()).to_hex().to_string()
+}
+
+impl From<&LockedPackage> for Package {
+    fn from(locked: &LockedPackage) -> Self {
+        Package {
+            name: locked.name.clone(),
+            version: locked.version.clone(),
+            dependencies: locked
+                .dependencies
+                .iter()
+                .map(|name| Dependency { name: name.clone(), version_spec: "*".to_string(), markers: None, source: DependencySource::Registry, extras: Vec::new(), optional: false })
+                .collect(),
+            download_url: locked.url.clone(),
+            hash: locked.hash.clone(),
+            size: locked.size,
+            description: None,
+            author: None,
+            homepage: None,
+            markers: locked.markers.clone(),
+            yanked: locked.yanked,
+            yanked_reason: locked.yanked_reason.clone(),
+            requires_python: None,
+        }
+    }
+}
+
+impl From<&Package> for LockedPackage {
+    fn from(package: &Package) -> Self {
+        Self {
+            name: package.name.clone(),
+            version: package.version.clone(),
+            url: package.download_url.clone(),
+            hash: package.hash.clone(),
+            size: package.size,
+            dependencies: package.dependencies.iter().map(|d| d.name.clone()).collect(),
+            markers: package.markers.clone(),
+            overridden: false,
+            yanked: package.yanked,
+            yanked_reason: package.yanked_reason.clone(),
+        }
+    }
+}
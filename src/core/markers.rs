@@ -0,0 +1,410 @@
+use crate::{Result, CobraError, normalize_name};
+use crate::core::python::PythonEnvironment;
+use crate::core::version::{Specifier, Version};
+
+/// Values of the PEP 508 environment marker variables for one target platform
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarkerEnvironment {
+    pub os_name: String,
+    pub sys_platform: String,
+    pub platform_system: String,
+    pub platform_machine: String,
+    pub platform_python_implementation: String,
+    pub implementation_name: String,
+    pub python_version: String,
+    pub python_full_version: String,
+}
+
+impl MarkerEnvironment {
+    /// Environment of the machine Cobra runs on, for the given Python version
+    /// (`3.11`, `3.11.7` or `Python 3.11.7`)
+    pub fn current(python_version: &str) -> Self {
+        let full = python_version.trim().trim_start_matches("Python").trim().to_string();
+        let short = full.split('.').take(2).collect::<Vec<_>>().join(".");
+
+        let (os_name, sys_platform, platform_system) = match std::env::consts::OS {
+            "windows" => ("nt", "win32", "Windows"),
+            "macos" => ("posix", "darwin", "Darwin"),
+            "linux" => ("posix", "linux", "Linux"),
+            "freebsd" => ("posix", "freebsd", "FreeBSD"),
+            other => ("posix", other, other),
+        };
+        let platform_machine = match (std::env::consts::OS, std::env::consts::ARCH) {
+            ("windows", "x86_64") => "AMD64",
+            ("macos", "aarch64") => "arm64",
+            (_, arch) => arch,
+        };
+
+        Self {
+            os_name: os_name.to_string(),
+            sys_platform: sys_platform.to_string(),
+            platform_system: platform_system.to_string(),
+            platform_machine: platform_machine.to_string(),
+            platform_python_implementation: "CPython".to_string(),
+            implementation_name: "cpython".to_string(),
+            python_version: short,
+            python_full_version: full,
+        }
+    }
+
+    /// Environment of the detected Python interpreter, or of `fallback_version`
+    /// (cobra.toml's `python-version`) when none can be run
+    pub async fn detect(fallback_version: &str) -> Self {
+        match PythonEnvironment::detect().await {
+            Ok(python) => Self::current(&python.version),
+            Err(_) => Self::current(fallback_version),
+        }
+    }
+
+    /// The interpreter version, for checking releases' `requires_python`
+    pub fn python(&self) -> Option<Version> {
+        Version::parse(&self.python_full_version).ok()
+    }
+
+    /// Whether a package needed under `marker` (`None` = always) belongs here
+    pub fn includes(&self, marker: Option<&str>) -> bool {
+        marker.is_none_or(|m| self.evaluate(m).unwrap_or(true))
+    }
+
+    fn value(&self, variable: &str) -> Option<&str> {
+        Some(match variable {
+            "os_name" | "os.name" => &self.os_name,
+            "sys_platform" | "sys.platform" => &self.sys_platform,
+            "platform_system" => &self.platform_system,
+            "platform_machine" | "platform.machine" => &self.platform_machine,
+            "platform_python_implementation" | "python_implementation" => &self.platform_python_implementation,
+            "implementation_name" => &self.implementation_name,
+            "python_version" => &self.python_version,
+            "python_full_version" | "implementation_version" => &self.python_full_version,
+            _ => return None,
+        })
+    }
+
+    /// Evaluate a marker expression; `extra` clauses are false since no extras are requested
+    pub fn evaluate(&self, marker: &str) -> Result<bool> {
+        let expr = MarkerExpr::parse(marker)?;
+        Ok(expr.eval(&|clause: &Clause| clause.eval(self)))
+    }
+}
+
+/// Whether a marker can hold on some platform: every environment comparison
+/// counts as true, only `extra` clauses (unrequested extras) are false
+pub fn applies_on_some_platform(marker: &str) -> Result<bool> {
+    let expr = MarkerExpr::parse(marker)?;
+    Ok(expr.eval(&|clause: &Clause| !clause.mentions_extra()))
+}
+
+/// Decide the `extra` clauses of `marker` for a package installed with
+/// `extras`: `None` when the requirement is not needed, `Some(None)` when it
+/// always is, otherwise the environment marker that remains
+pub fn apply_extras(marker: &str, extras: &[String]) -> Result<Option<Option<String>>> {
+    let requested: Vec<String> = extras.iter().map(|e| normalize_name(e)).collect();
+    Ok(match MarkerExpr::parse(marker)?.decide(&|clause: &Clause| clause.extra_requested(&requested)) {
+        Decided::Always => Some(None),
+        Decided::Never => None,
+        Decided::When(expr) => Some(Some(expr.to_string())),
+    })
+}
+
+/// `(a) and (b)`, where `None` means "always"
+pub fn and_markers(a: Option<&str>, b: Option<&str>) -> Option<String> {
+    match (a, b) {
+        (None, None) => None,
+        (Some(m), None) | (None, Some(m)) => Some(m.to_string()),
+        (Some(a), Some(b)) if a == b => Some(a.to_string()),
+        (Some(a), Some(b)) => Some(format!("({}) and ({})", a, b)),
+    }
+}
+
+/// `(a) or (b)`, where `None` means "always"
+pub fn or_markers(a: Option<&str>, b: Option<&str>) -> Option<String> {
+    match (a, b) {
+        (Some(a), Some(b)) if a == b => Some(a.to_string()),
+        (Some(a), Some(b)) => Some(format!("({}) or ({})", a, b)),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum MarkerExpr {
+    Clause(Clause),
+    And(Box<MarkerExpr>, Box<MarkerExpr>),
+    Or(Box<MarkerExpr>, Box<MarkerExpr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    Variable(String),
+    Literal(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Clause {
+    left: Operand,
+    op: String,
+    right: Operand,
+}
+
+impl MarkerExpr {
+    fn parse(input: &str) -> Result<Self> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser { tokens, pos: 0, input };
+        let expr = parser.or_expr()?;
+        if parser.pos != parser.tokens.len() {
+            return Err(parser.error());
+        }
+        Ok(expr)
+    }
+
+    fn eval(&self, clause: &dyn Fn(&Clause) -> bool) -> bool {
+        match self {
+            MarkerExpr::Clause(c) => clause(c),
+            MarkerExpr::And(a, b) => a.eval(clause) && b.eval(clause),
+            MarkerExpr::Or(a, b) => a.eval(clause) || b.eval(clause),
+        }
+    }
+}
+
+/// A marker with some clauses already known to be true or false
+enum Decided {
+    Always,
+    Never,
+    When(MarkerExpr),
+}
+
+impl MarkerExpr {
+    /// Substitute the clauses `known` can decide and simplify what is left
+    fn decide(self, known: &dyn Fn(&Clause) -> Option<bool>) -> Decided {
+        match self {
+            MarkerExpr::Clause(c) => match known(&c) {
+                Some(true) => Decided::Always,
+                Some(false) => Decided::Never,
+                None => Decided::When(MarkerExpr::Clause(c)),
+            },
+            MarkerExpr::And(a, b) => match (a.decide(known), b.decide(known)) {
+                (Decided::Never, _) | (_, Decided::Never) => Decided::Never,
+                (Decided::Always, other) | (other, Decided::Always) => other,
+                (Decided::When(a), Decided::When(b)) => Decided::When(MarkerExpr::And(Box::new(a), Box::new(b))),
+            },
+            MarkerExpr::Or(a, b) => match (a.decide(known), b.decide(known)) {
+                (Decided::Always, _) | (_, Decided::Always) => Decided::Always,
+                (Decided::Never, other) | (other, Decided::Never) => other,
+                (Decided::When(a), Decided::When(b)) => Decided::When(MarkerExpr::Or(Box::new(a), Box::new(b))),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for MarkerExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let nested = |expr: &MarkerExpr| match expr {
+            MarkerExpr::Clause(_) => expr.to_string(),
+            _ => format!("({})", expr),
+        };
+        match self {
+            MarkerExpr::Clause(c) => write!(f, "{} {} {}", c.left, c.op, c.right),
+            MarkerExpr::And(a, b) => write!(f, "{} and {}", nested(a), nested(b)),
+            MarkerExpr::Or(a, b) => write!(f, "{} or {}", nested(a), nested(b)),
+        }
+    }
+}
+
+impl std::fmt::Display for Operand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operand::Variable(name) => write!(f, "{}", name),
+            Operand::Literal(value) if value.contains('"') => write!(f, "'{}'", value),
+            Operand::Literal(value) => write!(f, "\"{}\"", value),
+        }
+    }
+}
+
+impl Clause {
+    /// Whether an `extra == "..."` clause holds for the requested extras;
+    /// `None` for other clauses
+    fn extra_requested(&self, requested: &[String]) -> Option<bool> {
+        let named = match (&self.left, &self.right) {
+            (Operand::Variable(v), Operand::Literal(name)) | (Operand::Literal(name), Operand::Variable(v)) if v == "extra" => {
+                requested.contains(&normalize_name(name))
+            }
+            _ => return None,
+        };
+        match self.op.as_str() {
+            "==" | "===" => Some(named),
+            "!=" => Some(!named),
+            _ => None,
+        }
+    }
+
+    fn mentions_extra(&self) -> bool {
+        [&self.left, &self.right].iter().any(|o| matches!(o, Operand::Variable(v) if v == "extra"))
+    }
+
+    fn eval(&self, env: &MarkerEnvironment) -> bool {
+        if self.mentions_extra() {
+            return false;
+        }
+        let resolve = |operand: &Operand| match operand {
+            Operand::Variable(name) => env.value(name).unwrap_or_default().to_string(),
+            Operand::Literal(value) => value.clone(),
+        };
+        let (left, right) = (resolve(&self.left), resolve(&self.right));
+
+        match self.op.as_str() {
+            "in" => right.contains(&left),
+            "not in" => !right.contains(&left),
+            op => compare(&left, op, &right),
+        }
+    }
+}
+
+/// Compare as PEP 440 versions when both sides parse, else as strings
+fn compare(left: &str, op: &str, right: &str) -> bool {
+    if let Ok(version) = Version::parse(left)
+        && let Ok(spec) = Specifier::parse(&format!("{}{}", op, right)) {
+        return spec.contains(&version);
+    }
+    match op {
+        "==" | "===" => left == right,
+        "!=" => left != right,
+        "<" => left < right,
+        "<=" => left <= right,
+        ">" => left > right,
+        ">=" => left >= right,
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Op(String),
+    LParen,
+    RParen,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let invalid = || CobraError::InvalidInput(format!("Invalid marker: {}", input));
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            ' ' | '\t' => i += 1,
+            '(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            '\'' | '"' => {
+                let end = chars[i + 1..].iter().position(|&ch| ch == c).ok_or_else(invalid)?;
+                tokens.push(Token::Str(chars[i + 1..i + 1 + end].iter().collect()));
+                i += end + 2;
+            }
+            '=' | '!' | '<' | '>' | '~' => {
+                let len = chars[i..].iter().take_while(|ch| matches!(ch, '=' | '!' | '<' | '>' | '~')).count();
+                tokens.push(Token::Op(chars[i..i + len].iter().collect()));
+                i += len;
+            }
+            c if c.is_ascii_alphanumeric() || c == '_' || c == '.' => {
+                let len = chars[i..]
+                    .iter()
+                    .take_while(|ch| ch.is_ascii_alphanumeric() || **ch == '_' || **ch == '.')
+                    .count();
+                tokens.push(Token::Ident(chars[i..i + len].iter().collect()));
+                i += len;
+            }
+            _ => return Err(invalid()),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    input: &'a str,
+}
+
+impl Parser<'_> {
+    fn error(&self) -> CobraError {
+        CobraError::InvalidInput(format!("Invalid marker: {}", self.input))
+    }
+
+    fn keyword(&mut self, word: &str) -> bool {
+        if matches!(self.tokens.get(self.pos), Some(Token::Ident(w)) if w == word) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or_expr(&mut self) -> Result<MarkerExpr> {
+        let mut expr = self.and_expr()?;
+        while self.keyword("or") {
+            expr = MarkerExpr::Or(Box::new(expr), Box::new(self.and_expr()?));
+        }
+        Ok(expr)
+    }
+
+    fn and_expr(&mut self) -> Result<MarkerExpr> {
+        let mut expr = self.atom()?;
+        while self.keyword("and") {
+            expr = MarkerExpr::And(Box::new(expr), Box::new(self.atom()?));
+        }
+        Ok(expr)
+    }
+
+    fn atom(&mut self) -> Result<MarkerExpr> {
+        if self.tokens.get(self.pos) == Some(&Token::LParen) {
+            self.pos += 1;
+            let expr = self.or_expr()?;
+            if self.tokens.get(self.pos) != Some(&Token::RParen) {
+                return Err(self.error());
+            }
+            self.pos += 1;
+            return Ok(expr);
+        }
+
+        let left = self.operand()?;
+        let op = match self.tokens.get(self.pos).cloned() {
+            Some(Token::Op(op)) if matches!(op.as_str(), "<" | "<=" | "!=" | "==" | ">=" | ">" | "~=" | "===") => {
+                self.pos += 1;
+                op
+            }
+            Some(Token::Ident(word)) if word == "in" => {
+                self.pos += 1;
+                "in".to_string()
+            }
+            Some(Token::Ident(word)) if word == "not" => {
+                self.pos += 1;
+                if !self.keyword("in") {
+                    return Err(self.error());
+                }
+                "not in".to_string()
+            }
+            _ => return Err(self.error()),
+        };
+        let right = self.operand()?;
+        Ok(MarkerExpr::Clause(Clause { left, op, right }))
+    }
+
+    fn operand(&mut self) -> Result<Operand> {
+        let operand = match self.tokens.get(self.pos) {
+            Some(Token::Str(value)) => Operand::Literal(value.clone()),
+            Some(Token::Ident(name)) if !matches!(name.as_str(), "and" | "or" | "in" | "not") => {
+                Operand::Variable(name.clone())
+            }
+            _ => return Err(self.error()),
+        };
+        self.pos += 1;
+        Ok(operand)
+    }
+}
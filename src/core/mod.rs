@@ -3,9 +3,23 @@
 //! This module contains the main business logic and core components
 //! that implement the high-performance package management operations.
 
+pub mod build;
 pub mod cache;
 pub mod config;
+pub mod find_links;
+pub mod hooks;
+pub mod imports;
+pub mod index;
 pub mod installer;
+pub mod layout;
+pub mod lockfile;
+pub mod markers;
 pub mod package_manager;
 pub mod python;
+pub mod record;
+pub mod requirements;
 pub mod resolver;
+pub mod store;
+pub mod vcs;
+pub mod version;
+pub mod wheel_cache;
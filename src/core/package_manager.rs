@@ -1,4 +1,7 @@
-use crate::{Result, CobraError, Package};
+use crate::{Result, CobraError, Package, normalize_name};
+use crate::core::layout::{wheel_name, InstallLayout, LayoutKind};
+use crate::core::record::{verify_files, FileProblem, RecordEntry};
+use crate::utils::fs::{atomic_write, dir_size, move_into_place};
 use std::path::{Path, PathBuf};
 use tokio::fs;
 use serde::{Deserialize, Serialize};
@@ -10,6 +13,18 @@ pub struct InstalledPackage {
     pub version: String,
     pub install_path: PathBuf,
     pub installed_at: chrono::DateTime<chrono::Utc>,
+    /// Hash of the local project directory this package was built from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_hash: Option<String>,
+    /// Files listed in the wheel's RECORD when it was installed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<RecordEntry>,
+    /// sha256 of the wheel store entry the files were linked from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub store_entry: Option<String>,
+    /// Bytes the installed files take up, measured after extraction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size_on_disk: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -17,80 +32,170 @@ pub struct PackageRegistry {
     pub packages: HashMap<String, InstalledPackage>,
 }
 
+impl PackageRegistry {
+    /// Read the registry at `path`; a missing file is an empty registry
+    pub async fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(path).await?;
+        serde_json::from_str(&contents)
+            .map_err(|e| CobraError::Config(format!("Failed to parse registry: {}", e)))
+    }
+}
+
+/// What `cobra clean` removes to bring an install directory back in line
+/// with its registry
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CleanPlan {
+    /// Files and directories on disk that no registered package owns
+    pub orphans: Vec<PathBuf>,
+    /// Registered packages whose files are all gone
+    pub missing: Vec<String>,
+}
+
+impl CleanPlan {
+    pub fn is_empty(&self) -> bool {
+        self.orphans.is_empty() && self.missing.is_empty()
+    }
+}
+
+/// How the shared .pth file covers this project's install directories
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PthStatus {
+    /// The layout is importable without a .pth file, or nothing is installed
+    NotNeeded,
+    /// Every needed directory is listed
+    Complete,
+    /// These needed directories are not listed
+    Missing(Vec<PathBuf>),
+}
+
 pub struct LocalPackageManager {
+    layout: InstallLayout,
     install_dir: PathBuf,
     registry_path: PathBuf,
+    /// Serializes read-modify-write updates of the registry by parallel installs
+    registry_lock: tokio::sync::Mutex<()>,
+    /// Keep housekeeping messages off stdout
+    quiet: bool,
 }
 
 impl LocalPackageManager {
     pub fn new(install_dir: PathBuf) -> Self {
+        Self::with_layout(InstallLayout::flat(install_dir))
+    }
+
+    pub fn with_layout(layout: InstallLayout) -> Self {
+        let install_dir = layout.base_dir().to_path_buf();
         let registry_path = install_dir.join("cobra-registry.json");
         Self {
+            layout,
             install_dir,
             registry_path,
+            registry_lock: tokio::sync::Mutex::new(()),
+            quiet: false,
         }
     }
 
+    /// Stop printing housekeeping messages, for runs whose stdout is a report
+    pub fn with_quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
     /// Ensure the installation directory exists
     pub async fn ensure_install_dir(&self) -> Result<()> {
         if !self.install_dir.exists() {
             fs::create_dir_all(&self.install_dir).await?;
-            println!("📁 Created installation directory: {}", self.install_dir.display());
+            if !self.quiet {
+                println!("📁 Created installation directory: {}", self.install_dir.display());
+            }
         }
         Ok(())
     }
 
     /// Load the package registry
     pub async fn load_registry(&self) -> Result<PackageRegistry> {
-        if !self.registry_path.exists() {
-            return Ok(PackageRegistry::default());
-        }
+        PackageRegistry::load(&self.registry_path).await
+    }
 
-        let contents = fs::read_to_string(&self.registry_path).await?;
-        let registry: PackageRegistry = serde_json::from_str(&contents)
-            .map_err(|e| CobraError::Config(format!("Failed to parse registry: {}", e)))?;
-        Ok(registry)
+    /// Where the package registry is stored
+    pub fn registry_path(&self) -> &Path {
+        &self.registry_path
     }
 
     /// Save the package registry
     pub async fn save_registry(&self, registry: &PackageRegistry) -> Result<()> {
         let contents = serde_json::to_string_pretty(registry)
             .map_err(|e| CobraError::Config(format!("Failed to serialize registry: {}", e)))?;
-        fs::write(&self.registry_path, contents).await?;
+        atomic_write(&self.registry_path, contents.as_bytes()).await?;
         Ok(())
     }
 
     /// Check if a package is already installed with the correct version
     pub async fn is_package_installed(&self, name: &str, version: &str) -> Result<bool> {
         let registry = self.load_registry().await?;
-        
-        if let Some(installed) = registry.packages.get(name) {
-            // Check if the installed version satisfies the requirement
-            if self.version_satisfies(&installed.version, version) {
-                // Also verify the package directory still exists
-                if installed.install_path.exists() {
-                    return Ok(true);
-                } else {
-                    // Package directory was deleted, remove from registry
-                    let mut updated_registry = registry;
-                    updated_registry.packages.remove(name);
-                    self.save_registry(&updated_registry).await?;
-                }
-            }
+
+        // A package whose files were deleted keeps its entry, so replacing
+        // it still knows what it had recorded
+        Ok(registry.packages.get(name).is_some_and(|installed| {
+            crate::core::version::satisfies(&installed.version, version) && self.is_present(installed)
+        }))
+    }
+
+    /// Whether any file recorded for `package` is still on disk. Packages
+    /// registered before files were recorded need their directory.
+    pub fn is_present(&self, package: &InstalledPackage) -> bool {
+        if package.files.is_empty() {
+            return package.install_path.exists();
         }
-        
-        Ok(false)
+        let root = self.layout.site_packages(&package.name);
+        package.files.iter().any(|entry| resolve_dots(&root.join(&entry.path)).exists())
+    }
+
+    /// Registered packages that are still on disk
+    pub async fn list_present(&self) -> Result<Vec<InstalledPackage>> {
+        let mut installed = self.list_installed().await?;
+        installed.retain(|package| self.is_present(package));
+        Ok(installed)
     }
 
     /// Register a newly installed package
     pub async fn register_package(&self, package: &Package) -> Result<()> {
+        self.register_package_with_source(package, None).await
+    }
+
+    /// Register a package, remembering the source hash of a locally built one
+    pub async fn register_package_with_source(&self, package: &Package, source_hash: Option<&str>) -> Result<()> {
+        self.register_package_files(package, source_hash, Vec::new()).await
+    }
+
+    /// Register a package together with the RECORD of the wheel it came from
+    pub async fn register_package_files(&self, package: &Package, source_hash: Option<&str>, files: Vec<RecordEntry>) -> Result<()> {
+        self.register(package, source_hash, files, None).await
+    }
+
+    /// Register a package whose files were linked from the wheel store entry `sha256`
+    pub async fn register_stored_package(&self, package: &Package, files: Vec<RecordEntry>, sha256: &str) -> Result<()> {
+        self.register(package, None, files, Some(sha256.to_string())).await
+    }
+
+    async fn register(&self, package: &Package, source_hash: Option<&str>, files: Vec<RecordEntry>, store_entry: Option<String>) -> Result<()> {
+        let size_on_disk = self.size_on_disk(&package.name, &files).await;
+        let _guard = self.registry_lock.lock().await;
         let mut registry = self.load_registry().await?;
         
         let installed_package = InstalledPackage {
             name: package.name.clone(),
             version: package.version.clone(),
-            install_path: self.install_dir.join(&package.name),
+            install_path: self.layout.package_dir(&package.name),
             installed_at: chrono::Utc::now(),
+            source_hash: source_hash.map(str::to_string),
+            files,
+            store_entry,
+            size_on_disk,
         };
         
         registry.packages.insert(package.name.clone(), installed_package);
@@ -98,6 +203,15 @@ impl LocalPackageManager {
         Ok(())
     }
 
+    /// Footprint of a package: its whole directory when it has one of its
+    /// own or nothing was recorded, otherwise the sizes in its RECORD
+    async fn size_on_disk(&self, name: &str, files: &[RecordEntry]) -> Option<u64> {
+        if files.is_empty() || self.layout.kind() == LayoutKind::PerPackage {
+            return dir_size(&self.layout.package_dir(name)).await.ok();
+        }
+        Some(files.iter().filter_map(|file| file.size).sum())
+    }
+
     /// Get list of installed packages
     pub async fn list_installed(&self) -> Result<Vec<InstalledPackage>> {
         let registry = self.load_registry().await?;
@@ -106,6 +220,7 @@ impl LocalPackageManager {
 
     /// Remove a package from registry
     pub async fn unregister_package(&self, name: &str) -> Result<bool> {
+        let _guard = self.registry_lock.lock().await;
         let mut registry = self.load_registry().await?;
         let removed = registry.packages.remove(name).is_some();
         if removed {
@@ -114,20 +229,244 @@ impl LocalPackageManager {
         Ok(removed)
     }
 
-    /// Simple version satisfaction check (can be enhanced later)
-    fn version_satisfies(&self, installed: &str, required: &str) -> bool {
-        if required == "*" {
-            return true;
+    /// Recorded files of `package` that are missing or no longer match their hash
+    pub async fn verify_package(&self, package: &InstalledPackage) -> Result<Vec<FileProblem>> {
+        verify_files(&self.layout.site_packages(&package.name), &package.files).await
+    }
+
+    /// Remove an installed package's files and registry entry, returning
+    /// `false` when `name` is not installed
+    pub async fn uninstall(&self, name: &str) -> Result<bool> {
+        let installed_packages = self.list_installed().await?;
+        let Some(pkg) = installed_packages.iter().find(|p| normalize_name(&p.name) == normalize_name(name)) else {
+            return Ok(false);
+        };
+
+        // Exactly the files the install wrote; packages registered before
+        // files were recorded fall back to their package and dist-info directories
+        if !self.remove_recorded_files(pkg).await? {
+            if pkg.install_path.exists() {
+                fs::remove_dir_all(&pkg.install_path).await?;
+            }
+
+            // Also under the un-normalized dist-info name older installs used
+            let dist_info_paths = [
+                self.layout.dist_info_dir(&pkg.name, &pkg.version),
+                self.layout.site_packages(&pkg.name).join(format!("{}-{}.dist-info", pkg.name, pkg.version)),
+            ];
+            for dist_info_path in &dist_info_paths {
+                if dist_info_path.exists() {
+                    fs::remove_dir_all(dist_info_path).await?;
+                }
+            }
         }
-        
-        // Handle exact version matches
-        if required.starts_with("==") {
-            return installed == &required[2..];
+
+        // Per-package layouts own a whole directory
+        let package_root = self.layout.site_packages(&pkg.name);
+        if self.layout.kind() == LayoutKind::PerPackage && package_root.exists() {
+            fs::remove_dir_all(&package_root).await?;
         }
-        
-        // For now, just check exact match for other cases
-        // TODO: Implement proper semantic versioning
-        installed == required
+
+        self.unregister_package(&pkg.name).await?;
+        Ok(true)
+    }
+
+    /// Like [`Self::uninstall`], but the package's files are moved under
+    /// `backup` rather than deleted, so [`Self::restore`] can put the
+    /// returned entry back. `backup` must be on the install's filesystem.
+    pub async fn set_aside(&self, name: &str, backup: &Path) -> Result<Option<InstalledPackage>> {
+        let installed_packages = self.list_installed().await?;
+        let Some(pkg) = installed_packages.into_iter().find(|p| normalize_name(&p.name) == normalize_name(name)) else {
+            return Ok(None);
+        };
+
+        // Whole directories first, so the files inside them go along
+        let root = self.layout.site_packages(&pkg.name);
+        let mut paths = Vec::new();
+        if self.layout.kind() == LayoutKind::PerPackage {
+            paths.push(root.clone());
+        }
+        if pkg.files.is_empty() {
+            paths.push(pkg.install_path.clone());
+            paths.push(self.layout.dist_info_dir(&pkg.name, &pkg.version));
+            paths.push(root.join(format!("{}-{}.dist-info", pkg.name, pkg.version)));
+        }
+        paths.extend(pkg.files.iter().map(|entry| resolve_dots(&root.join(&entry.path))));
+
+        let base = self.layout.base_dir();
+        for path in paths {
+            let Ok(relative) = path.strip_prefix(base) else { continue };
+            if fs::symlink_metadata(&path).await.is_err() {
+                continue;
+            }
+            let kept = backup.join(relative);
+            if let Some(parent) = kept.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            fs::rename(&path, &kept).await?;
+        }
+
+        // Bytecode and the directories the move left empty
+        self.remove_recorded_files(&pkg).await?;
+        self.unregister_package(&pkg.name).await?;
+        Ok(Some(pkg))
+    }
+
+    /// Move the files [`Self::set_aside`] kept under `backup` back into
+    /// place and register `package` again
+    pub async fn restore(&self, package: InstalledPackage, backup: &Path) -> Result<()> {
+        if backup.exists() {
+            move_into_place(backup, self.layout.base_dir())?;
+        }
+
+        let _guard = self.registry_lock.lock().await;
+        let mut registry = self.load_registry().await?;
+        registry.packages.insert(package.name.clone(), package);
+        self.save_registry(&registry).await
+    }
+
+    /// Cross-reference the registry with the install directory. A venv's
+    /// site-packages also holds packages other tools installed, so only its
+    /// registry entries are checked. Elsewhere only entries that look like
+    /// an install are orphans, and only in a directory holding a registry.
+    pub async fn plan_clean(&self) -> Result<CleanPlan> {
+        let mut installed = self.list_installed().await?;
+        installed.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut plan = CleanPlan::default();
+        let mut owned = std::collections::HashSet::new();
+        for package in &installed {
+            let names = self.owned_entries(package);
+            let root = self.layout.site_packages(&package.name);
+            let present = match self.layout.kind() {
+                LayoutKind::PerPackage => root.exists(),
+                _ if package.files.is_empty() => names.iter().any(|name| root.join(name).exists()),
+                _ => package.files.iter().any(|file| root.join(&file.path).symlink_metadata().is_ok()),
+            };
+            if present {
+                owned.extend(names);
+            } else {
+                plan.missing.push(package.name.clone());
+            }
+        }
+
+        // A configured install-dir may point at a directory other tools share
+        if self.layout.kind() == LayoutKind::Venv || !self.registry_path.exists() {
+            return Ok(plan);
+        }
+        let Ok(mut entries) = fs::read_dir(self.layout.base_dir()).await else {
+            return Ok(plan);
+        };
+        let keep = [self.registry_path.as_path(), self.layout.scripts_dir(), self.layout.data_dir(), self.layout.base_dir()];
+        let mut candidates = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let path = entry.path();
+            // Dotfiles such as a .gitignore are the user's, not an install's
+            if owned.contains(&name) || name == "__pycache__" || name.starts_with('.') || keep.contains(&path.as_path()) {
+                continue;
+            }
+            candidates.push((name, entry.file_type().await?.is_dir(), path));
+        }
+
+        // Left by an install: dist-info directories, the package directories
+        // they name and top-level modules. Anything else is someone else's.
+        let dist_infos: Vec<String> = candidates.iter()
+            .filter_map(|(name, is_dir, _)| name.strip_suffix(".dist-info").filter(|_| *is_dir))
+            .filter_map(|stem| stem.split_once('-').map(|(project, _)| wheel_name(project).to_lowercase()))
+            .collect();
+        for (name, is_dir, path) in candidates {
+            let looks_installed = if is_dir {
+                name.ends_with(".dist-info") || dist_infos.contains(&wheel_name(&name).to_lowercase())
+            } else {
+                name.ends_with(".py")
+            };
+            if looks_installed {
+                plan.orphans.push(path);
+            }
+        }
+        plan.orphans.sort();
+        Ok(plan)
+    }
+
+    /// Top-level names a package occupies in the install directory
+    fn owned_entries(&self, package: &InstalledPackage) -> Vec<String> {
+        if self.layout.kind() == LayoutKind::PerPackage {
+            return vec![package.name.clone()];
+        }
+        let mut names: Vec<String> = package.files.iter()
+            .filter_map(|file| file.path.split('/').next())
+            .map(str::to_string)
+            .collect();
+        if package.files.is_empty() {
+            // Registered before files were recorded; the directories uninstall falls back to
+            names.push(package.name.clone());
+            names.push(format!("{}-{}.dist-info", wheel_name(&package.name), package.version));
+            names.push(format!("{}-{}.dist-info", package.name, package.version));
+        }
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Delete the orphans and unregister the missing packages of `plan`
+    pub async fn clean(&self, plan: &CleanPlan) -> Result<()> {
+        for orphan in &plan.orphans {
+            match fs::symlink_metadata(orphan).await {
+                Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(orphan).await?,
+                Ok(_) => fs::remove_file(orphan).await?,
+                Err(_) => {}
+            }
+        }
+        if !plan.missing.is_empty() {
+            let _guard = self.registry_lock.lock().await;
+            let mut registry = self.load_registry().await?;
+            for name in &plan.missing {
+                registry.packages.remove(name);
+            }
+            self.save_registry(&registry).await?;
+        }
+        Ok(())
+    }
+
+    /// Delete the files recorded for `package`, their bytecode, and the
+    /// directories this leaves empty. Returns `false` without touching
+    /// anything for packages registered before files were recorded.
+    pub async fn remove_recorded_files(&self, package: &InstalledPackage) -> Result<bool> {
+        if package.files.is_empty() {
+            return Ok(false);
+        }
+
+        let root = self.layout.site_packages(&package.name);
+        let base = self.layout.base_dir();
+        let mut dirs = std::collections::BTreeSet::new();
+        for entry in &package.files {
+            let path = resolve_dots(&root.join(&entry.path));
+            remove_if_present(&path).await?;
+            let Some(parent) = path.parent() else { continue };
+
+            // Bytecode Python compiled next to the module, e.g. __pycache__/six.cpython-311.pyc
+            if path.extension().is_some_and(|ext| ext == "py")
+                && let Some(stem) = path.file_stem().map(|s| format!("{}.", s.to_string_lossy()))
+                && let Ok(mut cached) = fs::read_dir(parent.join("__pycache__")).await {
+                while let Some(pyc) = cached.next_entry().await? {
+                    if pyc.file_name().to_string_lossy().starts_with(&stem) {
+                        remove_if_present(&pyc.path()).await?;
+                    }
+                }
+                dirs.insert(parent.join("__pycache__"));
+            }
+            // Files outside site-packages, like scripts under `../bin` or headers
+            // under `include/<pkg>`, empty directories up to the install's base
+            let top = if path.starts_with(&root) { root.as_path() } else { base };
+            dirs.extend(parent.ancestors().take_while(|dir| dir.starts_with(top) && *dir != top).map(Path::to_path_buf));
+        }
+
+        // Deepest first, so emptied children let their parents go too
+        for dir in dirs.iter().rev() {
+            let _ = fs::remove_dir(dir).await;
+        }
+        Ok(true)
     }
 
     /// Get the installation directory
@@ -135,57 +474,171 @@ impl LocalPackageManager {
         &self.install_dir
     }
 
-    /// Create a .pth file to make packages discoverable by Python
-    pub async fn create_pth_file(&self) -> Result<()> {
-        // Get user site-packages directory
-        let output = std::process::Command::new("python3")
-            .arg("-c")
-            .arg("import site; print(site.getusersitepackages())")
-            .output()
-            .map_err(|e| CobraError::PythonEnv(format!("Failed to get user site-packages: {}", e)))?;
+    /// Get the on-disk layout packages are installed with
+    pub fn layout(&self) -> &InstallLayout {
+        &self.layout
+    }
 
-        if !output.status.success() {
-            return Err(CobraError::PythonEnv("Failed to get user site-packages".to_string()));
+    /// `PYTHONPATH` that makes every registered package importable,
+    /// whatever the layout, ahead of the caller's own entries
+    pub async fn import_path(&self) -> Result<std::ffi::OsString> {
+        let mut paths: Vec<PathBuf> = self.list_installed().await?
+            .iter()
+            .map(|p| self.layout.site_packages(&p.name))
+            .collect();
+        paths.sort();
+        paths.dedup();
+        if let Some(existing) = std::env::var_os("PYTHONPATH") {
+            paths.extend(std::env::split_paths(&existing));
         }
+        std::env::join_paths(paths)
+            .map_err(|e| CobraError::InstallationFailed(format!("Invalid PYTHONPATH: {}", e)))
+    }
 
-        let user_site_packages = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        let user_site_path = PathBuf::from(&user_site_packages);
-
-        // Ensure user site-packages exists
-        fs::create_dir_all(&user_site_path).await?;
+    /// Add this project's install directories to the shared .pth file so
+    /// Python can discover the packages, keeping other projects' entries
+    pub async fn create_pth_file(&self) -> Result<()> {
+        if matches!(self.layout.kind(), LayoutKind::Venv | LayoutKind::Target) {
+            return Ok(());
+        }
+        let installed: Vec<String> = self.list_installed().await?
+            .into_iter()
+            .map(|p| p.name)
+            .collect();
+        // A project with nothing installed gives up its lines entirely
+        let entries = if installed.is_empty() {
+            Vec::new()
+        } else {
+            self.layout.pth_entries(&installed)
+        };
 
-        // Create .pth file pointing to our installation directory
-        let pth_file = user_site_path.join("cobra-packages.pth");
-        let install_dir_str = self.install_dir.to_string_lossy().to_string();
-        
-        fs::write(&pth_file, format!("{}\n", install_dir_str)).await?;
-        
-        println!("📝 Created Python path file: {}", pth_file.display());
-        println!("🔗 Packages are now available to Python globally!");
-        
+        let pth_file = user_pth_file().await?;
+        if self.update_pth_file(&pth_file, &entries).await? && !self.quiet {
+            println!("📝 Updated Python path file: {}", pth_file.display());
+            println!("🔗 Packages are now available to Python globally!");
+        }
         Ok(())
     }
 
-    /// Remove the .pth file
+    /// Remove this project's entries from the shared .pth file
     pub async fn remove_pth_file(&self) -> Result<()> {
-        let output = std::process::Command::new("python3")
-            .arg("-c")
-            .arg("import site; print(site.getusersitepackages())")
-            .output()
-            .map_err(|e| CobraError::PythonEnv(format!("Failed to get user site-packages: {}", e)))?;
+        // Silently skip if we can't get site-packages
+        let Ok(pth_file) = user_pth_file().await else {
+            return Ok(());
+        };
+        if pth_file.exists() && !self.update_pth_file(&pth_file, &[]).await? && !self.quiet {
+            println!("🗑️  Removed Python path file: {}", pth_file.display());
+        }
+        Ok(())
+    }
+
+    /// Replace this project's lines in `pth_file` with `entries`, deleting the
+    /// file once no project has entries left. Returns whether the file remains.
+    pub async fn update_pth_file(&self, pth_file: &Path, entries: &[PathBuf]) -> Result<bool> {
+        let existing = match fs::read_to_string(pth_file).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
 
-        if !output.status.success() {
-            return Ok(()); // Silently fail if we can't get site-packages
+        let contents = merge_pth_entries(&existing, self.layout.base_dir(), entries);
+        if contents.is_empty() {
+            if pth_file.exists() {
+                fs::remove_file(pth_file).await?;
+            }
+            return Ok(false);
+        }
+        if contents != existing {
+            atomic_write(pth_file, contents.as_bytes()).await?;
         }
+        Ok(true)
+    }
 
-        let user_site_packages = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        let pth_file = PathBuf::from(&user_site_packages).join("cobra-packages.pth");
+    /// Check that `pth_file` lists every directory Python needs to import the
+    /// installed packages
+    pub async fn pth_status(&self, pth_file: &Path) -> Result<PthStatus> {
+        let installed: Vec<String> = self.list_installed().await?
+            .into_iter()
+            .map(|p| p.name)
+            .collect();
+        let entries = self.layout.pth_entries(&installed);
+        if installed.is_empty() || entries.is_empty() {
+            return Ok(PthStatus::NotNeeded);
+        }
 
-        if pth_file.exists() {
-            fs::remove_file(&pth_file).await?;
-            println!("🗑️  Removed Python path file: {}", pth_file.display());
+        let existing = match fs::read_to_string(pth_file).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let listed: Vec<&Path> = existing.lines().map(|line| Path::new(line.trim_end())).collect();
+        let missing: Vec<PathBuf> = entries.into_iter().filter(|entry| !listed.contains(&entry.as_path())).collect();
+        Ok(if missing.is_empty() { PthStatus::Complete } else { PthStatus::Missing(missing) })
+    }
+}
+
+/// `path` with `..` components folded into their parents, without touching the disk
+fn resolve_dots(path: &Path) -> PathBuf {
+    let mut resolved = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::ParentDir => { resolved.pop(); }
+            std::path::Component::CurDir => {}
+            other => resolved.push(other),
         }
+    }
+    resolved
+}
 
-        Ok(())
+async fn remove_if_present(path: &Path) -> Result<()> {
+    match fs::remove_file(path).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Location of Cobra's .pth file in the user site-packages directory
+pub async fn user_pth_file() -> Result<PathBuf> {
+    let output = tokio::process::Command::new("python3")
+        .arg("-c")
+        .arg("import site; print(site.getusersitepackages())")
+        .output()
+        .await
+        .map_err(|e| CobraError::PythonEnv(format!("Failed to get user site-packages: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(CobraError::PythonEnv("Failed to get user site-packages".to_string()));
+    }
+
+    let user_site_packages = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok(PathBuf::from(user_site_packages).join("cobra-packages.pth"))
+}
+
+/// Merge `.pth` contents: lines under `owned_root` are replaced by `entries`
+/// at the position of the first one, every other line is kept in place, and
+/// duplicates are dropped
+pub fn merge_pth_entries(existing: &str, owned_root: &Path, entries: &[PathBuf]) -> String {
+    let added: Vec<String> = entries.iter().map(|entry| entry.to_string_lossy().into_owned()).collect();
+    let mut merged: Vec<String> = Vec::new();
+    let mut placed = false;
+
+    for line in existing.lines().map(str::trim_end).filter(|line| !line.is_empty()) {
+        if !Path::new(line).starts_with(owned_root) {
+            merged.push(line.to_string());
+        } else if !placed {
+            merged.extend(added.iter().cloned());
+            placed = true;
+        }
+    }
+    if !placed {
+        merged.extend(added);
+    }
+
+    let mut lines: Vec<String> = Vec::new();
+    for line in merged {
+        if !lines.contains(&line) {
+            lines.push(line);
+        }
     }
+    lines.iter().map(|line| format!("{}\n", line)).collect()
 }
@@ -1,6 +1,6 @@
 use crate::{Result, CobraError};
 use std::path::PathBuf;
-use std::process::Command;
+use tokio::process::Command;
 
 #[derive(Debug, Clone)]
 pub struct PythonEnvironment {
@@ -17,6 +17,7 @@ impl PythonEnvironment {
         let output = Command::new(python_cmd)
             .arg("--version")
             .output()
+            .await
             .map_err(|e| CobraError::PythonEnv(format!("Failed to execute python: {}", e)))?;
         
         if !output.status.success() {
@@ -32,6 +33,7 @@ impl PythonEnvironment {
             .arg("-c")
             .arg("import site; print(site.getsitepackages()[0])")
             .output()
+            .await
             .map_err(|e| CobraError::PythonEnv(format!("Failed to get site-packages: {}", e)))?;
         
         let site_packages = PathBuf::from(
@@ -43,6 +45,7 @@ impl PythonEnvironment {
             .arg("-c")
             .arg("import sys; print(sys.executable)")
             .output()
+            .await
             .map_err(|e| CobraError::PythonEnv(format!("Failed to get python path: {}", e)))?;
         
         let python_path = PathBuf::from(
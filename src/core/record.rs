@@ -0,0 +1,141 @@
+use crate::Result;
+use crate::utils::fs::file_url;
+use crate::utils::hash::compute_sha256;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+/// One file listed in a wheel's `.dist-info/RECORD`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordEntry {
+    /// Path relative to the directory the wheel was extracted into
+    pub path: String,
+    /// Hex sha256; `None` for entries RECORD lists without a hash (RECORD itself)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+}
+
+/// Whether `name` is the top-level `RECORD` of a wheel
+pub fn is_record_file(name: &str) -> bool {
+    name.strip_suffix("/RECORD")
+        .is_some_and(|dir| dir.ends_with(".dist-info") && !dir.contains('/'))
+}
+
+/// Parse RECORD's `path,sha256=<urlsafe-b64>,size` lines. Paths leaving the
+/// extraction directory are dropped, as are hashes of other algorithms.
+pub fn parse_record(contents: &str) -> Vec<RecordEntry> {
+    contents.lines()
+        .filter_map(|line| {
+            // Only the path can contain commas, so split from the right
+            let mut fields = line.rsplitn(3, ',');
+            let size = fields.next()?;
+            let hash = fields.next()?;
+            let path = fields.next()?;
+            let path = path.strip_prefix('"')
+                .and_then(|p| p.strip_suffix('"'))
+                .map(|p| p.replace("\"\"", "\""))
+                .unwrap_or_else(|| path.to_string());
+            if path.is_empty() || !Path::new(&path).components().all(|c| matches!(c, Component::Normal(_))) {
+                return None;
+            }
+
+            let sha256 = hash.strip_prefix("sha256=")
+                .and_then(|digest| URL_SAFE_NO_PAD.decode(digest).ok())
+                .map(|digest| digest.iter().map(|b| format!("{:02x}", b)).collect());
+            Some(RecordEntry { path, sha256, size: size.trim().parse().ok() })
+        })
+        .collect()
+}
+
+/// Format entries as RECORD lines, quoting paths that contain commas or quotes
+pub fn format_record(entries: &[RecordEntry]) -> String {
+    entries.iter()
+        .map(|entry| {
+            let path = if entry.path.contains([',', '"']) {
+                format!("\"{}\"", entry.path.replace('"', "\"\""))
+            } else {
+                entry.path.clone()
+            };
+            let hash = entry.sha256.as_deref()
+                .and_then(decode_hex)
+                .map(|digest| format!("sha256={}", URL_SAFE_NO_PAD.encode(digest)))
+                .unwrap_or_default();
+            let size = entry.size.map(|size| size.to_string()).unwrap_or_default();
+            format!("{},{},{}\n", path, hash, size)
+        })
+        .collect()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    (0..hex.len()).step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Where a package came from when it was not installed from an index,
+/// written to `.dist-info/direct_url.json` (PEP 610)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DirectUrl {
+    /// A local project directory
+    Dir(PathBuf),
+    Git {
+        url: String,
+        requested_revision: Option<String>,
+        commit_id: String,
+    },
+}
+
+impl DirectUrl {
+    pub fn to_json(&self) -> String {
+        let value = match self {
+            DirectUrl::Dir(dir) => serde_json::json!({
+                "url": file_url(dir),
+                "dir_info": {},
+            }),
+            DirectUrl::Git { url, requested_revision, commit_id } => {
+                let mut vcs_info = serde_json::json!({ "vcs": "git", "commit_id": commit_id });
+                if let Some(revision) = requested_revision {
+                    vcs_info["requested_revision"] = revision.as_str().into();
+                }
+                serde_json::json!({ "url": url, "vcs_info": vcs_info })
+            }
+        };
+        value.to_string()
+    }
+}
+
+/// What is wrong with an installed file
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FileProblem {
+    Missing(String),
+    Modified(String),
+}
+
+impl std::fmt::Display for FileProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileProblem::Missing(path) => write!(f, "{} is missing", path),
+            FileProblem::Modified(path) => write!(f, "{} was modified", path),
+        }
+    }
+}
+
+/// Re-hash every recorded file under `root`, listing those that are gone or changed
+pub async fn verify_files(root: &Path, files: &[RecordEntry]) -> Result<Vec<FileProblem>> {
+    let mut problems = Vec::new();
+    for entry in files {
+        let path = root.join(&entry.path);
+        if !path.is_file() {
+            problems.push(FileProblem::Missing(entry.path.clone()));
+            continue;
+        }
+        if let Some(expected) = &entry.sha256
+            && compute_sha256(&path).await? != *expected {
+            problems.push(FileProblem::Modified(entry.path.clone()));
+        }
+    }
+    Ok(problems)
+}
@@ -0,0 +1,148 @@
+use crate::{Result, CobraError, Dependency};
+use crate::core::version::VersionSpec;
+use crate::registry::client::parse_dependency;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// Parse a pip requirements file, following `-r`/`--requirement` includes
+/// relative to the including file. An included file's requirements take the
+/// place of its `-r` line, as with pip; a file already read is not read again.
+/// Options such as `-i` or `-e` are skipped.
+pub async fn parse_requirements_file(path: &Path) -> Result<Vec<Dependency>> {
+    let mut dependencies = Vec::new();
+    let mut visited = HashSet::new();
+    // Files being read, the innermost include last, with their remaining lines
+    let mut open: Vec<(PathBuf, std::vec::IntoIter<String>)> = Vec::new();
+    open.extend(read_requirement_lines(path, &mut visited).await?);
+
+    while let Some((base, lines)) = open.last_mut() {
+        let Some(line) = lines.next() else {
+            open.pop();
+            continue;
+        };
+        match parse_line(&line) {
+            RequirementLine::Include(target) => {
+                let included = base.join(target);
+                open.extend(read_requirement_lines(&included, &mut visited).await?);
+            }
+            RequirementLine::Dependency(dep) => dependencies.push(dep),
+            RequirementLine::Skip => {}
+        }
+    }
+
+    Ok(dependencies)
+}
+
+/// The directory includes in `file` are relative to and its logical lines,
+/// or `None` if `file` was already read
+async fn read_requirement_lines(
+    file: &Path,
+    visited: &mut HashSet<PathBuf>,
+) -> Result<Option<(PathBuf, std::vec::IntoIter<String>)>> {
+    let canonical = fs::canonicalize(file).await.map_err(|e| {
+        CobraError::Config(format!("Failed to read {}: {}", file.display(), e))
+    })?;
+    if !visited.insert(canonical) {
+        return Ok(None);
+    }
+    let contents = fs::read_to_string(file).await?;
+    let base = file.parent().map(Path::to_path_buf).unwrap_or_default();
+    Ok(Some((base, logical_lines(&contents).into_iter())))
+}
+
+/// Parse requirements text without include support
+pub fn parse_requirements(contents: &str) -> Vec<Dependency> {
+    logical_lines(contents)
+        .iter()
+        .filter_map(|line| match parse_line(line) {
+            RequirementLine::Dependency(dep) => Some(dep),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Requirements given as command-line arguments, parsed like `cobra add`'s
+/// with [`parse_package_arg`]
+pub fn parse_requirement_args(specs: &[String]) -> Result<Vec<Dependency>> {
+    specs.iter().map(|spec| parse_package_arg(spec)).collect()
+}
+
+/// A package named on the command line: a requirement such as
+/// `requests==2.31.0` or `"flask>=2,<3"`, or `name@spec` as in
+/// `requests@^2.31`. A bare name gets the spec `*`; anything that isn't a
+/// requirement is an error.
+pub fn parse_package_arg(spec: &str) -> Result<Dependency> {
+    let dependency = match spec.split_once('@') {
+        Some((name, version)) => parse_dependency(name)
+            .filter(|dep| dep.name == name.trim())
+            .map(|dep| Dependency { version_spec: version.trim().to_string(), ..dep }),
+        None => parse_dependency(spec),
+    };
+    Some(spec)
+        .filter(|spec| !spec.starts_with('-'))
+        .and(dependency)
+        .filter(|dep| VersionSpec::parse(&dep.version_spec).is_ok())
+        .ok_or_else(|| CobraError::InvalidInput(format!(
+            "'{}' is not a requirement such as requests==2.31.0", spec
+        )))
+}
+
+enum RequirementLine {
+    Include(String),
+    Dependency(Dependency),
+    Skip,
+}
+
+/// Join `\` continuations and strip comments
+fn logical_lines(contents: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for raw in contents.lines() {
+        let line = strip_comment(raw);
+        match line.trim_end().strip_suffix('\\') {
+            Some(continued) => current.push_str(continued),
+            None => {
+                current.push_str(line);
+                let trimmed = current.trim();
+                if !trimmed.is_empty() {
+                    lines.push(trimmed.to_string());
+                }
+                current.clear();
+            }
+        }
+    }
+    if !current.trim().is_empty() {
+        lines.push(current.trim().to_string());
+    }
+    lines
+}
+
+/// A `#` starts a comment at line start or after whitespace (so `#egg=` URLs survive)
+fn strip_comment(line: &str) -> &str {
+    let mut previous = None;
+    for (idx, c) in line.char_indices() {
+        if c == '#' && previous.is_none_or(char::is_whitespace) {
+            return &line[..idx];
+        }
+        previous = Some(c);
+    }
+    line
+}
+
+fn parse_line(line: &str) -> RequirementLine {
+    for flag in ["-r", "--requirement"] {
+        if let Some(rest) = line.strip_prefix(flag)
+            && (rest.starts_with([' ', '=']) || (flag == "-r" && !rest.is_empty())) {
+            return RequirementLine::Include(rest.trim_start_matches('=').trim().to_string());
+        }
+    }
+    if line.starts_with('-') {
+        return RequirementLine::Skip;
+    }
+
+    match parse_dependency(line) {
+        Some(dep) => RequirementLine::Dependency(dep),
+        None => RequirementLine::Skip,
+    }
+}
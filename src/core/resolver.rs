@@ -1,125 +1,956 @@
-use crate::{Result, CobraError, Package, Dependency};
-use crate::core::cache::MultiLevelCache;
-use crate::registry::client::RegistryClient;
+use crate::{Result, CobraError, Package, Dependency, normalize_name, constants::{MAX_CONCURRENT_DOWNLOADS, MAX_RESOLUTION_PASSES, METADATA_CACHE_TTL, INDEX_MAX_AGE}};
+use crate::core::cache::{MultiLevelCache, METADATA_PREFIX};
+use crate::core::config::Settings;
+use crate::core::find_links::FindLinks;
+use crate::core::index::LocalIndex;
+use crate::core::markers::{self, and_markers, or_markers};
+use crate::core::version::{self, Operator, Version, VersionSpec};
+use crate::registry::backend::RegistryBackend;
+use crate::registry::client::{self, Release};
+use crate::registry::packagecloud::PrivateRegistries;
+use chrono::{DateTime, Utc};
 use petgraph::Graph;
-use petgraph::algo::toposort;
-use rayon::prelude::*;
+use petgraph::algo::{all_simple_paths, tarjan_scc, toposort};
+use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::visit::EdgeRef;
 use std::sync::Arc;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet, VecDeque};
 use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::{OnceCell, Semaphore};
 
-pub struct DependencyResolver {
-    client: Arc<RegistryClient>,
-    cache: Option<Arc<MultiLevelCache>>,
+/// How the resolver picks among releases that satisfy a requirement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ResolutionStrategy {
+    /// Newest satisfying release
+    #[default]
+    Highest,
+    /// Oldest satisfying release, for testing declared lower bounds
+    Lowest,
 }
 
-impl DependencyResolver {
-    pub fn new(client: Arc<RegistryClient>, cache: Option<Arc<MultiLevelCache>>) -> Self {
-        Self { client, cache }
+impl fmt::Display for ResolutionStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolutionStrategy::Highest => write!(f, "highest"),
+            ResolutionStrategy::Lowest => write!(f, "lowest"),
+        }
     }
+}
 
-    /// Resolve dependencies in parallel with topological sorting
-    pub async fn resolve(&self, dependencies: &[Dependency]) -> Result<Vec<Package>> {
-        if dependencies.is_empty() {
-            return Ok(Vec::new());
-        }
+/// A requirement whose spec was replaced by an `[overrides]` entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct OverriddenRequirement {
+    /// `name@version` of the requiring package, `None` for cobra.toml itself
+    pub parent: Option<String>,
+    pub package: String,
+    pub requested: String,
+    pub forced: String,
+}
 
-        // Fetch metadata for all packages in parallel
-        let futures: Vec<_> = dependencies.iter()
-            .map(|dep| self.fetch_package_metadata(&dep.name, &dep.version_spec))
+/// An `optional = true` dependency left out because it could not be resolved
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkippedDependency {
+    pub package: String,
+    pub reason: String,
+}
+
+/// Resolved packages with the requirement edges between them, kept for
+/// questions like `cobra why` that the flat install order cannot answer
+#[derive(Debug, Clone)]
+pub struct DependencyGraph {
+    graph: Graph<String, Option<String>>,
+    packages: FxHashMap<String, Package>,
+    roots: Vec<NodeIndex>,
+}
+
+/// One package along a dependency path, with the marker of the requirement
+/// that pulled it in (always `None` for the root)
+#[derive(Debug, Clone)]
+pub struct PathStep {
+    pub package: Package,
+    pub markers: Option<String>,
+}
+
+impl DependencyGraph {
+    /// The resolved package called `name`, if any
+    pub fn package(&self, name: &str) -> Option<&Package> {
+        let key = normalize_name(name);
+        self.packages.values().find(|p| normalize_name(&p.name) == key)
+    }
+
+    /// Every path from a root dependency down to `name`, sorted by the names
+    /// along it; empty when `name` was not resolved
+    pub fn paths_to(&self, name: &str) -> Vec<Vec<PathStep>> {
+        let key = normalize_name(name);
+        let Some(target) = self.graph.node_indices().find(|n| {
+            self.packages.get(&self.graph[*n]).is_some_and(|p| normalize_name(&p.name) == key)
+        }) else {
+            return Vec::new();
+        };
+
+        let mut paths: Vec<Vec<NodeIndex>> = Vec::new();
+        for &root in &self.roots {
+            if root == target {
+                paths.push(vec![root]);
+            } else {
+                paths.extend(all_simple_paths::<Vec<_>, _>(&self.graph, root, target, 0, None));
+            }
+        }
+        let mut steps: Vec<Vec<PathStep>> = paths.into_iter()
+            .map(|path| {
+                let mut previous: Option<NodeIndex> = None;
+                path.into_iter()
+                    .map(|node| {
+                        let markers = previous
+                            .and_then(|from| self.graph.find_edge(from, node))
+                            .and_then(|edge| self.graph[edge].clone());
+                        previous = Some(node);
+                        PathStep { package: self.packages[&self.graph[node]].clone(), markers }
+                    })
+                    .collect()
+            })
             .collect();
+        steps.sort_by_cached_key(|path| path.iter().map(|step| normalize_name(&step.package.name)).collect::<Vec<_>>());
+        steps
+    }
+}
 
-        let packages = futures::future::try_join_all(futures).await?;
+/// Metadata lookups of the current resolution, reported if it times out
+#[derive(Debug, Default)]
+struct LookupProgress {
+    resolved: HashSet<String>,
+    pending: Vec<String>,
+}
 
-        // Build dependency graph
-        let mut graph = Graph::<String, ()>::new();
-        let mut node_map: FxHashMap<String, _> = FxHashMap::default();
-        let mut all_packages: FxHashMap<String, Package> = FxHashMap::default();
+pub struct DependencyResolver {
+    client: Arc<dyn RegistryBackend>,
+    cache: Option<Arc<MultiLevelCache>>,
+    index: Option<Arc<LocalIndex>>,
+    find_links: Option<Arc<FindLinks>>,
+    no_index: bool,
+    registries: Arc<PrivateRegistries>,
+    exclude_newer: Option<DateTime<Utc>>,
+    python: Option<Version>,
+    strategy: ResolutionStrategy,
+    pins: HashMap<String, String>,
+    overrides: HashMap<String, String>,
+    overridden: Mutex<Vec<OverriddenRequirement>>,
+    skipped: Mutex<Vec<SkippedDependency>>,
+    all_extras: bool,
+    offline: bool,
+    timeout: Option<Duration>,
+    metadata_ttl: Duration,
+    index_max_age: Duration,
+    progress: Mutex<LookupProgress>,
+    /// One cell per `name@spec` looked up this resolution; concurrent lookups
+    /// of a key wait for the first one instead of sending their own request
+    lookups: Mutex<HashMap<String, Arc<OnceCell<Package>>>>,
+    downloads: Semaphore,
+}
 
-        // Add root packages
-        for pkg in &packages {
-            let node = graph.add_node(format!("{}@{}", pkg.name, pkg.version));
-            node_map.insert(format!("{}@{}", pkg.name, pkg.version), node);
-            all_packages.insert(format!("{}@{}", pkg.name, pkg.version), pkg.clone());
+impl DependencyResolver {
+    pub fn new(client: Arc<dyn RegistryBackend>, cache: Option<Arc<MultiLevelCache>>) -> Self {
+        Self {
+            client,
+            cache,
+            index: None,
+            find_links: None,
+            no_index: false,
+            registries: Arc::default(),
+            exclude_newer: None,
+            python: None,
+            strategy: ResolutionStrategy::default(),
+            pins: HashMap::new(),
+            overrides: HashMap::new(),
+            overridden: Mutex::new(Vec::new()),
+            skipped: Mutex::new(Vec::new()),
+            all_extras: false,
+            offline: false,
+            timeout: None,
+            metadata_ttl: METADATA_CACHE_TTL,
+            index_max_age: INDEX_MAX_AGE,
+            progress: Mutex::new(LookupProgress::default()),
+            lookups: Mutex::new(HashMap::new()),
+            downloads: Semaphore::new(MAX_CONCURRENT_DOWNLOADS),
         }
+    }
 
-        // Recursively resolve dependencies
-        let mut to_process: Vec<Package> = packages.clone();
-        let mut processed: HashSet<String> = HashSet::new();
+    /// Replace every requirement on these packages (keyed by normalized name)
+    pub fn with_overrides(mut self, overrides: HashMap<String, String>) -> Self {
+        self.overrides = overrides;
+        self
+    }
 
-        while let Some(pkg) = to_process.pop() {
-            let pkg_key = format!("{}@{}", pkg.name, pkg.version);
-            
-            if processed.contains(&pkg_key) {
-                continue;
-            }
-            processed.insert(pkg_key.clone());
-
-            // Fetch dependencies in parallel
-            if !pkg.dependencies.is_empty() {
-                let dep_futures: Vec<_> = pkg.dependencies.iter()
-                    .map(|dep| self.fetch_package_metadata(&dep.name, &dep.version_spec))
-                    .collect();
-
-                let dep_packages = futures::future::try_join_all(dep_futures).await?;
-
-                for dep_pkg in dep_packages {
-                    let dep_key = format!("{}@{}", dep_pkg.name, dep_pkg.version);
-                    
-                    // Add node if not exists
-                    if !node_map.contains_key(&dep_key) {
-                        let node = graph.add_node(dep_key.clone());
-                        node_map.insert(dep_key.clone(), node);
-                        all_packages.insert(dep_key.clone(), dep_pkg.clone());
-                        to_process.push(dep_pkg);
-                    }
-
-                    // Add edge from package to dependency
-                    if let (Some(&from), Some(&to)) = (node_map.get(&pkg_key), node_map.get(&dep_key)) {
-                        graph.add_edge(from, to, ());
-                    }
+    /// Requirements that were replaced by overrides during the last resolution
+    pub fn overridden_requirements(&self) -> Vec<OverriddenRequirement> {
+        self.overridden.lock().unwrap().clone()
+    }
+
+    /// Optional dependencies the last resolution left out
+    pub fn skipped_dependencies(&self) -> Vec<SkippedDependency> {
+        self.skipped.lock().unwrap().clone()
+    }
+
+    /// Apply `[overrides]` to a requirement, remembering what was replaced
+    fn effective_spec(&self, parent: Option<&str>, dep: &Dependency) -> String {
+        match self.overrides.get(&normalize_name(&dep.name)) {
+            Some(forced) => {
+                if forced != &dep.version_spec {
+                    self.overridden.lock().unwrap().push(OverriddenRequirement {
+                        parent: parent.map(|p| p.to_string()),
+                        package: dep.name.clone(),
+                        requested: dep.version_spec.clone(),
+                        forced: forced.clone(),
+                    });
                 }
+                forced.clone()
             }
+            None => dep.version_spec.clone(),
         }
+    }
+
+    /// Follow requirements of every extra instead of skipping `extra == "..."` ones
+    pub fn with_all_extras(mut self, all_extras: bool) -> Self {
+        self.all_extras = all_extras;
+        self
+    }
+
+    /// Consult the local metadata index before the cache and the registry,
+    /// for packages no `[tool.cobra.registries]` entry serves instead
+    pub fn with_local_index(mut self, index: Option<Arc<LocalIndex>>) -> Self {
+        self.index = index;
+        self
+    }
+
+    /// Pick matching wheels from these local files before consulting any index
+    pub fn with_find_links(mut self, find_links: Option<Arc<FindLinks>>) -> Self {
+        self.find_links = find_links;
+        self
+    }
+
+    /// Only resolve from `--find-links` wheels, never the local index, cache or registry
+    pub fn with_no_index(mut self, no_index: bool) -> Self {
+        self.no_index = no_index;
+        self
+    }
+
+    /// Consult these PackageCloud repositories before PyPI
+    pub fn with_registries(mut self, registries: Arc<PrivateRegistries>) -> Self {
+        self.registries = registries;
+        self
+    }
+
+    /// Answer every metadata lookup from the cache, never contacting the registry
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Give up on the whole resolution after `timeout`, cancelling in-flight lookups
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Refetch cached metadata older than `ttl`; offline lookups use it at any age
+    pub fn with_metadata_ttl(mut self, ttl: Duration) -> Self {
+        self.metadata_ttl = ttl;
+        self
+    }
+
+    /// Ask the registry about indexed packages synced longer than `max_age`
+    /// ago; offline lookups use index entries at any age
+    pub fn with_index_max_age(mut self, max_age: Duration) -> Self {
+        self.index_max_age = max_age;
+        self
+    }
+
+    /// Size the metadata request limit from `settings`
+    pub fn with_settings(self, settings: &Settings) -> Self {
+        self.with_parallel_downloads(settings.parallel_downloads)
+    }
+
+    /// Send at most `limit` metadata requests to the registry at once
+    pub fn with_parallel_downloads(mut self, limit: usize) -> Self {
+        self.downloads = Semaphore::new(limit);
+        self
+    }
+
+    /// Choose between newest and oldest satisfying releases
+    pub fn with_strategy(mut self, strategy: ResolutionStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Force the given `name -> version` pins regardless of requested specs
+    pub fn with_pins(mut self, pins: HashMap<String, String>) -> Self {
+        self.pins = pins.into_iter().map(|(name, version)| (normalize_name(&name), version)).collect();
+        self
+    }
+
+    /// Ignore any release file uploaded after `cutoff` during version selection
+    pub fn with_exclude_newer(mut self, cutoff: Option<DateTime<Utc>>) -> Self {
+        self.exclude_newer = cutoff;
+        self
+    }
+
+    /// Skip releases whose `requires_python` excludes this interpreter version
+    pub fn with_python_version(mut self, python: Option<Version>) -> Self {
+        self.python = python;
+        self
+    }
+
+    /// Resolve dependencies in parallel with topological sorting
+    pub async fn resolve(&self, dependencies: &[Dependency]) -> Result<Vec<Package>> {
+        if dependencies.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let (graph, all_packages) = self.build_graph_within_deadline(dependencies).await?;
 
         // Topological sort for install order
         let sorted = toposort(&graph, None)
             .map_err(|_| CobraError::ResolutionFailed("Circular dependency detected".to_string()))?;
 
+        // Markers flow from requirers to dependencies: a package is needed
+        // wherever any path to it from the roots applies
+        let mut markers: HashMap<NodeIndex, Option<String>> = HashMap::new();
+        for dep in dependencies {
+            if let Some(node) = graph.node_indices().find(|n| {
+                all_packages.get(&graph[*n]).is_some_and(|p| normalize_name(&p.name) == normalize_name(&dep.name))
+            }) {
+                let merged = match markers.get(&node) {
+                    Some(existing) => or_markers(existing.as_deref(), dep.markers.as_deref()),
+                    None => dep.markers.clone(),
+                };
+                markers.insert(node, merged);
+            }
+        }
+        for node in &sorted {
+            let Some(marker) = markers.get(node).cloned() else {
+                continue;
+            };
+            for edge in graph.edges(*node) {
+                let child = and_markers(marker.as_deref(), edge.weight().as_deref());
+                let merged = match markers.get(&edge.target()) {
+                    Some(existing) => or_markers(existing.as_deref(), child.as_deref()),
+                    None => child,
+                };
+                markers.insert(edge.target(), merged);
+            }
+        }
+
         // Return packages in install order (reverse topological order)
         let mut result = Vec::new();
         for node in sorted.iter().rev() {
             let pkg_key = &graph[*node];
             if let Some(pkg) = all_packages.get(pkg_key) {
-                result.push(pkg.clone());
+                let mut pkg = pkg.clone();
+                pkg.markers = markers.get(node).cloned().flatten();
+                result.push(pkg);
             }
         }
 
         Ok(result)
     }
 
-    async fn fetch_package_metadata(&self, name: &str, version_spec: &str) -> Result<Package> {
-        // Check cache first
-        if let Some(cache) = &self.cache {
-            let cache_key = format!("metadata:{}:{}", name, version_spec);
-            if let Some(data) = cache.get(&cache_key).await {
-                if let Ok(pkg) = serde_json::from_slice::<Package>(&data) {
-                    return Ok(pkg);
+    /// Resolve `dependencies` and keep the graph between the packages instead
+    /// of flattening it into an install order
+    pub async fn resolve_graph(&self, dependencies: &[Dependency]) -> Result<DependencyGraph> {
+        let (graph, packages) = if dependencies.is_empty() {
+            (Graph::new(), FxHashMap::default())
+        } else {
+            self.build_graph_within_deadline(dependencies).await?
+        };
+
+        let mut roots = Vec::new();
+        for dep in dependencies {
+            if let Some(node) = graph.node_indices().find(|n| {
+                packages.get(&graph[*n]).is_some_and(|p| normalize_name(&p.name) == normalize_name(&dep.name))
+            }) && !roots.contains(&node) {
+                roots.push(node);
+            }
+        }
+        Ok(DependencyGraph { graph, packages, roots })
+    }
+
+    /// Every package reachable from `dependencies`, in no particular order.
+    /// Unlike `resolve`, cycles are not an error.
+    pub async fn resolve_unordered(&self, dependencies: &[Dependency]) -> Result<Vec<Package>> {
+        if dependencies.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let (_, all_packages) = self.build_graph_within_deadline(dependencies).await?;
+        Ok(all_packages.into_values().collect())
+    }
+
+    /// Run `build_graph` under the configured deadline. Timing out drops the
+    /// graph future, which cancels every metadata request still in flight.
+    async fn build_graph_within_deadline(
+        &self,
+        dependencies: &[Dependency],
+    ) -> Result<(Graph<String, Option<String>>, FxHashMap<String, Package>)> {
+        let Some(timeout) = self.timeout else {
+            return self.build_graph(dependencies).await;
+        };
+
+        match tokio::time::timeout(timeout, self.build_graph(dependencies)).await {
+            Ok(result) => result,
+            Err(_) => {
+                let progress = self.progress.lock().unwrap();
+                let mut pending = progress.pending.clone();
+                pending.sort();
+                pending.dedup();
+                Err(CobraError::ResolutionTimeout {
+                    secs: timeout.as_secs(),
+                    resolved: progress.resolved.len(),
+                    pending,
+                })
+            }
+        }
+    }
+
+    /// Fetch metadata for the whole dependency closure, one release per
+    /// project, keyed by `name@version`. A pass picks each project against
+    /// every spec on it known by then; requirers found later in the pass can
+    /// add specs, so passes repeat until the picks account for all of them.
+    async fn build_graph(
+        &self,
+        dependencies: &[Dependency],
+    ) -> Result<(Graph<String, Option<String>>, FxHashMap<String, Package>)> {
+        *self.progress.lock().unwrap() = LookupProgress::default();
+        self.lookups.lock().unwrap().clear();
+
+        let mut known = HashMap::new();
+        for _ in 0..MAX_RESOLUTION_PASSES {
+            let (graph, packages, specs) = self.resolve_pass(dependencies, &known).await?;
+            if specs == known {
+                return Ok((graph, packages));
+            }
+            known = specs;
+        }
+        Err(CobraError::ResolutionFailed(format!(
+            "Requirements did not settle on one version per package after {} passes",
+            MAX_RESOLUTION_PASSES
+        )))
+    }
+
+    /// One walk of the dependency closure, picking each project against its
+    /// specs in `known` and those seen so far. Returns the graph, its packages
+    /// and the specs on every project in it, keyed by normalized name.
+    async fn resolve_pass(
+        &self,
+        dependencies: &[Dependency],
+        known: &HashMap<String, BTreeSet<String>>,
+    ) -> Result<(Graph<String, Option<String>>, FxHashMap<String, Package>, HashMap<String, BTreeSet<String>>)> {
+        self.overridden.lock().unwrap().clear();
+        self.skipped.lock().unwrap().clear();
+        let mut specs: HashMap<String, BTreeSet<String>> = HashMap::new();
+        let spec_of = |specs: &HashMap<String, BTreeSet<String>>, name: &str| {
+            combined_spec(known.get(name).into_iter().chain(specs.get(name)).flatten())
+        };
+
+        // A project may be listed more than once, e.g. in both dependency groups
+        let mut roots: Vec<(String, Vec<&Dependency>)> = Vec::new();
+        for dep in dependencies {
+            let key = normalize_name(&dep.name);
+            specs.entry(key.clone()).or_default().insert(self.effective_spec(None, dep));
+            match roots.iter_mut().find(|(name, _)| *name == key) {
+                Some((_, deps)) => deps.push(dep),
+                None => roots.push((key, vec![dep])),
+            }
+        }
+
+        // Fetch metadata for all packages in parallel
+        let futures: Vec<_> = roots.iter()
+            .map(|(key, deps)| {
+                let spec = spec_of(&specs, key);
+                async move { self.fetch_package_metadata(&deps[0].name, &spec).await }
+            })
+            .collect();
+
+        // Build dependency graph; edges carry the requirement's marker
+        let mut graph = Graph::<String, Option<String>>::new();
+        let mut node_map: FxHashMap<String, NodeIndex> = FxHashMap::default();
+        let mut all_packages: FxHashMap<String, Package> = FxHashMap::default();
+
+        // Add root packages
+        let mut frontier: Vec<Package> = Vec::new();
+        for ((key, deps), result) in roots.iter().zip(futures::future::join_all(futures).await) {
+            let pkg = match result {
+                Ok(pkg) if self.all_extras => pkg,
+                Ok(pkg) => {
+                    let extras: Vec<String> = deps.iter().flat_map(|dep| dep.extras.iter().cloned()).collect();
+                    self.without_inapplicable(with_extras(pkg, &extras))
+                }
+                Err(e) if deps.iter().all(|dep| dep.optional) => {
+                    self.skipped.lock().unwrap().push(SkippedDependency {
+                        package: deps[0].name.clone(),
+                        reason: e.to_string(),
+                    });
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            let pkg_key = format!("{}@{}", pkg.name, pkg.version);
+            node_map.insert(key.clone(), graph.add_node(pkg_key.clone()));
+            all_packages.insert(pkg_key, pkg.clone());
+            frontier.push(pkg);
+        }
+
+        // Resolve level by level: the requirements of every package found in
+        // one round are looked up together in the next
+        while !frontier.is_empty() {
+            let mut requirements = Vec::new();
+            for pkg in &frontier {
+                let pkg_key = format!("{}@{}", pkg.name, pkg.version);
+                let parent = node_map[&normalize_name(&pkg.name)];
+                for dep in &pkg.dependencies {
+                    let key = normalize_name(&dep.name);
+                    specs.entry(key.clone()).or_default().insert(self.effective_spec(Some(&pkg_key), dep));
+                    requirements.push((parent, dep, key));
                 }
             }
+
+            // Projects required by several packages are fetched once, against all their specs
+            let mut new: Vec<(&String, &Dependency)> = Vec::new();
+            for (_, dep, key) in &requirements {
+                if !node_map.contains_key(key) && !new.iter().any(|(seen, _)| *seen == key) {
+                    new.push((key, dep));
+                }
+            }
+            let fetches = new.iter().map(|(key, dep)| {
+                let spec = spec_of(&specs, key);
+                async move { self.fetch_package_metadata(&dep.name, &spec).await }
+            });
+            let dep_packages = futures::future::try_join_all(fetches).await?;
+
+            let mut next = Vec::new();
+            for ((key, _), dep_pkg) in new.iter().zip(dep_packages) {
+                let dep_pkg = self.without_inapplicable(dep_pkg);
+                let dep_key = format!("{}@{}", dep_pkg.name, dep_pkg.version);
+                node_map.insert((*key).clone(), graph.add_node(dep_key.clone()));
+                all_packages.insert(dep_key, dep_pkg.clone());
+                next.push(dep_pkg);
+            }
+
+            // Edge from package to dependency, carrying the requirement's marker
+            for (parent, dep, key) in &requirements {
+                graph.add_edge(*parent, node_map[key], dep.markers.clone());
+            }
+            frontier = next;
+        }
+
+        Ok((graph, all_packages, specs))
+    }
+
+    /// Drop requirements whose markers can hold on no platform (unrequested
+    /// extras), unless every extra was asked for
+    fn without_inapplicable(&self, mut pkg: Package) -> Package {
+        if !self.all_extras {
+            pkg.dependencies.retain(|dep| match &dep.markers {
+                Some(marker) => markers::applies_on_some_platform(marker).unwrap_or(true),
+                None => true,
+            });
+        }
+        pkg
+    }
+
+    /// Look up metadata once per `name@spec`, sharing the result with every
+    /// concurrent and later lookup of the same key. A failed lookup is not
+    /// remembered, so the next caller tries again.
+    async fn fetch_package_metadata(&self, name: &str, version_spec: &str) -> Result<Package> {
+        let key = format!("{}@{}", normalize_name(name), version_spec);
+        let cell = Arc::clone(self.lookups.lock().unwrap().entry(key).or_default());
+        cell.get_or_try_init(|| self.track_lookup(name, version_spec)).await.cloned()
+    }
+
+    /// Look up metadata, tracking which lookups are still pending
+    async fn track_lookup(&self, name: &str, version_spec: &str) -> Result<Package> {
+        self.progress.lock().unwrap().pending.push(name.to_string());
+        let result = self.lookup_metadata(name, version_spec).await;
+
+        let mut progress = self.progress.lock().unwrap();
+        if let Some(index) = progress.pending.iter().position(|p| p == name) {
+            progress.pending.swap_remove(index);
+        }
+        if let Ok(pkg) = &result {
+            progress.resolved.insert(normalize_name(&pkg.name));
+        }
+        result
+    }
+
+    async fn lookup_metadata(&self, name: &str, version_spec: &str) -> Result<Package> {
+        let pinned = self.pins.get(&normalize_name(name)).map(|version| format!("=={}", version));
+        let version_spec = pinned.as_deref().unwrap_or(version_spec);
+
+        // Wheels shipped alongside the project win over every index
+        if let Some(pkg) = self.find_links.as_ref()
+            .and_then(|links| links.select(name, version_spec, self.python.as_ref(), self.strategy)) {
+            return Ok(pkg);
+        }
+        if self.no_index {
+            return Err(CobraError::ResolutionFailed(format!(
+                "No wheel of {} matching '{}' in the --find-links locations, and --no-index rules out the index",
+                name, version_spec
+            )));
+        }
+
+        // Packages synced into the local index never need the registry,
+        // unless a private registry is routed to before PyPI. A default one
+        // is asked first and the index answers for what it doesn't have.
+        let claimed = self.registries.claims(name);
+        let private_first = !claimed && !self.offline && self.registries.has_default();
+        if !claimed && !private_first
+            && let Some(pkg) = self.indexed(name, version_spec).await? {
+            return Ok(pkg);
+        }
+
+        let mut cache_key = format!("{}{}:{}", METADATA_PREFIX, name, version_spec);
+        if self.strategy != ResolutionStrategy::Highest {
+            cache_key.push_str(&format!(":{}", self.strategy));
+        }
+        if let Some(cutoff) = &self.exclude_newer {
+            cache_key.push_str(&format!(":{}", cutoff.to_rfc3339()));
+        }
+        if let Some(python) = &self.python {
+            cache_key.push_str(&format!(":py{}", python));
+        }
+
+        // Check cache first; stale metadata could hide newer releases
+        let cached = match &self.cache {
+            Some(cache) if self.offline => cache.get(&cache_key).await,
+            Some(cache) => cache.get_fresh(&cache_key, self.metadata_ttl).await,
+            None => None,
+        };
+        if let Some(data) = cached
+            && let Ok(pkg) = serde_json::from_slice::<Package>(&data) {
+            return Ok(pkg);
+        }
+
+        if self.offline {
+            return Err(CobraError::Offline(format!(
+                "no cached metadata for {} {}; run an online install first", name, version_spec
+            )));
         }
 
         // Fetch from registry
-        let pkg = self.client.get_package_info(name, version_spec).await?;
+        let _permit = self.downloads.acquire().await
+            .map_err(|e| CobraError::ResolutionFailed(e.to_string()))?;
+        let pkg = if let Some(pkg) = self.registries.get_package(name, version_spec).await? {
+            // PackageCloud only reports its newest release
+            if !version::satisfies(&pkg.version, version_spec) {
+                return Err(CobraError::ResolutionFailed(format!(
+                    "{} {} from a private registry does not match '{}'", name, pkg.version, version_spec
+                )));
+            }
+            if let Some(python) = &self.python
+                && !supports_python(pkg.requires_python.as_deref(), python) {
+                return Err(unsupported_python(name, &pkg.version, pkg.requires_python.as_deref(), python));
+            }
+            pkg
+        } else if private_first && let Some(pkg) = self.indexed(name, version_spec).await? {
+            pkg
+        } else if self.exclude_newer.is_none() && self.strategy == ResolutionStrategy::Highest
+            && is_direct_lookup(version_spec) {
+            let pkg = self.client.get_package_info(name, version_spec).await?;
+            // An index may still report a yanked release, or one this Python
+            // can't run, as the latest one
+            let unsupported = self.python.as_ref()
+                .is_some_and(|python| !supports_python(pkg.requires_python.as_deref(), python));
+            if unsupported || (pkg.yanked && !VersionSpec::parse(version_spec).is_ok_and(|spec| spec.is_pinned())) {
+                self.fetch_selected(name, version_spec).await?
+            } else {
+                pkg
+            }
+        } else {
+            self.fetch_selected(name, version_spec).await?
+        };
 
         // Cache the result
-        if let Some(cache) = &self.cache {
-            let cache_key = format!("metadata:{}:{}", name, version_spec);
-            if let Ok(data) = serde_json::to_vec(&pkg) {
-                let _ = cache.put(cache_key, bytes::Bytes::from(data)).await;
+        if let Some(cache) = &self.cache
+            && let Ok(data) = serde_json::to_vec(&pkg) {
+            let _ = cache.put(cache_key, bytes::Bytes::from(data)).await;
+        }
+
+        Ok(pkg)
+    }
+
+    /// A release of `name` picked from the local index. Entries synced more
+    /// than `index_max_age` ago could hide newer releases, so they are left
+    /// to the registry unless offline.
+    async fn indexed(&self, name: &str, version_spec: &str) -> Result<Option<Package>> {
+        let Some(index) = &self.index else {
+            return Ok(None);
+        };
+        let Some(project) = index.project(name).await? else {
+            return Ok(None);
+        };
+        let age = (Utc::now() - project.synced_at).to_std().unwrap_or_default();
+        if age > self.index_max_age {
+            tracing::warn!(
+                "local index entry for {} was synced {} days ago; run 'cobra index sync {}'",
+                name, age.as_secs() / 86_400, name
+            );
+            if !self.offline {
+                return Ok(None);
             }
         }
+        index.select(name, version_spec, self.exclude_newer.as_ref(), self.python.as_ref(), self.strategy).await
+    }
+
+    /// Select a release from the full release list according to the strategy and cutoff
+    async fn fetch_selected(&self, name: &str, version_spec: &str) -> Result<Package> {
+        let spec = VersionSpec::parse(version_spec)?;
+        let releases = self.client.get_releases(name).await?;
+        let cutoff = self.exclude_newer.as_ref();
+        let python = self.python.as_ref();
+
+        // A release that only fails on requires-python gets its own message
+        if let Some(python) = python
+            && select_release(&releases, &spec, cutoff, Some(python), self.strategy).is_none()
+            && let Some((release, files)) = select_release(&releases, &spec, cutoff, None, self.strategy) {
+            let requires_python = client::select_artifact(&files).and_then(|f| f.requires_python.as_deref());
+            return Err(unsupported_python(name, &release.version, requires_python, python));
+        }
+
+        let (release, files) = select_release(&releases, &spec, cutoff, python, self.strategy)
+            .ok_or_else(|| match cutoff {
+                Some(cutoff) => CobraError::ResolutionFailed(format!(
+                    "No release of {} matching '{}' was uploaded before {}",
+                    name, version_spec, cutoff.to_rfc3339()
+                )),
+                None => CobraError::ResolutionFailed(format!(
+                    "No release of {} matches '{}'", name, version_spec
+                )),
+            })?;
+
+        let mut pkg = self.client.get_package_version(name, &release.version).await?;
+
+        // Only install an artifact that existed at the cutoff
+        if let Some(artifact) = client::select_artifact(&files) {
+            pkg.download_url = artifact.url.clone();
+            pkg.hash = artifact.sha256.clone();
+            pkg.size = artifact.size;
+            pkg.yanked = artifact.yanked;
+            pkg.yanked_reason = artifact.yanked_reason.clone();
+            pkg.requires_python = pkg.requires_python.or_else(|| artifact.requires_python.clone());
+        }
 
         Ok(pkg)
     }
 }
+
+/// Every requirement on one project as a single spec: a lone spec as
+/// written, otherwise the distinct clauses joined so a release must meet all
+fn combined_spec<'a>(specs: impl Iterator<Item = &'a String>) -> String {
+    let specs: BTreeSet<&str> = specs.map(|spec| spec.trim()).collect();
+    if specs.len() == 1 {
+        return specs.into_iter().next().unwrap_or_default().to_string();
+    }
+    specs.into_iter()
+        .filter(|spec| !spec.is_empty() && *spec != "*")
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Keep the requirements of `pkg` that the requested `extras` turn on, with
+/// their `extra` clauses dropped, and drop those of other extras
+fn with_extras(mut pkg: Package, extras: &[String]) -> Package {
+    if extras.is_empty() {
+        return pkg;
+    }
+    pkg.dependencies.retain_mut(|dep| {
+        let Some(marker) = &dep.markers else {
+            return true;
+        };
+        match markers::apply_extras(marker, extras) {
+            Ok(Some(remaining)) => {
+                dep.markers = remaining;
+                true
+            }
+            Ok(None) => false,
+            Err(_) => true,
+        }
+    });
+    pkg
+}
+
+/// Whether the registry can answer `version_spec` with one project or
+/// release page: any version, or a single exact `==` pin. Ranges need the
+/// release list. Specs that aren't PEP 440 keep the direct lookup.
+fn is_direct_lookup(version_spec: &str) -> bool {
+    match VersionSpec::parse(version_spec) {
+        Ok(spec) => match spec.specifiers.as_slice() {
+            [] => true,
+            [only] => only.operator == Operator::Equal && !only.wildcard,
+            _ => false,
+        },
+        Err(_) => true,
+    }
+}
+
+/// Pick the newest (or oldest, per `strategy`) release satisfying `spec` from
+/// releases sorted oldest first, considering only files uploaded up to `cutoff`
+/// and supporting `python` when those are given. Returns the release and its
+/// eligible files.
+pub fn select_release<'a>(
+    releases: &'a [Release],
+    spec: &VersionSpec,
+    cutoff: Option<&DateTime<Utc>>,
+    python: Option<&Version>,
+    strategy: ResolutionStrategy,
+) -> Option<(&'a Release, Vec<client::ReleaseFile>)> {
+    let candidate = |release: &'a Release| {
+        let version = release.parsed_version()?;
+        if !spec.contains(&version) || (version.is_prerelease() && !spec.allows_prereleases()) {
+            return None;
+        }
+
+        let mut files = match cutoff {
+            Some(cutoff) => release.files_uploaded_before(cutoff),
+            None => release.files.clone(),
+        };
+        // Yanked files are only picked for an exact `==` pin (PEP 592)
+        if !spec.is_pinned() {
+            files.retain(|f| !f.yanked);
+        }
+        if let Some(python) = python {
+            files.retain(|f| supports_python(f.requires_python.as_deref(), python));
+        }
+        (!files.is_empty()).then_some((release, files))
+    };
+
+    match strategy {
+        ResolutionStrategy::Highest => releases.iter().rev().find_map(candidate),
+        ResolutionStrategy::Lowest => releases.iter().find_map(candidate),
+    }
+}
+
+/// Whether a `requires_python` specifier such as `>=3.8, <4` admits `python`.
+/// Missing, empty and unparseable specifiers admit every version.
+pub fn supports_python(requires_python: Option<&str>, python: &Version) -> bool {
+    requires_python
+        .and_then(|spec| VersionSpec::parse(spec).ok())
+        .is_none_or(|spec| spec.contains(python))
+}
+
+fn unsupported_python(name: &str, version: &str, requires_python: Option<&str>, python: &Version) -> CobraError {
+    CobraError::ResolutionFailed(format!(
+        "No release of {} supports Python {}: {} {} requires Python '{}'",
+        name, python, name, version, requires_python.unwrap_or("")
+    ))
+}
+
+/// A chain of packages that depend on each other in a loop
+#[derive(Debug, Clone, PartialEq)]
+pub struct DependencyCycle {
+    /// Package names along the cycle; the first name is repeated at the end
+    pub chain: Vec<String>,
+    /// The loop only exists through extras or environment-marker requirements
+    pub conditional: bool,
+}
+
+/// Find dependency cycles among resolved packages. Cycles made only of
+/// unconditional requirements are reported first; cycles that need at least
+/// one extra/marker requirement are reported as conditional.
+pub fn find_cycles(packages: &[Package]) -> Vec<DependencyCycle> {
+    let mut graph = DiGraph::<String, bool>::new();
+    let mut nodes = HashMap::new();
+    for pkg in packages {
+        let key = normalize_name(&pkg.name);
+        nodes.entry(key).or_insert_with(|| graph.add_node(pkg.name.clone()));
+    }
+    for pkg in packages {
+        let from = nodes[&normalize_name(&pkg.name)];
+        for dep in &pkg.dependencies {
+            if let Some(&to) = nodes.get(&normalize_name(&dep.name)) {
+                graph.add_edge(from, to, dep.is_conditional());
+            }
+        }
+    }
+
+    let hard = graph.filter_map(|_, name| Some(name.clone()), |_, &conditional| (!conditional).then_some(()));
+    let mut cycles = Vec::new();
+
+    for component in tarjan_scc(&hard) {
+        let Some(start) = component.iter().copied().min_by_key(|n| &hard[*n]) else {
+            continue;
+        };
+        let members: HashSet<NodeIndex> = component.iter().copied().collect();
+        if let Some(path) = shortest_path(&hard, start, start, &members) {
+            cycles.push(DependencyCycle { chain: path_names(&hard, &path), conditional: false });
+        }
+    }
+
+    // Every conditional edge inside a strongly connected component closes a
+    // loop; report the shortest one through it, once per set of packages
+    let mut seen = HashSet::new();
+    for component in tarjan_scc(&graph) {
+        let members: HashSet<NodeIndex> = component.iter().copied().collect();
+        let mut conditional_edges: Vec<_> = graph
+            .edge_references()
+            .filter(|e| *e.weight() && members.contains(&e.source()) && members.contains(&e.target()))
+            .map(|e| (e.source(), e.target()))
+            .collect();
+        conditional_edges.sort_by(|a, b| (&graph[a.0], &graph[a.1]).cmp(&(&graph[b.0], &graph[b.1])));
+
+        for (from, to) in conditional_edges {
+            let Some(back) = shortest_path(&graph, to, from, &members) else {
+                continue;
+            };
+            let mut path = vec![from];
+            path.extend(back);
+
+            let mut key: Vec<NodeIndex> = path[1..].to_vec();
+            key.sort();
+            if seen.insert(key) {
+                cycles.push(DependencyCycle { chain: path_names(&graph, &path), conditional: true });
+            }
+        }
+    }
+
+    cycles
+}
+
+/// Shortest path `from -> ... -> to` staying inside `members`; `from == to`
+/// finds the shortest loop
+fn shortest_path<N, E>(
+    graph: &DiGraph<N, E>,
+    from: NodeIndex,
+    to: NodeIndex,
+    members: &HashSet<NodeIndex>,
+) -> Option<Vec<NodeIndex>> {
+    let mut previous: HashMap<NodeIndex, NodeIndex> = HashMap::new();
+    let mut queue = VecDeque::from([from]);
+    while let Some(node) = queue.pop_front() {
+        for next in graph.neighbors(node) {
+            if next == to {
+                let mut path = vec![to, node];
+                let mut current = node;
+                while current != from {
+                    current = previous[&current];
+                    path.push(current);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if next != from && members.contains(&next) && !previous.contains_key(&next) {
+                previous.insert(next, node);
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+fn path_names(graph: &DiGraph<String, impl Sized>, path: &[NodeIndex]) -> Vec<String> {
+    path.iter().map(|n| graph[*n].clone()).collect()
+}
@@ -0,0 +1,269 @@
+use crate::{Result, CobraError};
+use crate::core::package_manager::PackageRegistry;
+use crate::core::record::{format_record, is_record_file, parse_record, verify_files, RecordEntry};
+use crate::utils::fs::{dir_size, get_cache_dir};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Files of a stored wheel live under this directory of its entry
+const FILES_DIR: &str = "files";
+/// RECORD of the stored files, including the wheel's own RECORD path
+const MANIFEST: &str = "RECORD";
+/// Registry files of the install directories linking the entry, one per line
+const USERS: &str = "users";
+
+/// How files were placed from the store into an install directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkMode {
+    /// Copy-on-write clone sharing the stored blocks
+    Reflink,
+    Hardlink,
+    /// Full copy, when the store is on another filesystem
+    Copy,
+}
+
+/// Wheels extracted once under the cache directory, keyed by the sha256 of
+/// their archive, and linked into every install directory that needs them
+#[derive(Debug, Clone)]
+pub struct WheelStore {
+    root: PathBuf,
+}
+
+/// A wheel present in the store
+#[derive(Debug, Clone)]
+pub struct StoredWheel {
+    pub sha256: String,
+    /// The extracted wheel, without its RECORD
+    pub files: PathBuf,
+    pub record: Vec<RecordEntry>,
+    pub dist_info: Option<String>,
+}
+
+impl WheelStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// The store under cobra's cache directory
+    pub fn open_default() -> Result<Self> {
+        Ok(Self::new(get_cache_dir()?.join("store")))
+    }
+
+    /// Directory holding every stored wheel
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn entry_dir(&self, sha256: &str) -> PathBuf {
+        self.root.join(sha256.to_ascii_lowercase())
+    }
+
+    /// The stored wheel whose archive has this sha256, if any
+    pub fn get(&self, sha256: &str) -> Option<StoredWheel> {
+        let dir = self.entry_dir(sha256);
+        let manifest = fs::read_to_string(dir.join(MANIFEST)).ok()?;
+        let mut record = parse_record(&manifest);
+        let dist_info = record.iter()
+            .find(|entry| is_record_file(&entry.path))
+            .and_then(|entry| entry.path.split_once('/'))
+            .map(|(dir, _)| dir.to_string());
+        record.retain(|entry| !is_record_file(&entry.path));
+        Some(StoredWheel {
+            sha256: sha256.to_ascii_lowercase(),
+            files: dir.join(FILES_DIR),
+            record,
+            dist_info,
+        })
+    }
+
+    /// Store a wheel under `sha256`, with `extract` writing its files into the
+    /// directory it is given. The entry only appears once it is complete; when
+    /// another process stores the same wheel first, its entry is kept.
+    pub fn insert(
+        &self,
+        sha256: &str,
+        extract: impl FnOnce(&Path) -> Result<(Vec<RecordEntry>, Option<String>)>,
+    ) -> Result<StoredWheel> {
+        fs::create_dir_all(&self.root)?;
+        let staging = tempfile::tempdir_in(&self.root)?;
+        let (mut record, dist_info) = extract(&staging.path().join(FILES_DIR))?;
+        if let Some(dist_info) = &dist_info {
+            record.push(RecordEntry { path: format!("{}/RECORD", dist_info), sha256: None, size: None });
+        }
+        fs::write(staging.path().join(MANIFEST), format_record(&record))?;
+
+        let dir = self.entry_dir(sha256);
+        if let Err(e) = fs::rename(staging.path(), &dir)
+            && !dir.join(MANIFEST).exists() {
+            return Err(CobraError::Cache(format!("Failed to store {}: {}", sha256, e)));
+        }
+        // The staging directory was moved; don't let its guard delete the entry
+        let _ = staging.keep();
+        self.get(sha256)
+            .ok_or_else(|| CobraError::Cache(format!("Stored wheel {} disappeared", sha256)))
+    }
+
+    /// Whether every stored file still matches its recorded hash; files
+    /// hardlinked into a project change with it
+    pub async fn is_intact(&self, wheel: &StoredWheel) -> Result<bool> {
+        Ok(verify_files(&wheel.files, &wheel.record).await?.is_empty())
+    }
+
+    /// Drop an entry, e.g. one found damaged
+    pub fn remove(&self, sha256: &str) -> Result<()> {
+        let dir = self.entry_dir(sha256);
+        if dir.exists() {
+            fs::remove_dir_all(dir)?;
+        }
+        Ok(())
+    }
+
+    /// Note that the install directory owning `registry` links this entry
+    pub fn add_user(&self, sha256: &str, registry: &Path) -> Result<()> {
+        if self.users(sha256).iter().any(|user| user == registry) {
+            return Ok(());
+        }
+        let mut users = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.entry_dir(sha256).join(USERS))?;
+        writeln!(users, "{}", registry.display())?;
+        Ok(())
+    }
+
+    /// Registry files of the install directories that linked this entry
+    pub fn users(&self, sha256: &str) -> Vec<PathBuf> {
+        fs::read_to_string(self.entry_dir(sha256).join(USERS))
+            .map(|users| users.lines().filter(|l| !l.is_empty()).map(PathBuf::from).collect())
+            .unwrap_or_default()
+    }
+
+    /// Hashes of every stored wheel
+    pub fn entries(&self) -> Result<Vec<String>> {
+        let Ok(dir) = fs::read_dir(&self.root) else {
+            return Ok(Vec::new());
+        };
+        let mut entries = Vec::new();
+        for entry in dir {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if entry.path().join(MANIFEST).exists() {
+                entries.push(name);
+            }
+        }
+        entries.sort();
+        Ok(entries)
+    }
+
+    /// Remove entries no install directory links any more, i.e. whose users'
+    /// registries no longer list a package from them. Returns how many
+    /// entries were removed and their size in bytes.
+    pub async fn prune(&self) -> Result<(usize, u64)> {
+        let mut removed = 0;
+        let mut freed = 0;
+        for sha256 in self.entries()? {
+            let mut live = Vec::new();
+            for user in self.users(&sha256) {
+                let registry = PackageRegistry::load(&user).await.unwrap_or_default();
+                if registry.packages.values().any(|p| p.store_entry.as_deref() == Some(sha256.as_str())) {
+                    live.push(user);
+                }
+            }
+            if live.is_empty() {
+                freed += dir_size(&self.entry_dir(&sha256)).await.unwrap_or(0);
+                self.remove(&sha256)?;
+                removed += 1;
+            } else {
+                let users: String = live.iter().map(|user| format!("{}\n", user.display())).collect();
+                fs::write(self.entry_dir(&sha256).join(USERS), users)?;
+            }
+        }
+        Ok((removed, freed))
+    }
+}
+
+impl StoredWheel {
+    /// Place every stored file under `dest`: reflinked where the filesystem
+    /// supports it, hardlinked otherwise, and copied across filesystems.
+    /// Returns the mode the last file was placed with.
+    pub fn link_into(&self, dest: &Path) -> Result<LinkMode> {
+        let mut mode = LinkMode::Reflink;
+        for entry in walkdir::WalkDir::new(&self.files).min_depth(1) {
+            let entry = entry.map_err(|e| CobraError::Cache(format!("Failed to read stored wheel: {}", e)))?;
+            let relative = entry.path().strip_prefix(&self.files).unwrap_or(entry.path());
+            let target = dest.join(relative);
+            let file_type = entry.file_type();
+
+            if file_type.is_dir() {
+                fs::create_dir_all(&target)?;
+                continue;
+            }
+            if target.symlink_metadata().is_ok() {
+                fs::remove_file(&target)?;
+            }
+            if file_type.is_symlink() {
+                copy_symlink(entry.path(), &target)?;
+            } else {
+                link_file(entry.path(), &target, &mut mode)?;
+            }
+        }
+        Ok(mode)
+    }
+}
+
+/// Place `src` at `dst` with `mode`, downgrading it for this and later files
+/// when the filesystem refuses
+fn link_file(src: &Path, dst: &Path, mode: &mut LinkMode) -> Result<()> {
+    loop {
+        match *mode {
+            LinkMode::Reflink => match reflink(src, dst) {
+                Ok(()) => return Ok(()),
+                Err(_) => *mode = LinkMode::Hardlink,
+            },
+            LinkMode::Hardlink => match fs::hard_link(src, dst) {
+                Ok(()) => return Ok(()),
+                Err(_) => *mode = LinkMode::Copy,
+            },
+            LinkMode::Copy => {
+                fs::copy(src, dst)?;
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn reflink(src: &Path, dst: &Path) -> std::io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let source = fs::File::open(src)?;
+    let clone = fs::File::create(dst)?;
+    // SAFETY: both descriptors are open for the duration of the call
+    if unsafe { libc::ioctl(clone.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) } == 0 {
+        // A clone is a new inode, so executable bits must be carried over
+        return clone.set_permissions(source.metadata()?.permissions());
+    }
+    let error = std::io::Error::last_os_error();
+    drop(clone);
+    let _ = fs::remove_file(dst);
+    Err(error)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reflink(_src: &Path, _dst: &Path) -> std::io::Result<()> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(unix)]
+fn copy_symlink(src: &Path, dst: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(src)?, dst)?;
+    Ok(())
+}
+
+/// Stored wheels never hold symlinks here; extraction copies their targets
+#[cfg(not(unix))]
+fn copy_symlink(src: &Path, dst: &Path) -> Result<()> {
+    fs::copy(src, dst)?;
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use crate::{Result, CobraError, DependencySource};
+use std::path::Path;
+use tokio::process::Command;
+
+/// Parse a pip-style `git+<url>[@rev]` requirement; `None` when `spec` is not one.
+/// The revision is split off the URL path, so `git+ssh://git@host/repo` keeps its user.
+pub fn parse_git_spec(spec: &str) -> Option<DependencySource> {
+    let url = spec.strip_prefix("git+")?;
+    let path_start = url.find("://").map(|i| i + 3).unwrap_or(0);
+    let path_start = url[path_start..].find('/').map(|i| path_start + i).unwrap_or(url.len());
+    let (git, rev) = match url[path_start..].rsplit_once('@') {
+        Some((path, rev)) if !rev.is_empty() => (format!("{}{}", &url[..path_start], path), Some(rev.to_string())),
+        _ => (url.to_string(), None),
+    };
+    Some(DependencySource::Git { git, rev })
+}
+
+/// Clone `url` into `dest` and check out `rev`, or leave the default branch when `None`
+pub async fn checkout(url: &str, rev: Option<&str>, dest: &Path) -> Result<()> {
+    let mut clone = Command::new("git");
+    clone.args(["clone", "--quiet", "--", url]).arg(dest);
+    run(&mut clone, &format!("clone {}", url)).await?;
+
+    let Some(rev) = rev else {
+        return Ok(());
+    };
+    // Only the default branch exists locally after a clone; other branches
+    // are reachable through their remote-tracking ref
+    let checkout = |target: String| {
+        let mut command = Command::new("git");
+        command.arg("-C").arg(dest).args(["checkout", "--quiet", "--detach", &target]);
+        command
+    };
+    let action = format!("check out {} of {}", rev, url);
+    match run(&mut checkout(rev.to_string()), &action).await {
+        Err(e) if run(&mut checkout(format!("origin/{}", rev)), &action).await.is_err() => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Full hash of the commit checked out in `repo`
+pub async fn head_commit(repo: &Path) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .await
+        .map_err(|e| CobraError::InstallationFailed(format!("Failed to run git: {}", e)))?;
+    if !output.status.success() {
+        return Err(CobraError::InstallationFailed(format!(
+            "Failed to read the commit of {}: {}",
+            repo.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+async fn run(command: &mut Command, action: &str) -> Result<()> {
+    let output = command
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .await
+        .map_err(|e| CobraError::InstallationFailed(format!("Failed to run git to {}: {}", action, e)))?;
+    if !output.status.success() {
+        return Err(CobraError::InstallationFailed(format!(
+            "Failed to {}:\n{}",
+            action,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,514 @@
+use crate::{Result, CobraError};
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// Pre-release phase of a PEP 440 version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PreRelease {
+    Alpha,
+    Beta,
+    Rc,
+}
+
+/// One dot-separated part of a local version label (`+cu118.1`)
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LocalSegment {
+    /// Sorts before any number and lexically among other text
+    Text(String),
+    Number(u64),
+}
+
+impl fmt::Display for LocalSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LocalSegment::Text(text) => write!(f, "{}", text),
+            LocalSegment::Number(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+/// A parsed PEP 440 version (`[N!]N(.N)*[{a|b|rc}N][.postN][.devN][+local]`)
+#[derive(Debug, Clone)]
+pub struct Version {
+    pub epoch: u64,
+    pub release: Vec<u64>,
+    pub pre: Option<(PreRelease, u64)>,
+    pub post: Option<u64>,
+    pub dev: Option<u64>,
+    /// Local version label, empty for public versions
+    pub local: Vec<LocalSegment>,
+}
+
+impl Version {
+    pub fn parse(input: &str) -> Result<Self> {
+        let invalid = || CobraError::InvalidInput(format!("Invalid version: {}", input));
+        let normalized = input.trim().to_ascii_lowercase();
+        let (public, local) = match normalized.split_once('+') {
+            Some((public, label)) => (public, parse_local(label).ok_or_else(invalid)?),
+            None => (normalized.as_str(), Vec::new()),
+        };
+        let mut rest = public.strip_prefix('v').unwrap_or(public);
+
+        let mut epoch = 0;
+        if let Some((e, r)) = rest.split_once('!') {
+            epoch = e.parse().map_err(|_| invalid())?;
+            rest = r;
+        }
+
+        // Release segment
+        let release_end = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let release_str = rest[..release_end].trim_end_matches('.');
+        if release_str.is_empty() {
+            return Err(invalid());
+        }
+        let release = release_str
+            .split('.')
+            .map(|part| part.parse::<u64>().map_err(|_| invalid()))
+            .collect::<Result<Vec<_>>>()?;
+        let mut rest = &rest[release_str.len()..];
+
+        let mut pre = None;
+        let mut post = None;
+        let mut dev = None;
+
+        // Pre-release segment
+        let trimmed = rest.trim_start_matches(['.', '-', '_']);
+        for (label, kind) in [
+            ("alpha", PreRelease::Alpha),
+            ("beta", PreRelease::Beta),
+            ("preview", PreRelease::Rc),
+            ("pre", PreRelease::Rc),
+            ("rc", PreRelease::Rc),
+            ("a", PreRelease::Alpha),
+            ("b", PreRelease::Beta),
+            ("c", PreRelease::Rc),
+        ] {
+            if let Some(after) = trimmed.strip_prefix(label) {
+                let (number, remaining) = take_number(after);
+                pre = Some((kind, number.unwrap_or(0)));
+                rest = remaining;
+                break;
+            }
+        }
+
+        // Post-release segment (implicit `-N` form included)
+        if let Some(after) = rest.strip_prefix('-')
+            && after.starts_with(|c: char| c.is_ascii_digit()) {
+            let (number, remaining) = take_number(after);
+            post = number;
+            rest = remaining;
+        } else {
+            let trimmed = rest.trim_start_matches(['.', '-', '_']);
+            for label in ["post", "rev", "r"] {
+                if let Some(after) = trimmed.strip_prefix(label) {
+                    let (number, remaining) = take_number(after);
+                    post = Some(number.unwrap_or(0));
+                    rest = remaining;
+                    break;
+                }
+            }
+        }
+
+        // Development release segment
+        let trimmed = rest.trim_start_matches(['.', '-', '_']);
+        if let Some(after) = trimmed.strip_prefix("dev") {
+            let (number, remaining) = take_number(after);
+            dev = Some(number.unwrap_or(0));
+            rest = remaining;
+        }
+
+        if !rest.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(Self { epoch, release, pre, post, dev, local })
+    }
+
+    /// Whether this is a pre-release or development release
+    pub fn is_prerelease(&self) -> bool {
+        self.pre.is_some() || self.dev.is_some()
+    }
+
+    /// Release segment padded with zeros to at least `len` components
+    fn release_padded(&self, len: usize) -> Vec<u64> {
+        let mut release = self.release.clone();
+        release.resize(len.max(release.len()), 0);
+        release
+    }
+
+    /// The final release this version belongs to (drops pre/post/dev/local)
+    fn base(&self) -> Version {
+        Version {
+            epoch: self.epoch,
+            release: self.release.clone(),
+            pre: None,
+            post: None,
+            dev: None,
+            local: Vec::new(),
+        }
+    }
+
+    /// This version without its local label
+    pub fn public(&self) -> Version {
+        Version { local: Vec::new(), ..self.clone() }
+    }
+
+    fn sort_key(&self) -> SortKey {
+        let mut release = self.release.clone();
+        while release.len() > 1 && release.last() == Some(&0) {
+            release.pop();
+        }
+
+        // A dev release of a final version sorts before its pre-releases
+        let pre = match (self.pre, self.post, self.dev) {
+            (None, None, Some(_)) => (-1, 0, 0),
+            (None, _, _) => (1, 0, 0),
+            (Some((kind, n)), _, _) => (0, kind as u64, n),
+        };
+        let post = match self.post {
+            None => (-1, 0),
+            Some(n) => (0, n),
+        };
+        let dev = match self.dev {
+            None => (1, 0),
+            Some(n) => (0, n),
+        };
+        // No label sorts first, then segment by segment, shorter prefixes first
+        (self.epoch, release, pre, post, dev, self.local.clone())
+    }
+}
+
+/// (epoch, trimmed release, pre, post, dev, local) ordering key
+type SortKey = (u64, Vec<u64>, (i8, u64, u64), (i8, u64), (i8, u64), Vec<LocalSegment>);
+
+/// Split a local label on `.`, `-` or `_`; `None` if a segment is empty or
+/// not alphanumeric
+fn parse_local(label: &str) -> Option<Vec<LocalSegment>> {
+    label.split(['.', '-', '_'])
+        .map(|segment| {
+            if segment.is_empty() || !segment.chars().all(|c| c.is_ascii_alphanumeric()) {
+                None
+            } else if let Ok(n) = segment.parse() {
+                Some(LocalSegment::Number(n))
+            } else {
+                Some(LocalSegment::Text(segment.to_string()))
+            }
+        })
+        .collect()
+}
+
+fn take_number(input: &str) -> (Option<u64>, &str) {
+    let input = input
+        .strip_prefix(['.', '-', '_'])
+        .filter(|s| s.starts_with(|c: char| c.is_ascii_digit()))
+        .unwrap_or(input);
+    let end = input.find(|c: char| !c.is_ascii_digit()).unwrap_or(input.len());
+    if end == 0 {
+        (None, input)
+    } else {
+        (input[..end].parse().ok(), &input[end..])
+    }
+}
+
+impl FromStr for Version {
+    type Err = CobraError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Version::parse(s)
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sort_key().cmp(&other.sort_key())
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.epoch != 0 {
+            write!(f, "{}!", self.epoch)?;
+        }
+        let release: Vec<String> = self.release.iter().map(|n| n.to_string()).collect();
+        write!(f, "{}", release.join("."))?;
+        if let Some((kind, n)) = self.pre {
+            let label = match kind {
+                PreRelease::Alpha => "a",
+                PreRelease::Beta => "b",
+                PreRelease::Rc => "rc",
+            };
+            write!(f, "{}{}", label, n)?;
+        }
+        if let Some(n) = self.post {
+            write!(f, ".post{}", n)?;
+        }
+        if let Some(n) = self.dev {
+            write!(f, ".dev{}", n)?;
+        }
+        if !self.local.is_empty() {
+            let local: Vec<String> = self.local.iter().map(|s| s.to_string()).collect();
+            write!(f, "+{}", local.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+/// Comparison operator of a single version specifier
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    /// `===`: the normalized version strings are identical
+    ArbitraryEqual,
+    Equal,
+    NotEqual,
+    Compatible,
+    LessThanEqual,
+    GreaterThanEqual,
+    LessThan,
+    GreaterThan,
+}
+
+/// A single clause such as `>=2.0` or `==1.4.*`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Specifier {
+    pub operator: Operator,
+    pub version: Version,
+    pub wildcard: bool,
+}
+
+impl Specifier {
+    pub fn parse(input: &str) -> Result<Self> {
+        let input = input.trim();
+        let (operator, rest) = [
+            ("===", Operator::ArbitraryEqual),
+            ("~=", Operator::Compatible),
+            ("==", Operator::Equal),
+            ("!=", Operator::NotEqual),
+            ("<=", Operator::LessThanEqual),
+            (">=", Operator::GreaterThanEqual),
+            ("<", Operator::LessThan),
+            (">", Operator::GreaterThan),
+        ]
+        .into_iter()
+        .find_map(|(prefix, op)| input.strip_prefix(prefix).map(|rest| (op, rest.trim())))
+        .ok_or_else(|| CobraError::InvalidInput(format!("Unsupported version specifier: {}", input)))?;
+
+        let (version_str, wildcard) = match rest.strip_suffix(".*") {
+            Some(prefix) if matches!(operator, Operator::Equal | Operator::NotEqual) => (prefix, true),
+            _ => (rest, false),
+        };
+        let version = Version::parse(version_str)?;
+
+        if operator == Operator::Compatible && version.release.len() < 2 {
+            return Err(CobraError::InvalidInput(format!(
+                "'~=' requires at least two release segments: {}", input
+            )));
+        }
+
+        Ok(Self { operator, version, wildcard })
+    }
+
+    pub fn contains(&self, candidate: &Version) -> bool {
+        let spec = &self.version;
+        match self.operator {
+            Operator::ArbitraryEqual => candidate.to_string() == spec.to_string(),
+            Operator::Equal if self.wildcard => prefix_matches(spec, candidate),
+            Operator::NotEqual if self.wildcard => !prefix_matches(spec, candidate),
+            Operator::Equal => local_equal(spec, candidate),
+            Operator::NotEqual => !local_equal(spec, candidate),
+            // Local labels never make a version newer or older than a public one
+            Operator::LessThanEqual => candidate.public() <= *spec,
+            Operator::GreaterThanEqual => candidate.public() >= *spec,
+            Operator::LessThan => {
+                // `<V` must not admit pre-releases of V itself unless V is one
+                candidate < spec
+                    && (spec.is_prerelease() || !candidate.is_prerelease() || candidate.base() != spec.base())
+            }
+            Operator::GreaterThan => {
+                // `>V` must not admit post-releases or local builds of V itself
+                candidate > spec
+                    && (spec.post.is_some() || candidate.post.is_none() || candidate.base() != spec.base())
+                    && (candidate.local.is_empty() || candidate.base() != spec.base())
+            }
+            Operator::Compatible => {
+                let mut prefix = spec.base();
+                prefix.release.pop();
+                candidate >= spec && prefix_matches(&prefix, candidate)
+            }
+        }
+    }
+}
+
+/// `==V` ignores the candidate's local label unless V has one
+fn local_equal(spec: &Version, candidate: &Version) -> bool {
+    if spec.local.is_empty() {
+        candidate.public() == *spec
+    } else {
+        candidate == spec
+    }
+}
+
+/// `==1.4.*` style match on the release segment
+fn prefix_matches(prefix: &Version, candidate: &Version) -> bool {
+    if prefix.epoch != candidate.epoch {
+        return false;
+    }
+    let len = prefix.release.len();
+    candidate.release_padded(len)[..len] == prefix.release[..]
+}
+
+impl fmt::Display for Specifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self.operator {
+            Operator::ArbitraryEqual => "===",
+            Operator::Equal => "==",
+            Operator::NotEqual => "!=",
+            Operator::Compatible => "~=",
+            Operator::LessThanEqual => "<=",
+            Operator::GreaterThanEqual => ">=",
+            Operator::LessThan => "<",
+            Operator::GreaterThan => ">",
+        };
+        write!(f, "{}{}{}", op, self.version, if self.wildcard { ".*" } else { "" })
+    }
+}
+
+/// A comma-separated set of specifiers; empty or `*` matches everything.
+/// Cargo-style `^V` and `~V` clauses are expanded into `>=V,<upper` pairs and
+/// a bare `V` is an exact pin, as in Poetry.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct VersionSpec {
+    pub specifiers: Vec<Specifier>,
+}
+
+impl VersionSpec {
+    pub fn parse(input: &str) -> Result<Self> {
+        let input = input.trim();
+        if input.is_empty() || input == "*" {
+            return Ok(Self::default());
+        }
+
+        let mut specifiers = Vec::new();
+        for part in input.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+            if let Some(version) = part.strip_prefix('^') {
+                specifiers.extend(bounded_range(version, Bump::Caret)?);
+            } else if let Some(version) = part.strip_prefix('~').filter(|_| !part.starts_with("~=")) {
+                specifiers.extend(bounded_range(version, Bump::Tilde)?);
+            } else if part.starts_with(|c: char| c.is_ascii_digit()) {
+                specifiers.push(Specifier::parse(&format!("=={}", part))?);
+            } else {
+                specifiers.push(Specifier::parse(part)?);
+            }
+        }
+        Ok(Self { specifiers })
+    }
+
+    pub fn is_any(&self) -> bool {
+        self.specifiers.is_empty()
+    }
+
+    pub fn contains(&self, version: &Version) -> bool {
+        self.specifiers.iter().all(|s| s.contains(version))
+    }
+
+    /// Whether a `==` clause names one exact version, which may select yanked releases
+    pub fn is_pinned(&self) -> bool {
+        self.specifiers.iter()
+            .any(|s| matches!(s.operator, Operator::Equal | Operator::ArbitraryEqual) && !s.wildcard)
+    }
+
+    /// Pre-releases are only eligible when a specifier explicitly names one
+    pub fn allows_prereleases(&self) -> bool {
+        self.specifiers.iter().any(|s| s.version.is_prerelease())
+    }
+}
+
+/// Which release component a `^` or `~` upper bound increments
+#[derive(Clone, Copy)]
+enum Bump {
+    /// Leftmost non-zero component: `^2.31.0` → `<3.0.0`, `^0.2.3` → `<0.3.0`
+    Caret,
+    /// Minor component, or major when only one is given: `~1.2.3` → `<1.3.0`
+    Tilde,
+}
+
+/// `>=V,<upper` for a caret or tilde clause
+fn bounded_range(input: &str, bump: Bump) -> Result<[Specifier; 2]> {
+    let lower = Version::parse(input)?;
+    let release = &lower.release;
+    let index = match bump {
+        Bump::Caret => release.iter().position(|&n| n != 0).unwrap_or(release.len() - 1),
+        Bump::Tilde => release.len().min(2) - 1,
+    };
+
+    let mut upper_release = release.clone();
+    upper_release[index] += 1;
+    upper_release[index + 1..].iter_mut().for_each(|n| *n = 0);
+    let upper = Version {
+        epoch: lower.epoch,
+        release: upper_release,
+        pre: None,
+        post: None,
+        dev: None,
+        local: Vec::new(),
+    };
+
+    Ok([
+        Specifier { operator: Operator::GreaterThanEqual, version: lower, wildcard: false },
+        Specifier { operator: Operator::LessThan, version: upper, wildcard: false },
+    ])
+}
+
+impl FromStr for VersionSpec {
+    type Err = CobraError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        VersionSpec::parse(s)
+    }
+}
+
+impl fmt::Display for VersionSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.specifiers.is_empty() {
+            return write!(f, "*");
+        }
+        let parts: Vec<String> = self.specifiers.iter().map(|s| s.to_string()).collect();
+        write!(f, "{}", parts.join(","))
+    }
+}
+
+/// Whether an installed `version` satisfies `requirement`: a specifier set,
+/// or a bare version that must be equal including any local label.
+/// Input that isn't PEP 440 is compared as plain strings.
+pub fn satisfies(version: &str, requirement: &str) -> bool {
+    let requirement = requirement.trim();
+    if requirement.is_empty() || requirement == "*" {
+        return true;
+    }
+    let Ok(parsed) = Version::parse(version) else {
+        return version == requirement.trim_start_matches("==");
+    };
+    if let Ok(exact) = Version::parse(requirement) {
+        return parsed == exact;
+    }
+    match VersionSpec::parse(requirement) {
+        Ok(spec) => spec.contains(&parsed),
+        Err(_) => version == requirement,
+    }
+}
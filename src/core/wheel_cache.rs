@@ -0,0 +1,127 @@
+use crate::{Result, Package};
+use crate::utils::fs::{atomic_write, get_cache_dir};
+use crate::utils::hash::digest_matches;
+use bytes::Bytes;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::fs;
+
+/// Downloaded package archives kept under their original filenames, so
+/// other tools (e.g. `pip install --find-links`) can reuse them and their
+/// digests can be rechecked with `sha256sum`
+#[derive(Debug, Clone)]
+pub struct WheelCache {
+    root: PathBuf,
+}
+
+/// An archive in the wheel cache
+#[derive(Debug, Clone)]
+pub struct CachedWheel {
+    pub filename: String,
+    pub size: u64,
+    /// Last written or installed from
+    pub last_used: SystemTime,
+}
+
+impl WheelCache {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// The wheel cache under cobra's cache directory
+    pub fn open_default() -> Result<Self> {
+        Ok(Self::new(get_cache_dir()?.join("wheels")))
+    }
+
+    /// Directory holding the cached archives
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The cached archive of `package` as stored; checking its digest, and
+    /// evicting it when that fails, is left to the caller so `--no-verify`
+    /// can still install it
+    pub async fn get(&self, package: &Package) -> Result<Option<Bytes>> {
+        let path = self.root.join(artifact_filename(&package.download_url));
+        let data = match fs::read(&path).await {
+            Ok(data) => Bytes::from(data),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        // Pruning goes by when an archive was last used
+        let _ = std::fs::File::options().append(true).open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+        Ok(Some(data))
+    }
+
+    /// Keep the archive of `package`; archives without a matching digest from
+    /// the index are not cached. Returns whether it was stored.
+    pub async fn insert(&self, package: &Package, data: &[u8]) -> Result<bool> {
+        if !package.hash.as_ref().is_some_and(|hash| digest_matches(hash, data)) {
+            return Ok(false);
+        }
+        atomic_write(&self.root.join(artifact_filename(&package.download_url)), data).await?;
+        Ok(true)
+    }
+
+    /// Drop the cached archive of `package`, if there is one
+    pub async fn remove(&self, package: &Package) -> Result<()> {
+        match fs::remove_file(self.root.join(artifact_filename(&package.download_url))).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Every cached archive, by filename
+    pub async fn list(&self) -> Result<Vec<CachedWheel>> {
+        let mut wheels = Vec::new();
+        let mut entries = match fs::read_dir(&self.root).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(wheels),
+            Err(e) => return Err(e.into()),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            let filename = entry.file_name().to_string_lossy().into_owned();
+            // Half-written archives are hidden temp files
+            if !metadata.is_file() || filename.starts_with('.') {
+                continue;
+            }
+            wheels.push(CachedWheel { filename, size: metadata.len(), last_used: metadata.modified()? });
+        }
+        wheels.sort_by(|a, b| a.filename.cmp(&b.filename));
+        Ok(wheels)
+    }
+
+    /// Remove archives not used for `max_age`, or all of them without one,
+    /// returning how many were removed and the bytes freed
+    pub async fn prune(&self, max_age: Option<Duration>) -> Result<(usize, u64)> {
+        let (mut removed, mut freed) = (0, 0);
+        for wheel in self.list().await? {
+            let stale = max_age.is_none_or(|max_age| wheel.last_used.elapsed().is_ok_and(|age| age > max_age));
+            if stale && fs::remove_file(self.root.join(&wheel.filename)).await.is_ok() {
+                removed += 1;
+                freed += wheel.size;
+            }
+        }
+        Ok((removed, freed))
+    }
+}
+
+/// The archive of `package` in a `--find-links` directory, unless, when the
+/// index published one, its sha256 differs
+pub async fn find_link(dir: &Path, package: &Package) -> Result<Option<Bytes>> {
+    let data = match fs::read(dir.join(artifact_filename(&package.download_url))).await {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let matches = package.hash.as_ref().is_none_or(|hash| digest_matches(hash, &data));
+    Ok(matches.then(|| Bytes::from(data)))
+}
+
+/// Filename of the artifact at `url`, without any query or `#sha256=` fragment
+pub fn artifact_filename(url: &str) -> &str {
+    let path = url.split(['#', '?']).next().unwrap_or(url);
+    path.rsplit('/').next().unwrap_or(path)
+}
@@ -27,6 +27,7 @@ pub use core::{
 };
 
 pub use registry::{
+    backend::RegistryBackend,
     client::RegistryClient,
     packagecloud::PackageCloudRegistry,
     pypi::PyPIRegistry,
@@ -69,11 +70,56 @@ pub enum CobraError {
     #[error("Archive extraction error: {0}")]
     Archive(String),
     
-    #[error("Hash verification failed")]
-    HashMismatch,
+    #[error("Hash verification failed for {package}: expected {algorithm} {expected}, computed {computed}")]
+    HashMismatch {
+        package: String,
+        algorithm: utils::hash::HashAlgo,
+        expected: String,
+        computed: String,
+    },
     
     #[error("Invalid input: {0}")]
     InvalidInput(String),
+    
+    #[error("Offline mode: {0}")]
+    Offline(String),
+    
+    #[error("Invalid {path}:\n  {}", .problems.join("\n  "))]
+    InvalidConfig {
+        path: String,
+        problems: Vec<String>,
+    },
+    
+    #[error("Credential store error: {0}")]
+    Credentials(String),
+    
+    #[error("Dependency resolution timed out after {secs}s with {resolved} packages resolved; still waiting on: {}", .pending.join(", "))]
+    ResolutionTimeout {
+        secs: u64,
+        resolved: usize,
+        pending: Vec<String>,
+    },
+    
+    #[error("{last_error} (gave up after {attempts} attempts)")]
+    RetriesExhausted {
+        attempts: u32,
+        last_error: String,
+    },
+    
+    #[error("Not enough disk space (pass --no-space-check to install anyway):\n  {}", .shortfalls.join("\n  "))]
+    InsufficientSpace {
+        shortfalls: Vec<String>,
+    },
+    
+    #[error("Installation of {package} failed, so the {} package(s) installed alongside it were rolled back: {reason}", .rolled_back.len())]
+    RolledBack {
+        package: String,
+        reason: String,
+        rolled_back: Vec<String>,
+    },
+    
+    #[error("Interrupted")]
+    Cancelled,
 }
 
 pub type Result<T> = std::result::Result<T, CobraError>;
@@ -90,12 +136,96 @@ pub struct Package {
     pub description: Option<String>,
     pub author: Option<String>,
     pub homepage: Option<String>,
+    /// Environment marker under which this package is needed; `None` means everywhere
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub markers: Option<String>,
+    /// The release was yanked from its index and was only chosen because it was pinned
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub yanked: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub yanked_reason: Option<String>,
+    /// Python versions the release supports, e.g. `>=3.8`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requires_python: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Dependency {
     pub name: String,
     pub version_spec: String,
+    /// Environment marker after `;`, including `extra == "..."` conditions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub markers: Option<String>,
+    /// Where the distribution comes from; anything but the index is built locally
+    #[serde(default, skip_serializing_if = "DependencySource::is_registry")]
+    pub source: DependencySource,
+    /// Extras requested for the package, like `socks` in `requests[socks]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extras: Vec<String>,
+    /// Skip the package with a warning instead of failing when it cannot be resolved
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub optional: bool,
+}
+
+impl Dependency {
+    /// Whether the requirement only applies under an environment marker or extra
+    pub fn is_conditional(&self) -> bool {
+        self.markers.is_some()
+    }
+}
+
+/// Where a dependency's distribution comes from. In cobra.toml's `[sources]`
+/// table this is `{ path = "libs/mylib" }` or `{ git = "https://...", rev = "v1.0" }`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum DependencySource {
+    /// PyPI or a configured private registry
+    #[default]
+    Registry,
+    /// Project directory built into a wheel at install time, relative to cobra.toml
+    Path { path: String },
+    /// Git repository cloned and built into a wheel at install time
+    Git {
+        git: String,
+        /// Branch, tag or commit; the default branch when unset
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rev: Option<String>,
+    },
+}
+
+impl DependencySource {
+    pub fn is_registry(&self) -> bool {
+        matches!(self, DependencySource::Registry)
+    }
+}
+
+impl std::fmt::Display for DependencySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DependencySource::Registry => write!(f, "index"),
+            DependencySource::Path { path } => write!(f, "path: {}", path),
+            DependencySource::Git { git, rev: Some(rev) } => write!(f, "git: {}@{}", git, rev),
+            DependencySource::Git { git, rev: None } => write!(f, "git: {}", git),
+        }
+    }
+}
+
+/// Normalize a project name per PEP 503: lowercase with runs of `-`, `_`, `.` collapsed to `-`
+pub fn normalize_name(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    let mut last_was_separator = false;
+    for c in name.trim().chars() {
+        if matches!(c, '-' | '_' | '.') {
+            if !last_was_separator {
+                normalized.push('-');
+            }
+            last_was_separator = true;
+        } else {
+            normalized.push(c.to_ascii_lowercase());
+            last_was_separator = false;
+        }
+    }
+    normalized
 }
 
 /// Global constants for performance tuning
@@ -104,8 +234,28 @@ pub mod constants {
     
     pub const MAX_CONCURRENT_DOWNLOADS: usize = 16;
     pub const MAX_CONCURRENT_INSTALLS: usize = 16;
-    pub const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+    /// Upper bound for `parallel-downloads`/`parallel-installs` and `COBRA_PARALLEL*`
+    pub const MAX_PARALLELISM: usize = 128;
+    pub const DOWNLOAD_SEGMENTS: usize = 4;
+    pub const SEGMENTED_DOWNLOAD_MIN_SIZE: u64 = 20 * 1024 * 1024;
+    pub const STALE_DOWNLOAD_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+    /// Staging directories this old were left by an install that died
+    pub const STALE_STAGING_AGE: Duration = Duration::from_secs(60 * 60);
+    pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+    pub const METADATA_TIMEOUT: Duration = Duration::from_secs(30);
+    pub const MAX_RETRIES: u32 = 3;
+    pub const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+    pub const RESOLVE_TIMEOUT: Duration = Duration::from_secs(120);
+    /// Times the resolver re-walks the graph for specs a pass turned up late
+    pub const MAX_RESOLUTION_PASSES: usize = 20;
+    pub const METADATA_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+    /// Local index entries synced longer ago are only used offline
+    pub const INDEX_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
     pub const CACHE_SIZE_MB: usize = 500;
     pub const MEMORY_CACHE_ENTRIES: usize = 1000;
     pub const CHUNK_SIZE: usize = 8192;
+    /// Rough ratio of a wheel's extracted size to its compressed archive
+    pub const EXTRACTION_FACTOR: u64 = 3;
+    /// Most a single wheel may unpack to before it is taken for a decompression bomb
+    pub const MAX_UNPACKED_SIZE: u64 = 8 * 1024 * 1024 * 1024;
 }
@@ -1,12 +1,28 @@
 use clap::{Parser, Subcommand};
-use cobra::{Result, CobraError};
+use cobra::cli::cache::ClearScope;
+use cobra::cli::export::ExportFormat;
+use cobra::cli::install::InstallOptions;
+use cobra::cli::update::Bump;
+use cobra::core::config::DependencySelection;
+use cobra::core::imports::ImportCheck;
+use cobra::core::resolver::ResolutionStrategy;
 use colored::Colorize;
+use std::path::PathBuf;
+use std::process::ExitCode;
 use std::time::Instant;
+use tokio_util::sync::CancellationToken;
 
 #[derive(Parser)]
 #[command(name = "cobra")]
 #[command(about = "⚡ Ultra-fast Python package manager - 20x faster than pip", long_about = None)]
 #[command(version)]
+#[command(after_help = "\
+Environment variables:
+  COBRA_PARALLEL            Parallel downloads and installs (1-128), overriding cobra.toml
+  COBRA_PARALLEL_DOWNLOADS  Parallel downloads, overriding COBRA_PARALLEL
+  COBRA_PARALLEL_INSTALLS   Parallel installs, overriding COBRA_PARALLEL
+  COBRA_INSTALL_DIR         Install directory, overriding install-dir in cobra.toml
+  COBRA_OFFLINE             Resolve and install from the cache only, like --offline")]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
@@ -23,15 +39,105 @@ enum Commands {
         path: String,
     },
     
-    /// Install packages from cobra.toml
+    /// Install packages from cobra.toml, or the named packages and
+    /// requirements files without one
     Install {
+        /// Requirements such as requests==2.31.0, or wheel files such as
+        /// ./dist/mylib-1.0-py3-none-any.whl, to install instead of cobra.toml's
+        #[arg(value_name = "PACKAGE")]
+        packages: Vec<String>,
+        /// Install the requirements in this pip requirements file instead of
+        /// cobra.toml's; may be repeated
+        #[arg(short = 'r', long = "requirement", value_name = "FILE")]
+        requirements: Vec<PathBuf>,
+        /// Also add the named packages and requirements to cobra.toml's
+        /// [dependencies] once installed, if there is a cobra.toml
+        #[arg(long)]
+        save: bool,
         #[arg(short, long)]
         no_cache: bool,
+        /// Ignore releases uploaded after this RFC 3339 timestamp
+        #[arg(long, value_name = "TIMESTAMP")]
+        exclude_newer: Option<chrono::DateTime<chrono::Utc>>,
+        /// Pick the newest or the oldest release satisfying each requirement
+        #[arg(long, value_enum, default_value_t = ResolutionStrategy::Highest)]
+        resolution: ResolutionStrategy,
+        /// Install exactly the versions recorded in cobra.lock
+        #[arg(long)]
+        frozen: bool,
+        /// Also install [dev-dependencies]
+        #[arg(long, conflicts_with = "only_dev")]
+        dev: bool,
+        /// Install only [dev-dependencies]
+        #[arg(long)]
+        only_dev: bool,
+        /// Never contact the registry; resolve and install from the cache
+        #[arg(long, env = "COBRA_OFFLINE", value_parser = clap::builder::FalseyValueParser::new())]
+        offline: bool,
+        /// Skip sha256 verification, for registries that don't publish digests
+        #[arg(long)]
+        no_verify: bool,
+        /// Cap the combined download rate, in bytes per second
+        #[arg(long, value_name = "BYTES_PER_SEC", value_parser = clap::value_parser!(u64).range(1..))]
+        max_rate: Option<u64>,
+        /// Remove and reinstall the named packages, or every package when none are named
+        #[arg(long, value_name = "PACKAGE", num_args = 0..)]
+        force_reinstall: Option<Vec<String>>,
+        /// Re-resolve the named packages, or every package, from fresh metadata
+        /// instead of cobra.lock and reinstall them
+        #[arg(long, value_name = "PACKAGE", num_args = 0.., conflicts_with = "frozen")]
+        upgrade: Option<Vec<String>>,
+        /// Install into this directory, e.g. to vendor a bundle, without touching
+        /// the project's install directory or Python's path
+        #[arg(long, value_name = "DIR")]
+        target: Option<PathBuf>,
+        /// Don't check for enough free disk space before downloading
+        #[arg(long)]
+        no_space_check: bool,
+        /// Don't run the [tool.cobra.hooks] commands
+        #[arg(long)]
+        no_hooks: bool,
+        /// Import each installed package's top-level modules afterwards; with
+        /// `=warn` failures are reported without failing the install
+        #[arg(long, value_enum, value_name = "MODE", num_args = 0..=1, require_equals = true, default_missing_value = "fail")]
+        verify_imports: Option<ImportCheck>,
+        /// Resolve from the wheels in this directory before any index, and
+        /// install archives found there instead of downloading them; may be repeated
+        #[arg(long, value_name = "DIR")]
+        find_links: Vec<PathBuf>,
+        /// Resolve only from --find-links directories and wheel files, never an index
+        #[arg(long)]
+        no_index: bool,
+        /// Install every package or none: if one fails, uninstall the ones
+        /// this run installed
+        #[arg(long)]
+        atomic: bool,
+        /// Write a JSON report of what was installed, with sources, digests
+        /// and timings; `-` prints only the report to stdout
+        #[arg(long, value_name = "FILE")]
+        report: Option<PathBuf>,
+        /// Print each package's size, source, download and extract times and
+        /// speed, slowest first, above the totals
+        #[arg(long)]
+        timings: bool,
     },
     
     /// Add a package to cobra.toml
     Add {
+        /// Package names, local project directories (`./libs/mylib`) or `git+<url>[@rev]`
         packages: Vec<String>,
+        /// Add to [dev-dependencies] instead of [dependencies]
+        #[arg(long)]
+        dev: bool,
+        /// Add a local project directory, built into a wheel on install
+        #[arg(long, value_name = "DIR")]
+        path: Option<String>,
+        /// Pin to the newest matching release with `==` instead of a range
+        #[arg(long, conflicts_with = "path")]
+        exact: bool,
+        /// Only edit cobra.toml instead of installing straight away
+        #[arg(long)]
+        no_install: bool,
     },
     
     /// Remove a package from cobra.toml
@@ -43,10 +149,31 @@ enum Commands {
     Update {
         #[arg(short, long)]
         package: Option<String>,
+        /// Show each dependency's installed, newest allowed and newest
+        /// version and the upgrade diff, without installing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// With --dry-run, also show what moving each spec to the newest
+        /// major release would pull in
+        #[arg(long, requires = "dry_run")]
+        latest: bool,
+        /// Don't ask for confirmation before downgrading packages
+        #[arg(short, long)]
+        yes: bool,
+        /// Only apply patch upgrades, holding back minor and major ones
+        #[arg(long, conflicts_with = "minor_only")]
+        patch_only: bool,
+        /// Apply patch and minor upgrades, holding back major ones
+        #[arg(long)]
+        minor_only: bool,
     },
     
     /// List installed packages
-    List,
+    List {
+        /// List the packages installed with `cobra install --target <DIR>`
+        #[arg(long, value_name = "DIR")]
+        target: Option<PathBuf>,
+    },
     
     /// Show detailed package information
     Show {
@@ -60,6 +187,37 @@ enum Commands {
         limit: usize,
     },
     
+    /// Install, reinstall and remove packages so the environment matches cobra.toml
+    Sync {
+        /// Show what would change without touching the environment
+        #[arg(long)]
+        dry_run: bool,
+        /// Also keep [dev-dependencies] installed
+        #[arg(long)]
+        dev: bool,
+    },
+    
+    /// Warn about installed packages that drifted from cobra.lock
+    Status,
+    
+    /// Remove untracked files from the install directory and registry entries whose files are gone
+    Clean {
+        /// Show what would be removed without deleting anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    
+    /// Print environment details for bug reports and flag common problems
+    #[command(alias = "info")]
+    Doctor,
+    
+    /// Check installed files against the hashes recorded at install time
+    Verify {
+        /// Reinstall corrupted packages from cobra.lock
+        #[arg(long)]
+        fix: bool,
+    },
+    
     /// Uninstall packages from system
     Uninstall {
         packages: Vec<String>,
@@ -73,41 +231,214 @@ enum Commands {
         format: String,
     },
     
+    /// Write the locked packages as requirements.txt, constraints.txt or a
+    /// pyproject.toml dependencies block for other tools
+    Export {
+        #[arg(long, value_enum, default_value_t = ExportFormat::Requirements)]
+        format: ExportFormat,
+        /// Add `--hash=sha256:` lines so pip verifies each archive
+        #[arg(long)]
+        hashes: bool,
+        /// Write to this file instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    
+    /// Import dependencies from a pip requirements file into cobra.toml
+    Import {
+        requirements: String,
+        /// Import into [dev-dependencies] instead of [dependencies]
+        #[arg(long)]
+        dev: bool,
+    },
+    
     /// Check for dependency conflicts and issues
-    Check,
+    Check {
+        /// Expect [dev-dependencies] to be installed too
+        #[arg(long)]
+        dev: bool,
+        /// Install missing packages, replace conflicting versions and
+        /// reinstall corrupted packages
+        #[arg(long)]
+        fix: bool,
+        /// With --fix, list the repairs without making them
+        #[arg(long, requires = "fix")]
+        dry_run: bool,
+    },
+    
+    /// Show the resolved dependency tree
+    Tree,
+    
+    /// Explain why a package is part of the resolution
+    Why {
+        /// Package to trace back to cobra.toml
+        package: String,
+        /// Include [dev-dependencies]
+        #[arg(long)]
+        dev: bool,
+    },
+    
+    /// Manage the package cache
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+    
+    /// Manage the local metadata index
+    Index {
+        #[command(subcommand)]
+        command: IndexCommands,
+    },
+    
+    /// Run a command with the installed packages importable, e.g. `cobra run pytest`
+    Run {
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+    
+    /// Save a registry token in the OS credential store
+    Login {
+        registry_url: String,
+    },
+    
+    /// Remove a registry token from the OS credential store
+    Logout {
+        registry_url: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheCommands {
+    /// Remove cached metadata and package archives
+    Clear {
+        /// Only drop resolver metadata, keeping downloaded archives
+        #[arg(long, conflicts_with = "blobs_only")]
+        metadata_only: bool,
+        /// Only drop downloaded archives, keeping resolver metadata
+        #[arg(long)]
+        blobs_only: bool,
+    },
+    /// Remove stored wheels that no project's install directory links any more
+    Prune,
+    /// List downloaded archives kept in the wheel cache
+    ListWheels,
+    /// Remove archives from the wheel cache
+    PruneWheels {
+        /// Only remove archives no install has used for this many days
+        #[arg(long, value_name = "DAYS")]
+        older_than: Option<u64>,
+    },
+}
+
+#[derive(Subcommand)]
+enum IndexCommands {
+    /// Mirror version lists and requirements of packages for fast, offline resolution
+    Sync {
+        #[arg(required = true)]
+        packages: Vec<String>,
+    },
+}
+
+/// A token fired by the first Ctrl-C (or SIGTERM) so the command can wind
+/// down and clean up; a second one exits at once
+fn cancel_on_interrupt() -> CancellationToken {
+    let cancel = CancellationToken::new();
+    let token = cancel.clone();
+    tokio::spawn(async move {
+        if interrupted().await.is_ok() {
+            eprintln!("\n{} Interrupted, cleaning up (press Ctrl-C again to quit now)", "⚠".yellow());
+            token.cancel();
+            if interrupted().await.is_ok() {
+                std::process::exit(130);
+            }
+        }
+    });
+    cancel
+}
+
+/// Wait for Ctrl-C or, on unix, SIGTERM
+async fn interrupted() -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        let mut terminate = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result,
+            _ = terminate.recv() => Ok(()),
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await
 }
 
 #[tokio::main]
-async fn main() {
-    // Initialize tracing
+async fn main() -> ExitCode {
+    let start = Instant::now();
+    let cli = Cli::parse();
+    
+    // Initialize tracing; --verbose shows warnings such as request retries
+    let level = if cli.verbose { tracing::Level::DEBUG } else { tracing::Level::ERROR };
     tracing_subscriber::fmt()
         .with_env_filter(
             tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive(tracing::Level::INFO.into()),
+                .add_directive(level.into()),
         )
         .init();
     
-    let start = Instant::now();
-    let cli = Cli::parse();
-    
+    // The report owns stdout, so the summary stays off it
+    let report_to_stdout = matches!(&cli.command, Commands::Install { report: Some(path), .. } if path.as_os_str() == "-");
+
     let result = match cli.command {
         Commands::Init { path } => {
             cobra::cli::init::execute(&path).await
         }
-        Commands::Install { no_cache } => {
-            cobra::cli::install::execute(no_cache).await
+        Commands::Install { packages, requirements, save, no_cache, exclude_newer, resolution, frozen, dev, only_dev, offline, no_verify, max_rate, force_reinstall, upgrade, target, no_space_check, no_hooks, verify_imports, find_links, no_index, atomic, report, timings } => {
+            cobra::cli::install::execute(InstallOptions {
+                no_cache,
+                exclude_newer,
+                resolution,
+                frozen,
+                dependencies: DependencySelection::from_flags(dev, only_dev),
+                offline,
+                no_verify,
+                max_rate,
+                force_reinstall,
+                upgrade,
+                target,
+                no_space_check,
+                no_hooks,
+                verify_imports,
+                find_links,
+                no_index,
+                atomic,
+                packages,
+                requirements,
+                save,
+                report,
+                timings,
+                cancel: cancel_on_interrupt(),
+            }).await
         }
-        Commands::Add { packages } => {
-            cobra::cli::add::execute(packages).await
+        Commands::Add { packages, dev, path, exact, no_install } => {
+            let install = (!no_install).then(|| InstallOptions {
+                dependencies: DependencySelection::from_flags(dev, false),
+                cancel: cancel_on_interrupt(),
+                ..InstallOptions::default()
+            });
+            cobra::cli::add::execute(packages, dev, path, exact, install).await
         }
         Commands::Remove { packages } => {
             cobra::cli::remove::execute(packages).await
         }
-        Commands::Update { package } => {
-            cobra::cli::update::execute(package).await
+        Commands::Update { package, dry_run, latest, yes, patch_only, minor_only } => {
+            let max_bump = match (patch_only, minor_only) {
+                (true, _) => Some(Bump::Patch),
+                (_, true) => Some(Bump::Minor),
+                _ => None,
+            };
+            cobra::cli::update::execute(package, dry_run, latest, yes, max_bump).await
         }
-        Commands::List => {
-            cobra::cli::list::execute().await
+        Commands::List { target } => {
+            cobra::cli::list::execute(target).await
         }
         Commands::Show { package } => {
             cobra::cli::show::execute(package).await
@@ -115,18 +446,77 @@ async fn main() {
         Commands::Search { query, limit } => {
             cobra::cli::search::execute(query, Some(limit)).await
         }
+        Commands::Sync { dry_run, dev } => {
+            cobra::cli::sync::execute(dry_run, DependencySelection::from_flags(dev, false)).await
+        }
+        Commands::Status => {
+            cobra::cli::status::execute().await
+        }
+        Commands::Doctor => {
+            cobra::cli::doctor::execute().await
+        }
+        Commands::Clean { dry_run } => {
+            cobra::cli::clean::execute(dry_run).await
+        }
+        Commands::Verify { fix } => {
+            cobra::cli::verify::execute(fix).await
+        }
         Commands::Uninstall { packages } => {
             cobra::cli::uninstall::execute(packages).await
         }
         Commands::Freeze { output, format } => {
             cobra::cli::freeze::execute_with_format(output, Some(format)).await
         }
-        Commands::Check => {
-            cobra::cli::check::execute().await
+        Commands::Export { format, hashes, output } => {
+            cobra::cli::export::execute(format, hashes, output).await
+        }
+        Commands::Import { requirements, dev } => {
+            cobra::cli::import::execute(requirements, dev).await
+        }
+        Commands::Check { dev, fix, dry_run } => {
+            cobra::cli::check::execute(dev, fix, dry_run).await
+        }
+        Commands::Tree => {
+            cobra::cli::tree::execute().await
+        }
+        Commands::Why { package, dev } => {
+            cobra::cli::why::execute(package, dev).await
+        }
+        Commands::Cache { command: CacheCommands::Clear { metadata_only, blobs_only } } => {
+            cobra::cli::cache::clear(ClearScope::from_flags(metadata_only, blobs_only)).await
+        }
+        Commands::Cache { command: CacheCommands::Prune } => {
+            cobra::cli::cache::prune().await
+        }
+        Commands::Cache { command: CacheCommands::ListWheels } => {
+            cobra::cli::cache::list_wheels().await
+        }
+        Commands::Cache { command: CacheCommands::PruneWheels { older_than } } => {
+            cobra::cli::cache::prune_wheels(older_than).await
+        }
+        Commands::Index { command: IndexCommands::Sync { packages } } => {
+            cobra::cli::index::sync(packages).await
+        }
+        // The command's own output and exit code stand for cobra's
+        Commands::Run { command } => {
+            return match cobra::cli::run::execute(command).await {
+                Ok(code) => ExitCode::from(code),
+                Err(e) => {
+                    eprintln!("{} {}", "✗".red().bold(), e);
+                    ExitCode::FAILURE
+                }
+            };
+        }
+        Commands::Login { registry_url } => {
+            cobra::cli::login::execute(registry_url).await
+        }
+        Commands::Logout { registry_url } => {
+            cobra::cli::logout::execute(registry_url).await
         }
     };
     
     match result {
+        Ok(_) if report_to_stdout => ExitCode::SUCCESS,
         Ok(_) => {
             let elapsed = start.elapsed();
             println!(
@@ -134,10 +524,17 @@ async fn main() {
                 "✓".green().bold(),
                 elapsed.as_secs_f64()
             );
+            ExitCode::SUCCESS
+        }
+        Err(cobra::CobraError::Cancelled) => {
+            eprintln!("{} Interrupted, cleaned up", "✗".red().bold());
+            ExitCode::from(130)
         }
+        // Returning instead of exiting lets the runtime shut down and drop
+        // aborted tasks, cleaning up their temp files
         Err(e) => {
             eprintln!("{} {}", "✗".red().bold(), e);
-            std::process::exit(1);
+            ExitCode::FAILURE
         }
     }
 }
@@ -0,0 +1,58 @@
+use crate::{Result, Package};
+use crate::registry::client::{version_from_filename, Release, ReleaseFile};
+use async_trait::async_trait;
+use reqwest::Response;
+
+/// A package index the resolver and installer can work against: PyPI,
+/// a PyPI mirror or a PackageCloud repository
+#[async_trait]
+pub trait RegistryBackend: Send + Sync {
+    /// Metadata of the latest release for `*`, of the release an exact `==`
+    /// pin names, or of the newest release a range allows where the index
+    /// can list them
+    async fn get_package_info(&self, name: &str, version_spec: &str) -> Result<Package>;
+
+    /// GET a package file
+    async fn download(&self, url: &str) -> Result<Response>;
+
+    /// Metadata of one exact release
+    async fn get_package_version(&self, name: &str, version: &str) -> Result<Package> {
+        self.get_package_info(name, &format!("=={}", version)).await
+    }
+
+    /// Every release of a project with its files, oldest version first.
+    /// Indexes that can't list releases offer only the latest one.
+    async fn get_releases(&self, name: &str) -> Result<Vec<Release>> {
+        let latest = self.get_package_info(name, "*").await?;
+        let filename = latest.download_url.rsplit('/').next().unwrap_or_default().to_string();
+        let packagetype = version_from_filename(name, &filename).map_or("bdist_wheel", |(_, kind)| kind);
+        Ok(vec![Release {
+            version: latest.version.clone(),
+            files: vec![ReleaseFile {
+                filename,
+                url: latest.download_url,
+                packagetype: packagetype.to_string(),
+                size: latest.size,
+                sha256: latest.hash,
+                upload_time: None,
+                requires_python: latest.requires_python,
+                yanked: latest.yanked,
+                yanked_reason: latest.yanked_reason,
+            }],
+        }])
+    }
+
+    /// Download a package file from byte `offset` on. Backends without range
+    /// support answer 200 with the whole file, which callers start over from.
+    async fn download_package(&self, url: &str, offset: u64) -> Result<Response> {
+        let _ = offset;
+        self.download(url).await
+    }
+
+    /// Download bytes `start..=end` of a file, or the whole file with a 200
+    /// when the backend doesn't support ranges
+    async fn download_range(&self, url: &str, start: u64, end: u64) -> Result<Response> {
+        let _ = (start, end);
+        self.download(url).await
+    }
+}
@@ -1,31 +1,153 @@
-use crate::{Result, CobraError, Package, Dependency, constants::*};
-use reqwest::{Client, ClientBuilder, Response};
+use crate::{Result, CobraError, Package, Dependency, DependencySource, normalize_name, constants::*};
+use crate::core::resolver::{select_release, ResolutionStrategy};
+use crate::core::version::{Version, VersionSpec};
+use crate::registry::backend::RegistryBackend;
+use crate::registry::json::{releases_from_slice, ProjectDocument};
+use chrono::{DateTime, Utc};
+use reqwest::header::{ACCEPT, CONTENT_TYPE, RANGE, RETRY_AFTER};
+use reqwest::{Client, ClientBuilder, RequestBuilder, Response, StatusCode, Url};
 use std::time::Duration;
 
+/// A single distribution file belonging to a release
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ReleaseFile {
+    pub filename: String,
+    pub url: String,
+    pub packagetype: String,
+    pub size: Option<u64>,
+    pub sha256: Option<String>,
+    pub upload_time: Option<DateTime<Utc>>,
+    /// PEP 345 `Requires-Python` constraint of this file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requires_python: Option<String>,
+    /// PEP 592: only installable when pinned with `==`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub yanked: bool,
+    /// Why the file was yanked, when the index says
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub yanked_reason: Option<String>,
+}
+
+/// A published release of a project together with its files
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Release {
+    pub version: String,
+    pub files: Vec<ReleaseFile>,
+}
+
+impl Release {
+    /// Parsed PEP 440 version, `None` for non-conforming legacy versions
+    pub fn parsed_version(&self) -> Option<Version> {
+        Version::parse(&self.version).ok()
+    }
+
+    /// Drop files uploaded after `cutoff`; files without an upload time are kept
+    pub fn files_uploaded_before(&self, cutoff: &DateTime<Utc>) -> Vec<ReleaseFile> {
+        self.files
+            .iter()
+            .filter(|f| f.upload_time.is_none_or(|t| t <= *cutoff))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Media type of PEP 691 JSON Simple API responses
+pub const SIMPLE_JSON_V1: &str = "application/vnd.pypi.simple.v1+json";
+
+/// How long registry requests may take. Metadata requests fail fast, while
+/// archive downloads have no deadline unless one is configured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HttpTimeouts {
+    pub connect: Duration,
+    pub metadata: Duration,
+    pub download: Option<Duration>,
+}
+
+impl Default for HttpTimeouts {
+    fn default() -> Self {
+        Self { connect: CONNECT_TIMEOUT, metadata: METADATA_TIMEOUT, download: None }
+    }
+}
+
+/// How failed requests are retried: connection errors, timeouts and 5xx
+/// responses are retried with exponential backoff plus jitter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt
+    pub retries: u32,
+    /// Delay before the first retry, doubled for every further one
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { retries: MAX_RETRIES, base_delay: RETRY_BASE_DELAY }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry` (from 1), with up to one base delay of jitter
+    fn backoff(&self, retry: u32) -> Duration {
+        let exponential = self.base_delay.saturating_mul(1 << (retry - 1).min(16));
+        exponential + self.base_delay.mul_f64(fastrand::f64())
+    }
+}
+
+/// Longest `Retry-After` honored, so a misbehaving server can't stall an install
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
 /// High-performance HTTP client with connection pooling and HTTP/2
 pub struct RegistryClient {
     client: Client,
     pypi_base_url: String,
+    timeouts: HttpTimeouts,
+    retry: RetryPolicy,
 }
 
 impl RegistryClient {
     pub fn new() -> Self {
-        let client = Self::create_optimized_client();
+        Self::with_base_url("https://pypi.org".to_string())
+    }
+
+    /// Point the client at a PyPI-compatible index other than pypi.org
+    pub fn with_base_url(base_url: String) -> Self {
+        let timeouts = HttpTimeouts::default();
         Self {
-            client,
-            pypi_base_url: "https://pypi.org".to_string(),
+            client: Self::create_optimized_client(&timeouts),
+            pypi_base_url: base_url.trim_end_matches('/').to_string(),
+            timeouts,
+            retry: RetryPolicy::default(),
         }
     }
 
-    /// Create optimized HTTP client with aggressive performance settings
-    fn create_optimized_client() -> Client {
+    /// Root URL of the PyPI-compatible index
+    pub fn base_url(&self) -> &str {
+        &self.pypi_base_url
+    }
+
+    /// Retry failed requests according to `retry` instead of the default policy
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Use `timeouts` instead of the defaults
+    pub fn with_timeouts(mut self, timeouts: HttpTimeouts) -> Self {
+        self.client = Self::create_optimized_client(&timeouts);
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Create optimized HTTP client with aggressive performance settings.
+    /// Request deadlines are set per request, by kind.
+    fn create_optimized_client(timeouts: &HttpTimeouts) -> Client {
         ClientBuilder::new()
             .pool_max_idle_per_host(32)
             .pool_idle_timeout(Duration::from_secs(30))
             .tcp_keepalive(Duration::from_secs(60))
             .tcp_nodelay(true)
             .http1_title_case_headers()
-            .timeout(HTTP_TIMEOUT)
+            .connect_timeout(timeouts.connect)
             .user_agent("cobra/1.0 (blazingly-fast-python-package-manager)")
             .gzip(true)
             .brotli(true)
@@ -33,121 +155,121 @@ impl RegistryClient {
             .expect("Failed to create HTTP client")
     }
 
-    /// Get package information from PyPI
+    /// Get package information from PyPI: the latest release for `*`, the
+    /// release an exact `==` pin names, or the newest release a range allows
     pub async fn get_package_info(&self, name: &str, version_spec: &str) -> Result<Package> {
-        let url = if version_spec == "*" || version_spec.is_empty() {
-            format!("{}/pypi/{}/json", self.pypi_base_url, name)
-        } else {
-            // For specific versions, strip operators like ==, >=, etc.
-            let version = version_spec.trim_start_matches("==")
-                .trim_start_matches(">=")
-                .trim_start_matches("<=")
-                .trim_start_matches("~=")
-                .trim_start_matches("^")
-                .trim();
-            format!("{}/pypi/{}/{}/json", self.pypi_base_url, name, version)
-        };
+        let spec = version_spec.trim();
+        let pinned = spec.strip_prefix("==").unwrap_or(spec).trim();
+        if !pinned.is_empty() && Version::parse(pinned).is_ok() {
+            return self.get_package_version(name, pinned).await;
+        }
 
-        let response = self.client.get(&url)
-            .send()
-            .await?;
+        let url = format!("{}/pypi/{}/json", self.pypi_base_url, name);
+        let body = self.fetch_document(name, &url).await?;
+        let mut document = ProjectDocument::from_slice(&body)?;
+        let spec = VersionSpec::parse(spec)?;
+        if spec.is_any() {
+            return document.into_package(name);
+        }
+
+        // A range may exclude the latest release the top-level `info` and
+        // `urls` describe, so pick from every release's files instead
+        let releases = releases_from_slice(&body)?;
+        let (release, files) = select_release(&releases, &spec, None, None, ResolutionStrategy::Highest)
+            .ok_or_else(|| CobraError::PackageNotFound(format!("{} {}", name, version_spec)))?;
+        if document.info.version.as_deref() != Some(release.version.as_str()) {
+            // Requirements are only listed in the document of their own release
+            let url = format!("{}/pypi/{}/{}/json", self.pypi_base_url, name, release.version);
+            document = ProjectDocument::from_slice(&self.fetch_document(name, &url).await?)?;
+        }
+        document.urls = files;
+        document.into_package(name)
+    }
+
+    /// Get the metadata of one exact release
+    pub async fn get_package_version(&self, name: &str, version: &str) -> Result<Package> {
+        let url = format!("{}/pypi/{}/{}/json", self.pypi_base_url, name, version);
+        let body = self.fetch_document(name, &url).await?;
+        ProjectDocument::from_slice(&body)?.into_package(name)
+    }
+
+    /// List every release of a project with its files, oldest version first.
+    /// Uses the PEP 691 Simple API and falls back to the legacy JSON API on
+    /// indexes that don't serve it.
+    pub async fn get_releases(&self, name: &str) -> Result<Vec<Release>> {
+        if let Some(releases) = self.get_simple_releases(name).await? {
+            return Ok(releases);
+        }
 
+        let url = format!("{}/pypi/{}/json", self.pypi_base_url, name);
+        let body = self.fetch_document(name, &url).await?;
+        releases_from_slice(&body)
+    }
+
+    /// `/simple/<name>/` as PEP 691 JSON, `None` when the index only serves
+    /// HTML or doesn't know the page
+    async fn get_simple_releases(&self, name: &str) -> Result<Option<Vec<Release>>> {
+        let url = format!("{}/simple/{}/", self.pypi_base_url, normalize_name(name));
+        let response = self.send(&url, || {
+            self.client.get(&url)
+                .header(ACCEPT, SIMPLE_JSON_V1)
+                .timeout(self.timeouts.metadata)
+        }).await?;
+
+        if matches!(response.status(), StatusCode::NOT_FOUND | StatusCode::NOT_ACCEPTABLE) {
+            return Ok(None);
+        }
         if !response.status().is_success() {
             return Err(CobraError::PackageNotFound(name.to_string()));
         }
+        let is_json = response.headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with(SIMPLE_JSON_V1));
+        if !is_json {
+            return Ok(None);
+        }
 
+        let page_url = response.url().clone();
         let json: serde_json::Value = response.json().await?;
-        
-        // Parse package info
-        let info = &json["info"];
-        let version = info["version"].as_str()
-            .ok_or_else(|| CobraError::PackageNotFound(format!("Invalid package data for {}", name)))?
-            .to_string();
-
-        // Get download URL for wheel file (prefer wheels over source)
-        let urls = &json["urls"];
-        let mut download_url = String::new();
-        let mut size = None;
-        let mut hash = None;
-
-        if let Some(urls_array) = urls.as_array() {
-            // Prefer wheel files
-            for url_info in urls_array {
-                if url_info["packagetype"].as_str() == Some("bdist_wheel") {
-                    download_url = url_info["url"].as_str().unwrap_or("").to_string();
-                    size = url_info["size"].as_u64();
-                    if let Some(digests) = url_info["digests"].as_object() {
-                        hash = digests.get("sha256")
-                            .and_then(|v| v.as_str())
-                            .map(|s| s.to_string());
-                    }
-                    break;
-                }
-            }
+        Ok(Some(releases_from_simple_json(name, &page_url, &json)))
+    }
 
-            // Fallback to source distribution
-            if download_url.is_empty() {
-                for url_info in urls_array {
-                    if url_info["packagetype"].as_str() == Some("sdist") {
-                        download_url = url_info["url"].as_str().unwrap_or("").to_string();
-                        size = url_info["size"].as_u64();
-                        if let Some(digests) = url_info["digests"].as_object() {
-                            hash = digests.get("sha256")
-                                .and_then(|v| v.as_str())
-                                .map(|s| s.to_string());
-                        }
-                        break;
-                    }
-                }
-            }
-        }
+    /// Raw body of a JSON API document, parsed by the typed views in `registry::json`
+    async fn fetch_document(&self, name: &str, url: &str) -> Result<bytes::Bytes> {
+        let response = self.send(url, || self.client.get(url).timeout(self.timeouts.metadata)).await?;
 
-        if download_url.is_empty() {
-            return Err(CobraError::PackageNotFound(
-                format!("No download URL found for {}", name)
-            ));
+        if !response.status().is_success() {
+            return Err(CobraError::PackageNotFound(name.to_string()));
         }
 
-        // Parse dependencies
-        let mut dependencies = Vec::new();
-        if let Some(requires_dist) = info["requires_dist"].as_array() {
-            for dep in requires_dist {
-                if let Some(dep_str) = dep.as_str() {
-                    if let Some((dep_name, dep_version)) = parse_dependency(dep_str) {
-                        dependencies.push(Dependency {
-                            name: dep_name,
-                            version_spec: dep_version,
-                        });
-                    }
-                }
-            }
-        }
+        Ok(response.bytes().await?)
+    }
 
-        // Extract additional metadata
-        let description = info["summary"].as_str().map(|s| s.to_string());
-        let author = info["author"].as_str().map(|s| s.to_string());
-        let homepage = info["home_page"].as_str()
-            .or_else(|| info["project_url"].as_str())
-            .map(|s| s.to_string());
-
-        Ok(Package {
-            name: name.to_string(),
-            version,
-            dependencies,
-            download_url,
-            hash,
-            size,
-            description,
-            author,
-            homepage,
-        })
-    }
-
-    /// Download package file
-    pub async fn download_package(&self, url: &str) -> Result<Response> {
-        let response = self.client.get(url)
-            .send()
-            .await?;
+    /// Download package file, from byte `offset` on when resuming. Servers
+    /// that ignore the range answer 200 with the whole file.
+    pub async fn download_package(&self, url: &str, offset: u64) -> Result<Response> {
+        let range = (offset > 0).then(|| format!("bytes={}-", offset));
+        self.fetch_file(url, range).await
+    }
+
+    /// Download bytes `start..=end` of a file, one segment of a parallel
+    /// download. Servers that ignore the range answer 200 with the whole file.
+    pub async fn download_range(&self, url: &str, start: u64, end: u64) -> Result<Response> {
+        self.fetch_file(url, Some(format!("bytes={}-{}", start, end))).await
+    }
+
+    async fn fetch_file(&self, url: &str, range: Option<String>) -> Result<Response> {
+        let response = self.send(url, || {
+            let mut request = self.client.get(url);
+            if let Some(timeout) = self.timeouts.download {
+                request = request.timeout(timeout);
+            }
+            if let Some(range) = &range {
+                request = request.header(RANGE, range);
+            }
+            request
+        }).await?;
 
         if !response.status().is_success() {
             return Err(CobraError::InstallationFailed(
@@ -157,6 +279,79 @@ impl RegistryClient {
 
         Ok(response)
     }
+
+    /// Send the GET built by `request`, retrying per the retry policy. After
+    /// the last retry the error names the number of attempts.
+    async fn send(&self, url: &str, request: impl Fn() -> RequestBuilder) -> Result<Response> {
+        let mut attempt = 1;
+        loop {
+            let result = request().send().await;
+            let reason = match &result {
+                Ok(response) if response.status().is_server_error() => format!("HTTP {}", response.status()),
+                Err(e) if e.is_connect() || e.is_timeout() || e.is_request() => e.to_string(),
+                _ => return Ok(result?),
+            };
+
+            if attempt > self.retry.retries {
+                if attempt == 1 {
+                    return Ok(result?);
+                }
+                return Err(CobraError::RetriesExhausted {
+                    attempts: attempt,
+                    last_error: format!("{}: {}", url, reason),
+                });
+            }
+            let delay = result.as_ref().ok()
+                .and_then(retry_after)
+                .unwrap_or_else(|| self.retry.backoff(attempt));
+            tracing::warn!(
+                "{} failed ({}); retry {}/{} in {:.1}s",
+                url, reason, attempt, self.retry.retries, delay.as_secs_f64()
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// `Retry-After` as seconds or an HTTP date, capped at `MAX_RETRY_AFTER`
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+    let delay = match value.parse::<u64>() {
+        Ok(secs) => Duration::from_secs(secs),
+        Err(_) => (DateTime::parse_from_rfc2822(value).ok()?.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    };
+    Some(delay.min(MAX_RETRY_AFTER))
+}
+
+/// The default backend, talking to PyPI or any index serving its APIs
+#[async_trait::async_trait]
+impl RegistryBackend for RegistryClient {
+    async fn get_package_info(&self, name: &str, version_spec: &str) -> Result<Package> {
+        RegistryClient::get_package_info(self, name, version_spec).await
+    }
+
+    async fn download(&self, url: &str) -> Result<Response> {
+        self.fetch_file(url, None).await
+    }
+
+    async fn get_package_version(&self, name: &str, version: &str) -> Result<Package> {
+        RegistryClient::get_package_version(self, name, version).await
+    }
+
+    async fn get_releases(&self, name: &str) -> Result<Vec<Release>> {
+        RegistryClient::get_releases(self, name).await
+    }
+
+    async fn download_package(&self, url: &str, offset: u64) -> Result<Response> {
+        RegistryClient::download_package(self, url, offset).await
+    }
+
+    async fn download_range(&self, url: &str, start: u64, end: u64) -> Result<Response> {
+        RegistryClient::download_range(self, url, start, end).await
+    }
 }
 
 impl Default for RegistryClient {
@@ -165,27 +360,127 @@ impl Default for RegistryClient {
     }
 }
 
-/// Parse dependency string like "requests (>=2.0.0)" into (name, version_spec)
-fn parse_dependency(dep_str: &str) -> Option<(String, String)> {
-    // Skip extras and environment markers
-    let dep_str = dep_str.split(';').next()?.trim();
-    let dep_str = dep_str.split('[').next()?.trim();
-
-    if let Some(pos) = dep_str.find('(') {
-        let name = dep_str[..pos].trim().to_string();
-        let version = dep_str[pos+1..].trim_end_matches(')').trim().to_string();
-        Some((name, version))
-    } else if dep_str.contains("==") || dep_str.contains(">=") || dep_str.contains("<=") {
-        // Handle inline version specs
-        for op in &["==", ">=", "<=", "~=", "!="] {
-            if let Some(pos) = dep_str.find(op) {
-                let name = dep_str[..pos].trim().to_string();
-                let version = dep_str[pos..].trim().to_string();
-                return Some((name, version));
-            }
+/// `url` on `mirror`: the scheme, host and port are replaced by the mirror's
+/// root, which may carry a path of its own, so `https://files.example/packages/a.whl`
+/// on `https://mirror.corp/pypi` is `https://mirror.corp/pypi/packages/a.whl`
+pub fn mirror_url(url: &str, mirror: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let mut mirrored = format!("{}{}", mirror.trim_end_matches('/'), url.path());
+    if let Some(query) = url.query() {
+        mirrored.push('?');
+        mirrored.push_str(query);
+    }
+    Some(mirrored)
+}
+
+/// Group the files of a PEP 691 project page into releases by the version in
+/// their filename; relative file URLs are resolved against `page_url`
+pub fn releases_from_simple_json(name: &str, page_url: &Url, json: &serde_json::Value) -> Vec<Release> {
+    let mut files = Vec::new();
+    for file in json["files"].as_array().into_iter().flatten() {
+        let Some(filename) = file["filename"].as_str() else {
+            continue;
+        };
+        let Some((version, packagetype)) = version_from_filename(name, filename) else {
+            continue;
+        };
+        let Some(url) = file["url"].as_str().and_then(|u| page_url.join(u).ok()) else {
+            continue;
+        };
+
+        let release_file = ReleaseFile {
+            filename: filename.to_string(),
+            url: url.to_string(),
+            packagetype: packagetype.to_string(),
+            size: file["size"].as_u64(),
+            sha256: file["hashes"]["sha256"].as_str().map(|s| s.to_string()),
+            upload_time: file["upload-time"].as_str()
+                .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+                .map(|t| t.with_timezone(&Utc)),
+            requires_python: file["requires-python"].as_str().map(|s| s.to_string()),
+            // `yanked` is either a bool or the reason as a string
+            yanked: file["yanked"].as_bool().unwrap_or_else(|| file["yanked"].is_string()),
+            yanked_reason: file["yanked"].as_str().filter(|r| !r.is_empty()).map(|r| r.to_string()),
+        };
+        files.push((version, release_file));
+    }
+    group_releases(files)
+}
+
+/// Gather files into releases by their version, oldest version first
+pub(crate) fn group_releases(files: Vec<(String, ReleaseFile)>) -> Vec<Release> {
+    let mut by_version: Vec<Release> = Vec::new();
+    for (version, file) in files {
+        match by_version.iter_mut().find(|r| r.version == version) {
+            Some(release) => release.files.push(file),
+            None => by_version.push(Release { version, files: vec![file] }),
         }
-        None
-    } else {
-        Some((dep_str.to_string(), "*".to_string()))
     }
+    sort_releases(&mut by_version);
+    by_version
+}
+
+/// Version and package type encoded in a wheel or sdist filename
+pub fn version_from_filename(name: &str, filename: &str) -> Option<(String, &'static str)> {
+    if let Some(stem) = filename.strip_suffix(".whl") {
+        // {name}-{version}(-{build})?-{python}-{abi}-{platform}.whl
+        let version = stem.split('-').nth(1)?;
+        return Some((version.to_string(), "bdist_wheel"));
+    }
+
+    let stem = [".tar.gz", ".zip", ".tar.bz2"]
+        .iter()
+        .find_map(|ext| filename.strip_suffix(ext))?;
+    let (project, version) = stem.rsplit_once('-')?;
+    (normalize_name(project) == normalize_name(name)).then(|| (version.to_string(), "sdist"))
+}
+
+/// Oldest version first, falling back to string order for legacy versions
+pub(crate) fn sort_releases(releases: &mut [Release]) {
+    releases.sort_by(|a, b| match (a.parsed_version(), b.parsed_version()) {
+        (Some(va), Some(vb)) => va.cmp(&vb),
+        _ => a.version.cmp(&b.version),
+    });
+}
+
+/// Pick the file to install from a release: wheels first, then the sdist
+pub fn select_artifact(files: &[ReleaseFile]) -> Option<&ReleaseFile> {
+    files.iter()
+        .find(|f| f.packagetype == "bdist_wheel")
+        .or_else(|| files.iter().find(|f| f.packagetype == "sdist"))
+}
+
+/// Parse dependency string like "requests (>=2.0.0); extra == 'socks'"
+pub fn parse_dependency(dep_str: &str) -> Option<Dependency> {
+    // Environment markers are kept aside, not evaluated here
+    let (dep_str, markers) = match dep_str.split_once(';') {
+        Some((requirement, markers)) => (requirement.trim(), Some(markers.trim().to_string())),
+        None => (dep_str.trim(), None),
+    };
+    let markers = markers.filter(|m| !m.is_empty());
+
+    let name_end = dep_str
+        .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+        .unwrap_or(dep_str.len());
+    let name = dep_str[..name_end].trim();
+    if name.is_empty() {
+        return None;
+    }
+
+    // Skip extras like `requests[socks]`
+    let mut rest = dep_str[name_end..].trim();
+    if rest.starts_with('[') {
+        rest = rest.split_once(']').map(|(_, r)| r.trim()).unwrap_or("");
+    }
+
+    let version = rest.trim_start_matches('(').trim_end_matches(')').trim();
+    let version = if version.is_empty() { "*" } else { version };
+    Some(Dependency {
+        name: name.to_string(),
+        version_spec: version.replace(' ', ""),
+        markers,
+        source: DependencySource::Registry,
+        extras: Vec::new(),
+        optional: false,
+    })
 }
@@ -0,0 +1,175 @@
+//! Typed views of `/pypi/<name>[/<version>]/json` documents.
+//!
+//! Projects with thousands of releases produce multi-megabyte documents.
+//! Deserializing straight into these types keeps only the fields cobra uses
+//! and skips everything else (descriptions, classifiers, the `releases` map
+//! when only `urls` is wanted) while parsing, instead of building a full
+//! `serde_json::Value` tree first.
+
+use crate::{Result, CobraError, Package};
+use crate::registry::client::{parse_dependency, select_artifact, sort_releases, Release, ReleaseFile};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::de::{Deserializer, MapAccess, Visitor};
+use serde::Deserialize;
+use std::fmt;
+
+/// The `info` object, reduced to the fields cobra reads
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct ReleaseInfo {
+    pub version: Option<String>,
+    pub requires_python: Option<String>,
+    pub requires_dist: Option<Vec<String>>,
+    pub summary: Option<String>,
+    pub author: Option<String>,
+    pub home_page: Option<String>,
+    pub project_url: Option<String>,
+}
+
+/// A project or release document without its `releases` map
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProjectDocument {
+    #[serde(default)]
+    pub info: ReleaseInfo,
+    /// Files of the release the document describes
+    #[serde(default, deserialize_with = "release_files")]
+    pub urls: Vec<ReleaseFile>,
+}
+
+impl ProjectDocument {
+    pub fn from_slice(body: &[u8]) -> Result<Self> {
+        serde_json::from_slice(body).map_err(invalid_document)
+    }
+
+    /// Build the `Package` to install, preferring a wheel over the sdist
+    pub fn into_package(self, name: &str) -> Result<Package> {
+        let info = self.info;
+        let version = info.version
+            .ok_or_else(|| CobraError::PackageNotFound(format!("Invalid package data for {}", name)))?;
+        let artifact = select_artifact(&self.urls)
+            .ok_or_else(|| CobraError::PackageNotFound(
+                format!("No download URL found for {}", name)
+            ))?;
+
+        let dependencies = info.requires_dist
+            .iter()
+            .flatten()
+            .filter_map(|dep| parse_dependency(dep))
+            .collect();
+
+        Ok(Package {
+            name: name.to_string(),
+            version,
+            dependencies,
+            download_url: artifact.url.clone(),
+            hash: artifact.sha256.clone(),
+            size: artifact.size,
+            description: info.summary,
+            author: info.author,
+            homepage: info.home_page.or(info.project_url),
+            markers: None,
+            yanked: artifact.yanked,
+            yanked_reason: artifact.yanked_reason.clone(),
+            requires_python: info.requires_python.filter(|r| !r.is_empty()).or_else(|| artifact.requires_python.clone()),
+        })
+    }
+}
+
+/// Every release of a project document, oldest version first; `info` and
+/// `urls` are skipped
+pub fn releases_from_slice(body: &[u8]) -> Result<Vec<Release>> {
+    #[derive(Deserialize)]
+    struct ReleasesDocument {
+        #[serde(default, deserialize_with = "releases")]
+        releases: Vec<Release>,
+    }
+
+    let document: ReleasesDocument = serde_json::from_slice(body).map_err(invalid_document)?;
+    Ok(document.releases)
+}
+
+fn invalid_document(e: serde_json::Error) -> CobraError {
+    CobraError::PackageNotFound(format!("Invalid package data: {}", e))
+}
+
+/// One entry of `urls` or of a `releases` list
+#[derive(Deserialize)]
+struct FileEntry {
+    filename: Option<String>,
+    url: Option<String>,
+    packagetype: Option<String>,
+    size: Option<u64>,
+    digests: Option<Digests>,
+    upload_time_iso_8601: Option<String>,
+    upload_time: Option<String>,
+    requires_python: Option<String>,
+    yanked: Option<bool>,
+    yanked_reason: Option<String>,
+}
+
+/// PyPI also lists md5 and blake2b_256 digests; sha256 is the one verified
+#[derive(Deserialize)]
+struct Digests {
+    sha256: Option<String>,
+}
+
+impl FileEntry {
+    /// Entries without a URL can't be installed and are dropped
+    fn into_release_file(self) -> Option<ReleaseFile> {
+        let upload_time = self.upload_time_iso_8601.as_deref()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.with_timezone(&Utc))
+            .or_else(|| {
+                self.upload_time.as_deref()
+                    .and_then(|t| NaiveDateTime::parse_from_str(t, "%Y-%m-%dT%H:%M:%S").ok())
+                    .map(|t| t.and_utc())
+            });
+
+        Some(ReleaseFile {
+            filename: self.filename.unwrap_or_default(),
+            url: self.url?,
+            packagetype: self.packagetype.unwrap_or_default(),
+            size: self.size,
+            sha256: self.digests.and_then(|d| d.sha256),
+            upload_time,
+            requires_python: self.requires_python,
+            yanked: self.yanked.unwrap_or(false),
+            yanked_reason: self.yanked_reason.filter(|r| !r.is_empty()),
+        })
+    }
+}
+
+fn release_files<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<ReleaseFile>, D::Error> {
+    let files: Option<Vec<FileEntry>> = Deserialize::deserialize(deserializer)?;
+    Ok(files.into_iter().flatten().filter_map(FileEntry::into_release_file).collect())
+}
+
+/// Read the `releases` map one version at a time straight into `Release`s
+fn releases<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<Release>, D::Error> {
+    struct ReleasesVisitor;
+
+    impl<'de> Visitor<'de> for ReleasesVisitor {
+        type Value = Vec<Release>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a map of versions to release files")
+        }
+
+        fn visit_unit<E: serde::de::Error>(self) -> std::result::Result<Self::Value, E> {
+            Ok(Vec::new())
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<Self::Value, A::Error> {
+            let mut releases = Vec::with_capacity(map.size_hint().unwrap_or(0));
+            while let Some((version, files)) = map.next_entry::<String, Option<Vec<FileEntry>>>()? {
+                releases.push(Release {
+                    version,
+                    files: files.into_iter().flatten().filter_map(FileEntry::into_release_file).collect(),
+                });
+            }
+            sort_releases(&mut releases);
+            Ok(releases)
+        }
+    }
+
+    deserializer.deserialize_any(ReleasesVisitor)
+}
@@ -1,3 +1,6 @@
+pub mod backend;
 pub mod client;
+pub mod json;
 pub mod pypi;
 pub mod packagecloud;
+pub mod simple;
@@ -1,38 +1,50 @@
-use crate::{Result, CobraError, Package};
-use reqwest::Client;
+use crate::{Result, CobraError, Package, normalize_name};
+use crate::registry::backend::RegistryBackend;
+use crate::registry::client::parse_dependency;
+use crate::utils::credentials;
+use reqwest::{Client, Response};
+use std::sync::Arc;
+
+/// The hosted PackageCloud service
+pub const PACKAGECLOUD_URL: &str = "https://packagecloud.io";
 
 /// PackageCloud.io registry implementation (for custom/private packages)
+#[derive(Clone)]
 pub struct PackageCloudRegistry {
     client: Client,
     base_url: String,
     api_token: Option<String>,
+    /// Repository served when used as a `RegistryBackend`
+    repo: Option<String>,
 }
 
 impl PackageCloudRegistry {
+    /// packagecloud.io, authenticated with the token saved by `cobra login` if any
     pub fn new() -> Self {
-        Self {
-            client: Client::new(),
-            base_url: "https://packagecloud.io".to_string(),
-            api_token: None,
-        }
+        Self::with_custom_url(PACKAGECLOUD_URL.to_string(), None)
     }
 
     pub fn with_token(token: String) -> Self {
-        Self {
-            client: Client::new(),
-            base_url: "https://packagecloud.io".to_string(),
-            api_token: Some(token),
-        }
+        Self::with_custom_url(PACKAGECLOUD_URL.to_string(), Some(token))
     }
 
+    /// Without an inline `token`, the one saved by `cobra login <url>` is used
     pub fn with_custom_url(url: String, token: Option<String>) -> Self {
+        let api_token = token.or_else(|| credentials::lookup_token(&url));
         Self {
             client: Client::new(),
             base_url: url,
-            api_token: token,
+            api_token,
+            repo: None,
         }
     }
 
+    /// Serve packages from `repo` when used as a `RegistryBackend`
+    pub fn with_repo(mut self, repo: &str) -> Self {
+        self.repo = Some(repo.to_string());
+        self
+    }
+
     pub async fn get_package(&self, repo: &str, package_name: &str) -> Result<Package> {
         let url = format!("{}/api/v1/repos/{}/package/python/{}.json", 
             self.base_url, repo, package_name);
@@ -64,16 +76,29 @@ impl PackageCloudRegistry {
             .ok_or_else(|| CobraError::PackageNotFound(package_name.to_string()))?
             .to_string();
 
+        // PEP 508 requirement strings, under either key depending on the upload tool
+        let requirements = json["requires_dist"].as_array()
+            .or_else(|| json["dependencies"].as_array());
+        let dependencies = requirements.into_iter()
+            .flatten()
+            .filter_map(|dep| parse_dependency(dep.as_str()?))
+            .collect();
+
+        let text = |key: &str| json[key].as_str().map(str::to_string);
         Ok(Package {
             name,
             version,
-            dependencies: Vec::new(),
+            dependencies,
             download_url,
-            hash: None,
-            size: None,
-            description: None,
+            hash: text("sha256sum"),
+            size: json["size"].as_u64(),
+            description: text("description"),
             author: None,
             homepage: None,
+            markers: None,
+            yanked: false,
+            yanked_reason: None,
+            requires_python: None,
         })
     }
 
@@ -107,8 +132,138 @@ impl PackageCloudRegistry {
     }
 }
 
+/// The API only describes a package's current release, so an exact pin
+/// on any other version is not found
+#[async_trait::async_trait]
+impl RegistryBackend for PackageCloudRegistry {
+    async fn get_package_info(&self, name: &str, version_spec: &str) -> Result<Package> {
+        let repo = self.repo.as_deref().ok_or_else(|| CobraError::Config(
+            format!("No PackageCloud repository set for {}", self.base_url)
+        ))?;
+        let package = self.get_package(repo, name).await?;
+        match version_spec.strip_prefix("==").map(str::trim) {
+            Some(version) if version != package.version => {
+                Err(CobraError::PackageNotFound(format!("{}=={}", name, version)))
+            }
+            _ => Ok(package),
+        }
+    }
+
+    async fn download(&self, url: &str) -> Result<Response> {
+        let mut request = self.client.get(url);
+        if let Some(token) = &self.api_token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(CobraError::InstallationFailed(
+                format!("Failed to download: {}", response.status())
+            ));
+        }
+        Ok(response)
+    }
+}
+
 impl Default for PackageCloudRegistry {
     fn default() -> Self {
         Self::new()
     }
 }
+
+/// A private index and the package names it serves
+#[derive(Clone)]
+struct PrivateRepo {
+    backend: Arc<dyn RegistryBackend>,
+    /// Normalized name patterns; `*` matches any run of characters
+    patterns: Vec<String>,
+}
+
+/// PackageCloud repositories and simple indexes consulted before PyPI. A package matching a
+/// repository's patterns only ever comes from that repository; any other
+/// package is tried in the default repository, then on PyPI.
+#[derive(Clone, Default)]
+pub struct PrivateRegistries {
+    repos: Vec<PrivateRepo>,
+    default: Option<usize>,
+}
+
+impl PrivateRegistries {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve packages matching `patterns` from `repo`; a `default` repository
+    /// is also tried for packages no pattern claims
+    pub fn with_repo(self, registry: PackageCloudRegistry, repo: &str, patterns: &[String], default: bool) -> Self {
+        self.with_backend(Arc::new(registry.with_repo(repo)), patterns, default)
+    }
+
+    /// Like `with_repo`, for any index, e.g. a PEP 503 `SimpleIndex`
+    pub fn with_backend(mut self, backend: Arc<dyn RegistryBackend>, patterns: &[String], default: bool) -> Self {
+        if default {
+            self.default = Some(self.repos.len());
+        }
+        self.repos.push(PrivateRepo {
+            backend,
+            patterns: patterns.iter().map(|p| normalize_name(p)).collect(),
+        });
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.repos.is_empty()
+    }
+
+    /// Whether a repository's patterns claim `name`, so only it may serve it
+    pub fn claims(&self, name: &str) -> bool {
+        self.claimer(&normalize_name(name)).is_some()
+    }
+
+    /// Whether a default repository is tried for unclaimed packages
+    pub fn has_default(&self) -> bool {
+        self.default.is_some()
+    }
+
+    fn claimer(&self, normalized: &str) -> Option<&PrivateRepo> {
+        self.repos.iter().find(|r| r.patterns.iter().any(|p| matches_pattern(p, normalized)))
+    }
+
+    /// Metadata of `name` from the private repository serving it; `None`
+    /// when PyPI should answer instead. PackageCloud only reports its newest
+    /// release, while simple indexes pick one matching `version_spec`.
+    pub async fn get_package(&self, name: &str, version_spec: &str) -> Result<Option<Package>> {
+        let normalized = normalize_name(name);
+        if let Some(claimed) = self.claimer(&normalized) {
+            return claimed.backend.get_package_info(name, version_spec).await.map(Some);
+        }
+
+        let Some(default) = self.default.map(|i| &self.repos[i]) else {
+            return Ok(None);
+        };
+        match default.backend.get_package_info(name, version_spec).await {
+            Ok(package) => Ok(Some(package)),
+            Err(CobraError::PackageNotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Whether `name` matches `pattern`, where each `*` stands for any run of characters
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
@@ -1,5 +1,7 @@
-use crate::{Result, CobraError};
-use reqwest::Client;
+use crate::{Result, CobraError, Package};
+use crate::registry::backend::RegistryBackend;
+use crate::registry::json::ProjectDocument;
+use reqwest::{Client, Response};
 
 /// PyPI registry implementation
 pub struct PyPIRegistry {
@@ -52,6 +54,33 @@ impl PyPIRegistry {
     }
 }
 
+/// A plain PyPI JSON API client, e.g. for a mirror, without the retries
+/// and Simple API support of `RegistryClient`
+#[async_trait::async_trait]
+impl RegistryBackend for PyPIRegistry {
+    async fn get_package_info(&self, name: &str, version_spec: &str) -> Result<Package> {
+        let url = match version_spec.trim_start_matches("==").trim() {
+            "*" | "" => format!("{}/pypi/{}/json", self.base_url, name),
+            version => format!("{}/pypi/{}/{}/json", self.base_url, name, version),
+        };
+        let response = self.client.get(&url).send().await?;
+        if !response.status().is_success() {
+            return Err(CobraError::PackageNotFound(name.to_string()));
+        }
+        ProjectDocument::from_slice(&response.bytes().await?)?.into_package(name)
+    }
+
+    async fn download(&self, url: &str) -> Result<Response> {
+        let response = self.client.get(url).send().await?;
+        if !response.status().is_success() {
+            return Err(CobraError::InstallationFailed(
+                format!("Failed to download: {}", response.status())
+            ));
+        }
+        Ok(response)
+    }
+}
+
 impl Default for PyPIRegistry {
     fn default() -> Self {
         Self::new()
@@ -0,0 +1,313 @@
+//! PEP 503 simple repository pages, for indexes that don't serve PyPI's JSON
+//! API (devpi, GitLab's package registry, a directory behind nginx, ...).
+//! Indexes answering with PEP 691 JSON are read through the same client.
+
+use crate::{Result, CobraError, Package, Dependency, normalize_name};
+use crate::core::build::{parse_core_metadata, read_wheel_metadata};
+use crate::core::resolver::{select_release, ResolutionStrategy};
+use crate::core::version::VersionSpec;
+use crate::registry::backend::RegistryBackend;
+use crate::registry::client::{group_releases, releases_from_simple_json, version_from_filename, select_artifact, Release, ReleaseFile, SIMPLE_JSON_V1};
+use crate::utils::credentials;
+use reqwest::header::{ACCEPT, CONTENT_TYPE, RANGE};
+use reqwest::{Client, RequestBuilder, Response, Url};
+
+/// A simple index rooted at `<url>`, with project pages at `<url>/<name>/`
+#[derive(Clone)]
+pub struct SimpleIndex {
+    client: Client,
+    base_url: String,
+    token: Option<String>,
+}
+
+impl SimpleIndex {
+    /// Without an inline `token`, the one saved by `cobra login <url>` is used
+    pub fn new(url: String, token: Option<String>) -> Self {
+        let token = token.or_else(|| credentials::lookup_token(&url));
+        Self {
+            client: Client::new(),
+            base_url: url.trim_end_matches('/').to_string(),
+            token,
+        }
+    }
+
+    fn get(&self, url: &str) -> RequestBuilder {
+        let request = self.client.get(url);
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    /// Every release on the project's page, oldest version first
+    pub async fn releases(&self, name: &str) -> Result<Vec<Release>> {
+        let url = format!("{}/{}/", self.base_url, normalize_name(name));
+        let response = self.get(&url)
+            .header(ACCEPT, format!("{}, text/html;q=0.1", SIMPLE_JSON_V1))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(CobraError::PackageNotFound(name.to_string()));
+        }
+
+        let page_url = response.url().clone();
+        let is_json = response.headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with(SIMPLE_JSON_V1));
+        if is_json {
+            let json: serde_json::Value = response.json().await?;
+            return Ok(releases_from_simple_json(name, &page_url, &json));
+        }
+        Ok(releases_from_simple_html(name, &page_url, &response.text().await?))
+    }
+
+    /// `Package` for one release, with requirements read from the file it installs
+    async fn package(&self, name: &str, version: &str, files: &[ReleaseFile]) -> Result<Package> {
+        let artifact = select_artifact(files)
+            .ok_or_else(|| CobraError::PackageNotFound(format!("No download URL found for {} {}", name, version)))?;
+        Ok(Package {
+            name: name.to_string(),
+            version: version.to_string(),
+            dependencies: self.requirements(artifact).await?,
+            download_url: artifact.url.clone(),
+            hash: artifact.sha256.clone(),
+            size: artifact.size,
+            description: None,
+            author: None,
+            homepage: None,
+            markers: None,
+            yanked: artifact.yanked,
+            yanked_reason: artifact.yanked_reason.clone(),
+            requires_python: artifact.requires_python.clone(),
+        })
+    }
+
+    /// Requirements from the PEP 658 `.metadata` file next to `file`, or from
+    /// the wheel itself when the index doesn't serve one. An sdist's are only
+    /// known once it is built.
+    async fn requirements(&self, file: &ReleaseFile) -> Result<Vec<Dependency>> {
+        let url = file.url.split('#').next().unwrap_or(&file.url);
+        let metadata = self.get(&format!("{}.metadata", url)).send().await;
+        if let Ok(response) = metadata
+            && response.status().is_success() {
+            return Ok(parse_core_metadata(&response.text().await?).requires);
+        }
+        if file.packagetype != "bdist_wheel" {
+            return Ok(Vec::new());
+        }
+        let wheel = self.download(&file.url).await?.bytes().await?;
+        Ok(read_wheel_metadata(std::io::Cursor::new(wheel), &file.filename)?.requires)
+    }
+
+    async fn fetch_file(&self, url: &str, range: Option<String>) -> Result<Response> {
+        let mut request = self.get(url);
+        if let Some(range) = range {
+            request = request.header(RANGE, range);
+        }
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(CobraError::InstallationFailed(
+                format!("Failed to download: {}", response.status())
+            ));
+        }
+        Ok(response)
+    }
+}
+
+#[async_trait::async_trait]
+impl RegistryBackend for SimpleIndex {
+    /// The newest release matching `version_spec`, which may be any range
+    async fn get_package_info(&self, name: &str, version_spec: &str) -> Result<Package> {
+        let releases = self.releases(name).await?;
+        let spec = VersionSpec::parse(version_spec)?;
+        let (release, files) = select_release(&releases, &spec, None, None, ResolutionStrategy::Highest)
+            .ok_or_else(|| CobraError::PackageNotFound(format!("{} {}", name, version_spec)))?;
+        self.package(name, &release.version, &files).await
+    }
+
+    async fn download(&self, url: &str) -> Result<Response> {
+        self.fetch_file(url, None).await
+    }
+
+    async fn get_package_version(&self, name: &str, version: &str) -> Result<Package> {
+        let releases = self.releases(name).await?;
+        let release = releases.iter()
+            .find(|release| release.version == version)
+            .ok_or_else(|| CobraError::PackageNotFound(format!("{}=={}", name, version)))?;
+        self.package(name, version, &release.files).await
+    }
+
+    async fn get_releases(&self, name: &str) -> Result<Vec<Release>> {
+        self.releases(name).await
+    }
+
+    async fn download_package(&self, url: &str, offset: u64) -> Result<Response> {
+        self.fetch_file(url, (offset > 0).then(|| format!("bytes={}-", offset))).await
+    }
+
+    async fn download_range(&self, url: &str, start: u64, end: u64) -> Result<Response> {
+        self.fetch_file(url, Some(format!("bytes={}-{}", start, end))).await
+    }
+}
+
+/// Releases listed by the anchors of a PEP 503 project page. Versions come
+/// from the filenames, sha256 digests from `#sha256=` URL fragments, and
+/// `data-requires-python`/`data-yanked` are kept per file. Relative links
+/// are resolved against `page_url`.
+pub fn releases_from_simple_html(name: &str, page_url: &Url, html: &str) -> Vec<Release> {
+    let mut files = Vec::new();
+    for anchor in anchors(html) {
+        let Some(mut url) = anchor.attribute("href").and_then(|href| page_url.join(href).ok()) else {
+            continue;
+        };
+        let sha256 = url.fragment()
+            .and_then(|fragment| fragment.strip_prefix("sha256="))
+            .map(|digest| digest.to_ascii_lowercase());
+        url.set_fragment(None);
+        let filename = match anchor.text.trim() {
+            "" => url.path_segments().and_then(|mut segments| segments.next_back()).unwrap_or_default().to_string(),
+            text => text.to_string(),
+        };
+        let Some((version, packagetype)) = version_from_filename(name, &filename) else {
+            continue;
+        };
+
+        let yanked = anchor.attribute("data-yanked");
+        files.push((version, ReleaseFile {
+            filename,
+            url: url.to_string(),
+            packagetype: packagetype.to_string(),
+            size: None,
+            sha256,
+            upload_time: None,
+            requires_python: anchor.attribute("data-requires-python").map(str::to_string),
+            yanked: yanked.is_some(),
+            yanked_reason: yanked.filter(|reason| !reason.is_empty()).map(str::to_string),
+        }));
+    }
+    group_releases(files)
+}
+
+/// An `<a>` element: its unescaped attributes and text
+struct Anchor {
+    attributes: Vec<(String, String)>,
+    text: String,
+}
+
+impl Anchor {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+}
+
+/// Every `<a ...>text</a>` in `html`. Enough HTML for index pages, which
+/// are generated and flat; quoted attribute values may contain `>`.
+fn anchors(html: &str) -> Vec<Anchor> {
+    let lower = html.to_ascii_lowercase();
+    let mut anchors = Vec::new();
+    let mut at = 0;
+    while let Some(found) = lower[at..].find("<a") {
+        let start = at + found + 2;
+        at = start;
+        if !lower[start..].starts_with(|c: char| c.is_ascii_whitespace() || c == '>') {
+            continue;
+        }
+
+        let (attributes, tag_end) = parse_attributes(html, start);
+        let text_end = lower[tag_end..].find("</a").map_or(html.len(), |i| tag_end + i);
+        anchors.push(Anchor { attributes, text: unescape(&html[tag_end..text_end]) });
+        at = text_end;
+    }
+    anchors
+}
+
+/// Attributes of the tag whose name ends at `at`, and where its content starts
+fn parse_attributes(html: &str, mut at: usize) -> (Vec<(String, String)>, usize) {
+    let bytes = html.as_bytes();
+    let mut attributes = Vec::new();
+    loop {
+        while at < bytes.len() && (bytes[at].is_ascii_whitespace() || bytes[at] == b'/') {
+            at += 1;
+        }
+        if at >= bytes.len() {
+            return (attributes, at);
+        }
+        if bytes[at] == b'>' {
+            return (attributes, at + 1);
+        }
+
+        let name_start = at;
+        while at < bytes.len() && !matches!(bytes[at], b'=' | b'>' | b'/') && !bytes[at].is_ascii_whitespace() {
+            at += 1;
+        }
+        let name = html[name_start..at].to_ascii_lowercase();
+        while at < bytes.len() && bytes[at].is_ascii_whitespace() {
+            at += 1;
+        }
+        if at >= bytes.len() || bytes[at] != b'=' {
+            // A bare attribute such as `data-yanked`
+            attributes.push((name, String::new()));
+            continue;
+        }
+        at += 1;
+        while at < bytes.len() && bytes[at].is_ascii_whitespace() {
+            at += 1;
+        }
+
+        let value = match bytes.get(at) {
+            Some(&quote @ (b'"' | b'\'')) => {
+                let end = html[at + 1..].find(quote as char).map_or(html.len(), |i| at + 1 + i);
+                let value = &html[at + 1..end];
+                at = (end + 1).min(html.len());
+                value
+            }
+            _ => {
+                let start = at;
+                while at < bytes.len() && bytes[at] != b'>' && !bytes[at].is_ascii_whitespace() {
+                    at += 1;
+                }
+                &html[start..at]
+            }
+        };
+        attributes.push((name, unescape(value)));
+    }
+}
+
+/// Decode the character references index pages use (`&gt;=3.8`, `&amp;`, `&#39;`)
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest.find(';').and_then(|semi| {
+            let entity = &rest[1..semi];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ => match entity.strip_prefix('#') {
+                    Some(hex) if hex.starts_with(['x', 'X']) => u32::from_str_radix(&hex[1..], 16).ok().and_then(char::from_u32),
+                    Some(decimal) => decimal.parse().ok().and_then(char::from_u32),
+                    None => None,
+                },
+            }?;
+            Some((c, semi + 1))
+        });
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
@@ -0,0 +1,46 @@
+//! Registry tokens kept in the OS credential store, keyed by registry URL
+
+use crate::{Result, CobraError};
+use keyring::Entry;
+
+/// Keyring service that every cobra token is stored under
+const SERVICE: &str = "cobra";
+
+/// Spellings of a registry URL differing only in case or a trailing slash share one entry
+fn entry(registry_url: &str) -> keyring::Result<Entry> {
+    let account = registry_url.trim().trim_end_matches('/').to_ascii_lowercase();
+    Entry::new(SERVICE, &account)
+}
+
+fn store_error(registry_url: &str, e: keyring::Error) -> CobraError {
+    CobraError::Credentials(format!("{}: {}", registry_url, e))
+}
+
+/// Token saved for `registry_url`; `None` if there is none or no credential
+/// store is available, so public registries keep working without one
+pub fn lookup_token(registry_url: &str) -> Option<String> {
+    match entry(registry_url).and_then(|entry| entry.get_password()) {
+        Ok(token) => Some(token),
+        Err(keyring::Error::NoEntry) => None,
+        Err(e) => {
+            tracing::debug!("No credential store for {}: {}", registry_url, e);
+            None
+        }
+    }
+}
+
+/// Save `token` for `registry_url`, replacing any previous one
+pub fn store_token(registry_url: &str, token: &str) -> Result<()> {
+    entry(registry_url)
+        .and_then(|entry| entry.set_password(token))
+        .map_err(|e| store_error(registry_url, e))
+}
+
+/// Remove the token saved for `registry_url`; `false` if there was none
+pub fn delete_token(registry_url: &str) -> Result<bool> {
+    match entry(registry_url).and_then(|entry| entry.delete_password()) {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(store_error(registry_url, e)),
+    }
+}
@@ -2,6 +2,7 @@ use std::path::{Path, PathBuf};
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
 use crate::{Result, CobraError};
+use crate::core::installer::format_bytes;
 
 /// Atomic write operation - write to temp file then rename
 pub async fn atomic_write(path: &Path, contents: &[u8]) -> Result<()> {
@@ -10,19 +11,38 @@ pub async fn atomic_write(path: &Path, contents: &[u8]) -> Result<()> {
             std::io::ErrorKind::NotFound,
             "Parent directory not found"
         )))?;
+    // A bare file name lives in the current directory
+    let parent = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
     
     fs::create_dir_all(parent).await?;
     
-    // Write to temporary file first
-    let temp_path = parent.join(format!(".{}.tmp", 
-        path.file_name().unwrap().to_string_lossy()));
+    // Write to temporary file first, unique so concurrent writers never share one
+    let temp_path = tempfile::Builder::new()
+        .prefix(&format!(".{}.", path.file_name().unwrap().to_string_lossy()))
+        .suffix(".tmp")
+        .tempfile_in(parent)?
+        .into_temp_path();
     
-    let mut file = fs::File::create(&temp_path).await?;
+    let mut file = fs::OpenOptions::new().write(true).open(&temp_path).await?;
     file.write_all(contents).await?;
     file.sync_all().await?;
     
-    // Atomic rename
-    fs::rename(temp_path, path).await?;
+    // Atomic rename, made durable by flushing the directory entry too
+    fs::rename(&temp_path, path).await?;
+    let _ = temp_path.keep();
+    sync_dir(parent).await
+}
+
+/// Flush a directory's entries to disk so a crash can't undo a rename in it
+#[cfg(unix)]
+async fn sync_dir(dir: &Path) -> Result<()> {
+    fs::File::open(dir).await?.sync_all().await?;
+    Ok(())
+}
+
+/// Directories can't be opened for syncing here; NTFS journals the rename itself
+#[cfg(not(unix))]
+async fn sync_dir(_dir: &Path) -> Result<()> {
     Ok(())
 }
 
@@ -58,6 +78,230 @@ pub fn get_cache_dir() -> Result<PathBuf> {
     Ok(cache_dir)
 }
 
+/// Partial downloads that a later run resumes
+pub fn get_download_dir() -> Result<PathBuf> {
+    Ok(get_cache_dir()?.join("downloads"))
+}
+
+/// Remove files directly in `dir` last modified over `max_age` ago, such as
+/// downloads orphaned by a crash. Files another process holds locked are
+/// kept. Returns how many were removed.
+pub async fn remove_stale_files(dir: &Path, max_age: std::time::Duration) -> Result<usize> {
+    let mut removed = 0;
+    let mut entries = match fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+    while let Some(entry) = entries.next_entry().await? {
+        let metadata = entry.metadata().await?;
+        let stale = metadata.modified()?.elapsed().is_ok_and(|age| age > max_age);
+        if !metadata.is_file() || !stale {
+            continue;
+        }
+        let in_use = std::fs::File::open(entry.path())
+            .map(|file| file.try_lock().is_err())
+            .unwrap_or(false);
+        if !in_use && fs::remove_file(entry.path()).await.is_ok() {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Remove directories directly in `dir` last modified over `max_age` ago,
+/// such as staging directories of an install that was killed. Returns how
+/// many were removed.
+pub async fn remove_stale_dirs(dir: &Path, max_age: std::time::Duration) -> Result<usize> {
+    let mut removed = 0;
+    let mut entries = match fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+    while let Some(entry) = entries.next_entry().await? {
+        let metadata = entry.metadata().await?;
+        let stale = metadata.modified()?.elapsed().is_ok_and(|age| age > max_age);
+        if metadata.is_dir() && stale && fs::remove_dir_all(entry.path()).await.is_ok() {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Move everything in `from` into `to`, merging into directories that
+/// already exist there, such as a namespace package or `bin`, and replacing
+/// files. Renames within one filesystem, so each entry appears whole.
+pub fn move_into_place(from: &Path, to: &Path) -> Result<()> {
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        let is_dir = entry.file_type()?.is_dir();
+        match target.symlink_metadata() {
+            Ok(existing) if is_dir && existing.is_dir() => move_into_place(&entry.path(), &target)?,
+            Ok(existing) => {
+                if existing.is_dir() {
+                    std::fs::remove_dir_all(&target)?;
+                } else {
+                    std::fs::remove_file(&target)?;
+                }
+                std::fs::rename(entry.path(), &target)?;
+            }
+            Err(_) => std::fs::rename(entry.path(), &target)?,
+        }
+    }
+    Ok(())
+}
+
+/// Bytes available to this user on the filesystem holding `path`, which
+/// need not exist yet
+pub fn available_space(path: &Path) -> Result<u64> {
+    Ok(fs2::available_space(existing_ancestor(path))?)
+}
+
+/// Whether `a` and `b`, which need not exist yet, are on the same filesystem.
+/// Only known on unix; elsewhere they are assumed to differ.
+pub fn same_filesystem(a: &Path, b: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let device = |path: &Path| std::fs::metadata(existing_ancestor(path)).map(|m| m.dev()).ok();
+        device(a).is_some_and(|dev| device(b) == Some(dev))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (a, b);
+        false
+    }
+}
+
+/// Check that each filesystem has room for the bytes to be written to the
+/// `needs` locations on it, listing every one that falls short
+pub fn check_free_space(needs: &[(PathBuf, u64)]) -> Result<()> {
+    // Locations sharing a filesystem draw from the same free space
+    let mut filesystems: Vec<(Vec<&Path>, u64)> = Vec::new();
+    for (path, bytes) in needs.iter().filter(|(_, bytes)| *bytes > 0) {
+        match filesystems.iter_mut().find(|(paths, _)| same_filesystem(paths[0], path)) {
+            Some((paths, required)) => {
+                paths.push(path);
+                *required += bytes;
+            }
+            None => filesystems.push((vec![path], *bytes)),
+        }
+    }
+
+    let mut shortfalls = Vec::new();
+    for (paths, required) in filesystems {
+        let available = available_space(paths[0])?;
+        if required > available {
+            let locations: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+            shortfalls.push(format!(
+                "{}: needs {}, {} available",
+                locations.join(" and "),
+                format_bytes(required),
+                format_bytes(available)
+            ));
+        }
+    }
+    if shortfalls.is_empty() {
+        Ok(())
+    } else {
+        Err(CobraError::InsufficientSpace { shortfalls })
+    }
+}
+
+/// `path` or its closest ancestor that exists
+fn existing_ancestor(path: &Path) -> &Path {
+    path.ancestors().find(|p| p.exists()).unwrap_or(Path::new("."))
+}
+
+/// Whether files can be created in `dir`, or in its nearest existing ancestor
+/// when it has not been created yet
+pub fn is_writable(dir: &Path) -> bool {
+    dir.ancestors()
+        .find(|ancestor| ancestor.exists())
+        .is_some_and(|existing| tempfile::tempfile_in(existing).is_ok())
+}
+
+/// Fail before any work is done when packages can't be written to `dir`
+pub fn ensure_writable(dir: &Path) -> Result<()> {
+    if is_writable(dir) {
+        return Ok(());
+    }
+    Err(CobraError::InstallationFailed(format!(
+        "Install directory {} is not writable; point install-dir in cobra.toml or COBRA_INSTALL_DIR somewhere you can write to",
+        dir.display()
+    )))
+}
+
+/// Expand a leading `~` and `$VAR`, `${VAR}` or `%VAR%` references in a
+/// configured path, looking variables up through `env`. A reference to an
+/// unset variable is an error rather than an empty string.
+pub fn expand_path(value: &str, env: impl Fn(&str) -> Option<String>) -> Result<PathBuf> {
+    let lookup = |reference: &str, name: &str| env(name).ok_or_else(|| CobraError::Config(format!(
+        "'{}' refers to {}, which is not set", value, reference
+    )));
+    let is_name = |c: char| c.is_ascii_alphanumeric() || c == '_';
+
+    let mut expanded = String::new();
+    let mut rest = value;
+    if let Some(after) = rest.strip_prefix('~')
+        && (after.is_empty() || after.starts_with(['/', '\\'])) {
+        let home = env("HOME").or_else(|| env("USERPROFILE"));
+        expanded.push_str(&home.ok_or_else(|| CobraError::Config(format!(
+            "'{}' starts with ~, but neither HOME nor USERPROFILE is set", value
+        )))?);
+        rest = after;
+    }
+
+    while let Some(at) = rest.find(['$', '%']) {
+        expanded.push_str(&rest[..at]);
+        let (sigil, tail) = rest[at..].split_at(1);
+        let reference = if sigil == "%" {
+            tail.find('%')
+                .filter(|&end| end > 0 && tail[..end].chars().all(|c| is_name(c) || "()".contains(c)))
+                .map(|end| (&rest[at..at + end + 2], &tail[..end]))
+        } else if let Some(braced) = tail.strip_prefix('{') {
+            braced.find('}').filter(|&end| end > 0).map(|end| (&rest[at..at + end + 3], &braced[..end]))
+        } else {
+            let end = tail.find(|c: char| !is_name(c)).unwrap_or(tail.len());
+            (end > 0).then(|| (&rest[at..at + end + 1], &tail[..end]))
+        };
+        match reference {
+            Some((reference, name)) => {
+                expanded.push_str(&lookup(reference, name)?);
+                rest = &rest[at + reference.len()..];
+            }
+            // A lone `$` or `%` is part of the path
+            None => {
+                expanded.push_str(sigil);
+                rest = tail;
+            }
+        }
+    }
+    expanded.push_str(rest);
+    Ok(PathBuf::from(expanded))
+}
+
+/// `file://` URL of an absolute path
+pub fn file_url(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/").replace(' ', "%20");
+    if path.starts_with('/') {
+        format!("file://{}", path)
+    } else {
+        format!("file:///{}", path)
+    }
+}
+
+/// The path a `file://` URL made by [`file_url`] points at, ignoring any
+/// `#sha256=` fragment; `None` for other URLs
+pub fn file_url_path(url: &str) -> Option<PathBuf> {
+    let path = url.strip_prefix("file://")?.split('#').next()?.replace("%20", " ");
+    // Windows drive paths come as `file:///C:/...`
+    let windows_drive = path.len() > 2 && path.as_bytes()[2] == b':';
+    Some(PathBuf::from(if windows_drive { &path[1..] } else { &path[..] }))
+}
+
 /// Get config directory for Cobra
 pub fn get_config_dir() -> Result<PathBuf> {
     let config_dir = dirs::config_dir()
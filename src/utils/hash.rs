@@ -1,13 +1,79 @@
 use blake3::Hasher;
 use sha2::{Sha256, Digest};
+use std::fmt;
 use std::path::Path;
 use tokio::io::AsyncReadExt;
-use crate::Result;
+use crate::{Result, CobraError};
 
-/// Verify package hash using BLAKE3 (faster) or SHA256
+/// Digest algorithms a package hash can be checked with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgo {
+    /// What PyPI and simple indexes publish
+    Sha256,
+    Blake3,
+}
+
+impl HashAlgo {
+    /// The algorithm of `expected` and its bare hex digest. A `sha256:` or
+    /// `blake3:` prefix (or `=`, as in URL fragments) names it; an unprefixed
+    /// 64-digit digest is PyPI's sha256. md5 digests are refused, since an
+    /// index that publishes md5 also publishes sha256.
+    pub fn of_digest(expected: &str) -> Result<(Self, &str)> {
+        let expected = expected.trim();
+        let prefixed = strip_algorithm(expected, "sha256").map(|digest| (HashAlgo::Sha256, digest))
+            .or_else(|| strip_algorithm(expected, "blake3").map(|digest| (HashAlgo::Blake3, digest)));
+        let (algo, digest) = prefixed.unwrap_or((HashAlgo::Sha256, expected));
+
+        let is_hex = |len| digest.len() == len && digest.bytes().all(|b| b.is_ascii_hexdigit());
+        if is_hex(64) {
+            return Ok((algo, digest));
+        }
+        if prefixed.is_none() && is_hex(32) {
+            return Err(CobraError::InvalidInput(format!(
+                "'{}' looks like an md5 digest, which cobra does not verify; use the sha256 digest", expected
+            )));
+        }
+        Err(CobraError::InvalidInput(format!("'{}' is not a sha256 or blake3 hex digest", expected)))
+    }
+
+    /// Hex digest of `data`
+    pub fn digest(self, data: &[u8]) -> String {
+        match self {
+            HashAlgo::Sha256 => sha256_hex(data),
+            HashAlgo::Blake3 => blake3::hash(data).to_hex().to_string(),
+        }
+    }
+}
+
+/// `digest` without a leading `<name>:` or `<name>=`
+fn strip_algorithm<'a>(digest: &'a str, name: &str) -> Option<&'a str> {
+    let rest = digest.strip_prefix(name)?;
+    rest.strip_prefix(':').or_else(|| rest.strip_prefix('='))
+}
+
+impl fmt::Display for HashAlgo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            HashAlgo::Sha256 => "sha256",
+            HashAlgo::Blake3 => "blake3",
+        })
+    }
+}
+
+/// Whether `data` has the digest `expected`, in any form [`HashAlgo::of_digest`] reads
+pub fn digest_matches(expected: &str, data: &[u8]) -> bool {
+    HashAlgo::of_digest(expected).is_ok_and(|(algo, expected)| algo.digest(data).eq_ignore_ascii_case(expected))
+}
+
+/// Verify a package file against `expected_hash` with the algorithm its
+/// form names, see [`HashAlgo::of_digest`]
 pub async fn verify_package_hash(path: &Path, expected_hash: &str) -> Result<bool> {
-    let computed = compute_hash(path).await?;
-    Ok(computed == expected_hash)
+    let (algo, expected) = HashAlgo::of_digest(expected_hash)?;
+    let computed = match algo {
+        HashAlgo::Sha256 => compute_sha256(path).await?,
+        HashAlgo::Blake3 => compute_hash(path).await?,
+    };
+    Ok(computed.eq_ignore_ascii_case(expected))
 }
 
 /// Compute BLAKE3 hash for a file (3x faster than SHA256)
@@ -44,10 +110,15 @@ pub async fn compute_sha256(path: &Path) -> Result<String> {
     Ok(format!("{:x}", hasher.finalize()))
 }
 
+/// SHA256 hex digest of an in-memory archive, the form PyPI publishes
+pub fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
 /// Parallel hash computation for multiple files
 pub async fn compute_hashes_parallel(paths: Vec<&Path>) -> Result<Vec<String>> {
     let futures: Vec<_> = paths.into_iter()
-        .map(|path| compute_hash(path))
+        .map(compute_hash)
         .collect();
     
     futures::future::try_join_all(futures).await
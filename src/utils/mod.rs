@@ -1,3 +1,5 @@
 pub mod progress;
 pub mod hash;
 pub mod fs;
+pub mod throttle;
+pub mod credentials;
@@ -1,22 +1,42 @@
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use crate::core::installer::format_bytes;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
 
 /// High-performance progress tracker for parallel operations
 pub struct ProgressTracker {
     multi: Arc<MultiProgress>,
-    bars: Arc<Mutex<Vec<ProgressBar>>>,
+    bars: Mutex<Vec<ProgressBar>>,
+    /// When the first download started
+    started: OnceLock<Instant>,
+    downloads: AtomicUsize,
+    bytes: AtomicU64,
+    /// Drop lines instead of printing them
+    quiet: bool,
 }
 
 impl ProgressTracker {
     pub fn new() -> Self {
         Self {
             multi: Arc::new(MultiProgress::new()),
-            bars: Arc::new(Mutex::new(Vec::new())),
+            bars: Mutex::new(Vec::new()),
+            started: OnceLock::new(),
+            downloads: AtomicUsize::new(0),
+            bytes: AtomicU64::new(0),
+            quiet: false,
         }
     }
 
+    /// A tracker that draws nothing and drops its lines, for runs whose stdout is a report
+    pub fn hidden() -> Self {
+        let tracker = Self { quiet: true, ..Self::new() };
+        tracker.multi.set_draw_target(ProgressDrawTarget::hidden());
+        tracker
+    }
+
     pub async fn add_download(&self, name: &str, size: u64) -> ProgressBar {
+        self.started.get_or_init(Instant::now);
         let pb = self.multi.add(ProgressBar::new(size));
         pb.set_style(
             ProgressStyle::default_bar()
@@ -25,8 +45,24 @@ impl ProgressTracker {
                 .progress_chars("█▓▒░"),
         );
         pb.set_message(name.to_string());
-        self.bars.lock().await.push(pb.clone());
-        pb
+        self.track(pb)
+    }
+
+    /// Count a finished download towards the summary
+    pub fn record_download(&self, bytes: u64) {
+        self.downloads.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Bar counting finished packages, shown above the per-download bars
+    pub fn add_overall(&self, total: usize) -> ProgressBar {
+        let pb = self.multi.insert(0, ProgressBar::new(total as u64));
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template("{spinner:.green} {pos}/{len} packages {msg}")
+                .unwrap(),
+        );
+        self.track(pb)
     }
 
     pub async fn add_spinner(&self, msg: &str) -> ProgressBar {
@@ -38,16 +74,50 @@ impl ProgressTracker {
         );
         pb.set_message(msg.to_string());
         pb.enable_steady_tick(std::time::Duration::from_millis(100));
-        self.bars.lock().await.push(pb.clone());
+        self.track(pb)
+    }
+
+    /// Print a line above the bars without tearing them
+    pub fn println(&self, line: &str) {
+        if self.quiet {
+            return;
+        }
+        self.multi.suspend(|| println!("{}", line));
+    }
+
+    fn track(&self, pb: ProgressBar) -> ProgressBar {
+        self.bars.lock().unwrap().push(pb.clone());
         pb
     }
 
-    pub async fn finish_all(&self) {
-        let bars = self.bars.lock().await;
+    /// Clear every bar; safe to call from `Drop`
+    pub fn finish_all(&self) {
+        let bars = self.bars.lock().unwrap();
         for bar in bars.iter() {
             bar.finish_and_clear();
         }
     }
+
+    /// Bars not yet finished
+    pub fn active_bars(&self) -> usize {
+        self.bars.lock().unwrap().iter().filter(|bar| !bar.is_finished()).count()
+    }
+
+    /// Number of downloads recorded so far
+    pub fn downloads(&self) -> usize {
+        self.downloads.load(Ordering::Relaxed)
+    }
+
+    /// One-line summary such as "Downloaded 42 MB across 12 packages in 3.1s"
+    pub fn summary(&self) -> String {
+        let elapsed = self.started.get().map(Instant::elapsed).unwrap_or_default();
+        format!(
+            "Downloaded {} across {} packages in {:.1}s",
+            format_bytes(self.bytes.load(Ordering::Relaxed)),
+            self.downloads(),
+            elapsed.as_secs_f64()
+        )
+    }
 }
 
 impl Default for ProgressTracker {
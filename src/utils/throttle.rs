@@ -0,0 +1,50 @@
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+/// Token bucket shared by concurrent downloads to cap their combined rate.
+/// The bucket holds one second worth of bytes, so short bursts go through
+/// immediately and sustained throughput settles at the configured rate.
+pub struct RateLimiter {
+    bytes_per_sec: u64,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    /// Negative once callers have reserved bytes they are still waiting for
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec,
+            bucket: Mutex::new(Bucket {
+                tokens: bytes_per_sec as f64,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Wait until `bytes` may be transferred without exceeding the rate
+    pub async fn acquire(&self, bytes: usize) {
+        let rate = self.bytes_per_sec as f64;
+        let wait = {
+            let mut bucket = self.bucket.lock().await;
+            let now = Instant::now();
+            let refill = now.duration_since(bucket.refilled_at).as_secs_f64() * rate;
+            bucket.tokens = (bucket.tokens + refill).min(rate);
+            bucket.refilled_at = now;
+
+            // Reserve first, then sleep off the debt outside the lock so
+            // later callers queue up behind it
+            bucket.tokens -= bytes as f64;
+            (bucket.tokens < 0.0).then(|| Duration::from_secs_f64(-bucket.tokens / rate))
+        };
+
+        if let Some(wait) = wait {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use crate::support::{project_json, MockIndex};
+use cobra::cli::add::{add_registry_packages, parse_source_spec, with_rollback};
+use cobra::registry::packagecloud::PrivateRegistries;
+use cobra::{CobraConfig, DependencySource, RegistryClient};
+
+#[tokio::test]
+async fn exact_pins_the_newest_matching_release() {
+    let index = MockIndex::start().await;
+    index.serve_json("/pypi/requests/json", &project_json(&index.url, "requests", &[
+        ("2.30.0", "2023-05-03T12:00:00Z"),
+        ("2.31.0", "2023-05-22T12:00:00Z"),
+        ("3.0.0b1", "2023-06-01T12:00:00Z"),
+    ], &[]));
+    index.serve_json("/pypi/idna/json", &project_json(&index.url, "idna", &[
+        ("3.4", "2022-09-14T12:00:00Z"),
+        ("3.6", "2023-11-25T12:00:00Z"),
+    ], &[]));
+
+    let client = RegistryClient::with_base_url(index.url.clone());
+    let mut config = CobraConfig::default();
+    let packages = vec!["requests".to_string(), "idna@<3.5".to_string()];
+    add_registry_packages(&mut config, &client, &PrivateRegistries::new(), &packages, false, true).await.unwrap();
+
+    assert_eq!(config.get_dependency("requests").as_deref(), Some("==2.31.0"));
+    assert_eq!(config.get_dependency("idna").as_deref(), Some("==3.4"));
+}
+
+#[tokio::test]
+async fn bare_names_get_a_caret_pin_and_specs_are_written_as_given() {
+    let index = MockIndex::start().await;
+    index.serve_json("/pypi/requests/json", &project_json(&index.url, "requests", &[
+        ("2.31.0", "2023-05-22T12:00:00Z"),
+        ("3.0.0b1", "2023-06-01T12:00:00Z"),
+    ], &[]));
+
+    let client = RegistryClient::with_base_url(index.url.clone());
+    let mut config = CobraConfig::default();
+    let packages = vec!["requests".to_string(), "idna@>=3.4".to_string()];
+    add_registry_packages(&mut config, &client, &PrivateRegistries::new(), &packages, false, false).await.unwrap();
+
+    assert_eq!(config.get_dependency("requests").as_deref(), Some("^2.31.0"));
+    assert_eq!(config.get_dependency("idna").as_deref(), Some(">=3.4"));
+}
+
+#[test]
+fn git_urls_and_directories_are_recognized_as_sources() {
+    let git = |git: &str, rev: Option<&str>| Some(DependencySource::Git { git: git.to_string(), rev: rev.map(str::to_string) });
+    assert_eq!(parse_source_spec("git+https://github.com/org/repo@v1.2"), git("https://github.com/org/repo", Some("v1.2")));
+    assert_eq!(parse_source_spec("git+https://github.com/org/repo.git"), git("https://github.com/org/repo.git", None));
+    assert_eq!(parse_source_spec("git+ssh://git@github.com/org/repo"), git("ssh://git@github.com/org/repo", None));
+    assert_eq!(parse_source_spec("git+ssh://git@github.com/org/repo@main"), git("ssh://git@github.com/org/repo", Some("main")));
+
+    let path = |path: &str| Some(DependencySource::Path { path: path.to_string() });
+    assert_eq!(parse_source_spec("./libs/mypkg"), path("./libs/mypkg"));
+    assert_eq!(parse_source_spec("../shared"), path("../shared"));
+    assert_eq!(parse_source_spec("/opt/src/mypkg"), path("/opt/src/mypkg"));
+
+    assert_eq!(parse_source_spec("requests"), None);
+    assert_eq!(parse_source_spec("requests@>=2.0"), None);
+}
+
+#[tokio::test]
+async fn a_failed_install_restores_the_config_and_lock() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("cobra.toml");
+    let lock = dir.path().join("cobra.lock");
+    std::fs::write(&config, "[dependencies]\nrequests = \"^2.31\"\n").unwrap();
+
+    let result: cobra::Result<()> = with_rollback(&[&config, &lock], async {
+        std::fs::write(&config, "[dependencies]\nrequests = \"^2.31\"\nnope = \"*\"\n").unwrap();
+        std::fs::write(&lock, "version = 1\n").unwrap();
+        Err(cobra::CobraError::ResolutionFailed("nope".to_string()))
+    }).await;
+
+    assert!(result.is_err());
+    assert_eq!(std::fs::read_to_string(&config).unwrap(), "[dependencies]\nrequests = \"^2.31\"\n");
+    assert!(!lock.exists());
+
+    // A successful install keeps the edit
+    with_rollback(&[&config, &lock], async {
+        std::fs::write(&lock, "version = 1\n").unwrap();
+        Ok(())
+    }).await.unwrap();
+    assert!(lock.exists());
+}
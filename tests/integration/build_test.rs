@@ -0,0 +1,229 @@
+use crate::support::MockIndex;
+use cobra::core::build::{is_sdist, source_hash, wheel_metadata, BuildFrontend, WheelBuilder};
+use cobra::core::vcs;
+use cobra::core::layout::InstallLayout;
+use cobra::core::record::DirectUrl;
+use cobra::core::package_manager::LocalPackageManager;
+use cobra::utils::hash::sha256_hex;
+use cobra::{CobraError, Installer, Package, ProgressTracker, RegistryClient};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// In-tree PEP 517 backend with no build requirements, so the isolated
+/// environment can be created offline
+const BACKEND: &str = r#"
+import os, zipfile
+
+NAME, VERSION = "localdemo", "0.1.0"
+
+def build_wheel(wheel_directory, config_settings=None, metadata_directory=None):
+    filename = f"{NAME}-{VERSION}-py3-none-any.whl"
+    dist_info = f"{NAME}-{VERSION}.dist-info"
+    with zipfile.ZipFile(os.path.join(wheel_directory, filename), "w") as wheel:
+        wheel.write(os.path.join("src", NAME, "__init__.py"), f"{NAME}/__init__.py")
+        wheel.writestr(f"{dist_info}/METADATA",
+            f"Metadata-Version: 2.1\nName: {NAME}\nVersion: {VERSION}\nRequires-Dist: six (>=1.16)\n\nA demo.\n")
+        wheel.writestr(f"{dist_info}/WHEEL", "Wheel-Version: 1.0\nRoot-Is-Purelib: true\nTag: py3-none-any\n")
+        wheel.writestr(f"{dist_info}/RECORD", "")
+    return filename
+"#;
+
+fn write_project(dir: &Path) -> PathBuf {
+    let project = dir.join("localdemo");
+    std::fs::create_dir_all(project.join("src/localdemo")).unwrap();
+    std::fs::write(
+        project.join("pyproject.toml"),
+        "[build-system]\nrequires = []\nbuild-backend = \"backend\"\nbackend-path = [\".\"]\n\n[project]\nname = \"localdemo\"\n",
+    )
+    .unwrap();
+    std::fs::write(project.join("backend.py"), BACKEND).unwrap();
+    std::fs::write(project.join("src/localdemo/__init__.py"), "VALUE = 1\n").unwrap();
+    project
+}
+
+#[test]
+fn source_hash_ignores_build_outputs_and_tracks_sources() {
+    let dir = tempfile::tempdir().unwrap();
+    let project = write_project(dir.path());
+    let original = source_hash(&project).unwrap();
+
+    std::fs::create_dir_all(project.join("build/lib")).unwrap();
+    std::fs::write(project.join("build/lib/junk.py"), "x = 1\n").unwrap();
+    assert_eq!(source_hash(&project).unwrap(), original);
+
+    std::fs::write(project.join("src/localdemo/__init__.py"), "VALUE = 2\n").unwrap();
+    assert_ne!(source_hash(&project).unwrap(), original);
+}
+
+#[tokio::test]
+async fn builds_and_installs_local_project() {
+    let dir = tempfile::tempdir().unwrap();
+    let project = write_project(dir.path());
+
+    let wheel = WheelBuilder::new(PathBuf::from("python3"))
+        .build(&project, &dir.path().join("wheels"))
+        .await
+        .unwrap();
+    assert!(wheel.ends_with("localdemo-0.1.0-py3-none-any.whl"));
+
+    let metadata = wheel_metadata(&wheel).unwrap();
+    assert_eq!(metadata.name, "localdemo");
+    assert_eq!(metadata.version, "0.1.0");
+    assert_eq!(metadata.requires.len(), 1);
+    assert_eq!(metadata.requires[0].name, "six");
+    assert_eq!(metadata.requires[0].version_spec, ">=1.16");
+
+    let target = dir.path().join("target");
+    let package_manager = Arc::new(LocalPackageManager::with_layout(InstallLayout::target(target.clone())));
+    let installer = Installer::new(
+        Arc::new(RegistryClient::new()),
+        None,
+        Arc::new(ProgressTracker::new()),
+        package_manager.clone(),
+    );
+    let hash = source_hash(&project).unwrap();
+    installer
+        .install_built_wheel(&wheel, &metadata.to_package(&wheel), &hash, &DirectUrl::Dir(project.clone()))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(target.join("localdemo/__init__.py")).unwrap(),
+        "VALUE = 1\n"
+    );
+    let installed = package_manager.list_installed().await.unwrap();
+    assert_eq!(installed.len(), 1);
+    assert_eq!(installed[0].source_hash.as_deref(), Some(hash.as_str()));
+
+    let dist_info = target.join("localdemo-0.1.0.dist-info");
+    assert_eq!(std::fs::read_to_string(dist_info.join("INSTALLER")).unwrap(), "cobra\n");
+    let direct_url: serde_json::Value = serde_json::from_slice(&std::fs::read(dist_info.join("direct_url.json")).unwrap()).unwrap();
+    assert_eq!(direct_url["url"], format!("file://{}", project.display()));
+    assert_eq!(direct_url["dir_info"], serde_json::json!({}));
+    let record = std::fs::read_to_string(dist_info.join("RECORD")).unwrap();
+    assert!(record.contains("localdemo-0.1.0.dist-info/direct_url.json,sha256="), "{}", record);
+}
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = std::process::Command::new("git")
+        .args(["-c", "user.name=cobra", "-c", "user.email=cobra@example.com", "-c", "init.defaultBranch=main"])
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .status()
+        .unwrap();
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+#[tokio::test]
+async fn git_sources_check_out_the_requested_revision() {
+    let dir = tempfile::tempdir().unwrap();
+    let project = write_project(dir.path());
+    git(&project, &["init", "--quiet"]);
+    git(&project, &["add", "."]);
+    git(&project, &["commit", "--quiet", "-m", "v1"]);
+    git(&project, &["tag", "v0.1.0"]);
+    git(&project, &["checkout", "--quiet", "-b", "feature"]);
+    std::fs::write(project.join("src/localdemo/__init__.py"), "VALUE = 3\n").unwrap();
+    git(&project, &["commit", "--quiet", "-am", "feature"]);
+    git(&project, &["checkout", "--quiet", "main"]);
+    std::fs::write(project.join("src/localdemo/__init__.py"), "VALUE = 2\n").unwrap();
+    git(&project, &["commit", "--quiet", "-am", "v2"]);
+
+    let url = format!("file://{}", project.display());
+    let value = |checkout: &Path| std::fs::read_to_string(checkout.join("src/localdemo/__init__.py")).unwrap();
+    for (rev, expected) in [(None, "VALUE = 2\n"), (Some("v0.1.0"), "VALUE = 1\n"), (Some("feature"), "VALUE = 3\n")] {
+        let checkout = dir.path().join(format!("checkout-{}", rev.unwrap_or("default")));
+        vcs::checkout(&url, rev, &checkout).await.unwrap();
+        assert_eq!(value(&checkout), expected, "rev {:?}", rev);
+    }
+
+    let missing = vcs::checkout(&url, Some("no-such-rev"), &dir.path().join("missing")).await;
+    assert!(matches!(missing, Err(CobraError::InstallationFailed(_))));
+}
+
+/// `project` packed the way sdists are, under a `<name>-<version>/` directory
+fn sdist(project: &Path) -> Vec<u8> {
+    let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default()));
+    builder.append_dir_all("localdemo-0.1.0", project).unwrap();
+    builder.into_inner().unwrap().finish().unwrap()
+}
+
+async fn install_sdist(archive: Vec<u8>, target: &Path) -> cobra::Result<()> {
+    let index = MockIndex::start().await;
+    index.serve("/files/localdemo-0.1.0.tar.gz", archive.clone());
+    let package = Package {
+        name: "localdemo".to_string(),
+        version: "0.1.0".to_string(),
+        dependencies: Vec::new(),
+        download_url: format!("{}/files/localdemo-0.1.0.tar.gz", index.url),
+        hash: Some(sha256_hex(&archive)),
+        size: None,
+        description: None,
+        author: None,
+        homepage: None,
+        markers: None,
+        yanked: false,
+        yanked_reason: None,
+        requires_python: None,
+    };
+    let installer = Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        None,
+        Arc::new(ProgressTracker::new()),
+        Arc::new(LocalPackageManager::with_layout(InstallLayout::target(target.to_path_buf()))),
+    );
+    installer.install_parallel(vec![package]).await.map(|_| ())
+}
+
+#[test]
+fn sdists_are_recognized_by_filename() {
+    assert!(is_sdist("https://files.example/demo-1.0.tar.gz"));
+    assert!(is_sdist("demo-1.0.zip#sha256=abc"));
+    assert!(!is_sdist("https://files.example/demo-1.0-py3-none-any.whl"));
+}
+
+#[tokio::test]
+async fn packages_without_a_wheel_are_built_from_their_sdist() {
+    let dir = tempfile::tempdir().unwrap();
+    let project = write_project(dir.path());
+    let target = dir.path().join("target");
+    install_sdist(sdist(&project), &target).await.unwrap();
+
+    let output = std::process::Command::new("python3")
+        .args(["-c", "import localdemo; print(localdemo.VALUE)"])
+        .env("PYTHONPATH", &target)
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "1", "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[tokio::test]
+async fn failed_sdist_builds_report_the_backend_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let project = write_project(dir.path());
+    std::fs::write(
+        project.join("backend.py"),
+        "def build_wheel(wheel_directory, config_settings=None, metadata_directory=None):\n    raise RuntimeError('missing C compiler')\n",
+    )
+    .unwrap();
+
+    let err = install_sdist(sdist(&project), &dir.path().join("target")).await.unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains("localdemo 0.1.0 has no wheel and its sdist failed to build"), "{}", message);
+    assert!(message.contains("RuntimeError: missing C compiler"), "{}", message);
+}
+
+#[tokio::test]
+async fn pip_can_build_wheels_instead() {
+    let dir = tempfile::tempdir().unwrap();
+    let project = write_project(dir.path());
+
+    let wheel = WheelBuilder::new(PathBuf::from("python3"))
+        .with_frontend(BuildFrontend::Pip)
+        .build(&project, &dir.path().join("wheels"))
+        .await
+        .unwrap();
+    assert!(wheel.ends_with("wheels/localdemo-0.1.0-py3-none-any.whl"), "{}", wheel.display());
+    assert_eq!(wheel_metadata(&wheel).unwrap().name, "localdemo");
+}
@@ -0,0 +1,60 @@
+use cobra::MultiLevelCache;
+use std::path::Path;
+
+async fn populated(path: &Path) -> MultiLevelCache {
+    let cache = MultiLevelCache::open(path).await.unwrap();
+    cache.put("metadata:requests:*".to_string(), "{}".into()).await.unwrap();
+    cache.put("package:requests:2.31.0".to_string(), "wheel".into()).await.unwrap();
+    cache
+}
+
+#[tokio::test]
+async fn clearing_metadata_keeps_blobs() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = populated(&dir.path().join("cache")).await;
+    cache.get("package:requests:2.31.0").await.unwrap();
+
+    // Selective clears keep the hit/miss statistics
+    assert_eq!(cache.clear_metadata().await.unwrap(), 1);
+    assert_eq!(cache.hit_rate().await, 1.0);
+    assert!(cache.get("metadata:requests:*").await.is_none());
+    assert_eq!(cache.get("package:requests:2.31.0").await.unwrap(), "wheel");
+    drop(cache);
+
+    // The disk level was cleared too
+    let reopened = MultiLevelCache::open(&dir.path().join("cache")).await.unwrap();
+    assert!(reopened.get("metadata:requests:*").await.is_none());
+    assert!(reopened.get("package:requests:2.31.0").await.is_some());
+}
+
+#[tokio::test]
+async fn clearing_blobs_keeps_metadata() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = populated(&dir.path().join("cache")).await;
+
+    assert_eq!(cache.clear_blobs().await.unwrap(), 1);
+    assert!(cache.get("package:requests:2.31.0").await.is_none());
+    assert_eq!(cache.get("metadata:requests:*").await.unwrap(), "{}");
+    drop(cache);
+
+    let reopened = MultiLevelCache::open(&dir.path().join("cache")).await.unwrap();
+    assert!(reopened.get("package:requests:2.31.0").await.is_none());
+    assert!(reopened.get("metadata:requests:*").await.is_some());
+}
+
+#[tokio::test]
+async fn metadata_older_than_its_ttl_is_a_miss() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = populated(&dir.path().join("cache")).await;
+    let hour = std::time::Duration::from_secs(60 * 60);
+
+    assert_eq!(cache.get_fresh("metadata:requests:*", hour).await.unwrap(), "{}");
+    assert!(cache.get_fresh("metadata:requests:*", std::time::Duration::ZERO).await.is_none());
+    // Plain lookups ignore the age
+    assert!(cache.get("metadata:requests:*").await.is_some());
+    drop(cache);
+
+    // Write times survive a reopen
+    let reopened = MultiLevelCache::open(&dir.path().join("cache")).await.unwrap();
+    assert!(reopened.get_fresh("metadata:requests:*", hour).await.is_some());
+}
@@ -0,0 +1,61 @@
+use crate::support::dep;
+use cobra::cli::check::find_problems;
+use cobra::core::layout::InstallLayout;
+use cobra::core::package_manager::LocalPackageManager;
+use cobra::core::record::RecordEntry;
+use cobra::Package;
+use std::path::Path;
+
+/// Write `paths` under `root` and register them as `name` `version`'s files
+async fn install(manager: &LocalPackageManager, root: &Path, name: &str, version: &str, paths: &[&str]) {
+    let mut files = Vec::new();
+    for path in paths {
+        std::fs::create_dir_all(root.join(path).parent().unwrap()).unwrap();
+        std::fs::write(root.join(path), "").unwrap();
+        files.push(RecordEntry { path: path.to_string(), sha256: None, size: Some(0) });
+    }
+    let package = Package {
+        name: name.to_string(),
+        version: version.to_string(),
+        dependencies: Vec::new(),
+        download_url: String::new(),
+        hash: None,
+        size: None,
+        description: None,
+        author: None,
+        homepage: None,
+        markers: None,
+        yanked: false,
+        yanked_reason: None,
+        requires_python: None,
+    };
+    manager.register_package_files(&package, None, files).await.unwrap();
+}
+
+#[tokio::test]
+async fn problems_are_sorted_into_what_fix_repairs() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("target");
+    let manager = LocalPackageManager::with_layout(InstallLayout::target(root.clone()));
+    install(&manager, &root, "healthy", "1.0.0", &["healthy/__init__.py"]).await;
+    install(&manager, &root, "old", "1.0.0", &["old/__init__.py"]).await;
+    install(&manager, &root, "damaged", "1.0.0", &["damaged/__init__.py", "damaged/core.py"]).await;
+    install(&manager, &root, "deleted", "1.0.0", &["deleted/__init__.py"]).await;
+    std::fs::remove_file(root.join("damaged/core.py")).unwrap();
+    std::fs::remove_dir_all(root.join("deleted")).unwrap();
+
+    let configured = [dep("Healthy", ">=1.0"), dep("old", ">=2.0"), dep("absent", "*"), dep("damaged", "*")];
+    let installed = manager.list_installed().await.unwrap();
+    let problems = find_problems(&manager, &configured, &installed).await.unwrap();
+
+    assert_eq!(problems.missing, ["absent"]);
+    assert_eq!(problems.conflicts, [("old".to_string(), ">=2.0".to_string(), "1.0.0".to_string())]);
+    assert_eq!(problems.corrupted, [("damaged".to_string(), 1, 2)]);
+    assert_eq!(problems.vanished, ["deleted"]);
+    assert_eq!(problems.len(), 4);
+
+    // Forgetting a vanished package leaves it to be installed like any missing one
+    manager.unregister_package("deleted").await.unwrap();
+    let installed = manager.list_installed().await.unwrap();
+    assert!(find_problems(&manager, &configured, &installed).await.unwrap().vanished.is_empty());
+}
@@ -0,0 +1,101 @@
+use cobra::core::layout::InstallLayout;
+use cobra::core::package_manager::LocalPackageManager;
+use cobra::core::record::RecordEntry;
+use cobra::Package;
+use std::path::Path;
+
+fn package(name: &str) -> Package {
+    Package {
+        name: name.to_string(),
+        version: "1.0.0".to_string(),
+        dependencies: Vec::new(),
+        download_url: String::new(),
+        hash: None,
+        size: None,
+        description: None,
+        author: None,
+        homepage: None,
+        markers: None,
+        yanked: false,
+        yanked_reason: None,
+        requires_python: None,
+    }
+}
+
+/// Write `paths` under `root` and register them as `name`'s files
+async fn install(manager: &LocalPackageManager, root: &Path, name: &str, paths: &[&str]) {
+    let mut files = Vec::new();
+    for path in paths {
+        std::fs::create_dir_all(root.join(path).parent().unwrap()).unwrap();
+        std::fs::write(root.join(path), "").unwrap();
+        files.push(RecordEntry { path: path.to_string(), sha256: None, size: Some(0) });
+    }
+    manager.register_package_files(&package(name), None, files).await.unwrap();
+}
+
+#[tokio::test]
+async fn clean_removes_untracked_paths_and_entries_without_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("target");
+    let manager = LocalPackageManager::with_layout(InstallLayout::target(root.clone()));
+    install(&manager, &root, "kept", &["kept/__init__.py", "kept_compat.py", "kept-1.0.0.dist-info/RECORD"]).await;
+    install(&manager, &root, "deleted", &["deleted/__init__.py"]).await;
+
+    // A crash left files behind, and someone deleted a package by hand
+    std::fs::create_dir_all(root.join("leftover/sub")).unwrap();
+    std::fs::write(root.join("leftover/sub/mod.py"), "").unwrap();
+    std::fs::create_dir_all(root.join("leftover-2.0.dist-info")).unwrap();
+    std::fs::write(root.join("stray.py"), "").unwrap();
+    std::fs::write(root.join(".gitignore"), "*").unwrap();
+    std::fs::remove_dir_all(root.join("deleted")).unwrap();
+
+    let plan = manager.plan_clean().await.unwrap();
+    assert_eq!(plan.orphans, vec![root.join("leftover"), root.join("leftover-2.0.dist-info"), root.join("stray.py")]);
+    assert_eq!(plan.missing, vec!["deleted".to_string()]);
+
+    manager.clean(&plan).await.unwrap();
+    assert!(!root.join("leftover").exists());
+    assert!(!root.join("leftover-2.0.dist-info").exists());
+    assert!(!root.join("stray.py").exists());
+    assert!(root.join(".gitignore").exists());
+    assert!(root.join("kept_compat.py").exists());
+    let installed: Vec<String> = manager.list_installed().await.unwrap().into_iter().map(|p| p.name).collect();
+    assert_eq!(installed, vec!["kept".to_string()]);
+    assert!(manager.plan_clean().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn clean_only_prunes_the_registry_of_a_venv() {
+    let dir = tempfile::tempdir().unwrap();
+    let layout = InstallLayout::venv(dir.path().join("venv"), "3.11");
+    let site_packages = layout.site_packages("gone");
+    let manager = LocalPackageManager::with_layout(layout);
+    install(&manager, &site_packages, "gone", &["gone/__init__.py"]).await;
+    std::fs::remove_dir_all(site_packages.join("gone")).unwrap();
+
+    // Installed by pip, not cobra
+    std::fs::create_dir_all(site_packages.join("pip")).unwrap();
+
+    let plan = manager.plan_clean().await.unwrap();
+    assert!(plan.orphans.is_empty());
+    assert_eq!(plan.missing, vec!["gone".to_string()]);
+}
+
+#[tokio::test]
+async fn clean_leaves_paths_that_are_not_installs_alone() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("project");
+    let manager = LocalPackageManager::with_layout(InstallLayout::flat(root.clone()));
+    std::fs::create_dir_all(root.join("src")).unwrap();
+    std::fs::write(root.join("cobra.toml"), "").unwrap();
+    std::fs::write(root.join("stray.py"), "").unwrap();
+
+    // Without a registry nothing here is known to be cobra's
+    assert!(manager.plan_clean().await.unwrap().is_empty());
+
+    install(&manager, &root, "kept", &["kept/__init__.py"]).await;
+    let plan = manager.plan_clean().await.unwrap();
+    assert_eq!(plan.orphans, vec![root.join("stray.py")]);
+    manager.clean(&plan).await.unwrap();
+    assert!(root.join("cobra.toml").exists() && root.join("src").exists());
+}
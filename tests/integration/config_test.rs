@@ -0,0 +1,412 @@
+use cobra::{CobraConfig, DependencySource};
+use cobra::core::config::{DependencySelection, Settings};
+use cobra::core::imports::ImportCheck;
+use cobra::core::version::{satisfies, VersionSpec};
+
+async fn load(contents: &str) -> cobra::Result<CobraConfig> {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cobra.toml");
+    tokio::fs::write(&path, contents).await.unwrap();
+    CobraConfig::load(&path).await
+}
+
+const PROJECT: &str = "[project]\nname = \"demo\"\nversion = \"0.1.0\"\n";
+
+#[tokio::test]
+async fn overrides_are_keyed_by_normalized_name() {
+    let config = load(&format!("{}\n[overrides]\n\"URLLib3\" = \"==1.26.18\"\n", PROJECT)).await.unwrap();
+
+    let overrides = config.get_overrides().unwrap();
+    assert_eq!(overrides.get("urllib3").map(String::as_str), Some("==1.26.18"));
+}
+
+#[tokio::test]
+async fn conflicting_overrides_are_a_config_error() {
+    let toml = format!("{}\n[overrides]\nzope_interface = \"==5.0\"\n\"zope.interface\" = \"==6.0\"\n", PROJECT);
+
+    let err = load(&toml).await.unwrap_err();
+    assert!(err.to_string().contains("Conflicting overrides"), "{}", err);
+}
+
+#[tokio::test]
+async fn dependency_selection_merges_dev_dependencies() {
+    let toml = format!(
+        "{}\n[dependencies]\nrequests = \">=2.0\"\n\n[dev-dependencies]\npytest = \"*\"\nrequests = \"==2.31.0\"\n",
+        PROJECT
+    );
+    let config = load(&toml).await.unwrap();
+
+    let names = |selection| {
+        let mut deps = config.select_dependencies(selection);
+        deps.sort_by(|a, b| a.name.cmp(&b.name));
+        deps.into_iter().map(|d| format!("{}{}", d.name, d.version_spec)).collect::<Vec<_>>()
+    };
+
+    assert_eq!(names(DependencySelection::Main), vec!["requests>=2.0"]);
+    assert_eq!(names(DependencySelection::WithDev), vec!["pytest*", "requests>=2.0"]);
+    assert_eq!(names(DependencySelection::OnlyDev), vec!["pytest*", "requests==2.31.0"]);
+}
+
+#[tokio::test]
+async fn inline_dependency_tables_carry_markers() {
+    let toml = format!(
+        "{}\n[dependencies]\nrequests = \">=2.0\"\npywin32 = {{ version = \">=305\", markers = \"sys_platform == 'win32'\" }}\n",
+        PROJECT
+    );
+    let mut config = load(&toml).await.unwrap();
+
+    let mut deps = config.get_dependencies_list();
+    deps.sort_by(|a, b| a.name.cmp(&b.name));
+    assert_eq!(deps[0].name, "pywin32");
+    assert_eq!(deps[0].version_spec, ">=305");
+    assert_eq!(deps[0].markers.as_deref(), Some("sys_platform == 'win32'"));
+    assert_eq!(deps[1].markers, None);
+
+    // Changing the version keeps the marker
+    config.add_dependency("pywin32", "==306");
+    let pywin32 = config.get_dependencies_list().into_iter().find(|d| d.name == "pywin32").unwrap();
+    assert_eq!(pywin32.version_spec, "==306");
+    assert_eq!(pywin32.markers.as_deref(), Some("sys_platform == 'win32'"));
+}
+
+#[tokio::test]
+async fn concurrency_limits_default_to_constants_and_reject_zero() {
+    let config = load(PROJECT).await.unwrap();
+    assert_eq!(config.tool.cobra.parallel_downloads, cobra::constants::MAX_CONCURRENT_DOWNLOADS);
+    assert_eq!(config.tool.cobra.parallel_installs, cobra::constants::MAX_CONCURRENT_INSTALLS);
+
+    let config = load(&format!("{}\n[tool.cobra]\nparallel-downloads = 4\n", PROJECT)).await.unwrap();
+    assert_eq!(config.tool.cobra.parallel_downloads, 4);
+
+    let err = load(&format!("{}\n[tool.cobra]\nparallel-installs = 0\n", PROJECT)).await.unwrap_err();
+    assert!(err.to_string().contains("parallel-installs must be between 1 and 128, got 0"), "{}", err);
+    let err = load(&format!("{}\n[tool.cobra]\nparallel-downloads = 129\n", PROJECT)).await.unwrap_err();
+    assert!(err.to_string().contains("parallel-downloads must be between 1 and 128, got 129"), "{}", err);
+    let err = load(&format!("{}\n[tool.cobra]\nmax-unpacked-size = 0\n", PROJECT)).await.unwrap_err();
+    assert!(err.to_string().contains("max-unpacked-size must be at least 1"), "{}", err);
+}
+
+/// An environment holding only `vars`
+fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+    let vars: Vec<(String, String)> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+    move |name| vars.iter().find(|(key, _)| key == name).map(|(_, value)| value.clone())
+}
+
+#[tokio::test]
+async fn environment_overrides_concurrency_settings() {
+    let config = load(&format!("{}\n[tool.cobra]\nparallel-downloads = 8\nchunk-size = 4096\nmax-unpacked-size = 1048576\n", PROJECT)).await.unwrap();
+    let settings = Settings::from_config(&config.tool.cobra, env(&[])).unwrap();
+    assert_eq!((settings.parallel_downloads, settings.parallel_installs), (8, cobra::constants::MAX_CONCURRENT_INSTALLS));
+    assert_eq!(settings.chunk_size, 4096);
+    assert_eq!(settings.max_unpacked_size, 1048576);
+    assert_eq!(settings.timeouts, config.http_timeouts());
+
+    let settings = Settings::from_config(&config.tool.cobra, env(&[("COBRA_PARALLEL", "2")])).unwrap();
+    assert_eq!((settings.parallel_downloads, settings.parallel_installs), (2, 2));
+
+    let specific = env(&[("COBRA_PARALLEL", "2"), ("COBRA_PARALLEL_DOWNLOADS", "64")]);
+    let settings = Settings::from_config(&config.tool.cobra, specific).unwrap();
+    assert_eq!((settings.parallel_downloads, settings.parallel_installs), (64, 2));
+
+    for value in ["0", "500", "many"] {
+        let err = Settings::from_config(&config.tool.cobra, env(&[("COBRA_PARALLEL_INSTALLS", value)])).unwrap_err();
+        assert!(err.to_string().contains("COBRA_PARALLEL_INSTALLS must be"), "{}", err);
+    }
+}
+
+#[tokio::test]
+async fn install_dir_expands_variables_and_honors_the_environment_override() {
+    let cwd = std::path::Path::new("/work/project");
+    let config = load(PROJECT).await.unwrap();
+    assert_eq!(config.resolve_install_dir(cwd, env(&[])).unwrap(), cwd.join(".cobra_packages"));
+
+    let config = load(&format!("{}\n[tool.cobra]\ninstall-dir = \"~/pkgs/${{PROJECT}}\"\n", PROJECT)).await.unwrap();
+    let vars = env(&[("HOME", "/home/dev"), ("PROJECT", "demo")]);
+    assert_eq!(config.resolve_install_dir(cwd, vars).unwrap(), std::path::PathBuf::from("/home/dev/pkgs/demo"));
+
+    let vars = env(&[("HOME", "/home/dev"), ("COBRA_INSTALL_DIR", "/srv/shared/packages")]);
+    assert_eq!(config.resolve_install_dir(cwd, vars).unwrap(), std::path::PathBuf::from("/srv/shared/packages"));
+
+    let err = config.resolve_install_dir(cwd, env(&[("HOME", "/home/dev")])).unwrap_err();
+    assert!(err.to_string().contains("refers to ${PROJECT}, which is not set"), "{}", err);
+}
+
+#[tokio::test]
+async fn http_timeouts_are_configured_separately() {
+    let defaults = load(PROJECT).await.unwrap().http_timeouts();
+    assert_eq!(defaults, cobra::registry::client::HttpTimeouts::default());
+    assert_eq!(defaults.download, None);
+
+    let config = load(&format!("{}\n[tool.cobra]\nmetadata-timeout-secs = 5\ndownload-timeout-secs = 900\n", PROJECT)).await.unwrap();
+    let timeouts = config.http_timeouts();
+    assert_eq!(timeouts.metadata, std::time::Duration::from_secs(5));
+    assert_eq!(timeouts.download, Some(std::time::Duration::from_secs(900)));
+    assert_eq!(timeouts.connect, cobra::constants::CONNECT_TIMEOUT);
+
+    let err = load(&format!("{}\n[tool.cobra]\ndownload-timeout-secs = 0\n", PROJECT)).await.unwrap_err();
+    assert!(err.to_string().contains("download-timeout-secs must be at least 1 second"), "{}", err);
+}
+
+#[tokio::test]
+async fn retry_policy_is_read_from_tool_cobra() {
+    let defaults = load(PROJECT).await.unwrap().retry_policy();
+    assert_eq!(defaults, cobra::registry::client::RetryPolicy::default());
+    assert_eq!(defaults.retries, 3);
+
+    let config = load(&format!("{}\n[tool.cobra]\nretries = 0\nretry-base-delay-ms = 50\n", PROJECT)).await.unwrap();
+    let retry = config.retry_policy();
+    assert_eq!(retry.retries, 0);
+    assert_eq!(retry.base_delay, std::time::Duration::from_millis(50));
+}
+
+#[tokio::test]
+async fn install_hooks_are_read_in_order() {
+    assert!(load(PROJECT).await.unwrap().tool.cobra.hooks.is_empty());
+
+    let config = load(&format!(
+        "{}\n[tool.cobra.hooks]\npre-install = [\"./check-env.sh\"]\npost-install = [\"python -m compileall -q .cobra_packages\", \"./scan-licenses.sh\"]\n",
+        PROJECT
+    )).await.unwrap();
+    let hooks = &config.tool.cobra.hooks;
+    assert_eq!(hooks.pre_install, ["./check-env.sh"]);
+    assert_eq!(hooks.post_install, ["python -m compileall -q .cobra_packages", "./scan-licenses.sh"]);
+}
+
+#[tokio::test]
+async fn registries_are_read_and_only_one_can_be_the_default() {
+    let contents = format!(
+        "{}\n[tool.cobra.registries.mycorp]\nrepo = \"mycorp/python\"\ntoken = \"secret\"\npackages = [\"mycorp-*\"]\n",
+        PROJECT
+    );
+    let config = load(&contents).await.unwrap();
+    let registry = &config.tool.cobra.registries["mycorp"];
+    assert_eq!(registry.repo, "mycorp/python");
+    assert_eq!(registry.packages, vec!["mycorp-*".to_string()]);
+    assert!(!registry.default);
+    assert!(!config.private_registries().is_empty());
+
+    let contents = format!(
+        "{}\n[tool.cobra.registries.a]\nrepo = \"a/py\"\ndefault = true\n[tool.cobra.registries.b]\nrepo = \"b/py\"\ndefault = true\n",
+        PROJECT
+    );
+    let err = load(&contents).await.unwrap_err();
+    assert!(err.to_string().contains("only one registry can be the default"), "{}", err);
+}
+
+#[tokio::test]
+async fn path_and_git_sources_are_split_from_registry_dependencies() {
+    let contents = format!(
+        "{}\n[dependencies]\nrequests = \"*\"\nmylib = \"*\"\ntool = \"*\"\n\n[sources]\nmylib = {{ path = \"libs/mylib\" }}\ntool = {{ git = \"https://github.com/org/tool\", rev = \"v1.0\" }}\n",
+        PROJECT
+    );
+    let mut config = load(&contents).await.unwrap();
+
+    let registry = config.registry_dependencies(DependencySelection::Main);
+    assert_eq!(registry.len(), 1);
+    assert_eq!(registry[0].source, DependencySource::Registry);
+
+    let sources = config.source_dependencies(DependencySelection::Main);
+    assert_eq!(sources.iter().map(|d| d.name.as_str()).collect::<Vec<_>>(), vec!["mylib", "tool"]);
+    assert_eq!(sources[0].source, DependencySource::Path { path: "libs/mylib".to_string() });
+    assert_eq!(sources[1].source, DependencySource::Git {
+        git: "https://github.com/org/tool".to_string(),
+        rev: Some("v1.0".to_string()),
+    });
+
+    // Sources survive a save and are dropped with their dependency
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cobra.toml");
+    config.save(&path).await.unwrap();
+    assert_eq!(CobraConfig::load(&path).await.unwrap(), config);
+    assert!(config.remove_dependency("tool"));
+    assert_eq!(config.source_dependencies(DependencySelection::Main).len(), 1);
+}
+
+#[tokio::test]
+async fn syntax_errors_report_line_and_column() {
+    let err = load(&format!("{}\n[dependencies]\nrequests = >=2.0\n", PROJECT)).await.unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains("cobra.toml:6:12:"), "{}", message);
+}
+
+#[tokio::test]
+async fn every_semantic_problem_is_reported_with_its_line() {
+    let contents = "[project]\nname = \"\"\nversion = \"one\"\n\n[dependencies]\nrequests = \"*\"\nRequests = \"*\"\n\n[tool.cobra]\nparallel-downloads = 0\n";
+    let err = load(contents).await.unwrap_err();
+    let cobra::CobraError::InvalidConfig { problems, .. } = &err else {
+        panic!("expected an invalid config report, got {}", err);
+    };
+    assert_eq!(problems, &vec![
+        "line 2: [project] name must not be empty".to_string(),
+        "line 3: [project] version 'one' is not a valid PEP 440 version".to_string(),
+        "line 6: [dependencies] 'Requests' and 'requests' name the same package".to_string(),
+        "line 10: [tool.cobra] parallel-downloads must be between 1 and 128, got 0".to_string(),
+    ]);
+    assert!(err.to_string().contains("\n  line 10: [tool.cobra] parallel-downloads must be between 1 and 128, got 0"), "{}", err);
+}
+
+#[tokio::test]
+async fn version_constraints_round_trip_through_the_config() {
+    let contents = format!(
+        "{}\n[dependencies]\nrequests = \"^2.31.0\"\nurllib3 = \"~1.26\"\nidna = \">=3.4,<4\"\ncertifi = \"2024.2.2\"\nsix = \"==1.16.0\"\ncolorama = {{ version = \"^0.4\", markers = \"sys_platform == 'win32'\" }}\n",
+        PROJECT
+    );
+    let config = load(&contents).await.unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cobra.toml");
+    config.save(&path).await.unwrap();
+    let reloaded = CobraConfig::load(&path).await.unwrap();
+    assert_eq!(reloaded, config);
+
+    let spec = |name: &str| {
+        let dependency = reloaded.get_dependencies_list().into_iter().find(|d| d.name == name).unwrap();
+        VersionSpec::parse(&dependency.version_spec).unwrap().to_string()
+    };
+    assert_eq!(spec("requests"), ">=2.31.0,<3.0.0");
+    assert_eq!(spec("urllib3"), ">=1.26,<1.27");
+    assert_eq!(spec("idna"), ">=3.4,<4");
+    assert_eq!(spec("certifi"), "==2024.2.2");
+    assert_eq!(spec("six"), "==1.16.0");
+    assert_eq!(spec("colorama"), ">=0.4,<0.5");
+
+    let requests = reloaded.get_dependencies_list().into_iter().find(|d| d.name == "requests").unwrap();
+    assert!(satisfies("2.99.1", &requests.version_spec));
+    assert!(!satisfies("2.30.0", &requests.version_spec));
+    assert!(!satisfies("3.0.0", &requests.version_spec));
+}
+
+#[tokio::test]
+async fn unparsable_version_constraints_are_rejected() {
+    let err = load(&format!("{}\n[dependencies]\nrequests = \"latest\"\n", PROJECT)).await.unwrap_err();
+    assert!(
+        err.to_string().contains("line 6: [dependencies] requests has an invalid version requirement 'latest'"),
+        "{}", err
+    );
+}
+
+#[tokio::test]
+async fn detailed_dependencies_carry_extras_optional_and_index() {
+    let contents = format!(
+        "{}\n[dependencies]\nrequests = {{ version = \"^2.31\", extras = [\"socks\"], optional = true, index = \"private\" }}\nsix = \"*\"\n\n[tool.cobra.registries.private]\nrepo = \"mycorp/python\"\n",
+        PROJECT
+    );
+    let mut config = load(&contents).await.unwrap();
+
+    let requests = config.get_dependencies_list().into_iter().find(|d| d.name == "requests").unwrap();
+    assert_eq!(requests.extras, vec!["socks".to_string()]);
+    assert!(requests.optional);
+    let six = config.get_dependencies_list().into_iter().find(|d| d.name == "six").unwrap();
+    assert!(six.extras.is_empty() && !six.optional);
+
+    // Both forms survive an update, a removal and a save
+    config.add_dependency("requests", "^2.32");
+    config.add_dependency("idna", ">=3.4");
+    assert!(config.remove_dependency("six"));
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cobra.toml");
+    config.save(&path).await.unwrap();
+    let reloaded = CobraConfig::load(&path).await.unwrap();
+    assert_eq!(reloaded, config);
+    let requests = &reloaded.dependencies["requests"];
+    assert_eq!(requests.version(), "^2.32");
+    assert_eq!(requests.extras(), ["socks".to_string()]);
+    assert_eq!(requests.index(), Some("private"));
+    assert_eq!(reloaded.get_dependency("idna").as_deref(), Some(">=3.4"));
+}
+
+#[tokio::test]
+async fn dependencies_must_name_a_configured_index() {
+    let err = load(&format!("{}\n[dependencies]\nrequests = {{ version = \"*\", index = \"corp\" }}\n", PROJECT))
+        .await
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("[dependencies] requests uses index 'corp', which is not in [tool.cobra.registries]"),
+        "{}", err
+    );
+}
+
+#[tokio::test]
+async fn marker_groups_limit_their_packages_to_matching_platforms() {
+    let contents = format!(
+        "{}\n[dependencies]\nrequests = \"*\"\n\n[dependencies.'sys_platform == \"win32\"']\npywin32 = \">=305\"\ncolorama = {{ version = \"*\", markers = \"python_version >= '3.8'\" }}\n\n[dependencies.\"sys_platform == 'linux' and platform_machine == 'x86_64'\"]\nuvloop = \"^0.19\"\n",
+        PROJECT
+    );
+    let mut config = load(&contents).await.unwrap();
+
+    let markers = |config: &CobraConfig| -> Vec<(String, Option<String>)> {
+        config.get_dependencies_list().into_iter().map(|d| (d.name, d.markers)).collect()
+    };
+    assert_eq!(markers(&config), vec![
+        ("colorama".to_string(), Some("(sys_platform == \"win32\") and (python_version >= '3.8')".to_string())),
+        ("pywin32".to_string(), Some("sys_platform == \"win32\"".to_string())),
+        ("requests".to_string(), None),
+        ("uvloop".to_string(), Some("sys_platform == 'linux' and platform_machine == 'x86_64'".to_string())),
+    ]);
+
+    // Updates stay in the group; the groups survive a save
+    config.add_dependency("pywin32", "==306");
+    assert_eq!(config.get_dependency("pywin32").as_deref(), Some("==306"));
+    assert!(config.remove_dependency("uvloop"));
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cobra.toml");
+    config.save(&path).await.unwrap();
+    let reloaded = CobraConfig::load(&path).await.unwrap();
+    assert_eq!(reloaded, config);
+    assert_eq!(markers(&reloaded).len(), 3);
+    assert_eq!(reloaded.dependencies.len(), 2);
+}
+
+#[tokio::test]
+async fn marker_groups_are_validated() {
+    let contents = format!(
+        "{}\n[dependencies]\npywin32 = \"*\"\n\n[dependencies.'sys_platform = \"win32\"']\nsix = \"*\"\n\n[dependencies.'os_name == \"nt\"']\npywin32 = \"*\"\nidna = \"latest\"\n",
+        PROJECT
+    );
+    let err = load(&contents).await.unwrap_err().to_string();
+    for expected in [
+        "line 8: [dependencies] 'sys_platform = \"win32\"' is neither a package with a version nor a valid PEP 508 marker",
+        "line 13: [dependencies.os_name == \"nt\"] idna has an invalid version requirement 'latest'",
+        "[dependencies] pywin32 is listed more than once",
+    ] {
+        assert!(err.contains(expected), "missing {:?} in {}", expected, err);
+    }
+}
+
+#[tokio::test]
+async fn registries_need_the_settings_of_their_api() {
+    let contents = format!(
+        "{}\n[tool.cobra.registries.corp]\napi = \"simple\"\n\n[tool.cobra.registries.mycorp]\npackages = [\"mycorp-*\"]\n",
+        PROJECT
+    );
+    let err = load(&contents).await.unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains("[tool.cobra.registries.corp] a simple index needs the url of its root"), "{}", message);
+    assert!(message.contains("[tool.cobra.registries.mycorp] a PackageCloud registry needs a repo"), "{}", message);
+
+    let config = load(&format!("{}\n[tool.cobra.registries.corp]\napi = \"simple\"\nurl = \"https://gitlab.example/simple\"\n", PROJECT)).await.unwrap();
+    assert_eq!(config.tool.cobra.registries["corp"].api, cobra::core::config::RegistryApi::Simple);
+}
+
+#[tokio::test]
+async fn verify_imports_is_a_flag_or_warn() {
+    let mode = |value: &str| format!("{}\n[tool.cobra]\nverify-imports = {}\n", PROJECT, value);
+
+    assert_eq!(load(&mode("true")).await.unwrap().tool.cobra.verify_imports, ImportCheck::Fail);
+    assert_eq!(load(&mode("false")).await.unwrap().tool.cobra.verify_imports, ImportCheck::Off);
+    assert_eq!(load(&mode("\"warn\"")).await.unwrap().tool.cobra.verify_imports, ImportCheck::Warn);
+    let error = load(&mode("\"loud\"")).await.unwrap_err();
+    assert!(error.to_string().contains("verify-imports must be true, false or \"warn\""), "{}", error);
+}
+
+#[tokio::test]
+async fn mirrors_must_be_http_urls_and_reach_the_settings() {
+    let mirrors = |list: &str| format!("{}\n[tool.cobra]\nmirrors = {}\n", PROJECT, list);
+
+    let config = load(&mirrors("[\"https://mirror.corp/pypi\", \"http://10.0.0.5:8080\"]")).await.unwrap();
+    let settings = Settings::from_config(&config.tool.cobra, |_| None).unwrap();
+    assert_eq!(settings.mirrors, vec!["https://mirror.corp/pypi", "http://10.0.0.5:8080"]);
+
+    let error = load(&mirrors("[\"mirror.corp/pypi\"]")).await.unwrap_err();
+    assert!(error.to_string().contains("mirror 'mirror.corp/pypi' is not an http(s) URL"), "{}", error);
+}
@@ -0,0 +1,101 @@
+use cobra::cli::doctor::Diagnostics;
+use cobra::utils::fs::is_writable;
+use cobra::core::layout::InstallLayout;
+use cobra::core::package_manager::{LocalPackageManager, PthStatus};
+use cobra::{MultiLevelCache, Package};
+use std::path::PathBuf;
+
+fn package(name: &str) -> Package {
+    Package {
+        name: name.to_string(),
+        version: "1.0.0".to_string(),
+        dependencies: Vec::new(),
+        download_url: format!("https://files.example/{}-1.0.0.whl", name),
+        hash: None,
+        size: None,
+        description: None,
+        author: None,
+        homepage: None,
+        markers: None,
+        yanked: false,
+        yanked_reason: None,
+        requires_python: None,
+    }
+}
+
+fn healthy(dir: PathBuf) -> Diagnostics {
+    Diagnostics {
+        python: Err("unused".to_string()),
+        config_found: true,
+        install_dir: dir.join(".cobra_packages"),
+        install_dir_writable: true,
+        pth_file: Some(dir.join("cobra-packages.pth")),
+        pth_status: Ok(PthStatus::Complete),
+        cache_dir: dir.join("cache"),
+        cache_size: 0,
+        cache_lookups: Some((0, 0)),
+        index_url: "https://pypi.org".to_string(),
+        registries: Vec::new(),
+    }
+}
+
+#[test]
+fn common_problems_are_flagged() {
+    let dir = tempfile::tempdir().unwrap();
+    let diagnostics = healthy(dir.path().to_path_buf());
+    let problems = diagnostics.problems();
+    assert_eq!(problems.len(), 1);
+    assert!(problems[0].contains("No usable Python interpreter"), "{}", problems[0]);
+
+    let missing = dir.path().join(".cobra_packages");
+    let diagnostics = Diagnostics {
+        install_dir_writable: false,
+        pth_status: Ok(PthStatus::Missing(vec![missing.clone()])),
+        cache_lookups: None,
+        ..healthy(dir.path().to_path_buf())
+    };
+    let problems = diagnostics.problems().join("\n");
+    assert!(problems.contains("is not writable"), "{}", problems);
+    assert!(problems.contains(&format!(".pth file does not list {}", missing.display())), "{}", problems);
+    assert!(problems.contains("could not be opened"), "{}", problems);
+}
+
+#[test]
+fn install_dirs_not_created_yet_are_checked_through_their_parent() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(is_writable(&dir.path().join("not/created/yet")));
+}
+
+#[tokio::test]
+async fn pth_status_reports_unlisted_install_dirs() {
+    let dir = tempfile::tempdir().unwrap();
+    let pth = dir.path().join("cobra-packages.pth");
+    let root = dir.path().join(".cobra_packages");
+    let manager = LocalPackageManager::with_layout(InstallLayout::flat(root.clone()));
+    manager.ensure_install_dir().await.unwrap();
+    assert_eq!(manager.pth_status(&pth).await.unwrap(), PthStatus::NotNeeded);
+
+    manager.register_package(&package("requests")).await.unwrap();
+    let entries = manager.layout().pth_entries(&["requests".to_string()]);
+    assert_eq!(manager.pth_status(&pth).await.unwrap(), PthStatus::Missing(entries.clone()));
+
+    manager.update_pth_file(&pth, &entries).await.unwrap();
+    assert_eq!(manager.pth_status(&pth).await.unwrap(), PthStatus::Complete);
+}
+
+#[tokio::test]
+async fn cache_lookups_are_kept_across_runs() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cache");
+    let cache = MultiLevelCache::open(&path).await.unwrap();
+    cache.put("metadata:requests:*".to_string(), "{}".into()).await.unwrap();
+    cache.get("metadata:requests:*").await.unwrap();
+    assert!(cache.get("metadata:missing:*").await.is_none());
+    drop(cache);
+
+    let reopened = MultiLevelCache::open(&path).await.unwrap();
+    assert_eq!(reopened.lookups().await, (1, 1));
+    reopened.clear().await.unwrap();
+    drop(reopened);
+    assert_eq!(MultiLevelCache::open(&path).await.unwrap().lookups().await, (0, 0));
+}
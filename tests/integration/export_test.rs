@@ -0,0 +1,61 @@
+use cobra::cli::export::{render_export, ExportFormat};
+use cobra::core::lockfile::LockedPackage;
+
+fn locked(name: &str, version: &str, hash: Option<&str>, markers: Option<&str>) -> LockedPackage {
+    LockedPackage {
+        name: name.to_string(),
+        version: version.to_string(),
+        url: format!("https://files.example.com/{}-{}-py3-none-any.whl", name, version),
+        hash: hash.map(str::to_string),
+        size: None,
+        dependencies: Vec::new(),
+        markers: markers.map(str::to_string),
+        overridden: false,
+        yanked: false,
+        yanked_reason: None,
+    }
+}
+
+fn packages() -> Vec<LockedPackage> {
+    vec![
+        locked("requests", "2.31.0", Some(&"a".repeat(64)), None),
+        locked("colorama", "0.4.6", Some(&format!("sha256:{}", "b".repeat(64))), Some("sys_platform == \"win32\"")),
+    ]
+}
+
+#[test]
+fn requirements_are_pinned_sorted_and_carry_hashes_when_asked() {
+    let plain = render_export(&packages(), ExportFormat::Requirements, false, "cobra.lock").unwrap();
+    assert_eq!(plain, "# Generated by cobra export from cobra.lock\n\
+        colorama==0.4.6 ; sys_platform == \"win32\"\n\
+        requests==2.31.0\n");
+
+    let hashed = render_export(&packages(), ExportFormat::Requirements, true, "cobra.lock").unwrap();
+    assert!(hashed.contains(&format!("colorama==0.4.6 ; sys_platform == \"win32\" \\\n    --hash=sha256:{}\n", "b".repeat(64))), "{}", hashed);
+    assert!(hashed.contains(&format!("requests==2.31.0 \\\n    --hash=sha256:{}\n", "a".repeat(64))), "{}", hashed);
+
+    let constraints = render_export(&packages(), ExportFormat::Constraints, false, "cobra.lock").unwrap();
+    assert_eq!(constraints, plain);
+}
+
+#[test]
+fn hashes_need_a_sha256_for_every_package() {
+    let mut unhashed = packages();
+    unhashed.push(locked("idna", "3.6", None, None));
+    let err = render_export(&unhashed, ExportFormat::Requirements, true, "cobra.lock").unwrap_err();
+    assert!(err.to_string().contains("idna 3.6 has none"), "{}", err);
+
+    let err = render_export(&packages(), ExportFormat::Constraints, true, "cobra.lock").unwrap_err();
+    assert!(err.to_string().contains("--hashes only applies to --format requirements"), "{}", err);
+}
+
+#[test]
+fn pyproject_export_is_a_valid_pep_621_dependencies_block() {
+    let rendered = render_export(&packages(), ExportFormat::Pyproject, false, "cobra.toml").unwrap();
+    let parsed: toml::Value = toml::from_str(&rendered).unwrap();
+    let dependencies: Vec<&str> = parsed["project"]["dependencies"].as_array().unwrap()
+        .iter()
+        .map(|d| d.as_str().unwrap())
+        .collect();
+    assert_eq!(dependencies, vec!["colorama==0.4.6; sys_platform == \"win32\"", "requests==2.31.0"]);
+}
@@ -0,0 +1,116 @@
+use crate::support::dep;
+use cobra::core::find_links::{FindLinks, WheelFilename};
+use cobra::core::installer::{Installer, PackageSource};
+use cobra::core::layout::InstallLayout;
+use cobra::core::lockfile::Lockfile;
+use cobra::core::package_manager::LocalPackageManager;
+use cobra::core::resolver::ResolutionStrategy;
+use cobra::utils::progress::ProgressTracker;
+use cobra::{CobraError, DependencyResolver, RegistryClient};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Write a wheel of `name` `version` requiring `requires` into `dir`
+fn write_wheel(dir: &Path, name: &str, version: &str, requires: &[&str]) -> std::path::PathBuf {
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default();
+    writer.start_file(format!("{}/__init__.py", name), options).unwrap();
+    writer.write_all(b"VERSION = 1\n").unwrap();
+    writer.start_file(format!("{}-{}.dist-info/METADATA", name, version), options).unwrap();
+    let mut metadata = format!("Metadata-Version: 2.1\nName: {}\nVersion: {}\n", name, version);
+    for requirement in requires {
+        metadata.push_str(&format!("Requires-Dist: {}\n", requirement));
+    }
+    writer.write_all(metadata.as_bytes()).unwrap();
+    let path = dir.join(format!("{}-{}-py3-none-any.whl", name, version));
+    std::fs::write(&path, writer.finish().unwrap().into_inner()).unwrap();
+    path
+}
+
+/// A resolver whose index refuses every connection
+fn offline_resolver(find_links: FindLinks) -> DependencyResolver {
+    DependencyResolver::new(Arc::new(RegistryClient::with_base_url("http://127.0.0.1:9".to_string())), None)
+        .with_find_links(Some(Arc::new(find_links)))
+        .with_no_index(true)
+}
+
+#[test]
+fn wheel_filenames_are_split_into_name_version_and_tags() {
+    let wheel = WheelFilename::parse("numpy-1.26.4-1-cp312-cp312-manylinux_2_17_x86_64.manylinux2014_x86_64.whl").unwrap();
+    assert_eq!((wheel.name.as_str(), wheel.version.as_str(), wheel.build.as_deref()), ("numpy", "1.26.4", Some("1")));
+    assert_eq!(wheel.python_tags, ["cp312"]);
+    assert_eq!(wheel.abi_tags, ["cp312"]);
+    assert_eq!(wheel.platform_tags, ["manylinux_2_17_x86_64", "manylinux2014_x86_64"]);
+
+    let pure = WheelFilename::parse("six-1.16.0-py2.py3-none-any.whl").unwrap();
+    assert_eq!(pure.python_tags, ["py2", "py3"]);
+    assert!(pure.is_compatible(Some(&"3.11.4".parse().unwrap())));
+    assert!(!WheelFilename::parse("old-1.0-py2-none-any.whl").unwrap().is_compatible(None));
+    assert!(!WheelFilename::parse("ext-1.0-cp39-cp39-any.whl").unwrap().is_compatible(Some(&"3.11.4".parse().unwrap())));
+    assert!(WheelFilename::parse("ext-1.0-cp39-abi3-any.whl").unwrap().is_compatible(Some(&"3.11.4".parse().unwrap())));
+
+    assert!(WheelFilename::parse("six-1.16.0.tar.gz").is_none());
+    assert!(WheelFilename::parse("six-1.16.0.whl").is_none());
+}
+
+#[tokio::test]
+async fn no_index_resolves_from_find_links_wheels_only() {
+    let dir = tempfile::tempdir().unwrap();
+    write_wheel(dir.path(), "app", "1.0.0", &["helper>=2.0"]);
+    write_wheel(dir.path(), "helper", "2.0.0", &[]);
+    write_wheel(dir.path(), "helper", "2.1.0", &[]);
+    write_wheel(dir.path(), "helper", "1.0.0", &[]);
+    std::fs::write(dir.path().join("README.txt"), "not a wheel").unwrap();
+
+    let find_links = FindLinks::scan(&[dir.path().to_path_buf(), dir.path().join("missing")]).unwrap();
+    assert_eq!(find_links.wheels().len(), 4);
+
+    let resolved = offline_resolver(find_links.clone()).resolve(&[dep("app", "*")]).await.unwrap();
+    let mut versions: Vec<(String, String)> = resolved.iter().map(|p| (p.name.clone(), p.version.clone())).collect();
+    versions.sort();
+    assert_eq!(versions, [("app".to_string(), "1.0.0".to_string()), ("helper".to_string(), "2.1.0".to_string())]);
+
+    // The locally computed digest is what the lockfile records
+    let helper = resolved.iter().find(|p| p.name == "helper").unwrap();
+    let data = std::fs::read(dir.path().join("helper-2.1.0-py3-none-any.whl")).unwrap();
+    assert_eq!(helper.hash.as_deref(), Some(cobra::utils::hash::sha256_hex(&data).as_str()));
+    assert!(helper.download_url.starts_with("file://"));
+    let lockfile = Lockfile::from_packages(&resolved, ResolutionStrategy::Highest);
+    assert_eq!(lockfile.packages.iter().find(|p| p.name == "helper").unwrap().hash, helper.hash);
+
+    let lowest = offline_resolver(find_links.clone()).with_strategy(ResolutionStrategy::Lowest)
+        .resolve(&[dep("helper", ">=2.0")]).await.unwrap();
+    assert_eq!(lowest[0].version, "2.0.0");
+
+    let missing = offline_resolver(find_links).resolve(&[dep("other", "*")]).await;
+    assert!(matches!(missing, Err(CobraError::ResolutionFailed(message)) if message.contains("--no-index")));
+}
+
+#[tokio::test]
+async fn wheel_files_install_from_where_they_lie() {
+    let dir = tempfile::tempdir().unwrap();
+    let dist = dir.path().join("dist");
+    std::fs::create_dir_all(&dist).unwrap();
+    let wheel = write_wheel(&dist, "mylib", "1.0", &[]);
+
+    let mut find_links = FindLinks::scan(&[]).unwrap();
+    assert_eq!(find_links.add_file(&wheel).unwrap().filename.version, "1.0");
+    assert!(matches!(find_links.add_file(&dist.join("notes.txt")), Err(CobraError::InvalidInput(_))));
+
+    let resolved = offline_resolver(find_links).resolve(&[dep("mylib", "==1.0")]).await.unwrap();
+    let target = dir.path().join("target");
+    let stats = Installer::new(
+        Arc::new(RegistryClient::with_base_url("http://127.0.0.1:9".to_string())),
+        None,
+        Arc::new(ProgressTracker::new()),
+        Arc::new(LocalPackageManager::with_layout(InstallLayout::target(target.clone()))),
+    )
+    .with_offline(true)
+    .install_parallel(resolved)
+    .await
+    .unwrap();
+
+    assert_eq!(stats.packages[0].source, PackageSource::Disk);
+    assert!(target.join("mylib/__init__.py").is_file());
+}
@@ -0,0 +1,70 @@
+use cobra::CobraError;
+use cobra::utils::fs::{atomic_write, check_free_space, ensure_writable, expand_path};
+
+#[tokio::test]
+async fn atomic_write_replaces_the_file_and_leaves_no_temp_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("nested/cobra-registry.json");
+
+    atomic_write(&path, b"{\"packages\":{}}").await.unwrap();
+    atomic_write(&path, b"{}").await.unwrap();
+
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "{}");
+    let names: Vec<String> = std::fs::read_dir(dir.path().join("nested")).unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(names, vec!["cobra-registry.json"]);
+}
+
+#[test]
+fn free_space_is_checked_per_filesystem() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = dir.path().join("cache");
+    let install = dir.path().join("not/created/yet");
+    assert!(check_free_space(&[(cache.clone(), 1024), (install.clone(), 3072)]).is_ok());
+
+    // Each location fits alone, but both share one filesystem
+    let half = u64::MAX / 2;
+    match check_free_space(&[(cache.clone(), half), (install.clone(), half)]) {
+        Err(CobraError::InsufficientSpace { shortfalls }) => {
+            assert_eq!(shortfalls.len(), 1);
+            assert!(shortfalls[0].starts_with(&format!("{} and {}: needs", cache.display(), install.display())), "{}", shortfalls[0]);
+            assert!(shortfalls[0].ends_with("available"));
+        }
+        other => panic!("expected a space shortfall, got {:?}", other),
+    }
+}
+
+#[test]
+fn paths_expand_home_and_each_variable_syntax() {
+    let env = |name: &str| match name {
+        "HOME" => Some("/home/dev".to_string()),
+        "CI_CACHE" => Some("/cache".to_string()),
+        "LOCALAPPDATA" => Some("C:\\Users\\dev\\AppData\\Local".to_string()),
+        _ => None,
+    };
+    let expand = |value: &str| expand_path(value, env).unwrap().display().to_string();
+
+    assert_eq!(expand("~"), "/home/dev");
+    assert_eq!(expand("~/packages"), "/home/dev/packages");
+    assert_eq!(expand("$CI_CACHE/cobra"), "/cache/cobra");
+    assert_eq!(expand("${CI_CACHE}_v2"), "/cache_v2");
+    assert_eq!(expand("%LOCALAPPDATA%\\cobra"), "C:\\Users\\dev\\AppData\\Local\\cobra");
+    // Only a leading ~ is home, and lone sigils are literal
+    assert_eq!(expand("deps/~old/100%/$"), "deps/~old/100%/$");
+
+    let err = expand_path("$UNSET/pkgs", env).unwrap_err();
+    assert!(err.to_string().contains("'$UNSET/pkgs' refers to $UNSET, which is not set"), "{}", err);
+}
+
+#[test]
+fn unwritable_install_dirs_are_refused_up_front() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(ensure_writable(&dir.path().join("not/created/yet")).is_ok());
+
+    // A file where a directory is expected can't hold packages
+    let blocker = dir.path().join("blocker");
+    std::fs::write(&blocker, b"").unwrap();
+    let err = ensure_writable(&blocker.join("packages")).unwrap_err();
+    assert!(err.to_string().contains("is not writable"), "{}", err);
+}
@@ -0,0 +1,37 @@
+use cobra::utils::hash::{sha256_hex, HashAlgo};
+use cobra::verify_package_hash;
+
+const SHA256_OF_ABC: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+#[test]
+fn digest_algorithms_follow_their_prefix_and_default_to_sha256() {
+    assert_eq!(HashAlgo::of_digest(SHA256_OF_ABC).unwrap(), (HashAlgo::Sha256, SHA256_OF_ABC));
+    assert_eq!(
+        HashAlgo::of_digest(&format!("sha256={}", SHA256_OF_ABC)).unwrap(),
+        (HashAlgo::Sha256, SHA256_OF_ABC)
+    );
+    let blake3 = blake3::hash(b"abc").to_hex().to_string();
+    assert_eq!(
+        HashAlgo::of_digest(&format!("blake3:{}", blake3)).unwrap(),
+        (HashAlgo::Blake3, blake3.as_str())
+    );
+
+    let md5 = HashAlgo::of_digest("900150983cd24fb0d6963f7d28e17f72").unwrap_err();
+    assert!(md5.to_string().contains("md5"), "{}", md5);
+    assert!(HashAlgo::of_digest("sha256:abc").is_err());
+}
+
+#[tokio::test]
+async fn package_hashes_are_checked_with_the_digest_algorithm() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("demo-1.0-py3-none-any.whl");
+    std::fs::write(&path, b"abc").unwrap();
+    assert_eq!(sha256_hex(b"abc"), SHA256_OF_ABC);
+
+    // PyPI's bare sha256 digests used to be compared with a blake3 hash
+    assert!(verify_package_hash(&path, SHA256_OF_ABC).await.unwrap());
+    assert!(verify_package_hash(&path, &SHA256_OF_ABC.to_uppercase()).await.unwrap());
+    let blake3 = blake3::hash(b"abc").to_hex().to_string();
+    assert!(verify_package_hash(&path, &format!("blake3:{}", blake3)).await.unwrap());
+    assert!(!verify_package_hash(&path, &format!("sha256:{}", blake3)).await.unwrap());
+}
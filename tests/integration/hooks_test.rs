@@ -0,0 +1,42 @@
+use cobra::core::hooks::{run_hooks, HookContext, HookStage};
+use cobra::utils::progress::ProgressTracker;
+use cobra::CobraError;
+
+#[cfg(unix)]
+#[tokio::test]
+async fn hooks_see_the_install_dir_and_packages() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("env.txt");
+    let context = HookContext {
+        install_dir: dir.path().join("packages"),
+        packages: vec!["requests==2.31.0".to_string(), "idna==3.6".to_string()],
+    };
+    let command = format!(r#"printf '%s|%s|%s' "$COBRA_HOOK" "$COBRA_INSTALL_DIR" "$COBRA_PACKAGES" > '{}'"#, out.display());
+
+    run_hooks(HookStage::PostInstall, &[command], &context, &ProgressTracker::new()).await.unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(&out).unwrap(),
+        format!("post-install|{}|requests==2.31.0 idna==3.6", dir.path().join("packages").display())
+    );
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn a_failing_hook_stops_the_ones_after_it() {
+    let dir = tempfile::tempdir().unwrap();
+    let later = dir.path().join("later");
+    let commands = [
+        "echo scanning; echo 'GPL found' >&2; exit 3".to_string(),
+        format!("touch '{}'", later.display()),
+    ];
+
+    let error = run_hooks(HookStage::PreInstall, &commands, &HookContext::default(), &ProgressTracker::new())
+        .await
+        .unwrap_err();
+
+    let CobraError::InstallationFailed(message) = error else { panic!("unexpected error: {}", error) };
+    assert!(message.starts_with("pre-install hook `echo scanning;"), "{}", message);
+    assert!(message.contains("exit status: 3"), "{}", message);
+    assert!(!later.exists());
+}
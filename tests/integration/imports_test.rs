@@ -0,0 +1,57 @@
+use cobra::core::imports::{top_level_modules, verify_imports};
+use cobra::core::record::RecordEntry;
+
+fn record(paths: &[&str]) -> Vec<RecordEntry> {
+    paths.iter().map(|path| RecordEntry { path: path.to_string(), sha256: None, size: None }).collect()
+}
+
+#[test]
+fn modules_are_derived_from_record_without_top_level_txt() {
+    let files = record(&[
+        "six.py",
+        "requests/__init__.py",
+        "requests/api.py",
+        "google/protobuf/message.py",
+        "_cffi_backend.cpython-311-x86_64-linux-gnu.so",
+        "__pycache__/six.cpython-311.pyc",
+        "fixture-1.0.dist-info/METADATA",
+        "fixture-1.0.dist-info/RECORD",
+        "fixture-1.0.data/scripts/tool",
+        "../../bin/tool",
+        "fixture.pth",
+    ]);
+
+    let dir = tempfile::tempdir().unwrap();
+    assert_eq!(top_level_modules(&files, dir.path()), ["_cffi_backend", "google", "requests", "six"]);
+}
+
+#[test]
+fn top_level_txt_wins_over_record() {
+    let dir = tempfile::tempdir().unwrap();
+    let dist_info = dir.path().join("pyyaml-6.0.dist-info");
+    std::fs::create_dir_all(&dist_info).unwrap();
+    std::fs::write(dist_info.join("top_level.txt"), "_yaml\nyaml\n\n").unwrap();
+    let files = record(&["_yaml/__init__.py", "yaml/__init__.py", "tests/test_yaml.py", "pyyaml-6.0.dist-info/RECORD"]);
+
+    assert_eq!(top_level_modules(&files, dir.path()), ["_yaml", "yaml"]);
+}
+
+#[tokio::test]
+async fn failed_imports_name_the_package_module_and_traceback() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("fine.py"), "VALUE = 1\n").unwrap();
+    std::fs::write(dir.path().join("broken.py"), "import missing_native_lib\n").unwrap();
+    let targets = [
+        ("fine".to_string(), "fine".to_string()),
+        ("broken-pkg".to_string(), "broken".to_string()),
+    ];
+    let python = if cfg!(windows) { "python" } else { "python3" };
+
+    let failures = verify_imports(python.as_ref(), dir.path().as_os_str(), &targets, 2).await;
+
+    assert_eq!(failures.len(), 1, "{:?}", failures);
+    assert_eq!((failures[0].package.as_str(), failures[0].module.as_str()), ("broken-pkg", "broken"));
+    assert!(failures[0].traceback.starts_with("Traceback"), "{}", failures[0].traceback);
+    assert!(failures[0].traceback.contains("No module named 'missing_native_lib'"), "{}", failures[0].traceback);
+}
+
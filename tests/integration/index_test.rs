@@ -0,0 +1,97 @@
+use crate::support::{dep, project_json, version_json, MockIndex};
+use cobra::core::index::LocalIndex;
+use cobra::registry::packagecloud::{PackageCloudRegistry, PrivateRegistries};
+use cobra::{DependencyResolver, Dependency, DependencySource, RegistryClient};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn resolution_after_index_sync_makes_no_requests() {
+    let index = MockIndex::start().await;
+    let releases = [("1.0.0", "2023-01-10T12:00:00Z"), ("1.1.0", "2023-08-01T12:00:00Z")];
+    index.serve_json("/pypi/app/json", &project_json(&index.url, "app", &releases, &["lib>=2.0"]));
+    for (version, uploaded) in releases {
+        index.serve_json(
+            &format!("/pypi/app/{}/json", version),
+            &version_json(&index.url, "app", version, uploaded, &["lib>=2.0"]),
+        );
+    }
+    let lib_releases = [("2.0.0", "2023-02-01T12:00:00Z")];
+    index.serve_json("/pypi/lib/json", &project_json(&index.url, "lib", &lib_releases, &[]));
+    index.serve_json(
+        "/pypi/lib/2.0.0/json",
+        &version_json(&index.url, "lib", "2.0.0", "2023-02-01T12:00:00Z", &[]),
+    );
+
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let dir = tempfile::tempdir().unwrap();
+    let local = LocalIndex::open(dir.path().join("index"));
+    assert_eq!(local.sync(&client, "app", 4).await.unwrap(), 2);
+    assert_eq!(local.sync(&client, "lib", 4).await.unwrap(), 1);
+
+    // A fresh handle reads the synced entries back from disk
+    let local = Arc::new(LocalIndex::open(dir.path().join("index")));
+    let synced = index.requests().len();
+    let resolver = DependencyResolver::new(client, None).with_local_index(Some(local));
+    let app = Dependency { name: "app".to_string(), version_spec: "*".to_string(), markers: None, source: DependencySource::Registry, extras: Vec::new(), optional: false };
+    let mut resolved = resolver.resolve(&[app]).await.unwrap();
+    resolved.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let versions: Vec<_> = resolved.iter().map(|p| (p.name.as_str(), p.version.as_str())).collect();
+    assert_eq!(versions, [("app", "1.1.0"), ("lib", "2.0.0")]);
+    assert_eq!(index.requests().len(), synced);
+}
+
+/// Serve `app` with these releases, none with requirements
+fn serve_app(index: &MockIndex, releases: &[(&str, &str)]) {
+    index.serve_json("/pypi/app/json", &project_json(&index.url, "app", releases, &[]));
+    for (version, uploaded) in releases {
+        index.serve_json(&format!("/pypi/app/{}/json", version), &version_json(&index.url, "app", version, uploaded, &[]));
+    }
+}
+
+#[tokio::test]
+async fn index_entries_past_their_max_age_are_left_to_the_registry() {
+    let index = MockIndex::start().await;
+    serve_app(&index, &[("1.0.0", "2023-01-10T12:00:00Z")]);
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let dir = tempfile::tempdir().unwrap();
+    let local = Arc::new(LocalIndex::open(dir.path().join("index")));
+    local.sync(&client, "app", 4).await.unwrap();
+
+    // A release published since the sync is only seen by asking the registry
+    serve_app(&index, &[("1.0.0", "2023-01-10T12:00:00Z"), ("1.1.0", "2023-08-01T12:00:00Z")]);
+    let app = [dep("app", "*")];
+    let resolve = |offline: bool| {
+        DependencyResolver::new(client.clone(), None)
+            .with_local_index(Some(local.clone()))
+            .with_index_max_age(Duration::ZERO)
+            .with_offline(offline)
+    };
+    assert_eq!(resolve(false).resolve(&app).await.unwrap()[0].version, "1.1.0");
+
+    // Offline, the index is all there is
+    assert_eq!(resolve(true).resolve(&app).await.unwrap()[0].version, "1.0.0");
+}
+
+#[tokio::test]
+async fn packages_a_private_registry_claims_skip_the_local_index() {
+    let index = MockIndex::start().await;
+    serve_app(&index, &[("9.9.9", "2023-01-10T12:00:00Z")]);
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let dir = tempfile::tempdir().unwrap();
+    let local = Arc::new(LocalIndex::open(dir.path().join("index")));
+    local.sync(&client, "app", 4).await.unwrap();
+
+    index.serve_json(
+        "/api/v1/repos/mycorp/python/package/python/app.json",
+        &json!({ "name": "app", "version": "1.2.0", "download_url": format!("{}/files/app-1.2.0-py3-none-any.whl", index.url) }),
+    );
+    let registry = PackageCloudRegistry::with_custom_url(index.url.clone(), None);
+    let registries = Arc::new(PrivateRegistries::new().with_repo(registry, "mycorp/python", &["app".to_string()], false));
+    let resolver = DependencyResolver::new(client, None)
+        .with_local_index(Some(local))
+        .with_registries(registries);
+    assert_eq!(resolver.resolve(&[dep("app", "*")]).await.unwrap()[0].version, "1.2.0");
+}
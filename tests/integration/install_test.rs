@@ -0,0 +1,1958 @@
+use crate::support::MockIndex;
+use cobra::cli::install::package_manager_for;
+use cobra::core::installer::{InstallReport, InstallStats, PackageSource, SpaceEstimate};
+use cobra::core::layout::InstallLayout;
+use cobra::core::package_manager::LocalPackageManager;
+use cobra::core::store::WheelStore;
+use cobra::core::wheel_cache::WheelCache;
+use cobra::utils::hash::sha256_hex;
+use cobra::{Installer, MultiLevelCache, Package, ProgressTracker, RegistryClient};
+use sha2::Digest;
+use std::collections::HashSet;
+use std::io::Write;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+/// A wheel containing a single module padded to roughly `size` bytes
+fn wheel(module: &str, size: usize) -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default()
+        .compression_method(zip::CompressionMethod::Stored);
+    writer.start_file(format!("{}/__init__.py", module), options).unwrap();
+    writer.write_all(&vec![b'#'; size]).unwrap();
+    writer.finish().unwrap().into_inner()
+}
+
+/// Files left in a download directory, ignoring the lock files that outlive downloads
+fn download_files(dir: &std::path::Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(dir).unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .filter(|name| !name.ends_with(".lock"))
+        .collect();
+    names.sort();
+    names
+}
+
+/// A package whose recorded sha256 is that of `archive`
+fn package(name: &str, download_url: String, archive: &[u8]) -> Package {
+    Package {
+        name: name.to_string(),
+        version: "1.0.0".to_string(),
+        dependencies: Vec::new(),
+        download_url,
+        hash: Some(sha256_hex(archive)),
+        size: None,
+        description: None,
+        author: None,
+        homepage: None,
+        markers: None,
+        yanked: false,
+        yanked_reason: None,
+        requires_python: None,
+    }
+}
+
+#[tokio::test]
+async fn install_stats_count_cache_and_network_bytes() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+
+    let cached_wheel = wheel("statscached", 4096);
+    let downloaded_wheel = wheel("statsdownloaded", 1024);
+    index.serve("/files/statsdownloaded.whl", downloaded_wheel.clone());
+
+    let wheels = Arc::new(WheelCache::new(dir.path().join("wheels")));
+    std::fs::create_dir_all(wheels.root()).unwrap();
+    std::fs::write(wheels.root().join("statscached.whl"), &cached_wheel).unwrap();
+
+    // A target layout never writes a .pth file into the user's site-packages
+    let layout = InstallLayout::target(dir.path().join("target"));
+    let installer = Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        Some(wheels),
+        Arc::new(ProgressTracker::new()),
+        Arc::new(LocalPackageManager::with_layout(layout)),
+    );
+
+    let stats: InstallStats = installer
+        .install_parallel(vec![
+            package("statscached", format!("{}/files/statscached.whl", index.url), &cached_wheel),
+            package("statsdownloaded", format!("{}/files/statsdownloaded.whl", index.url), &downloaded_wheel),
+        ])
+        .await
+        .unwrap();
+
+    assert_eq!(stats.cached_packages, 1);
+    assert_eq!(stats.downloaded_packages, 1);
+    assert_eq!(stats.bytes_saved, cached_wheel.len() as u64);
+    assert_eq!(stats.bytes_downloaded, downloaded_wheel.len() as u64);
+    assert_eq!(stats.installed_bytes, 4096 + 1024);
+    assert_eq!(index.requests(), vec!["/files/statsdownloaded.whl".to_string()]);
+
+    let cached = stats.packages.iter().find(|p| p.name == "statscached").unwrap();
+    assert_eq!(cached.source, PackageSource::Disk);
+    assert_eq!(
+        stats.summary(),
+        "Saved 4 KB via cache (served 1 packages from cache, downloaded 1)"
+    );
+
+    // The figure reaches the JSON that --report writes
+    let json = serde_json::to_value(&stats).unwrap();
+    assert_eq!(json["bytes_saved"], cached_wheel.len() as u64);
+    let report = serde_json::to_value(InstallReport::new(&stats, 0.0)).unwrap();
+    assert_eq!(report["totals"]["bytes_saved"], cached_wheel.len() as u64);
+}
+
+#[tokio::test]
+async fn install_reports_describe_each_package_and_round_trip_as_json() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+
+    let cached_wheel = wheel("reportcached", 2048);
+    let downloaded_wheel = wheel("reportdownloaded", 512);
+    index.serve("/files/reportdownloaded.whl", downloaded_wheel.clone());
+    let wheels = Arc::new(WheelCache::new(dir.path().join("wheels")));
+    std::fs::create_dir_all(wheels.root()).unwrap();
+    std::fs::write(wheels.root().join("reportcached.whl"), &cached_wheel).unwrap();
+
+    let layout = InstallLayout::target(dir.path().join("target"));
+    let stats = Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        Some(wheels),
+        Arc::new(ProgressTracker::new()),
+        Arc::new(LocalPackageManager::with_layout(layout)),
+    )
+    .install_parallel(vec![
+        package("reportcached", format!("{}/files/reportcached.whl", index.url), &cached_wheel),
+        package("reportdownloaded", format!("{}/files/reportdownloaded.whl", index.url), &downloaded_wheel),
+    ])
+    .await
+    .unwrap();
+
+    let report = InstallReport::new(&stats, 0.75);
+    assert_eq!(report.totals.installed_packages, 2);
+    assert_eq!((report.totals.cached_packages, report.totals.downloaded_packages), (1, 1));
+    assert_eq!(report.totals.cache_hit_rate, 0.75);
+
+    let downloaded = report.packages.iter().find(|p| p.name == "reportdownloaded").unwrap();
+    assert_eq!(downloaded.url, format!("{}/files/reportdownloaded.whl", index.url));
+    assert_eq!(downloaded.sha256, sha256_hex(&downloaded_wheel));
+    assert_eq!(downloaded.size, downloaded_wheel.len() as u64);
+    assert!(!downloaded.cache_hit);
+    assert_eq!(downloaded.install_path, dir.path().join("target"));
+    let cached = report.packages.iter().find(|p| p.name == "reportcached").unwrap();
+    assert!(cached.cache_hit);
+    assert_eq!(cached.source, PackageSource::Disk);
+
+    assert_eq!(report.totals.elapsed_ms, stats.elapsed_ms);
+
+    let json = serde_json::to_string(&report).unwrap();
+    assert_eq!(serde_json::from_str::<InstallReport>(&json).unwrap(), report);
+}
+
+#[test]
+fn timings_list_packages_slowest_first_above_the_totals() {
+    let outcome = |name: &str, source, bytes, download_ms, extract_ms| cobra::core::installer::InstallOutcome {
+        name: name.to_string(),
+        version: "1.0".to_string(),
+        source,
+        bytes,
+        mirror: None,
+        url: format!("https://files.example.com/{}.whl", name),
+        sha256: String::new(),
+        download_ms,
+        extract_ms,
+        install_path: std::path::PathBuf::from("site-packages"),
+    };
+    let mut stats = InstallStats::default();
+    stats.record(outcome("quick", PackageSource::Disk, 1024 * 1024, 0, 100));
+    stats.record(outcome("numpy", PackageSource::Network, 4 * 1024 * 1024, 2000, 500));
+    stats.elapsed_ms = 2500;
+
+    let totals = "Installed 2 packages (5 MB, 4 MB downloaded) in 2.50s, 2.0 MB/s";
+    assert_eq!(stats.timings(false), totals);
+
+    let table = stats.timings(true);
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines.len(), 4, "{}", table);
+    assert!(lines[0].starts_with("Package") && lines[0].ends_with("MB/s"), "{}", table);
+    assert!(lines[1].starts_with("numpy 1.0") && lines[1].contains("network") && lines[1].ends_with("2.0"), "{}", table);
+    assert!(lines[2].starts_with("quick 1.0") && lines[2].contains("cache") && lines[2].ends_with("-"), "{}", table);
+    assert_eq!(lines[3], totals);
+}
+
+#[tokio::test]
+async fn archives_missing_from_their_host_fail_over_to_the_next_mirror() {
+    let index = MockIndex::start().await;
+    let broken_mirror = MockIndex::start().await;
+    let mirror = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+
+    let direct = wheel("mirrordirect", 64);
+    let mirrored = wheel("mirrorfallback", 64);
+    index.serve("/files/mirrordirect.whl", direct.clone());
+    mirror.serve("/files/mirrorfallback.whl", mirrored.clone());
+
+    let stats = Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        None,
+        Arc::new(ProgressTracker::new()),
+        Arc::new(LocalPackageManager::with_layout(InstallLayout::target(dir.path().join("target")))),
+    )
+    .with_mirrors(vec![broken_mirror.url.clone(), mirror.url.clone()])
+    .install_parallel(vec![
+        package("mirrordirect", format!("{}/files/mirrordirect.whl", index.url), &direct),
+        package("mirrorfallback", format!("{}/files/mirrorfallback.whl", index.url), &mirrored),
+    ])
+    .await
+    .unwrap();
+
+    // Only the archive its host lacks goes to the mirrors
+    assert_eq!(broken_mirror.requests(), vec!["/files/mirrorfallback.whl".to_string()]);
+    assert_eq!(mirror.requests(), vec!["/files/mirrorfallback.whl".to_string()]);
+    let served_by = |name: &str| stats.packages.iter().find(|p| p.name == name).unwrap().mirror.clone();
+    assert_eq!(served_by("mirrordirect"), None);
+    assert_eq!(served_by("mirrorfallback"), Some(mirror.url.clone()));
+    assert_eq!(stats.mirror_fallbacks, 1);
+    assert!(stats.summary().ends_with("downloaded 2, 1 from a fallback mirror)"), "{}", stats.summary());
+}
+
+/// A wheel with a shared library and a symlink entry pointing at `link_target`
+fn symlink_wheel(module: &str, link_target: &str) -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default();
+    writer.start_file(format!("{}/libfoo.so.1", module), options).unwrap();
+    writer.write_all(b"ELF").unwrap();
+    writer.add_symlink(format!("{}/libfoo.so", module), link_target, options).unwrap();
+    writer.finish().unwrap().into_inner()
+}
+
+async fn install_wheel(name: &str, body: Vec<u8>, target: std::path::PathBuf) -> cobra::Result<InstallStats> {
+    install_release(name, "1.0.0", body, target, HashSet::new()).await
+}
+
+/// Install `version` of `name`, replacing the `reinstall` packages whatever their version
+async fn install_release(
+    name: &str,
+    version: &str,
+    body: Vec<u8>,
+    target: std::path::PathBuf,
+    reinstall: HashSet<String>,
+) -> cobra::Result<InstallStats> {
+    let index = MockIndex::start().await;
+    let package = Package {
+        version: version.to_string(),
+        ..package(name, format!("{}/files/{}-{}.whl", index.url, name, version), &body)
+    };
+    index.serve(&format!("/files/{}-{}.whl", name, version), body);
+    let installer = Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        None,
+        Arc::new(ProgressTracker::new()),
+        Arc::new(LocalPackageManager::with_layout(InstallLayout::target(target))),
+    )
+    .with_reinstall(reinstall);
+    installer
+        .install_parallel(vec![package])
+        .await
+}
+
+#[tokio::test]
+async fn a_wheel_failing_mid_extraction_leaves_nothing_installed() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    writer.start_file("torn/__init__.py", options).unwrap();
+    writer.write_all(&[b'a'; 64]).unwrap();
+    writer.start_file("torn/core.py", options).unwrap();
+    writer.write_all(&[b'b'; 64]).unwrap();
+    let mut body = writer.finish().unwrap().into_inner();
+    // The second file no longer matches its checksum, failing once the first is written
+    let start = body.windows(64).position(|window| window == [b'b'; 64]).unwrap();
+    body[start..start + 64].fill(b'c');
+
+    let target = dir.path().join("target");
+    let error = install_wheel("torn", body, target.clone()).await.unwrap_err();
+
+    assert!(matches!(error, cobra::CobraError::Io(_) | cobra::CobraError::Archive(_)), "{}", error);
+    let left: Vec<_> = std::fs::read_dir(&target).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+    assert!(left.is_empty(), "{:?}", left);
+}
+
+#[tokio::test]
+async fn staging_directories_left_by_a_killed_install_are_swept() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("target");
+    let stale = target.join(".staging/numpy-1.26.0-abc123");
+    let fresh = target.join(".staging/scipy-1.11.0-def456");
+    std::fs::create_dir_all(stale.join("numpy")).unwrap();
+    std::fs::create_dir_all(&fresh).unwrap();
+    let day_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(24 * 60 * 60);
+    std::fs::File::open(&stale).unwrap().set_modified(day_ago).unwrap();
+
+    install_wheel("swept", wheel("swept", 64), target.clone()).await.unwrap();
+
+    assert!(!stale.exists());
+    assert!(fresh.exists(), "a staging directory of an install still running is kept");
+    assert!(target.join("swept/__init__.py").is_file());
+}
+
+#[test]
+fn space_estimate_expands_known_archive_sizes() {
+    let sized = |size: Option<u64>| Package { size, ..package("sized", String::new(), b"") };
+    let estimate = SpaceEstimate::for_packages(&[sized(Some(100)), sized(None), sized(Some(50))]);
+    assert_eq!(estimate, SpaceEstimate { archives: 150, extracted: 450 });
+    assert_eq!(SpaceEstimate::for_packages(&[]), SpaceEstimate::default());
+    assert_eq!(SpaceEstimate::for_packages(&[sized(Some(u64::MAX / 2))]).extracted, u64::MAX);
+}
+
+#[tokio::test]
+async fn installs_needing_more_space_than_available_stop_before_downloading() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let body = wheel("huge", 16);
+    index.serve("/files/huge.whl", body.clone());
+    let huge = Package { size: Some(u64::MAX / 4), ..package("huge", format!("{}/files/huge.whl", index.url), &body) };
+    let installer = |check: bool| Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        None,
+        Arc::new(ProgressTracker::new()),
+        Arc::new(LocalPackageManager::with_layout(InstallLayout::target(dir.path().join("target")))),
+    )
+    .with_space_check(check);
+
+    let result = installer(true).install_parallel(vec![huge.clone()]).await;
+    assert!(matches!(result, Err(cobra::CobraError::InsufficientSpace { .. })), "{:?}", result.map(|_| ()));
+    assert!(index.requests().is_empty());
+
+    // Without the check it goes on to download, and trips over the bogus size instead
+    let result = installer(false).install_parallel(vec![huge]).await;
+    assert!(matches!(result, Err(cobra::CobraError::InstallationFailed(_))), "{:?}", result.map(|_| ()));
+    assert!(!index.requests().is_empty());
+}
+
+#[test]
+fn target_directories_resolve_against_the_current_directory() {
+    let config = cobra::core::config::CobraConfig::default();
+    let manager = package_manager_for(&config, Some(std::path::Path::new("vendor"))).unwrap();
+    let vendor = std::env::current_dir().unwrap().join("vendor");
+    assert_eq!(manager.registry_path(), vendor.join("cobra-registry.json"));
+    assert!(manager.layout().pth_entries(&["pkg".to_string()]).is_empty());
+
+    let absolute = tempfile::tempdir().unwrap();
+    let manager = package_manager_for(&config, Some(absolute.path())).unwrap();
+    assert_eq!(manager.layout().base_dir(), absolute.path());
+}
+
+#[tokio::test]
+async fn missing_target_directories_are_created_and_reused() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("build/lambda/vendor");
+    let stats = install_wheel("vendored", wheel("vendored", 64), target.clone()).await.unwrap();
+    assert_eq!(stats.downloaded_packages, 1);
+    assert!(target.join("vendored/__init__.py").is_file());
+    assert!(target.join("cobra-registry.json").is_file());
+
+    // The standalone registry makes a second run a no-op
+    let stats = install_wheel("vendored", wheel("vendored", 64), target).await.unwrap();
+    assert_eq!(stats.downloaded_packages, 0);
+}
+
+#[tokio::test]
+async fn symlink_entries_are_recreated() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("target");
+    install_wheel("linked", symlink_wheel("linked", "libfoo.so.1"), target.clone()).await.unwrap();
+
+    let link = target.join("linked/libfoo.so");
+    assert_eq!(std::fs::read(&link).unwrap(), b"ELF");
+    #[cfg(unix)]
+    {
+        assert!(std::fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(std::fs::read_link(&link).unwrap(), std::path::Path::new("libfoo.so.1"));
+    }
+}
+
+#[tokio::test]
+async fn symlink_escaping_install_dir_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("target");
+    let result = install_wheel("escape", symlink_wheel("escape", "../../etc/passwd"), target.clone()).await;
+
+    assert!(result.is_err());
+    assert!(std::fs::symlink_metadata(target.join("escape/libfoo.so")).is_err());
+}
+
+/// A wheel with a legitimate module plus one file at `malicious`
+fn slip_wheel(malicious: &str) -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default();
+    writer.start_file("victim/__init__.py", options).unwrap();
+    writer.write_all(b"").unwrap();
+    writer.start_file(malicious, options).unwrap();
+    writer.write_all(b"pwned").unwrap();
+    writer.finish().unwrap().into_inner()
+}
+
+#[test]
+fn entries_escaping_the_install_dir_are_rejected() {
+    use cobra::core::installer::extract_wheel;
+
+    let dir = tempfile::tempdir().unwrap();
+    let site = dir.path().join("a/b/site");
+    for malicious in ["../../../outside.txt", "victim/../../outside.txt", "..\\..\\..\\outside.txt", "/tmp/outside.txt", "C:/outside.txt"] {
+        let archive = dir.path().join("slip.whl");
+        std::fs::write(&archive, slip_wheel(malicious)).unwrap();
+
+        let err = extract_wheel(&archive, &site).unwrap_err().to_string();
+        assert!(err.contains(malicious), "{}: {}", malicious, err);
+    }
+    assert!(!dir.path().join("outside.txt").exists());
+    assert!(!dir.path().join("a/outside.txt").exists());
+
+    // A symlink entry whose own name climbs out is refused like a file
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    writer.add_symlink("../escape", "victim", zip::write::FileOptions::default()).unwrap();
+    let archive = dir.path().join("link.whl");
+    std::fs::write(&archive, writer.finish().unwrap().into_inner()).unwrap();
+    assert!(extract_wheel(&archive, &site).is_err());
+    assert!(std::fs::symlink_metadata(dir.path().join("a/b/escape")).is_err());
+}
+
+#[test]
+fn wheels_unpacking_past_the_size_limit_are_refused() {
+    use cobra::core::installer::extract_wheel_within;
+
+    // A megabyte of zeros deflates to about a kilobyte
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    writer.start_file("bomb/zeros.bin", zip::write::FileOptions::default()).unwrap();
+    writer.write_all(&vec![0u8; 1024 * 1024]).unwrap();
+    let data = writer.finish().unwrap().into_inner();
+    assert!(data.len() < 16 * 1024);
+
+    let dir = tempfile::tempdir().unwrap();
+    let archive = dir.path().join("bomb.whl");
+    std::fs::write(&archive, &data).unwrap();
+    let site = dir.path().join("site");
+    let err = extract_wheel_within(&archive, &site, 64 * 1024).unwrap_err();
+    assert!(matches!(&err, cobra::CobraError::Archive(message) if message.contains("max-unpacked-size")), "{}", err);
+    assert!(!site.join("bomb").exists());
+
+    extract_wheel_within(&archive, &site, 2 * 1024 * 1024).unwrap();
+    assert_eq!(std::fs::metadata(site.join("bomb/zeros.bin")).unwrap().len(), 1024 * 1024);
+}
+
+#[test]
+fn entries_inflating_past_their_declared_size_are_refused() {
+    use cobra::core::installer::extract_wheel_within;
+
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    writer.start_file("bomb/zeros.bin", zip::write::FileOptions::default()).unwrap();
+    writer.write_all(&vec![0u8; 1024 * 1024]).unwrap();
+    let mut data = writer.finish().unwrap().into_inner();
+
+    // Claim 16 bytes in both the local header and the central directory,
+    // so the declared total stays far under the limit
+    let patch = |data: &mut Vec<u8>, signature: &[u8], offset: usize| {
+        let at = data.windows(4).position(|window| window == signature).unwrap() + offset;
+        data[at..at + 4].copy_from_slice(&16u32.to_le_bytes());
+    };
+    patch(&mut data, b"PK\x03\x04", 22);
+    patch(&mut data, b"PK\x01\x02", 24);
+
+    let dir = tempfile::tempdir().unwrap();
+    let archive = dir.path().join("liar.whl");
+    std::fs::write(&archive, &data).unwrap();
+    let site = dir.path().join("site");
+    let err = extract_wheel_within(&archive, &site, 64 * 1024).unwrap_err();
+    assert!(matches!(&err, cobra::CobraError::Archive(message) if message.contains("declared 16 bytes")), "{}", err);
+    assert!(!site.join("bomb/zeros.bin").exists());
+}
+
+#[test]
+fn nested_and_dotted_entries_extract_inside_the_install_dir() {
+    use cobra::core::installer::extract_wheel;
+
+    let dir = tempfile::tempdir().unwrap();
+    let archive = dir.path().join("nested.whl");
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default();
+    for path in ["nested/a/b/c/deep.py", "nested/./sub/../data.txt", "nested\\win.py"] {
+        writer.start_file(path, options).unwrap();
+        writer.write_all(path.as_bytes()).unwrap();
+    }
+    std::fs::write(&archive, writer.finish().unwrap().into_inner()).unwrap();
+
+    let site = dir.path().join("site");
+    let extracted = extract_wheel(&archive, &site).unwrap();
+
+    let recorded: Vec<&str> = extracted.record.iter().map(|entry| entry.path.as_str()).collect();
+    assert_eq!(recorded, ["nested/a/b/c/deep.py", "nested/data.txt", "nested/win.py"]);
+    assert_eq!(std::fs::read_to_string(site.join("nested/a/b/c/deep.py")).unwrap(), "nested/a/b/c/deep.py");
+    assert_eq!(std::fs::read_to_string(site.join("nested/data.txt")).unwrap(), "nested/./sub/../data.txt");
+    assert!(site.join("nested/win.py").is_file());
+}
+
+/// A wheel with an executable helper and every `.data` scheme
+fn data_wheel() -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let plain = zip::write::FileOptions::default().unix_permissions(0o644);
+    let executable = zip::write::FileOptions::default().unix_permissions(0o755);
+    let files = [
+        ("fixture/__init__.py", plain, "VALUE = 1\n"),
+        ("fixture/helper", executable, "\x7fELF"),
+        ("fixture-1.0.0.data/scripts/fixture-tool", plain, "#!python\nprint('hi')\n"),
+        ("fixture-1.0.0.data/scripts/plain.sh", plain, "#!/bin/sh\necho hi\n"),
+        ("fixture-1.0.0.data/data/share/fixture/readme.txt", plain, "docs"),
+        ("fixture-1.0.0.data/headers/fixture.h", plain, "int f(void);"),
+        ("fixture-1.0.0.data/purelib/fixture_extra.py", plain, "EXTRA = 1\n"),
+        ("fixture-1.0.0.dist-info/METADATA", plain, "Metadata-Version: 2.1\n"),
+    ];
+    for (path, options, contents) in files {
+        writer.start_file(path, options).unwrap();
+        writer.write_all(contents.as_bytes()).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+#[cfg(unix)]
+fn is_executable(path: &std::path::Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path).unwrap().permissions().mode() & 0o111 != 0
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn data_schemes_are_placed_and_executable_bits_kept() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("target");
+    install_wheel("fixture", data_wheel(), target.clone()).await.unwrap();
+
+    assert!(is_executable(&target.join("fixture/helper")));
+    assert!(!is_executable(&target.join("fixture/__init__.py")));
+
+    // Scripts land in bin, executable, with #!python pointed at a real interpreter
+    let tool = std::fs::read_to_string(target.join("bin/fixture-tool")).unwrap();
+    let (shebang, body) = tool.split_once('\n').unwrap();
+    assert!(shebang.starts_with("#!/") && shebang.contains("python"), "{}", shebang);
+    assert_eq!(body, "print('hi')\n");
+    assert!(is_executable(&target.join("bin/fixture-tool")));
+    assert_eq!(std::fs::read_to_string(target.join("bin/plain.sh")).unwrap(), "#!/bin/sh\necho hi\n");
+    assert!(is_executable(&target.join("bin/plain.sh")));
+
+    assert_eq!(std::fs::read_to_string(target.join("share/fixture/readme.txt")).unwrap(), "docs");
+    assert!(target.join("include/fixture/fixture.h").is_file());
+    assert!(target.join("fixture_extra.py").is_file());
+    assert!(!target.join("fixture-1.0.0.data").exists());
+
+    let manager = LocalPackageManager::with_layout(InstallLayout::target(target.clone()));
+    let installed = manager.list_installed().await.unwrap().remove(0);
+    assert!(installed.files.iter().any(|entry| entry.path == "bin/fixture-tool"));
+    assert!(manager.verify_package(&installed).await.unwrap().is_empty());
+
+    manager.uninstall("fixture").await.unwrap();
+    assert!(!target.join("bin/fixture-tool").exists());
+    assert!(!target.join("share/fixture/readme.txt").exists());
+}
+
+/// A wheel whose scripts are symlinks, one into the package and one to another script
+fn linked_scripts_wheel() -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let executable = zip::write::FileOptions::default().unix_permissions(0o755);
+    writer.start_file("linktool/cli.sh", executable).unwrap();
+    writer.write_all(b"#!/bin/sh\necho linked\n").unwrap();
+    writer.add_symlink("linktool-1.0.0.data/scripts/linktool", "../../linktool/cli.sh", executable).unwrap();
+    writer.add_symlink("linktool-1.0.0.data/scripts/lt", "linktool", executable).unwrap();
+    writer.start_file("linktool-1.0.0.dist-info/METADATA", zip::write::FileOptions::default()).unwrap();
+    writer.write_all(b"Metadata-Version: 2.1\n").unwrap();
+    writer.finish().unwrap().into_inner()
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn symlinked_scripts_point_at_their_targets_after_moving_to_bin() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let store = Arc::new(WheelStore::new(dir.path().join("store")));
+    let body = linked_scripts_wheel();
+    index.serve("/files/linktool.whl", body.clone());
+    let pkg = package("linktool", format!("{}/files/linktool.whl", index.url), &body);
+
+    // Extracted directly, then linked out of the wheel store
+    for project in ["first", "second"] {
+        let target = dir.path().join(project);
+        store_installer(&index, &store, target.clone()).install_parallel(vec![pkg.clone()]).await.unwrap();
+
+        let tool = target.join("bin/linktool");
+        assert_eq!(std::fs::read_link(&tool).unwrap(), std::path::Path::new("../linktool/cli.sh"));
+        assert_eq!(std::fs::read_link(target.join("bin/lt")).unwrap(), std::path::Path::new("linktool"));
+        assert_eq!(std::fs::read_to_string(target.join("bin/lt")).unwrap(), "#!/bin/sh\necho linked\n");
+        assert!(is_executable(&target.join("linktool/cli.sh")));
+        let output = std::process::Command::new(&tool).output().unwrap();
+        assert_eq!(output.stdout, b"linked\n");
+    }
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn venv_scripts_use_the_venv_interpreter_and_record_relative_paths() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let body = data_wheel();
+    index.serve("/files/fixture.whl", body.clone());
+    let layout = InstallLayout::venv(dir.path().join("venv"), "3.11");
+    let manager = Arc::new(LocalPackageManager::with_layout(layout.clone()));
+    Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        None,
+        Arc::new(ProgressTracker::new()),
+        manager.clone(),
+    )
+    .install_parallel(vec![package("fixture", format!("{}/files/fixture.whl", index.url), &body)])
+    .await
+    .unwrap();
+
+    let tool = std::fs::read_to_string(dir.path().join("venv/bin/fixture-tool")).unwrap();
+    assert!(tool.starts_with(&format!("#!{}\n", dir.path().join("venv/bin/python").display())), "{}", tool);
+    let installed = manager.list_installed().await.unwrap().remove(0);
+    assert!(installed.files.iter().any(|entry| entry.path == "../../../bin/fixture-tool"));
+    assert!(manager.verify_package(&installed).await.unwrap().is_empty());
+
+    // Uninstalling by any spelling of the name empties the directories the
+    // data schemes created, but leaves the venv's own bin
+    let venv = dir.path().join("venv");
+    std::fs::write(venv.join("bin/python"), "").unwrap();
+    assert!(manager.uninstall("Fixture").await.unwrap());
+    for gone in ["bin/fixture-tool", "bin/plain.sh", "include", "share"] {
+        assert!(!venv.join(gone).exists(), "{} was left behind", gone);
+    }
+    assert!(venv.join("bin/python").exists());
+    assert!(venv.join(layout.site_packages("fixture")).exists());
+    assert!(manager.list_installed().await.unwrap().is_empty());
+}
+
+/// A wheel with an empty `__init__.py` and an explicit empty directory entry
+fn sparse_wheel(module: &str) -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default();
+    writer.start_file(format!("{}/__init__.py", module), options).unwrap();
+    writer.add_directory(format!("{}/data/", module), options).unwrap();
+    writer.finish().unwrap().into_inner()
+}
+
+#[tokio::test]
+async fn zero_byte_files_and_empty_directories_are_created() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("target");
+    install_wheel("sparse", sparse_wheel("sparse"), target.clone()).await.unwrap();
+
+    let init = target.join("sparse/__init__.py");
+    assert!(init.is_file());
+    assert_eq!(std::fs::metadata(&init).unwrap().len(), 0);
+    assert!(target.join("sparse/data").is_dir());
+}
+
+#[tokio::test]
+async fn interrupted_download_resumes_with_a_range_request() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let archive = wheel("resumed", 4096);
+    let pkg = package("resumed", format!("{}/files/resumed.whl", index.url), &archive);
+    index.serve("/files/resumed.whl", archive);
+    index.accept_ranges("/files/resumed.whl");
+    index.cut_next("/files/resumed.whl", 1000);
+
+    let target = dir.path().join("target");
+    Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        None,
+        Arc::new(ProgressTracker::new()),
+        Arc::new(LocalPackageManager::with_layout(InstallLayout::target(target.clone()))),
+    )
+    .with_download_dir(dir.path().join("downloads"))
+    .install_parallel(vec![pkg])
+    .await
+    .unwrap();
+
+    assert_eq!(index.requests(), ["/files/resumed.whl", "/files/resumed.whl"]);
+    assert_eq!(index.ranges(), ["bytes=1000-"]);
+    assert!(target.join("resumed/__init__.py").is_file());
+    assert_eq!(download_files(&dir.path().join("downloads")), Vec::<String>::new());
+}
+
+#[tokio::test]
+async fn cancelling_keeps_finished_packages_and_removes_partial_downloads() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let finished = wheel("finished", 1024);
+    let stalled = wheel("stalled", 1024);
+    index.serve("/files/finished.whl", finished.clone());
+    index.stall("/files/stalled.whl");
+
+    let target = dir.path().join("target");
+    let package_manager = Arc::new(LocalPackageManager::with_layout(InstallLayout::target(target.clone())));
+    let cancel = CancellationToken::new();
+    let installer = Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        None,
+        Arc::new(ProgressTracker::new()),
+        package_manager.clone(),
+    )
+    .with_download_dir(dir.path().join("downloads"))
+    .with_cancellation(cancel.clone());
+
+    // Cancel once the package that can finish has been registered
+    let watcher = tokio::spawn({
+        let package_manager = package_manager.clone();
+        async move {
+            while package_manager.list_installed().await.unwrap().is_empty() {
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            }
+            cancel.cancel();
+        }
+    });
+    let result = installer
+        .install_parallel(vec![
+            package("finished", format!("{}/files/finished.whl", index.url), &finished),
+            package("stalled", format!("{}/files/stalled.whl", index.url), &stalled),
+        ])
+        .await;
+    watcher.await.unwrap();
+
+    assert!(matches!(result, Err(cobra::CobraError::Cancelled)), "{:?}", result.map(|s| s.packages));
+    let installed: Vec<String> = package_manager.list_installed().await.unwrap().into_iter().map(|p| p.name).collect();
+    assert_eq!(installed, ["finished"]);
+    assert!(target.join("finished/__init__.py").is_file());
+    assert!(!target.join("stalled").exists());
+    assert_eq!(download_files(&dir.path().join("downloads")), Vec::<String>::new());
+}
+
+#[tokio::test]
+async fn large_archives_download_in_parallel_segments() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let archive = wheel("segmented", 40_000);
+    let mut pkg = package("segmented", format!("{}/files/segmented.whl", index.url), &archive);
+    pkg.size = Some(archive.len() as u64);
+    let len = archive.len();
+    index.serve("/files/segmented.whl", archive);
+    index.accept_ranges("/files/segmented.whl");
+
+    let target = dir.path().join("target");
+    Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        None,
+        Arc::new(ProgressTracker::new()),
+        Arc::new(LocalPackageManager::with_layout(InstallLayout::target(target.clone()))),
+    )
+    .with_download_dir(dir.path().join("downloads"))
+    .with_segment_min_size(1024)
+    .install_parallel(vec![pkg])
+    .await
+    .unwrap();
+
+    let segment = len.div_ceil(4);
+    let mut ranges = index.ranges();
+    ranges.sort_by_key(|r| r.trim_start_matches("bytes=").split('-').next().unwrap().parse::<usize>().unwrap());
+    let expected: Vec<String> = (0..4)
+        .map(|i| format!("bytes={}-{}", i * segment, ((i + 1) * segment).min(len) - 1))
+        .collect();
+    assert_eq!(ranges, expected);
+    assert!(target.join("segmented/__init__.py").is_file());
+}
+
+#[tokio::test]
+async fn segmented_download_falls_back_to_one_stream_without_range_support() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let archive = wheel("single", 40_000);
+    let mut pkg = package("single", format!("{}/files/single.whl", index.url), &archive);
+    pkg.size = Some(archive.len() as u64);
+    index.serve("/files/single.whl", archive);
+
+    let target = dir.path().join("target");
+    Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        None,
+        Arc::new(ProgressTracker::new()),
+        Arc::new(LocalPackageManager::with_layout(InstallLayout::target(target.clone()))),
+    )
+    .with_download_dir(dir.path().join("downloads"))
+    .with_segment_min_size(1024)
+    .install_parallel(vec![pkg])
+    .await
+    .unwrap();
+
+    // The first segment's request is the probe; the server answered it in full
+    assert_eq!(index.requests().len(), 2);
+    assert!(target.join("single/__init__.py").is_file());
+}
+
+#[tokio::test]
+async fn segments_never_exceed_the_download_limit() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let archive = wheel("limited", 40_000);
+    let mut pkg = package("limited", format!("{}/files/limited.whl", index.url), &archive);
+    pkg.size = Some(archive.len() as u64);
+    index.serve("/files/limited.whl", archive);
+    index.accept_ranges("/files/limited.whl");
+
+    let target = dir.path().join("target");
+    Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        None,
+        Arc::new(ProgressTracker::new()),
+        Arc::new(LocalPackageManager::with_layout(InstallLayout::target(target.clone()))),
+    )
+    .with_download_dir(dir.path().join("downloads"))
+    .with_parallel_downloads(2)
+    .with_segment_min_size(1024)
+    .install_parallel(vec![pkg])
+    .await
+    .unwrap();
+
+    assert_eq!(index.ranges().len(), 2);
+    assert!(target.join("limited/__init__.py").is_file());
+}
+
+#[tokio::test]
+async fn stale_partial_download_restarts_when_server_ignores_range() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let archive = wheel("restarted", 4096);
+    let pkg = package("restarted", format!("{}/files/restarted.whl", index.url), &archive);
+    index.serve("/files/restarted.whl", archive);
+
+    // Left behind by an earlier run, and not a prefix of the archive
+    let downloads = dir.path().join("downloads");
+    std::fs::create_dir_all(&downloads).unwrap();
+    std::fs::write(downloads.join("restarted-1.0.0.whl.part"), vec![b'x'; 500]).unwrap();
+
+    let target = dir.path().join("target");
+    Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        None,
+        Arc::new(ProgressTracker::new()),
+        Arc::new(LocalPackageManager::with_layout(InstallLayout::target(target.clone()))),
+    )
+    .with_download_dir(downloads)
+    .install_parallel(vec![pkg])
+    .await
+    .unwrap();
+
+    assert_eq!(index.ranges(), ["bytes=500-"]);
+    assert_eq!(std::fs::metadata(target.join("restarted/__init__.py")).unwrap().len(), 4096);
+}
+
+#[tokio::test]
+async fn offline_install_uses_only_cached_metadata_and_archives() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let uploaded = "2024-01-01T00:00:00Z";
+    let archive = wheel("offlinepkg", 64);
+    let mut project = crate::support::project_json(&index.url, "offlinepkg", &[("1.0.0", uploaded)], &[]);
+    crate::support::set_archive(&mut project, &archive);
+    index.serve_json("/pypi/offlinepkg/json", &project);
+    index.serve("/files/offlinepkg-1.0.0-py3-none-any.whl", archive);
+    let roots = [cobra::Dependency { name: "offlinepkg".to_string(), version_spec: "*".to_string(), markers: None, source: cobra::DependencySource::Registry, extras: Vec::new(), optional: false }];
+
+    // Populate the caches with an online resolve and install
+    let cache = Arc::new(MultiLevelCache::open(&dir.path().join("cache")).await.unwrap());
+    let wheels = Arc::new(WheelCache::new(dir.path().join("wheels")));
+    let online = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let resolved = cobra::DependencyResolver::new(online.clone(), Some(cache.clone()))
+        .resolve(&roots)
+        .await
+        .unwrap();
+    Installer::new(
+        online,
+        Some(wheels.clone()),
+        Arc::new(ProgressTracker::new()),
+        Arc::new(LocalPackageManager::with_layout(InstallLayout::target(dir.path().join("online")))),
+    )
+    .install_parallel(resolved)
+    .await
+    .unwrap();
+    drop(cache);
+
+    // A fresh process with an unreachable registry sees the same cache
+    let cache = Arc::new(MultiLevelCache::open(&dir.path().join("cache")).await.unwrap());
+    let unreachable = Arc::new(RegistryClient::with_base_url("http://127.0.0.1:9".to_string()));
+    let resolver = cobra::DependencyResolver::new(unreachable.clone(), Some(cache.clone())).with_offline(true);
+    let resolved = resolver.resolve(&roots).await.unwrap();
+    let target = dir.path().join("offline");
+    let stats = Installer::new(
+        unreachable,
+        Some(wheels),
+        Arc::new(ProgressTracker::new()),
+        Arc::new(LocalPackageManager::with_layout(InstallLayout::target(target.clone()))),
+    )
+    .with_offline(true)
+    .install_parallel(resolved)
+    .await
+    .unwrap();
+
+    assert_eq!(stats.cached_packages, 1);
+    assert_eq!(stats.downloaded_packages, 0);
+    assert!(target.join("offlinepkg/__init__.py").is_file());
+
+    let missing = [cobra::Dependency { name: "uncached".to_string(), version_spec: "*".to_string(), markers: None, source: cobra::DependencySource::Registry, extras: Vec::new(), optional: false }];
+    let error = resolver.resolve(&missing).await.unwrap_err();
+    assert!(matches!(error, cobra::CobraError::Offline(ref message) if message.contains("uncached")));
+}
+
+#[tokio::test]
+async fn complete_lockfile_installs_without_metadata_requests() {
+    use cobra::core::lockfile::{input_hash, Lockfile};
+    use cobra::core::resolver::ResolutionStrategy;
+
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let archive = wheel("lockedpkg", 64);
+    index.serve("/files/lockedpkg-1.0.0-py3-none-any.whl", archive.clone());
+
+    let roots = [cobra::Dependency { name: "lockedpkg".to_string(), version_spec: ">=1.0".to_string(), markers: None, source: cobra::DependencySource::Registry, extras: Vec::new(), optional: false }];
+    let overrides = Default::default();
+    let hash = input_hash(&roots, &overrides, ResolutionStrategy::Highest, None, None);
+    let locked = package("lockedpkg", format!("{}/files/lockedpkg-1.0.0-py3-none-any.whl", index.url), &archive);
+    let lockfile = Lockfile::from_packages(&[locked], ResolutionStrategy::Highest).with_input_hash(hash.clone());
+
+    // Any change to the inputs invalidates the lock
+    let changed = input_hash(&roots, &overrides, ResolutionStrategy::Lowest, None, None);
+    assert!(lockfile.installable_packages(&changed).is_none());
+    let python = cobra::core::version::Version::parse("3.8.0").unwrap();
+    let other_python = input_hash(&roots, &overrides, ResolutionStrategy::Highest, None, Some(&python));
+    assert!(lockfile.installable_packages(&other_python).is_none());
+
+    let packages = lockfile.installable_packages(&hash).unwrap();
+    let target = dir.path().join("target");
+    Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        None,
+        Arc::new(ProgressTracker::new()),
+        Arc::new(LocalPackageManager::with_layout(InstallLayout::target(target.clone()))),
+    )
+    .install_parallel(packages)
+    .await
+    .unwrap();
+
+    assert!(target.join("lockedpkg/__init__.py").is_file());
+    assert_eq!(index.requests(), vec!["/files/lockedpkg-1.0.0-py3-none-any.whl".to_string()]);
+}
+
+#[tokio::test]
+async fn a_fully_installed_current_lock_is_up_to_date_without_any_requests() {
+    use cobra::cli::install::already_up_to_date;
+    use cobra::core::lockfile::{input_hash, Lockfile};
+    use cobra::core::markers::MarkerEnvironment;
+    use cobra::core::resolver::ResolutionStrategy;
+
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let archive = wheel("rangedpkg", 64);
+    index.serve("/files/rangedpkg-2.31.0-py3-none-any.whl", archive.clone());
+
+    let requirement = |spec: &str| [cobra::Dependency { name: "RangedPkg".to_string(), version_spec: spec.to_string(), markers: None, source: cobra::DependencySource::Registry, extras: Vec::new(), optional: false }];
+    let roots = requirement(">=2.0");
+    let hash = input_hash(&roots, &Default::default(), ResolutionStrategy::Highest, None, None);
+    let mut locked = package("rangedpkg", format!("{}/files/rangedpkg-2.31.0-py3-none-any.whl", index.url), &archive);
+    locked.version = "2.31.0".to_string();
+    let lockfile = Lockfile::from_packages(&[locked], ResolutionStrategy::Highest).with_input_hash(hash.clone());
+    let environment = MarkerEnvironment::current("3.11");
+
+    let manager = Arc::new(LocalPackageManager::with_layout(InstallLayout::target(dir.path().join("target"))));
+    assert!(!already_up_to_date(&lockfile, &hash, &roots, &[], &environment));
+    Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        None,
+        Arc::new(ProgressTracker::new()),
+        manager.clone(),
+    )
+    .install_parallel(lockfile.installable_packages(&hash).unwrap())
+    .await
+    .unwrap();
+    let requests = index.requests().len();
+
+    // 2.31.0 satisfies >=2.0, so nothing needs resolving or reinstalling
+    let installed = manager.list_installed().await.unwrap();
+    assert!(already_up_to_date(&lockfile, &hash, &roots, &installed, &environment));
+    assert_eq!(index.requests().len(), requests);
+
+    assert!(!already_up_to_date(&lockfile, &hash, &requirement(">=3.0"), &installed, &environment));
+    let edited = input_hash(&requirement(">=2.1"), &Default::default(), ResolutionStrategy::Highest, None, None);
+    assert!(!already_up_to_date(&lockfile, &edited, &requirement(">=2.1"), &installed, &environment));
+}
+
+#[tokio::test]
+async fn poisoned_cache_entry_aborts_install() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let archive = wheel("poisoned", 64);
+    let pkg = package("poisoned", format!("{}/files/poisoned.whl", index.url), &archive);
+    index.serve("/files/poisoned.whl", archive.clone());
+
+    // The cached artifact no longer matches the digest recorded in the lock
+    let wheels = Arc::new(WheelCache::new(dir.path().join("wheels")));
+    std::fs::create_dir_all(wheels.root()).unwrap();
+    let tampered = wheel("poisoned", 65);
+    std::fs::write(wheels.root().join("poisoned.whl"), &tampered).unwrap();
+
+    let target = dir.path().join("target");
+    let installer = |verify: bool| {
+        Installer::new(
+            Arc::new(RegistryClient::with_base_url(index.url.clone())),
+            Some(wheels.clone()),
+            Arc::new(ProgressTracker::new()),
+            Arc::new(LocalPackageManager::with_layout(InstallLayout::target(target.clone()))),
+        )
+        .with_verify(verify)
+    };
+
+    let error = installer(true).install_parallel(vec![pkg.clone()]).await.unwrap_err();
+    match error {
+        cobra::CobraError::HashMismatch { package, expected, computed, .. } => {
+            assert_eq!(package, "poisoned 1.0.0");
+            assert_eq!(Some(expected), pkg.hash.clone());
+            assert_eq!(computed, sha256_hex(&tampered));
+        }
+        other => panic!("expected a hash mismatch, got {}", other),
+    }
+    assert!(!target.join("poisoned/__init__.py").exists());
+    assert!(index.requests().is_empty());
+
+    // The poisoned entry was evicted, so a retry downloads the genuine archive
+    assert!(!wheels.root().join("poisoned.whl").exists());
+    installer(true).install_parallel(vec![pkg.clone()]).await.unwrap();
+    assert_eq!(index.requests(), ["/files/poisoned.whl"]);
+    assert_eq!(std::fs::metadata(target.join("poisoned/__init__.py")).unwrap().len(), 64);
+    assert_eq!(std::fs::read(wheels.root().join("poisoned.whl")).unwrap(), archive);
+}
+
+/// A wheel whose METADATA names `name` at `version`
+fn named_wheel(module: &str, name: &str, version: &str) -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default();
+    writer.start_file(format!("{}/__init__.py", module), options).unwrap();
+    writer.start_file(format!("{}-{}.dist-info/METADATA", module, version), options).unwrap();
+    write!(writer, "Metadata-Version: 2.1\nName: {}\nVersion: {}\n", name, version).unwrap();
+    writer.finish().unwrap().into_inner()
+}
+
+#[tokio::test]
+async fn a_cached_wheel_of_another_project_is_refused_and_evicted() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    // The digest matches, as when the index itself points at the wrong file
+    let archive = named_wheel("other", "Other_Project", "1.0.0");
+    let pkg = package("wanted", format!("{}/files/wanted.whl", index.url), &archive);
+
+    let wheels = Arc::new(WheelCache::new(dir.path().join("wheels")));
+    std::fs::create_dir_all(wheels.root()).unwrap();
+    std::fs::write(wheels.root().join("wanted.whl"), &archive).unwrap();
+
+    let target = dir.path().join("target");
+    let error = Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        Some(wheels.clone()),
+        Arc::new(ProgressTracker::new()),
+        Arc::new(LocalPackageManager::with_layout(InstallLayout::target(target.clone()))),
+    )
+    .install_parallel(vec![pkg])
+    .await
+    .unwrap_err();
+
+    let message = error.to_string();
+    assert!(message.contains("resolved as wanted 1.0.0 but its METADATA is for Other_Project 1.0.0"), "{}", message);
+    assert!(!wheels.root().join("wanted.whl").exists());
+    assert!(!target.join("other").exists());
+}
+
+#[tokio::test]
+async fn a_wheel_whose_version_differs_from_the_resolved_one_is_refused() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let archive = named_wheel("drift", "drift", "1.0.0");
+    let filename = "drift-2.0.0-py3-none-any.whl";
+    let pkg = package("drift", format!("{}/files/{}", index.url, filename), &archive);
+    index.serve(&format!("/files/{}", filename), archive);
+
+    let error = Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        None,
+        Arc::new(ProgressTracker::new()),
+        Arc::new(LocalPackageManager::with_layout(InstallLayout::target(dir.path().join("target")))),
+    )
+    .install_parallel(vec![pkg])
+    .await
+    .unwrap_err();
+
+    assert!(error.to_string().contains("its filename carries version 2.0.0"), "{}", error);
+
+    // Equal versions spelled differently still match
+    let archive = named_wheel("spelled", "spelled", "1.0");
+    let pkg = package("spelled", format!("{}/files/spelled-1.0.0-py3-none-any.whl", index.url), &archive);
+    index.serve("/files/spelled-1.0.0-py3-none-any.whl", archive);
+    Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        None,
+        Arc::new(ProgressTracker::new()),
+        Arc::new(LocalPackageManager::with_layout(InstallLayout::target(dir.path().join("target")))),
+    )
+    .install_parallel(vec![pkg])
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn no_verify_installs_whatever_the_cache_holds() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let pkg = package("unchecked", format!("{}/files/unchecked.whl", index.url), &wheel("unchecked", 64));
+
+    let wheels = Arc::new(WheelCache::new(dir.path().join("wheels")));
+    std::fs::create_dir_all(wheels.root()).unwrap();
+    std::fs::write(wheels.root().join("unchecked.whl"), wheel("unchecked", 65)).unwrap();
+
+    let target = dir.path().join("target");
+    Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        Some(wheels.clone()),
+        Arc::new(ProgressTracker::new()),
+        Arc::new(LocalPackageManager::with_layout(InstallLayout::target(target.clone()))),
+    )
+    .with_verify(false)
+    .install_parallel(vec![pkg])
+    .await
+    .unwrap();
+
+    assert_eq!(std::fs::metadata(target.join("unchecked/__init__.py")).unwrap().len(), 65);
+    assert!(index.requests().is_empty());
+}
+
+#[tokio::test]
+async fn tampered_download_is_not_cached() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let pkg = package("tampered", format!("{}/files/tampered.whl", index.url), &wheel("tampered", 64));
+    index.serve("/files/tampered.whl", wheel("tampered", 65));
+
+    let wheels = Arc::new(WheelCache::new(dir.path().join("wheels")));
+    let result = Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        Some(wheels.clone()),
+        Arc::new(ProgressTracker::new()),
+        Arc::new(LocalPackageManager::with_layout(InstallLayout::target(dir.path().join("target")))),
+    )
+    .install_parallel(vec![pkg])
+    .await;
+
+    assert!(matches!(result, Err(cobra::CobraError::HashMismatch { .. })));
+    assert!(wheels.list().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn find_links_directories_are_used_before_downloading() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let archive = wheel("linked", 64);
+    let pkg = package("linked", format!("{}/files/linked-1.0.0-py3-none-any.whl#sha256=abc", index.url), &archive);
+    let links = dir.path().join("links");
+    std::fs::create_dir_all(&links).unwrap();
+    std::fs::write(links.join("linked-1.0.0-py3-none-any.whl"), &archive).unwrap();
+
+    let target = dir.path().join("target");
+    let stats = Installer::new(
+        Arc::new(RegistryClient::with_base_url("http://127.0.0.1:9".to_string())),
+        None,
+        Arc::new(ProgressTracker::new()),
+        Arc::new(LocalPackageManager::with_layout(InstallLayout::target(target.clone()))),
+    )
+    .with_find_links(vec![dir.path().join("missing"), links])
+    .with_offline(true)
+    .install_parallel(vec![pkg])
+    .await
+    .unwrap();
+
+    assert_eq!(stats.packages[0].source, PackageSource::Disk);
+    assert!(target.join("linked/__init__.py").is_file());
+}
+
+#[tokio::test]
+async fn unused_cached_wheels_are_pruned() {
+    let dir = tempfile::tempdir().unwrap();
+    let archive = wheel("kept", 64);
+    let mut pkg = package("kept", "https://files.example/kept-1.0.0-py3-none-any.whl".to_string(), &archive);
+    let wheels = WheelCache::new(dir.path().join("wheels"));
+    assert!(wheels.insert(&pkg, &archive).await.unwrap());
+    pkg.hash = Some("0".repeat(64));
+    assert!(!wheels.insert(&pkg, &archive).await.unwrap());
+
+    let old = wheels.root().join("stale-1.0.0-py3-none-any.whl");
+    std::fs::write(&old, b"old").unwrap();
+    let month_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(30 * 24 * 60 * 60);
+    std::fs::File::options().append(true).open(&old).unwrap().set_modified(month_ago).unwrap();
+
+    let names = |wheels: Vec<cobra::core::wheel_cache::CachedWheel>| -> Vec<String> { wheels.into_iter().map(|w| w.filename).collect() };
+    assert_eq!(names(wheels.list().await.unwrap()), ["kept-1.0.0-py3-none-any.whl", "stale-1.0.0-py3-none-any.whl"]);
+    assert_eq!(wheels.prune(Some(std::time::Duration::from_secs(7 * 24 * 60 * 60))).await.unwrap(), (1, 3));
+    assert_eq!(names(wheels.list().await.unwrap()), ["kept-1.0.0-py3-none-any.whl"]);
+    assert_eq!(wheels.prune(None).await.unwrap(), (1, archive.len() as u64));
+}
+
+#[tokio::test]
+async fn max_download_rate_throttles_combined_downloads() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let first = wheel("throttledone", 48 * 1024);
+    let second = wheel("throttledtwo", 48 * 1024);
+    let packages = vec![
+        package("throttledone", format!("{}/files/throttledone.whl", index.url), &first),
+        package("throttledtwo", format!("{}/files/throttledtwo.whl", index.url), &second),
+    ];
+    index.serve("/files/throttledone.whl", first);
+    index.serve("/files/throttledtwo.whl", second);
+
+    // 96 KB at 64 KB/s: the first second's burst, then ~0.5s of throttling
+    let started = std::time::Instant::now();
+    let stats = Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        None,
+        Arc::new(ProgressTracker::new()),
+        Arc::new(LocalPackageManager::with_layout(InstallLayout::target(dir.path().join("target")))),
+    )
+    .with_max_download_rate(Some(64 * 1024))
+    .install_parallel(packages)
+    .await
+    .unwrap();
+
+    assert_eq!(stats.downloaded_packages, 2);
+    assert!(started.elapsed() >= std::time::Duration::from_millis(400), "{:?}", started.elapsed());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn failing_verification_command_fails_the_install() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let good = wheel("checkedgood", 64);
+    let bad = wheel("checkedbad", 64);
+    let packages = vec![
+        package("checkedgood", format!("{}/files/checkedgood.whl", index.url), &good),
+        package("Checked_Bad", format!("{}/files/checkedbad.whl", index.url), &bad),
+    ];
+    index.serve("/files/checkedgood.whl", good);
+    index.serve("/files/checkedbad.whl", bad);
+
+    let manager = Arc::new(LocalPackageManager::with_layout(InstallLayout::target(dir.path().join("target"))));
+    let checks = [
+        ("checkedgood".to_string(), "test -n \"$PYTHONPATH\"".to_string()),
+        ("checked-bad".to_string(), "echo broken >&2; exit 3".to_string()),
+    ];
+    let result = Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        None,
+        Arc::new(ProgressTracker::new()),
+        manager.clone(),
+    )
+    .with_post_install_checks(checks.into_iter().collect())
+    .install_parallel(packages)
+    .await;
+
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("Checked_Bad") && err.contains("broken"), "{}", err);
+    assert!(!err.contains("checkedgood"), "{}", err);
+    let installed: Vec<String> = manager.list_installed().await.unwrap().into_iter().map(|p| p.name).collect();
+    assert_eq!(installed, vec!["checkedgood"]);
+}
+
+#[tokio::test]
+async fn an_atomic_install_rolls_back_when_a_package_fails() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let first = wheel("atomicfirst", 64);
+    let second = wheel("atomicsecond", 64);
+    let packages = vec![
+        package("atomicfirst", format!("{}/files/atomicfirst.whl", index.url), &first),
+        package("atomicsecond", format!("{}/files/atomicsecond.whl", index.url), &second),
+        package("atomicmissing", format!("{}/files/atomicmissing.whl", index.url), b"never served"),
+    ];
+    index.serve("/files/atomicfirst.whl", first);
+    index.serve("/files/atomicsecond.whl", second);
+
+    let target = dir.path().join("target");
+    let manager = Arc::new(LocalPackageManager::with_layout(InstallLayout::target(target.clone())));
+    let error = Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        None,
+        Arc::new(ProgressTracker::new()),
+        manager.clone(),
+    )
+    .with_atomic(true)
+    .install_parallel(packages)
+    .await
+    .unwrap_err();
+
+    let cobra::CobraError::RolledBack { package, .. } = &error else {
+        panic!("{}", error);
+    };
+    assert_eq!(package, "atomicmissing");
+    assert!(manager.list_installed().await.unwrap().is_empty());
+    assert!(!target.join("atomicfirst").exists() && !target.join("atomicsecond").exists());
+}
+
+#[tokio::test]
+async fn a_failed_download_leaves_the_version_it_would_replace() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("target");
+    install_wheel("recorded", recorded_wheel("recorded"), target.clone()).await.unwrap();
+
+    let index = MockIndex::start().await;
+    let upgrade = Package {
+        version: "2.0.0".to_string(),
+        ..package("recorded", format!("{}/files/recorded-2.0.0.whl", index.url), b"never served")
+    };
+    let manager = Arc::new(LocalPackageManager::with_layout(InstallLayout::target(target.clone())));
+    Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        None,
+        Arc::new(ProgressTracker::new()),
+        manager.clone(),
+    )
+    .install_parallel(vec![upgrade])
+    .await
+    .unwrap_err();
+
+    let installed = manager.list_installed().await.unwrap();
+    assert_eq!(installed.len(), 1);
+    assert_eq!(installed[0].version, "1.0.0");
+    assert_eq!(std::fs::read_to_string(target.join("recorded/__init__.py")).unwrap(), "VALUE = 1\n");
+}
+
+#[tokio::test]
+async fn a_failed_atomic_upgrade_restores_the_previous_version() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("target");
+    install_wheel("recorded", recorded_wheel("recorded"), target.clone()).await.unwrap();
+
+    let index = MockIndex::start().await;
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    writer.start_file("recorded/__init__.py", zip::write::FileOptions::default()).unwrap();
+    writer.write_all(b"VALUE = 2\n").unwrap();
+    let upgrade = writer.finish().unwrap().into_inner();
+    let packages = vec![
+        Package {
+            version: "2.0.0".to_string(),
+            ..package("recorded", format!("{}/files/recorded-2.0.0.whl", index.url), &upgrade)
+        },
+        package("upgrademissing", format!("{}/files/upgrademissing.whl", index.url), b"never served"),
+    ];
+    index.serve("/files/recorded-2.0.0.whl", upgrade);
+
+    let manager = Arc::new(LocalPackageManager::with_layout(InstallLayout::target(target.clone())));
+    let error = Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        None,
+        Arc::new(ProgressTracker::new()),
+        manager.clone(),
+    )
+    .with_atomic(true)
+    .install_parallel(packages)
+    .await
+    .unwrap_err();
+
+    assert!(matches!(error, cobra::CobraError::RolledBack { .. }), "{}", error);
+    let installed = manager.list_installed().await.unwrap();
+    assert_eq!(installed.len(), 1);
+    assert_eq!(installed[0].version, "1.0.0");
+    assert!(manager.verify_package(&installed[0]).await.unwrap().is_empty());
+    let python = if cfg!(windows) { "python" } else { "python3" };
+    let output = std::process::Command::new(python)
+        .args(["-c", "import recorded; print(recorded.VALUE)"])
+        .env("PYTHONPATH", &target)
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "1", "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[tokio::test]
+async fn a_failed_package_leaves_the_others_registered_or_absent() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let packages: Vec<Package> = (0..6)
+        .map(|i| {
+            let name = format!("drained{}", i);
+            let body = bulky_wheel(&name, 50, 16 * 1024);
+            index.serve(&format!("/files/{}.whl", name), body.clone());
+            package(&name, format!("{}/files/{}.whl", index.url, name), &body)
+        })
+        .chain([package("drainedmissing", format!("{}/files/drainedmissing.whl", index.url), b"never served")])
+        .collect();
+
+    let target = dir.path().join("target");
+    let manager = Arc::new(LocalPackageManager::with_layout(InstallLayout::target(target.clone())));
+    let error = Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        None,
+        Arc::new(ProgressTracker::new()),
+        manager.clone(),
+    )
+    .install_parallel(packages)
+    .await
+    .unwrap_err();
+    assert!(!matches!(error, cobra::CobraError::Cancelled), "{}", error);
+
+    // Nothing is still being written once the error is returned, and every
+    // package on disk is registered and whole
+    let on_disk = || -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(&target).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with("drained") && !name.ends_with(".dist-info"))
+            .collect();
+        names.sort();
+        names
+    };
+    let before = on_disk();
+    std::thread::sleep(std::time::Duration::from_millis(300));
+    assert_eq!(on_disk(), before);
+    let installed = manager.list_installed().await.unwrap();
+    let mut registered: Vec<String> = installed.iter().map(|p| p.name.clone()).collect();
+    registered.sort();
+    assert_eq!(registered, before);
+    for package in &installed {
+        assert!(manager.verify_package(package).await.unwrap().is_empty(), "{}", package.name);
+    }
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn an_atomic_install_rolls_back_when_a_verification_command_fails() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let good = wheel("atomicgood", 64);
+    let bad = wheel("atomicbad", 64);
+    let packages = vec![
+        package("atomicgood", format!("{}/files/atomicgood.whl", index.url), &good),
+        package("atomicbad", format!("{}/files/atomicbad.whl", index.url), &bad),
+    ];
+    index.serve("/files/atomicgood.whl", good);
+    index.serve("/files/atomicbad.whl", bad);
+
+    let target = dir.path().join("target");
+    let manager = Arc::new(LocalPackageManager::with_layout(InstallLayout::target(target.clone())));
+    let checks = [("atomicbad".to_string(), "exit 1".to_string())];
+    let result = Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        None,
+        Arc::new(ProgressTracker::new()),
+        manager.clone(),
+    )
+    .with_post_install_checks(checks.into_iter().collect())
+    .with_atomic(true)
+    .install_parallel(packages)
+    .await;
+
+    assert!(result.unwrap_err().to_string().contains("atomicbad"));
+    assert!(manager.list_installed().await.unwrap().is_empty());
+    assert!(!target.join("atomicgood").exists() && !target.join("atomicbad").exists());
+}
+
+/// A wheel whose RECORD lists its module and itself
+fn recorded_wheel(module: &str) -> Vec<u8> {
+    use base64::Engine;
+    let files = [
+        (format!("{}/__init__.py", module), b"VALUE = 1\n".to_vec()),
+        (format!("{}/data.txt", module), b"payload".to_vec()),
+    ];
+    let mut record = String::new();
+    for (path, contents) in &files {
+        let digest = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(sha2::Sha256::digest(contents));
+        record.push_str(&format!("{},sha256={},{}\n", path, digest, contents.len()));
+    }
+    record.push_str(&format!("{}-1.0.0.dist-info/RECORD,,\n", module));
+
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default();
+    for (path, contents) in &files {
+        writer.start_file(path.as_str(), options).unwrap();
+        writer.write_all(contents).unwrap();
+    }
+    writer.start_file(format!("{}-1.0.0.dist-info/RECORD", module), options).unwrap();
+    writer.write_all(record.as_bytes()).unwrap();
+    writer.finish().unwrap().into_inner()
+}
+
+#[tokio::test]
+async fn verify_reports_modified_and_missing_recorded_files() {
+    use cobra::core::record::FileProblem;
+
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("target");
+    install_wheel("recorded", recorded_wheel("recorded"), target.clone()).await.unwrap();
+
+    let manager = LocalPackageManager::with_layout(InstallLayout::target(target.clone()));
+    let installed = manager.list_installed().await.unwrap().remove(0);
+    assert_eq!(installed.files.len(), 4);
+    assert!(manager.verify_package(&installed).await.unwrap().is_empty());
+
+    std::fs::write(target.join("recorded/__init__.py"), "VALUE = 2\n").unwrap();
+    std::fs::remove_file(target.join("recorded/data.txt")).unwrap();
+    let problems = manager.verify_package(&installed).await.unwrap();
+    assert_eq!(problems, vec![
+        FileProblem::Modified("recorded/__init__.py".to_string()),
+        FileProblem::Missing("recorded/data.txt".to_string()),
+    ]);
+}
+
+#[tokio::test]
+async fn record_is_rewritten_to_list_every_installed_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("target");
+
+    // The wheel's own RECORD is stale: it omits data.txt and has a wrong size
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default();
+    writer.start_file("stale/__init__.py", options).unwrap();
+    writer.write_all(b"VALUE = 1\n").unwrap();
+    writer.start_file("stale/data.txt", options).unwrap();
+    writer.write_all(b"payload").unwrap();
+    writer.start_file("stale-1.0.0.dist-info/METADATA", options).unwrap();
+    writer.write_all(b"Metadata-Version: 2.1\nName: stale\nVersion: 1.0.0\n").unwrap();
+    writer.start_file("stale-1.0.0.dist-info/RECORD", options).unwrap();
+    writer.write_all(b"stale/__init__.py,,99\n").unwrap();
+    install_wheel("stale", writer.finish().unwrap().into_inner(), target.clone()).await.unwrap();
+
+    assert_eq!(std::fs::read_to_string(target.join("stale-1.0.0.dist-info/INSTALLER")).unwrap(), "cobra\n");
+    assert!(!target.join("stale-1.0.0.dist-info/direct_url.json").exists());
+    let record = std::fs::read_to_string(target.join("stale-1.0.0.dist-info/RECORD")).unwrap();
+    assert_eq!(record, concat!(
+        "stale/__init__.py,sha256=4T34xEr13qHkEkA5ELmcxaSPLMv2imazN01quc75_GU,10\n",
+        "stale/data.txt,sha256=I59Z7VXnN8dxR89VrQwbAwttfudIp0JpUvm4UtWpNeU,7\n",
+        "stale-1.0.0.dist-info/METADATA,sha256=k3SulcRic7JfDCC_ZwfY9Q4mgo6rWnS6bBkrws1m76U,49\n",
+        "stale-1.0.0.dist-info/INSTALLER,sha256=LeT4BKKqEqFNqc-EGNWRSpdtIvqG4eLt-Qn3Rf1EmG8,6\n",
+        "stale-1.0.0.dist-info/RECORD,,\n",
+    ));
+
+    // RECORD round-trips through the parser and matches the files on disk
+    let manager = LocalPackageManager::with_layout(InstallLayout::target(target.clone()));
+    let installed = manager.list_installed().await.unwrap().remove(0);
+    assert_eq!(cobra::core::record::parse_record(&record), installed.files);
+    assert_eq!(installed.size_on_disk, Some(10 + 7 + 49 + 6));
+    assert!(manager.verify_package(&installed).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn uninstall_removes_every_recorded_file_and_nothing_else() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("target");
+
+    // Two top-level packages, a nested module and a lone module
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default();
+    for path in ["multi/__init__.py", "multi/sub/core.py", "multi_helpers/__init__.py", "multi_compat.py"] {
+        writer.start_file(path, options).unwrap();
+        writer.write_all(b"").unwrap();
+    }
+    install_wheel("multi", writer.finish().unwrap().into_inner(), target.clone()).await.unwrap();
+
+    // Bytecode Python wrote later, and a file belonging to someone else
+    std::fs::create_dir_all(target.join("multi/sub/__pycache__")).unwrap();
+    std::fs::write(target.join("multi/sub/__pycache__/core.cpython-311.pyc"), "").unwrap();
+    std::fs::create_dir_all(target.join("multi_helpers/__pycache__")).unwrap();
+    std::fs::write(target.join("multi_helpers/__pycache__/other.cpython-311.pyc"), "").unwrap();
+
+    let manager = LocalPackageManager::with_layout(InstallLayout::target(target.clone()));
+    assert!(manager.uninstall("multi").await.unwrap());
+
+    for gone in ["multi", "multi_compat.py", "multi-1.0.0.dist-info"] {
+        assert!(!target.join(gone).exists(), "{} was left behind", gone);
+    }
+    assert!(target.join("multi_helpers/__pycache__/other.cpython-311.pyc").exists());
+    assert!(!target.join("multi_helpers/__init__.py").exists());
+    assert!(manager.list_installed().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn uninstall_falls_back_to_directories_for_entries_without_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("target");
+    std::fs::create_dir_all(target.join("legacy/sub")).unwrap();
+    std::fs::write(target.join("legacy/sub/mod.py"), "").unwrap();
+    std::fs::create_dir_all(target.join("legacy-1.0.0.dist-info")).unwrap();
+    std::fs::write(target.join("legacy-1.0.0.dist-info/METADATA"), "").unwrap();
+
+    // Registered the way installs did before files were recorded
+    let manager = LocalPackageManager::with_layout(InstallLayout::target(target.clone()));
+    let package = package("legacy", String::new(), b"");
+    manager.register_package(&package).await.unwrap();
+
+    assert!(manager.uninstall("legacy").await.unwrap());
+    assert!(!target.join("legacy").exists());
+    assert!(!target.join("legacy-1.0.0.dist-info").exists());
+    assert!(target.exists());
+}
+
+#[tokio::test]
+async fn force_reinstall_restores_damaged_packages() {
+    use cobra::cli::install::select_reinstalls;
+
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("target");
+    install_wheel("recorded", recorded_wheel("recorded"), target.clone()).await.unwrap();
+    std::fs::remove_file(target.join("recorded/data.txt")).unwrap();
+
+    // The registry still lists it, so a plain install skips it
+    let stats = install_wheel("recorded", recorded_wheel("recorded"), target.clone()).await.unwrap();
+    assert_eq!(stats.skipped_packages, 1);
+    assert!(!target.join("recorded/data.txt").exists());
+
+    let resolved = [package("recorded", String::new(), b""), package("other", String::new(), b"")];
+    let candidates: Vec<&Package> = resolved.iter().collect();
+    assert_eq!(select_reinstalls(&[], &candidates).unwrap().len(), 2);
+    assert!(select_reinstalls(&["missing".to_string()], &candidates).is_err());
+    let selected = select_reinstalls(&["Recorded".to_string()], &candidates).unwrap();
+
+    let stats = install_release("recorded", "1.0.0", recorded_wheel("recorded"), target.clone(), selected).await.unwrap();
+    assert_eq!(stats.skipped_packages, 0);
+    assert_eq!(std::fs::read_to_string(target.join("recorded/data.txt")).unwrap(), "payload");
+}
+
+#[test]
+fn saved_specs_keep_the_requested_range_or_caret_pin_the_installed_version() {
+    use cobra::cli::install::saved_spec;
+    use cobra::core::requirements::parse_package_arg;
+
+    let installed = ["Rich==13.7.0".to_string(), "requests==2.31.0".to_string()];
+    assert_eq!(saved_spec(&parse_package_arg("requests>=2.0").unwrap(), &installed), ">=2.0");
+    assert_eq!(saved_spec(&parse_package_arg("rich").unwrap(), &installed), "^13.7.0");
+}
+
+#[tokio::test]
+async fn module_only_packages_count_as_installed_by_their_recorded_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("target");
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    writer.start_file("six.py", zip::write::FileOptions::default()).unwrap();
+    writer.write_all(b"VALUE = 1\n").unwrap();
+    let body = writer.finish().unwrap().into_inner();
+
+    install_wheel("six", body.clone(), target.clone()).await.unwrap();
+    assert!(!target.join("six").exists());
+    let stats = install_wheel("six", body.clone(), target.clone()).await.unwrap();
+    assert_eq!(stats.skipped_packages, 1);
+    let manager = LocalPackageManager::with_layout(InstallLayout::target(target.clone()));
+    assert_eq!(manager.list_present().await.unwrap().len(), 1);
+
+    // Deleting the module makes it missing, but its recorded files are kept
+    std::fs::remove_file(target.join("six.py")).unwrap();
+    assert!(manager.list_present().await.unwrap().is_empty());
+    assert!(!manager.is_package_installed("six", "1.0.0").await.unwrap());
+    assert!(!manager.list_installed().await.unwrap()[0].files.is_empty());
+    let stats = install_wheel("six", body, target.clone()).await.unwrap();
+    assert_eq!(stats.skipped_packages, 0);
+    assert!(target.join("six.py").is_file());
+}
+
+#[tokio::test]
+async fn a_different_resolved_version_replaces_the_installed_one() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("target");
+    install_wheel("recorded", recorded_wheel("recorded"), target.clone()).await.unwrap();
+
+    // 2.0.0 no longer ships data.txt
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    writer.start_file("recorded/__init__.py", zip::write::FileOptions::default()).unwrap();
+    writer.write_all(b"VALUE = 2\n").unwrap();
+    let stats = install_release("recorded", "2.0.0", writer.finish().unwrap().into_inner(), target.clone(), HashSet::new())
+        .await
+        .unwrap();
+
+    assert_eq!(stats.skipped_packages, 0);
+    assert!(!target.join("recorded/data.txt").exists());
+    assert!(!target.join("recorded-1.0.0.dist-info").exists());
+    let manager = LocalPackageManager::with_layout(InstallLayout::target(target.clone()));
+    let installed = manager.list_installed().await.unwrap();
+    assert_eq!(installed.len(), 1);
+    assert_eq!(installed[0].version, "2.0.0");
+}
+
+/// An installer for `target` that links wheels out of `store` and has no archive cache
+fn store_installer(index: &MockIndex, store: &Arc<WheelStore>, target: std::path::PathBuf) -> Installer {
+    Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        None,
+        Arc::new(ProgressTracker::new()),
+        Arc::new(LocalPackageManager::with_layout(InstallLayout::target(target))),
+    )
+    .with_store(Some(store.clone()))
+}
+
+/// A wheel of `files` modules of `size` bytes each
+fn bulky_wheel(module: &str, files: usize, size: usize) -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default();
+    for i in 0..files {
+        writer.start_file(format!("{}/mod{}.py", module, i), options).unwrap();
+        writer.write_all(&vec![b'#'; size]).unwrap();
+    }
+    writer.start_file(format!("{}-1.0.0.dist-info/METADATA", module), options).unwrap();
+    writer.write_all(b"Metadata-Version: 2.1\n").unwrap();
+    writer.finish().unwrap().into_inner()
+}
+
+#[tokio::test]
+async fn stored_wheels_install_into_another_project_without_downloading() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let store = Arc::new(WheelStore::new(dir.path().join("store")));
+    let body = bulky_wheel("stored", 200, 64 * 1024);
+    index.serve("/files/stored.whl", body.clone());
+    let stored = package("stored", format!("{}/files/stored.whl", index.url), &body);
+
+    let started = std::time::Instant::now();
+    let first = store_installer(&index, &store, dir.path().join("first"))
+        .install_parallel(vec![stored.clone()])
+        .await
+        .unwrap();
+    let extracting = started.elapsed();
+    assert_eq!(first.packages[0].source, PackageSource::Network);
+
+    let started = std::time::Instant::now();
+    let second = store_installer(&index, &store, dir.path().join("second"))
+        .install_parallel(vec![stored.clone()])
+        .await
+        .unwrap();
+    let linking = started.elapsed();
+    assert_eq!(second.packages[0].source, PackageSource::Store);
+    assert_eq!(index.requests().len(), 1);
+    assert!(linking < extracting, "linking took {:?}, extracting {:?}", linking, extracting);
+
+    // Each project gets its own INSTALLER and RECORD, and verifies cleanly
+    let target = dir.path().join("second");
+    assert_eq!(std::fs::read(target.join("stored/mod7.py")).unwrap().len(), 64 * 1024);
+    assert_eq!(std::fs::read_to_string(target.join("stored-1.0.0.dist-info/INSTALLER")).unwrap(), "cobra\n");
+    let manager = LocalPackageManager::with_layout(InstallLayout::target(target));
+    let installed = manager.list_installed().await.unwrap().remove(0);
+    assert_eq!(installed.store_entry, stored.hash);
+    assert!(manager.verify_package(&installed).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn force_reinstall_repairs_a_store_entry_damaged_through_a_link() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let store = Arc::new(WheelStore::new(dir.path().join("store")));
+    let target = dir.path().join("target");
+    let body = recorded_wheel("linked");
+    index.serve("/files/linked.whl", body.clone());
+    let linked = package("linked", format!("{}/files/linked.whl", index.url), &body);
+    store_installer(&index, &store, target.clone()).install_parallel(vec![linked.clone()]).await.unwrap();
+
+    // Writing in place goes through a hardlink into the store
+    std::fs::write(target.join("linked/data.txt"), "tampered").unwrap();
+    store_installer(&index, &store, target.clone())
+        .with_reinstall(HashSet::from(["linked".to_string()]))
+        .install_parallel(vec![linked])
+        .await
+        .unwrap();
+
+    assert_eq!(std::fs::read_to_string(target.join("linked/data.txt")).unwrap(), "payload");
+    let entry = store.get(&sha256_hex(&body)).unwrap();
+    assert!(store.is_intact(&entry).await.unwrap());
+}
+
+#[tokio::test]
+async fn prune_removes_store_entries_no_project_links() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let store = Arc::new(WheelStore::new(dir.path().join("store")));
+    let (kept_body, dropped_body) = (wheel("kept", 16), wheel("dropped", 4096));
+    index.serve("/files/kept.whl", kept_body.clone());
+    index.serve("/files/dropped.whl", dropped_body.clone());
+    let target = dir.path().join("target");
+    store_installer(&index, &store, target.clone())
+        .install_parallel(vec![
+            package("kept", format!("{}/files/kept.whl", index.url), &kept_body),
+            package("dropped", format!("{}/files/dropped.whl", index.url), &dropped_body),
+        ])
+        .await
+        .unwrap();
+    assert_eq!(store.entries().unwrap().len(), 2);
+
+    let manager = LocalPackageManager::with_layout(InstallLayout::target(target));
+    manager.uninstall("dropped").await.unwrap();
+    let (removed, freed) = store.prune().await.unwrap();
+
+    assert_eq!(removed, 1);
+    assert!(freed >= 4096);
+    assert_eq!(store.entries().unwrap(), vec![sha256_hex(&kept_body)]);
+    assert_eq!(store.users(&sha256_hex(&kept_body)), vec![manager.registry_path().to_path_buf()]);
+}
+
+#[test]
+fn parallel_extraction_writes_every_entry_in_archive_order() {
+    use cobra::core::installer::extract_wheel;
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default();
+    let paths: Vec<String> = (0..500).map(|i| format!("wide/sub{}/mod{}.py", i % 7, i)).collect();
+    for (i, path) in paths.iter().enumerate() {
+        writer.start_file(path.as_str(), options).unwrap();
+        writer.write_all(format!("VALUE = {}\n", i).repeat(i % 50 + 1).as_bytes()).unwrap();
+    }
+    let archive = dir.path().join("wide.whl");
+    std::fs::write(&archive, writer.finish().unwrap().into_inner()).unwrap();
+
+    let extracted = extract_wheel(&archive, &dir.path().join("site")).unwrap();
+
+    let recorded: Vec<&str> = extracted.record.iter().map(|entry| entry.path.as_str()).collect();
+    assert_eq!(recorded, paths);
+    for (i, path) in paths.iter().enumerate() {
+        let contents = std::fs::read_to_string(dir.path().join("site").join(path)).unwrap();
+        assert_eq!(contents, format!("VALUE = {}\n", i).repeat(i % 50 + 1));
+        assert_eq!(extracted.record[i].size, Some(contents.len() as u64));
+    }
+}
+
+#[test]
+fn record_parsing_keeps_sha256_entries_inside_the_wheel() {
+    use cobra::core::record::parse_record;
+
+    let record = concat!(
+        "pkg/__init__.py,sha256=47DEQpj8HBSa-_TImW-5JCeuQeRkm5NMpJWZG3hSuFU,0\n",
+        "\"pkg/odd,name.py\",sha256=47DEQpj8HBSa-_TImW-5JCeuQeRkm5NMpJWZG3hSuFU,0\n",
+        "pkg/legacy.py,md5=abc,3\n",
+        "../../bin/tool,sha256=47DEQpj8HBSa-_TImW-5JCeuQeRkm5NMpJWZG3hSuFU,0\n",
+        "pkg-1.0.dist-info/RECORD,,\n",
+    );
+    let entries = parse_record(record);
+
+    let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+    assert_eq!(paths, vec!["pkg/__init__.py", "pkg/odd,name.py", "pkg/legacy.py", "pkg-1.0.dist-info/RECORD"]);
+    assert_eq!(entries[0].sha256.as_deref(), Some(sha256_hex(b"").as_str()));
+    assert_eq!(entries[0].size, Some(0));
+    assert_eq!(entries[2].sha256, None);
+    assert_eq!(entries[3].size, None);
+}
+
+#[tokio::test]
+async fn progress_bars_finish_and_downloads_are_summarized() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let first = wheel("progressone", 2048);
+    let second = wheel("progresstwo", 2048);
+    let total = (first.len() + second.len()) as u64;
+    let packages = vec![
+        package("progressone", format!("{}/files/progressone.whl", index.url), &first),
+        package("progresstwo", format!("{}/files/progresstwo.whl", index.url), &second),
+        // Fails verification, ending the run with an error
+        package("progressbad", format!("{}/files/progressbad.whl", index.url), &wheel("progressbad", 64)),
+    ];
+    index.serve("/files/progressone.whl", first);
+    index.serve("/files/progresstwo.whl", second);
+    index.serve("/files/progressbad.whl", wheel("progressbad", 65));
+
+    let installer = |progress: Arc<ProgressTracker>, target: &str| Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        None,
+        progress,
+        Arc::new(LocalPackageManager::with_layout(InstallLayout::target(dir.path().join(target)))),
+    );
+
+    let progress = Arc::new(ProgressTracker::new());
+    installer(progress.clone(), "ok").install_parallel(packages[..2].to_vec()).await.unwrap();
+    assert_eq!(progress.active_bars(), 0);
+    assert_eq!(progress.downloads(), 2);
+    let summary = progress.summary();
+    assert!(summary.starts_with(&format!("Downloaded {} across 2 packages in ", cobra::core::installer::format_bytes(total))), "{}", summary);
+
+    let progress = Arc::new(ProgressTracker::new());
+    assert!(installer(progress.clone(), "failed").install_parallel(packages).await.is_err());
+    assert_eq!(progress.active_bars(), 0);
+}
+
+#[tokio::test]
+async fn concurrent_installs_of_the_same_package_share_a_download_dir() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let archive = wheel("shared", 4096);
+    let pkg = package("shared", format!("{}/files/shared.whl", index.url), &archive);
+    index.serve("/files/shared.whl", archive);
+
+    let install = |target: &str| {
+        Installer::new(
+            Arc::new(RegistryClient::with_base_url(index.url.clone())),
+            None,
+            Arc::new(ProgressTracker::new()),
+            Arc::new(LocalPackageManager::with_layout(InstallLayout::target(dir.path().join(target)))),
+        )
+        .with_download_dir(dir.path().join("downloads"))
+    };
+    let (first, second) = (install("first"), install("second"));
+    let (a, b) = tokio::join!(first.install_parallel(vec![pkg.clone()]), second.install_parallel(vec![pkg]));
+    a.unwrap();
+    b.unwrap();
+
+    assert!(dir.path().join("first/shared/__init__.py").is_file());
+    assert!(dir.path().join("second/shared/__init__.py").is_file());
+    assert_eq!(download_files(&dir.path().join("downloads")), Vec::<String>::new());
+}
+
+#[tokio::test]
+async fn a_locked_download_is_left_to_its_owner() {
+    let index = MockIndex::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let archive = wheel("busy", 4096);
+    let pkg = package("busy", format!("{}/files/busy.whl", index.url), &archive);
+    index.serve("/files/busy.whl", archive);
+
+    // Another process is midway through the same download
+    let downloads = dir.path().join("downloads");
+    std::fs::create_dir_all(&downloads).unwrap();
+    let lock = std::fs::File::create(downloads.join("busy-1.0.0.whl.lock")).unwrap();
+    lock.lock().unwrap();
+    std::fs::write(downloads.join("busy-1.0.0.whl.part"), b"in progress").unwrap();
+
+    let target = dir.path().join("target");
+    Installer::new(
+        Arc::new(RegistryClient::with_base_url(index.url.clone())),
+        None,
+        Arc::new(ProgressTracker::new()),
+        Arc::new(LocalPackageManager::with_layout(InstallLayout::target(target.clone()))),
+    )
+    .with_download_dir(downloads.clone())
+    .install_parallel(vec![pkg])
+    .await
+    .unwrap();
+
+    assert!(target.join("busy/__init__.py").is_file());
+    assert_eq!(std::fs::read(downloads.join("busy-1.0.0.whl.part")).unwrap(), b"in progress");
+    assert_eq!(download_files(&downloads), ["busy-1.0.0.whl.part"]);
+}
+
+#[tokio::test]
+async fn orphaned_downloads_older_than_a_day_are_swept() {
+    let dir = tempfile::tempdir().unwrap();
+    let downloads = dir.path().join("downloads");
+    std::fs::create_dir_all(&downloads).unwrap();
+    let two_days_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(2 * 24 * 60 * 60);
+    for name in ["crashed-1.0.0.whl.part", "crashed-1.0.0-x1y2.whl", "held-1.0.0.whl.lock"] {
+        let file = std::fs::File::create(downloads.join(name)).unwrap();
+        file.set_modified(two_days_ago).unwrap();
+    }
+    std::fs::write(downloads.join("recent-1.0.0.whl.part"), b"resume me").unwrap();
+    let held = std::fs::File::open(downloads.join("held-1.0.0.whl.lock")).unwrap();
+    held.lock().unwrap();
+
+    let removed = cobra::utils::fs::remove_stale_files(&downloads, cobra::constants::STALE_DOWNLOAD_AGE).await.unwrap();
+    assert_eq!(removed, 2);
+
+    let mut left: Vec<String> = std::fs::read_dir(&downloads).unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    left.sort();
+    assert!(left.contains(&"held-1.0.0.whl.lock".to_string()), "{:?}", left);
+    assert!(left.contains(&"recent-1.0.0.whl.part".to_string()), "{:?}", left);
+    assert!(!left.iter().any(|name| name.starts_with("crashed")), "{:?}", left);
+}
@@ -0,0 +1,88 @@
+use cobra::core::layout::{InstallLayout, LayoutKind};
+use std::path::{Path, PathBuf};
+
+#[test]
+fn flat_layout_shares_one_site_packages() {
+    let layout = InstallLayout::flat(PathBuf::from("/proj/.cobra_packages"));
+
+    assert_eq!(layout.kind(), LayoutKind::FlatSitePackages);
+    assert_eq!(layout.site_packages("requests"), Path::new("/proj/.cobra_packages"));
+    assert_eq!(layout.package_dir("requests"), Path::new("/proj/.cobra_packages/requests"));
+    assert_eq!(
+        layout.dist_info_dir("typing-extensions", "4.8.0"),
+        Path::new("/proj/.cobra_packages/typing_extensions-4.8.0.dist-info")
+    );
+    assert_eq!(layout.scripts_dir(), Path::new("/proj/.cobra_packages/bin"));
+    assert_eq!(layout.pth_entries(&["requests".to_string()]), vec![PathBuf::from("/proj/.cobra_packages")]);
+}
+
+#[test]
+fn per_package_layout_isolates_each_package() {
+    let layout = InstallLayout::per_package(PathBuf::from("/store"));
+    let names = vec!["six".to_string(), "attrs".to_string()];
+
+    assert_eq!(layout.site_packages("six"), Path::new("/store/six"));
+    assert_eq!(layout.package_dir("six"), Path::new("/store/six"));
+    assert_eq!(layout.dist_info_dir("six", "1.16.0"), Path::new("/store/six/six-1.16.0.dist-info"));
+    assert_eq!(
+        layout.pth_entries(&names),
+        vec![PathBuf::from("/store/attrs"), PathBuf::from("/store/six")]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn venv_layout_uses_interpreter_specific_site_packages() {
+    let layout = InstallLayout::venv(PathBuf::from("/proj/.venv"), "3.11");
+
+    assert_eq!(layout.site_packages("rich"), Path::new("/proj/.venv/lib/python3.11/site-packages"));
+    assert_eq!(layout.scripts_dir(), Path::new("/proj/.venv/bin"));
+    assert_eq!(layout.data_dir(), Path::new("/proj/.venv"));
+    assert!(layout.pth_entries(&["rich".to_string()]).is_empty());
+}
+
+#[test]
+fn target_layout_is_never_added_to_pth() {
+    let layout = InstallLayout::target(PathBuf::from("vendor"));
+
+    assert_eq!(layout.site_packages("boto3"), Path::new("vendor"));
+    assert_eq!(layout.base_dir(), Path::new("vendor"));
+    assert!(layout.pth_entries(&["boto3".to_string()]).is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn root_relocates_every_path() {
+    let layout = InstallLayout::venv(PathBuf::from("/opt/app/.venv"), "3.12")
+        .with_root(Path::new("/tmp/stage"));
+
+    assert_eq!(layout.base_dir(), Path::new("/tmp/stage/opt/app/.venv"));
+    assert_eq!(
+        layout.site_packages("rich"),
+        Path::new("/tmp/stage/opt/app/.venv/lib/python3.12/site-packages")
+    );
+    assert_eq!(layout.scripts_dir(), Path::new("/tmp/stage/opt/app/.venv/bin"));
+}
+
+#[tokio::test]
+async fn pth_file_keeps_entries_of_other_projects() {
+    use cobra::core::package_manager::LocalPackageManager;
+
+    let dir = tempfile::tempdir().unwrap();
+    let pth = dir.path().join("cobra-packages.pth");
+    let first = LocalPackageManager::with_layout(InstallLayout::flat(dir.path().join("one/.cobra_packages")));
+    let second = LocalPackageManager::with_layout(InstallLayout::flat(dir.path().join("two/.cobra_packages")));
+    let entry = |project: &str| dir.path().join(project).join(".cobra_packages");
+
+    first.update_pth_file(&pth, &[entry("one")]).await.unwrap();
+    second.update_pth_file(&pth, &[entry("two")]).await.unwrap();
+    first.update_pth_file(&pth, &[entry("one")]).await.unwrap();
+    let lines = |contents: String| contents.lines().map(PathBuf::from).collect::<Vec<_>>();
+    assert_eq!(lines(std::fs::read_to_string(&pth).unwrap()), vec![entry("one"), entry("two")]);
+
+    // Uninstalling one project leaves the other discoverable
+    assert!(first.update_pth_file(&pth, &[]).await.unwrap());
+    assert_eq!(lines(std::fs::read_to_string(&pth).unwrap()), vec![entry("two")]);
+    assert!(!second.update_pth_file(&pth, &[]).await.unwrap());
+    assert!(!pth.exists());
+}
@@ -0,0 +1,29 @@
+mod support;
+
+mod add_test;
+mod build_test;
+mod cache_test;
+mod check_test;
+mod clean_test;
+mod config_test;
+mod doctor_test;
+mod export_test;
+mod find_links_test;
+mod fs_test;
+mod hash_test;
+mod hooks_test;
+mod imports_test;
+mod index_test;
+mod install_test;
+mod layout_test;
+mod markers_test;
+mod private_registry_test;
+mod registry_test;
+mod requirements_test;
+mod resolver_test;
+mod run_test;
+mod simple_index_test;
+mod status_test;
+mod sync_test;
+mod update_test;
+mod version_test;
@@ -0,0 +1,62 @@
+use cobra::core::markers::{applies_on_some_platform, apply_extras, MarkerEnvironment};
+
+fn windows() -> MarkerEnvironment {
+    MarkerEnvironment {
+        os_name: "nt".to_string(),
+        sys_platform: "win32".to_string(),
+        platform_system: "Windows".to_string(),
+        platform_machine: "AMD64".to_string(),
+        platform_python_implementation: "CPython".to_string(),
+        implementation_name: "cpython".to_string(),
+        python_version: "3.11".to_string(),
+        python_full_version: "3.11.7".to_string(),
+    }
+}
+
+#[test]
+fn evaluates_platform_and_version_markers() {
+    let env = windows();
+    assert!(env.evaluate("sys_platform == 'win32'").unwrap());
+    assert!(!env.evaluate("sys_platform != \"win32\"").unwrap());
+    assert!(env.evaluate("python_version >= '3.8' and os_name == 'nt'").unwrap());
+    assert!(!env.evaluate("python_version < '3.10'").unwrap());
+    // 3.11 must not compare below 3.9 as a string would
+    assert!(env.evaluate("python_version > '3.9'").unwrap());
+    assert!(env.evaluate("(platform_system == 'Linux' or platform_system == 'Windows') and python_full_version >= '3.11.1'").unwrap());
+    assert!(env.evaluate("'win' in sys_platform").unwrap());
+    assert!(!env.evaluate("platform_machine not in 'AMD64 x86_64'").unwrap());
+}
+
+#[test]
+fn extras_only_apply_when_requested() {
+    let env = windows();
+    assert!(!env.evaluate("extra == 'socks'").unwrap());
+    assert!(!applies_on_some_platform("extra == \"test\"").unwrap());
+    assert!(!applies_on_some_platform("python_version < '3.8' and extra == 'test'").unwrap());
+    assert!(applies_on_some_platform("sys_platform == 'win32'").unwrap());
+}
+
+#[test]
+fn requested_extras_decide_their_clauses() {
+    let socks = vec!["SOCKS".to_string()];
+    assert_eq!(apply_extras("extra == 'socks'", &socks).unwrap(), Some(None));
+    assert_eq!(apply_extras("extra == 'test'", &socks).unwrap(), None);
+    assert_eq!(
+        apply_extras("sys_platform == 'win32' and extra == \"socks\"", &socks).unwrap(),
+        Some(Some("sys_platform == \"win32\"".to_string()))
+    );
+    assert_eq!(
+        apply_extras("(extra == 'test' or python_version < '3.8') and os_name == 'nt'", &socks).unwrap(),
+        Some(Some("python_version < \"3.8\" and os_name == \"nt\"".to_string()))
+    );
+    assert_eq!(apply_extras("extra == 'test' or extra == 'socks'", &socks).unwrap(), Some(None));
+}
+
+#[test]
+fn includes_unconditional_packages_and_rejects_bad_markers() {
+    let env = windows();
+    assert!(env.includes(None));
+    assert!(!env.includes(Some("sys_platform == 'linux'")));
+    assert!(env.evaluate("sys_platform ==").is_err());
+    assert!(env.evaluate("sys_platform = 'win32'").is_err());
+}
@@ -0,0 +1,162 @@
+use crate::support::{dep, project_json, version_json, MockIndex};
+use cobra::cli::add::add_registry_packages;
+use cobra::registry::packagecloud::{PackageCloudRegistry, PrivateRegistries};
+use cobra::core::layout::InstallLayout;
+use cobra::core::package_manager::LocalPackageManager;
+use cobra::{CobraConfig, CobraError, DependencyResolver, Installer, ProgressTracker, RegistryBackend, RegistryClient};
+use serde_json::json;
+use std::io::Write;
+use std::sync::Arc;
+
+/// Serve `name` from the PackageCloud repo `mycorp/python` on `index`
+fn serve_private(index: &MockIndex, name: &str, version: &str, requires: &[&str]) {
+    index.serve_json(
+        &format!("/api/v1/repos/mycorp/python/package/python/{}.json", name),
+        &json!({
+            "name": name,
+            "version": version,
+            "download_url": format!("{}/files/{}-{}-py3-none-any.whl", index.url, name, version),
+            "requires_dist": requires,
+        }),
+    );
+}
+
+fn registries(index: &MockIndex, patterns: &[&str], default: bool) -> Arc<PrivateRegistries> {
+    let registry = PackageCloudRegistry::with_custom_url(index.url.clone(), Some("secret".to_string()));
+    let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+    Arc::new(PrivateRegistries::new().with_repo(registry, "mycorp/python", &patterns, default))
+}
+
+#[tokio::test]
+async fn private_packages_resolve_with_their_public_dependencies() {
+    let index = MockIndex::start().await;
+    serve_private(&index, "mycorp-utils", "1.2.0", &["requests (>=2.0)", "mycorp-core"]);
+    serve_private(&index, "mycorp-core", "0.3.0", &[]);
+    index.serve_json("/pypi/requests/json", &project_json(&index.url, "requests", &[("2.31.0", "2023-05-22T12:00:00Z")], &[]));
+    index.serve_json("/pypi/requests/2.31.0/json", &version_json(&index.url, "requests", "2.31.0", "2023-05-22T12:00:00Z", &[]));
+
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let resolver = DependencyResolver::new(client, None).with_registries(registries(&index, &["mycorp-*"], false));
+    let resolved = resolver.resolve(&[dep("mycorp-utils", "^1.0")]).await.unwrap();
+
+    let mut versions: Vec<(String, String)> = resolved.into_iter().map(|p| (p.name, p.version)).collect();
+    versions.sort();
+    assert_eq!(versions, vec![
+        ("mycorp-core".to_string(), "0.3.0".to_string()),
+        ("mycorp-utils".to_string(), "1.2.0".to_string()),
+        ("requests".to_string(), "2.31.0".to_string()),
+    ]);
+    assert!(!index.requests().iter().any(|path| path.starts_with("/pypi/mycorp")));
+}
+
+#[tokio::test]
+async fn claimed_packages_never_fall_back_to_pypi() {
+    let index = MockIndex::start().await;
+    index.serve_json("/pypi/mycorp-utils/json", &project_json(&index.url, "mycorp-utils", &[("9.9.9", "2023-05-22T12:00:00Z")], &[]));
+
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let resolver = DependencyResolver::new(client, None).with_registries(registries(&index, &["mycorp-*"], false));
+    let result = resolver.resolve(&[dep("mycorp-utils", "*")]).await;
+    assert!(matches!(result, Err(CobraError::PackageNotFound(_))), "{:?}", result.map(|p| p.len()));
+}
+
+#[tokio::test]
+async fn default_registry_is_tried_before_pypi() {
+    let index = MockIndex::start().await;
+    serve_private(&index, "internal-tool", "2.0.0", &["idna"]);
+    index.serve_json("/pypi/idna/json", &project_json(&index.url, "idna", &[("3.6", "2023-11-25T12:00:00Z")], &[]));
+
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let resolver = DependencyResolver::new(client, None).with_registries(registries(&index, &[], true));
+    let resolved = resolver.resolve(&[dep("internal-tool", "*")]).await.unwrap();
+    assert_eq!(resolved.len(), 2);
+    assert!(index.requests().contains(&"/api/v1/repos/mycorp/python/package/python/idna.json".to_string()));
+}
+
+#[tokio::test]
+async fn dependencies_naming_an_index_are_served_by_that_registry() {
+    let index = MockIndex::start().await;
+    serve_private(&index, "internal-tool", "2.0.0", &[]);
+    index.serve_json("/pypi/internal-tool/json", &project_json(&index.url, "internal-tool", &[("9.9.9", "2023-05-22T12:00:00Z")], &[]));
+
+    let config: CobraConfig = toml::from_str(&format!(
+        "[project]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\ninternal-tool = {{ version = \"*\", index = \"corp\" }}\n\n[tool.cobra.registries.corp]\nrepo = \"mycorp/python\"\nurl = \"{}\"\ntoken = \"secret\"\n",
+        index.url
+    )).unwrap();
+
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let resolver = DependencyResolver::new(client, None).with_registries(config.private_registries());
+    let resolved = resolver.resolve(&config.get_dependencies_list()).await.unwrap();
+    assert_eq!(resolved[0].version, "2.0.0");
+    assert!(!index.requests().iter().any(|path| path.starts_with("/pypi/")));
+}
+
+#[tokio::test]
+async fn add_pins_the_private_release() {
+    let index = MockIndex::start().await;
+    serve_private(&index, "mycorp-utils", "1.2.0", &[]);
+
+    let client = RegistryClient::with_base_url(index.url.clone());
+    let mut config = CobraConfig::default();
+    let packages = vec!["mycorp-utils".to_string()];
+    add_registry_packages(&mut config, &client, &registries(&index, &["mycorp-*"], false), &packages, false, false)
+        .await
+        .unwrap();
+    assert_eq!(config.get_dependency("mycorp-utils").as_deref(), Some("^1.2.0"));
+}
+
+#[tokio::test]
+async fn inline_tokens_are_sent_and_a_missing_credential_store_is_not_an_error() {
+    let index = MockIndex::start().await;
+    serve_private(&index, "mycorp-utils", "1.2.0", &[]);
+
+    let registry = PackageCloudRegistry::with_custom_url(index.url.clone(), Some("secret".to_string()));
+    registry.get_package("mycorp/python", "mycorp-utils").await.unwrap();
+    assert_eq!(index.authorizations(), vec!["Bearer secret".to_string()]);
+
+    // No inline token and nothing saved by `cobra login` for this URL
+    let registry = PackageCloudRegistry::with_custom_url(index.url.clone(), None);
+    registry.get_package("mycorp/python", "mycorp-utils").await.unwrap();
+    assert_eq!(index.requests().len(), 2);
+    assert_eq!(index.authorizations().len(), 1);
+}
+
+#[tokio::test]
+async fn a_packagecloud_repository_resolves_and_installs_as_a_backend() {
+    let index = MockIndex::start().await;
+    serve_private(&index, "mycorp-utils", "1.2.0", &[]);
+    let mut wheel = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    wheel.start_file("mycorp_utils/__init__.py", zip::write::FileOptions::default()).unwrap();
+    wheel.write_all(b"VERSION = '1.2.0'\n").unwrap();
+    index.serve("/files/mycorp-utils-1.2.0-py3-none-any.whl", wheel.finish().unwrap().into_inner());
+
+    let backend: Arc<dyn RegistryBackend> = Arc::new(
+        PackageCloudRegistry::with_custom_url(index.url.clone(), Some("secret".to_string())).with_repo("mycorp/python"),
+    );
+    let resolver = DependencyResolver::new(backend.clone(), None);
+    assert!(matches!(
+        resolver.resolve(&[dep("mycorp-utils", "==1.1.0")]).await,
+        Err(CobraError::PackageNotFound(_))
+    ));
+    let resolved = resolver.resolve(&[dep("mycorp-utils", "^1.0")]).await.unwrap();
+    assert_eq!(resolved[0].version, "1.2.0");
+
+    let dir = tempfile::tempdir().unwrap();
+    Installer::new(
+        backend,
+        None,
+        Arc::new(ProgressTracker::new()),
+        Arc::new(LocalPackageManager::with_layout(InstallLayout::target(dir.path().to_path_buf()))),
+    )
+    .with_verify(false)
+    .install_parallel(resolved)
+    .await
+    .unwrap();
+    assert!(dir.path().join("mycorp_utils/__init__.py").is_file());
+
+    // Archive downloads carry the repository token as well
+    let downloads = index.requests().iter().filter(|path| path.starts_with("/files/")).count();
+    assert_eq!(downloads, 1);
+    assert!(index.authorizations().iter().all(|auth| auth == "Bearer secret"));
+    assert_eq!(index.authorizations().len(), index.requests().len());
+}
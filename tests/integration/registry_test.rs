@@ -0,0 +1,246 @@
+use crate::support::MockIndex;
+use cobra::registry::client::{mirror_url, HttpTimeouts, ReleaseFile, RetryPolicy};
+use cobra::{CobraError, RegistryClient};
+use cobra::registry::json::{releases_from_slice, ProjectDocument};
+use serde_json::{json, Value};
+use std::mem::size_of;
+use std::time::{Duration, Instant};
+
+/// A project document shaped like PyPI's, with `releases` files per version
+fn large_project(releases: usize) -> Value {
+    let file = |version: &str, kind: &str, ext: &str| json!({
+        "filename": format!("bigproject-{}-{}.{}", version, kind, ext),
+        "url": format!("https://files.example/bigproject/{}/{}.{}", version, kind, ext),
+        "packagetype": if ext == "whl" { "bdist_wheel" } else { "sdist" },
+        "size": 123_456,
+        "digests": { "sha256": format!("{:064x}", version.len()), "md5": "0".repeat(32), "blake2b_256": "1".repeat(64) },
+        "upload_time": "2023-05-01T12:00:00",
+        "upload_time_iso_8601": "2023-05-01T12:00:00.000000Z",
+        "requires_python": ">=3.8",
+        "comment_text": "",
+        "has_sig": false,
+        "python_version": "py3",
+        "yanked": false,
+        "yanked_reason": null,
+    });
+    let versions: Vec<String> = (0..releases).map(|i| format!("{}.{}.{}", i / 100, i / 10 % 10, i % 10)).collect();
+    let latest = versions.last().unwrap();
+    json!({
+        "info": {
+            "name": "bigproject",
+            "version": latest,
+            "requires_python": ">=3.8",
+            "requires_dist": ["requests (>=2.0)", "pywin32 ; sys_platform == 'win32'"],
+            "summary": "A project with many releases",
+            "description": "long description ".repeat(2000),
+            "classifiers": vec!["Programming Language :: Python :: 3"; 50],
+        },
+        "urls": [file(latest, "py3-none-any", "whl"), file(latest, "src", "tar.gz")],
+        "releases": versions.iter()
+            .map(|v| (v.clone(), json!([file(v, "py3-none-any", "whl"), file(v, "src", "tar.gz")])))
+            .collect::<serde_json::Map<_, _>>(),
+    })
+}
+
+/// Heap bytes held by a parsed `Value`: every node plus string and container buffers
+fn value_heap_bytes(value: &Value) -> usize {
+    match value {
+        Value::String(s) => s.capacity(),
+        Value::Array(items) => items.capacity() * size_of::<Value>() + items.iter().map(value_heap_bytes).sum::<usize>(),
+        Value::Object(map) => map.iter()
+            .map(|(key, value)| key.capacity() + size_of::<Value>() + value_heap_bytes(value))
+            .sum(),
+        _ => 0,
+    }
+}
+
+fn file_heap_bytes(file: &ReleaseFile) -> usize {
+    size_of::<ReleaseFile>()
+        + file.filename.capacity()
+        + file.url.capacity()
+        + file.packagetype.capacity()
+        + file.sha256.as_ref().map_or(0, String::capacity)
+        + file.requires_python.as_ref().map_or(0, String::capacity)
+}
+
+fn document_heap_bytes(document: &ProjectDocument) -> usize {
+    let info = &document.info;
+    let strings = [&info.version, &info.requires_python, &info.summary, &info.author, &info.home_page, &info.project_url];
+    strings.iter().map(|s| s.as_ref().map_or(0, String::capacity)).sum::<usize>()
+        + info.requires_dist.iter().flatten().map(|d| size_of::<String>() + d.capacity()).sum::<usize>()
+        + document.urls.iter().map(file_heap_bytes).sum::<usize>()
+}
+
+#[test]
+fn typed_parse_matches_value_parse_with_a_fraction_of_the_memory() {
+    let body = serde_json::to_vec(&large_project(5000)).unwrap();
+    assert!(body.len() > 4 * 1024 * 1024, "fixture is only {} bytes", body.len());
+
+    let naive: Value = serde_json::from_slice(&body).unwrap();
+    let document = ProjectDocument::from_slice(&body).unwrap();
+
+    let info = &naive["info"];
+    assert_eq!(document.info.version.as_deref(), info["version"].as_str());
+    assert_eq!(document.info.requires_python.as_deref(), info["requires_python"].as_str());
+    let requires_dist: Vec<&str> = info["requires_dist"].as_array().unwrap().iter().map(|d| d.as_str().unwrap()).collect();
+    assert_eq!(document.info.requires_dist.as_ref().unwrap(), &requires_dist);
+
+    let urls = naive["urls"].as_array().unwrap();
+    assert_eq!(document.urls.len(), urls.len());
+    for (file, expected) in document.urls.iter().zip(urls) {
+        assert_eq!(file.filename, expected["filename"].as_str().unwrap());
+        assert_eq!(file.url, expected["url"].as_str().unwrap());
+        assert_eq!(file.packagetype, expected["packagetype"].as_str().unwrap());
+        assert_eq!(file.size, expected["size"].as_u64());
+        assert_eq!(file.sha256.as_deref(), expected["digests"]["sha256"].as_str());
+        assert!(file.upload_time.is_some());
+    }
+
+    let package = document.clone().into_package("bigproject").unwrap();
+    assert_eq!(package.version, "49.9.9");
+    assert!(package.download_url.ends_with(".whl"));
+    assert_eq!(package.dependencies.len(), 2);
+
+    // The test binary shares the library's global allocator, so compare the
+    // heap each representation keeps rather than counting allocations
+    let naive_bytes = value_heap_bytes(&naive);
+    let typed_bytes = document_heap_bytes(&document);
+    assert!(typed_bytes * 1000 < naive_bytes, "typed {} bytes vs value {} bytes", typed_bytes, naive_bytes);
+}
+
+#[test]
+fn releases_are_read_without_the_rest_of_the_document() {
+    let body = serde_json::to_vec(&large_project(300)).unwrap();
+    let naive: Value = serde_json::from_slice(&body).unwrap();
+
+    let releases = releases_from_slice(&body).unwrap();
+    assert_eq!(releases.len(), naive["releases"].as_object().unwrap().len());
+    assert_eq!(releases.first().unwrap().version, "0.0.0");
+    assert_eq!(releases.last().unwrap().version, "2.9.9");
+    assert!(releases.iter().all(|r| r.files.len() == 2));
+
+    let expected = &naive["releases"]["1.2.3"][0];
+    let file = &releases.iter().find(|r| r.version == "1.2.3").unwrap().files[0];
+    assert_eq!(file.url, expected["url"].as_str().unwrap());
+    assert_eq!(file.sha256.as_deref(), expected["digests"]["sha256"].as_str());
+}
+
+#[test]
+fn null_fields_are_treated_as_missing() {
+    let body = br#"{"info": {"version": "1.0", "requires_dist": null, "summary": null}, "urls": null, "releases": null}"#;
+
+    let document = ProjectDocument::from_slice(body).unwrap();
+    assert_eq!(document.info.version.as_deref(), Some("1.0"));
+    assert_eq!(document.info.requires_dist, None);
+    assert!(document.urls.is_empty());
+    assert!(releases_from_slice(body).unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn metadata_timeout_fails_fast_without_limiting_downloads() {
+    let index = MockIndex::start().await;
+    index.stall("/pypi/dead/json");
+    index.stall("/files/slow.whl");
+
+    let timeouts = HttpTimeouts { metadata: Duration::from_millis(200), ..HttpTimeouts::default() };
+    let no_retry = RetryPolicy { retries: 0, ..RetryPolicy::default() };
+    let client = RegistryClient::with_base_url(index.url.clone()).with_timeouts(timeouts).with_retry(no_retry);
+    let started = Instant::now();
+    assert!(matches!(client.get_package_info("dead", "*").await, Err(CobraError::Network(_))));
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    // Downloads have no deadline by default, so a slow link keeps waiting
+    let url = format!("{}/files/slow.whl", index.url);
+    assert!(tokio::time::timeout(Duration::from_millis(600), client.download_package(&url, 0)).await.is_err());
+
+    let timeouts = HttpTimeouts { download: Some(Duration::from_millis(200)), ..timeouts };
+    let client = RegistryClient::with_base_url(index.url.clone()).with_timeouts(timeouts).with_retry(no_retry);
+    assert!(matches!(client.download_package(&url, 0).await, Err(CobraError::Network(_))));
+}
+
+/// Retries quick enough for tests
+fn fast_retry(retries: u32) -> RetryPolicy {
+    RetryPolicy { retries, base_delay: Duration::from_millis(10) }
+}
+
+#[tokio::test]
+async fn ranges_pick_the_newest_matching_release_from_the_releases_map() {
+    use crate::support::{project_json, version_json};
+
+    let index = MockIndex::start().await;
+    let releases = [("1.0.0", "2023-01-01T00:00:00"), ("1.5.0", "2023-06-01T00:00:00"), ("2.0.0", "2024-01-01T00:00:00")];
+    index.serve_json("/pypi/ranged/json", &project_json(&index.url, "ranged", &releases, &["newdep>=1"]));
+    index.serve_json("/pypi/ranged/1.5.0/json", &version_json(&index.url, "ranged", "1.5.0", "2023-06-01T00:00:00", &["olddep"]));
+    let client = RegistryClient::with_base_url(index.url.clone());
+
+    let package = client.get_package_info("ranged", ">=1.0,<2.0").await.unwrap();
+    assert_eq!(package.version, "1.5.0");
+    assert_eq!(package.download_url, format!("{}/files/ranged-1.5.0-py3-none-any.whl", index.url));
+    assert_eq!(package.hash.as_deref(), Some(format!("{:0>64}", "150").as_str()));
+    assert_eq!(package.dependencies.iter().map(|d| d.name.as_str()).collect::<Vec<_>>(), vec!["olddep"]);
+
+    // The latest release is described by the project document alone
+    let package = client.get_package_info("ranged", ">=1.5").await.unwrap();
+    assert_eq!(package.version, "2.0.0");
+    assert_eq!(package.dependencies[0].name, "newdep");
+    assert_eq!(index.requests().iter().filter(|p| p.as_str() == "/pypi/ranged/1.5.0/json").count(), 1);
+
+    assert!(matches!(client.get_package_info("ranged", ">=3").await, Err(CobraError::PackageNotFound(_))));
+}
+
+#[tokio::test]
+async fn transient_server_errors_are_retried() {
+    let index = MockIndex::start().await;
+    index.serve_json("/pypi/flaky/json", &crate::support::project_json(&index.url, "flaky", &[("1.0.0", "2023-01-01T00:00:00")], &[]));
+    index.fail_next("/pypi/flaky/json", 2, None);
+    index.serve("/files/flaky.whl", b"wheel".to_vec());
+    index.fail_next("/files/flaky.whl", 1, None);
+
+    let client = RegistryClient::with_base_url(index.url.clone()).with_retry(fast_retry(3));
+    let package = client.get_package_info("flaky", "*").await.unwrap();
+    assert_eq!(package.version, "1.0.0");
+    assert_eq!(index.requests().iter().filter(|p| *p == "/pypi/flaky/json").count(), 3);
+
+    let url = format!("{}/files/flaky.whl", index.url);
+    let response = client.download_package(&url, 0).await.unwrap();
+    assert_eq!(response.bytes().await.unwrap().as_ref(), b"wheel");
+}
+
+#[tokio::test]
+async fn exhausted_retries_report_the_attempt_count() {
+    let index = MockIndex::start().await;
+    index.serve_json("/pypi/down/json", &crate::support::project_json(&index.url, "down", &[("1.0.0", "2023-01-01T00:00:00")], &[]));
+    index.fail_next("/pypi/down/json", 10, None);
+
+    let client = RegistryClient::with_base_url(index.url.clone()).with_retry(fast_retry(2));
+    match client.get_package_info("down", "*").await {
+        Err(CobraError::RetriesExhausted { attempts, last_error }) => {
+            assert_eq!(attempts, 3);
+            assert!(last_error.contains("502"), "{}", last_error);
+        }
+        other => panic!("expected exhausted retries, got {:?}", other.map(|p| p.version)),
+    }
+    assert_eq!(index.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn retry_after_overrides_the_backoff() {
+    let index = MockIndex::start().await;
+    index.serve_json("/pypi/busy/json", &crate::support::project_json(&index.url, "busy", &[("1.0.0", "2023-01-01T00:00:00")], &[]));
+    index.fail_next("/pypi/busy/json", 1, Some(1));
+
+    let client = RegistryClient::with_base_url(index.url.clone()).with_retry(fast_retry(1));
+    let started = Instant::now();
+    client.get_package_info("busy", "*").await.unwrap();
+    assert!(started.elapsed() >= Duration::from_secs(1));
+}
+
+#[test]
+fn mirror_urls_keep_the_artifact_path_under_the_mirror_root() {
+    let url = "https://files.pythonhosted.org/packages/ab/cd/demo-1.0-py3-none-any.whl?v=1";
+    assert_eq!(
+        mirror_url(url, "https://mirror.corp/pypi/").as_deref(),
+        Some("https://mirror.corp/pypi/packages/ab/cd/demo-1.0-py3-none-any.whl?v=1")
+    );
+    assert_eq!(mirror_url("not a url", "https://mirror.corp"), None);
+}
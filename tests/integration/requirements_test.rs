@@ -0,0 +1,79 @@
+use crate::support::dep;
+use cobra::core::requirements::{parse_package_arg, parse_requirement_args, parse_requirements, parse_requirements_file};
+use cobra::cli::freeze::freeze_requirements;
+use cobra::Dependency;
+
+#[test]
+fn parses_specs_and_skips_comments_and_options() {
+    let contents = "\
+# pinned deps
+--index-url https://example.com/simple
+requests==2.31.0  # http
+flask >= 2.0, <3
+uvicorn[standard]>=0.20 ; python_version >= \"3.8\"
+numpy \\
+    ==1.26.0
+
+-e .
+";
+    assert_eq!(
+        parse_requirements(contents),
+        vec![
+            dep("requests", "==2.31.0"),
+            dep("flask", ">=2.0,<3"),
+            Dependency { markers: Some("python_version >= \"3.8\"".to_string()), ..dep("uvicorn", ">=0.20") },
+            dep("numpy", "==1.26.0"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn follows_nested_includes_relative_to_file() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("reqs/extra")).unwrap();
+    std::fs::write(dir.path().join("requirements.txt"), "flask>=2\n-r reqs/base.txt\npytest>=7\n").unwrap();
+    std::fs::write(dir.path().join("reqs/base.txt"), "click==8.1.7\n-r extra/more.txt\n--requirement=../requirements.txt\nrich\n").unwrap();
+    std::fs::write(dir.path().join("reqs/extra/more.txt"), "numpy==1.26.0\n").unwrap();
+
+    let deps = parse_requirements_file(&dir.path().join("requirements.txt")).await.unwrap();
+
+    // Each include is expanded where its -r line is; the cycle back is ignored
+    assert_eq!(deps, vec![
+        dep("flask", ">=2"),
+        dep("click", "==8.1.7"),
+        dep("numpy", "==1.26.0"),
+        dep("rich", "*"),
+        dep("pytest", ">=7"),
+    ]);
+}
+
+#[test]
+fn command_line_requirements_are_parsed_like_requirement_lines() {
+    let args = ["requests==2.31.0", "flask>=2.0,<3", "rich"].map(String::from);
+    assert_eq!(
+        parse_requirement_args(&args).unwrap(),
+        vec![dep("requests", "==2.31.0"), dep("flask", ">=2.0,<3"), dep("rich", "*")]
+    );
+
+    let error = parse_requirement_args(&["--pre".to_string()]).unwrap_err();
+    assert!(error.to_string().contains("'--pre' is not a requirement"), "{}", error);
+    assert!(parse_requirement_args(&["requests==>2".to_string()]).is_err());
+}
+
+#[test]
+fn package_args_also_take_cobra_add_name_at_spec_form() {
+    let args = ["requests@^2.31", "flask @ >=2,<3"].map(String::from);
+    assert_eq!(
+        parse_requirement_args(&args).unwrap(),
+        vec![dep("requests", "^2.31"), dep("flask", ">=2,<3")]
+    );
+    // A PEP 508 URL requirement is not a version spec
+    assert!(parse_package_arg("pkg @ https://example.com/pkg.whl").is_err());
+    assert!(parse_package_arg("requests>=2@1").is_err());
+}
+
+#[test]
+fn freeze_emits_pip_format() {
+    let frozen = freeze_requirements([("Flask", "3.0.0"), ("requests", "2.31.0")]);
+    assert_eq!(frozen, "Flask==3.0.0\nrequests==2.31.0\n");
+}
@@ -0,0 +1,634 @@
+use crate::support::{dep, project_json, version_json, MockIndex};
+use chrono::{DateTime, Utc};
+use cobra::core::lockfile::Lockfile;
+use cobra::cli::why::format_path;
+use cobra::core::resolver::{DependencyCycle, ResolutionStrategy};
+use cobra::core::version::Version;
+use cobra::{DependencyResolver, Dependency, Package, RegistryClient};
+use std::sync::Arc;
+
+#[tokio::test]
+async fn exclude_newer_selects_release_uploaded_before_cutoff() {
+    let index = MockIndex::start().await;
+    let releases = [
+        ("1.0.0", "2023-01-10T12:00:00Z"),
+        ("1.1.0", "2023-08-01T12:00:00Z"),
+        ("2.0.0", "2024-06-01T12:00:00Z"),
+    ];
+    index.serve_json("/pypi/demo/json", &project_json(&index.url, "demo", &releases, &[]));
+    index.serve_json(
+        "/pypi/demo/1.1.0/json",
+        &version_json(&index.url, "demo", "1.1.0", "2023-08-01T12:00:00Z", &[]),
+    );
+
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let cutoff: DateTime<Utc> = "2024-01-01T00:00:00Z".parse().unwrap();
+    let resolver = DependencyResolver::new(client, None).with_exclude_newer(Some(cutoff));
+
+    let resolved = resolver.resolve(&[dep("demo", "*")]).await.unwrap();
+
+    assert_eq!(resolved.len(), 1);
+    assert_eq!(resolved[0].version, "1.1.0");
+    assert!(resolved[0].download_url.ends_with("demo-1.1.0-py3-none-any.whl"));
+}
+
+#[tokio::test]
+async fn exclude_newer_fails_when_nothing_predates_cutoff() {
+    let index = MockIndex::start().await;
+    let releases = [("2.0.0", "2024-06-01T12:00:00Z")];
+    index.serve_json("/pypi/demo/json", &project_json(&index.url, "demo", &releases, &[]));
+
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let cutoff: DateTime<Utc> = "2024-01-01T00:00:00Z".parse().unwrap();
+    let resolver = DependencyResolver::new(client, None).with_exclude_newer(Some(cutoff));
+
+    assert!(resolver.resolve(&[dep("demo", ">=1.0")]).await.is_err());
+}
+
+#[tokio::test]
+async fn wide_graphs_resolve_level_by_level() {
+    // app -> lib0..lib7, each libN -> leafN, and every lib also needs `shared`
+    let index = MockIndex::start().await;
+    let uploaded = "2024-01-01T00:00:00Z";
+    let libs: Vec<String> = (0..8).map(|i| format!("lib{}", i)).collect();
+    let mut projects: Vec<(String, Vec<String>)> = vec![("app".to_string(), libs.clone()), ("shared".to_string(), vec![])];
+    for (i, lib) in libs.iter().enumerate() {
+        projects.push((lib.clone(), vec![format!("leaf{}", i), "shared".to_string()]));
+        projects.push((format!("leaf{}", i), vec![]));
+    }
+    for (name, requires) in &projects {
+        let requires: Vec<&str> = requires.iter().map(String::as_str).collect();
+        index.serve_json(&format!("/pypi/{}/json", name), &project_json(&index.url, name, &[("1.0.0", uploaded)], &requires));
+    }
+    let latency = std::time::Duration::from_millis(200);
+    index.set_latency(latency);
+
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let started = std::time::Instant::now();
+    let resolved = DependencyResolver::new(client, None).resolve(&[dep("app", "*")]).await.unwrap();
+    let elapsed = started.elapsed();
+
+    assert_eq!(resolved.len(), projects.len());
+    assert_eq!(resolved.last().unwrap().name, "app");
+    // Each package is looked up once, however many packages require it
+    let shared_lookups = index.requests().iter().filter(|path| path.contains("/shared/")).count();
+    assert_eq!(shared_lookups, 1);
+    // Three levels below the root cost about three round trips, not one per package
+    assert!(elapsed < latency * 6, "resolving took {:?}", elapsed);
+}
+
+#[tokio::test]
+async fn concurrent_lookups_of_one_requirement_share_a_request() {
+    // `six` is a root and, in the same resolution, required by alpha and beta
+    let index = MockIndex::start().await;
+    let uploaded = "2024-01-01T00:00:00Z";
+    for (name, requires) in [("alpha", vec!["six"]), ("beta", vec!["Six"]), ("six", vec![])] {
+        index.serve_json(&format!("/pypi/{}/json", name), &project_json(&index.url, name, &[("1.0.0", uploaded)], &requires));
+    }
+    index.set_latency(std::time::Duration::from_millis(50));
+
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let resolver = DependencyResolver::new(client, None);
+    let roots = [dep("six", "*"), dep("alpha", "*"), dep("beta", "*"), dep("six", "*")];
+    let resolved = resolver.resolve(&roots).await.unwrap();
+
+    assert_eq!(resolved.len(), 3);
+    let requests = index.requests();
+    assert_eq!(requests.iter().filter(|path| path.contains("/six/")).count(), 1, "{:?}", requests);
+}
+
+/// `modern` 1.0.0 runs anywhere, 2.0.0 needs Python 3.8 and 3.0.0 needs 3.12
+fn serve_python_gated_releases(index: &MockIndex) {
+    let releases = [
+        ("1.0.0", "2023-01-10T12:00:00Z"),
+        ("2.0.0", "2023-08-01T12:00:00Z"),
+        ("3.0.0", "2024-06-01T12:00:00Z"),
+    ];
+    let mut project = project_json(&index.url, "modern", &releases, &[]);
+    project["info"]["requires_python"] = ">=3.12".into();
+    project["urls"][0]["requires_python"] = ">=3.12".into();
+    project["releases"]["2.0.0"][0]["requires_python"] = ">=3.8, <4".into();
+    project["releases"]["3.0.0"][0]["requires_python"] = ">=3.12".into();
+    index.serve_json("/pypi/modern/json", &project);
+    for (version, uploaded) in &releases[..2] {
+        index.serve_json(
+            &format!("/pypi/modern/{}/json", version),
+            &version_json(&index.url, "modern", version, uploaded, &[]),
+        );
+    }
+}
+
+#[tokio::test]
+async fn releases_requiring_a_newer_python_are_skipped() {
+    let index = MockIndex::start().await;
+    serve_python_gated_releases(&index);
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+
+    for (python, expected) in [("3.11.4", "2.0.0"), ("3.7.9", "1.0.0")] {
+        let resolver = DependencyResolver::new(client.clone(), None)
+            .with_python_version(Some(Version::parse(python).unwrap()));
+        let resolved = resolver.resolve(&[dep("modern", "*")]).await.unwrap();
+        assert_eq!(resolved[0].version, expected, "on Python {}", python);
+        assert!(resolved[0].download_url.ends_with(&format!("modern-{}-py3-none-any.whl", expected)));
+    }
+
+    // Without an interpreter version the newest release wins as before
+    let resolved = DependencyResolver::new(client, None).resolve(&[dep("modern", "*")]).await.unwrap();
+    assert_eq!(resolved[0].version, "3.0.0");
+}
+
+#[tokio::test]
+async fn no_release_supporting_the_interpreter_names_both_versions() {
+    let index = MockIndex::start().await;
+    serve_python_gated_releases(&index);
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let resolver = DependencyResolver::new(client, None)
+        .with_python_version(Some(Version::parse("3.7.9").unwrap()));
+
+    let error = resolver.resolve(&[dep("modern", ">=2")]).await.unwrap_err().to_string();
+    assert!(error.contains("Python 3.7.9"), "{}", error);
+    assert!(error.contains("modern 3.0.0 requires Python '>=3.12'"), "{}", error);
+}
+
+#[tokio::test]
+async fn lowest_strategy_selects_oldest_satisfying_release() {
+    let index = MockIndex::start().await;
+    let releases = [
+        ("1.0.0", "2023-01-10T12:00:00Z"),
+        ("1.2.0", "2023-08-01T12:00:00Z"),
+        ("2.0.0", "2024-06-01T12:00:00Z"),
+    ];
+    index.serve_json("/pypi/demo/json", &project_json(&index.url, "demo", &releases, &[]));
+    index.serve_json(
+        "/pypi/demo/1.2.0/json",
+        &version_json(&index.url, "demo", "1.2.0", "2023-08-01T12:00:00Z", &[]),
+    );
+
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let resolver = DependencyResolver::new(client, None).with_strategy(ResolutionStrategy::Lowest);
+
+    let resolved = resolver.resolve(&[dep("demo", ">=1.1")]).await.unwrap();
+
+    assert_eq!(resolved[0].version, "1.2.0");
+}
+
+/// `left` needs `shared>=1.0` and `right` needs `Shared>=2.0`, with shared 1.0.0 to 3.0.0 released
+fn serve_shared_lower_bounds(index: &MockIndex) {
+    let uploaded = "2023-01-10T12:00:00Z";
+    let releases = [("1.0.0", uploaded), ("2.0.0", uploaded), ("3.0.0", uploaded)];
+    for (name, requires) in [("left", "shared (>=1.0)"), ("right", "Shared (>=2.0)")] {
+        index.serve_json(&format!("/pypi/{}/json", name), &project_json(&index.url, name, &[("1.0.0", uploaded)], &[requires]));
+        index.serve_json(&format!("/pypi/{}/1.0.0/json", name), &version_json(&index.url, name, "1.0.0", uploaded, &[requires]));
+    }
+    index.serve_json("/pypi/shared/json", &project_json(&index.url, "shared", &releases, &[]));
+    for (version, _) in releases {
+        index.serve_json(&format!("/pypi/shared/{}/json", version), &version_json(&index.url, "shared", version, uploaded, &[]));
+    }
+}
+
+#[tokio::test]
+async fn requirers_with_different_lower_bounds_share_one_release() {
+    let index = MockIndex::start().await;
+    serve_shared_lower_bounds(&index);
+
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let resolver = DependencyResolver::new(client, None).with_strategy(ResolutionStrategy::Lowest);
+
+    let resolved = resolver.resolve(&[dep("left", "*"), dep("right", "*")]).await.unwrap();
+
+    let shared: Vec<&str> = resolved.iter()
+        .filter(|p| p.name.eq_ignore_ascii_case("shared"))
+        .map(|p| p.version.as_str())
+        .collect();
+    assert_eq!(shared, vec!["2.0.0"]);
+    assert_eq!(resolved.len(), 3);
+}
+
+#[tokio::test]
+async fn frozen_pins_apply_whatever_the_name_spelling() {
+    let index = MockIndex::start().await;
+    serve_shared_lower_bounds(&index);
+
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let pins = [("Shared".to_string(), "3.0.0".to_string())].into_iter().collect();
+    let resolver = DependencyResolver::new(client, None).with_pins(pins);
+
+    let resolved = resolver.resolve(&[dep("left", "*")]).await.unwrap();
+
+    let shared = resolved.iter().find(|p| p.name == "shared").unwrap();
+    assert_eq!(shared.version, "3.0.0");
+}
+
+#[tokio::test]
+async fn lockfile_records_resolution_strategy() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cobra.lock");
+    let package = Package {
+        name: "demo".to_string(),
+        version: "1.2.0".to_string(),
+        dependencies: vec![dep("six", ">=1.0")],
+        download_url: "https://example.invalid/demo-1.2.0-py3-none-any.whl".to_string(),
+        hash: Some("ab".repeat(32)),
+        size: Some(1024),
+        description: None,
+        author: None,
+        homepage: None,
+        markers: None,
+        yanked: false,
+        yanked_reason: None,
+        requires_python: None,
+    };
+
+    Lockfile::from_packages(&[package], ResolutionStrategy::Lowest).save(&path).await.unwrap();
+    let loaded = Lockfile::load(&path).await.unwrap();
+
+    assert_eq!(loaded.resolution, ResolutionStrategy::Lowest);
+    assert_eq!(loaded.pins().get("demo").map(String::as_str), Some("1.2.0"));
+    assert_eq!(loaded.packages[0].dependencies, vec!["six".to_string()]);
+}
+
+#[tokio::test]
+async fn overrides_replace_transitive_requirements() {
+    let index = MockIndex::start().await;
+    let uploaded = "2023-01-10T12:00:00Z";
+    index.serve_json("/pypi/app/json", &version_json(&index.url, "app", "1.0.0", uploaded, &["urllib3 (>=2.0)"]));
+    index.serve_json("/pypi/urllib3/1.26.18/json", &version_json(&index.url, "urllib3", "1.26.18", uploaded, &[]));
+
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let overrides = [("urllib3".to_string(), "==1.26.18".to_string())].into_iter().collect();
+    let resolver = DependencyResolver::new(client, None).with_overrides(overrides);
+
+    let resolved = resolver.resolve(&[dep("app", "*")]).await.unwrap();
+
+    let urllib3 = resolved.iter().find(|p| p.name == "urllib3").unwrap();
+    assert_eq!(urllib3.version, "1.26.18");
+
+    let overridden = resolver.overridden_requirements();
+    assert_eq!(overridden.len(), 1);
+    assert_eq!(overridden[0].parent.as_deref(), Some("app@1.0.0"));
+    assert_eq!(overridden[0].requested, ">=2.0");
+}
+
+#[tokio::test]
+async fn check_reports_hard_and_conditional_cycles() {
+    let index = MockIndex::start().await;
+    let uploaded = "2024-01-01T00:00:00Z";
+    for (name, requires) in [
+        ("alpha", vec!["beta"]),
+        ("beta", vec!["gamma", "delta; extra == \"test\""]),
+        ("gamma", vec!["alpha"]),
+        ("delta", vec!["beta"]),
+    ] {
+        index.serve_json(
+            &format!("/pypi/{}/json", name),
+            &project_json(&index.url, name, &[("1.0.0", uploaded)], &requires),
+        );
+    }
+
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let resolver = DependencyResolver::new(client, None).with_all_extras(true);
+    let cycles = cobra::cli::check::check_circular_dependencies(&[dep("alpha", "*")], &resolver)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        cycles,
+        vec![
+            DependencyCycle { chain: names(&["alpha", "beta", "gamma", "alpha"]), conditional: false },
+            DependencyCycle { chain: names(&["beta", "delta", "beta"]), conditional: true },
+        ]
+    );
+}
+
+fn names(names: &[&str]) -> Vec<String> {
+    names.iter().map(|n| n.to_string()).collect()
+}
+
+#[tokio::test]
+async fn markers_propagate_to_resolved_packages_and_lockfile() {
+    let index = MockIndex::start().await;
+    let uploaded = "2024-01-01T00:00:00Z";
+    for (name, requires) in [
+        ("app", vec!["winhelper; sys_platform == 'win32'", "pytest; extra == 'test'", "core"]),
+        ("winhelper", vec!["core"]),
+        ("core", vec![]),
+        ("pywin32", vec![]),
+    ] {
+        index.serve_json(
+            &format!("/pypi/{}/json", name),
+            &project_json(&index.url, name, &[("1.0.0", uploaded)], &requires),
+        );
+    }
+
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let resolver = DependencyResolver::new(client, None);
+    let roots = [
+        dep("app", "*"),
+        Dependency { markers: Some("os_name == 'nt'".to_string()), ..dep("pywin32", "*") },
+    ];
+    let resolved = resolver.resolve(&roots).await.unwrap();
+
+    let marker = |name: &str| {
+        resolved.iter().find(|p| p.name == name).map(|p| p.markers.clone())
+    };
+    assert_eq!(marker("app"), Some(None));
+    assert_eq!(marker("winhelper"), Some(Some("sys_platform == 'win32'".to_string())));
+    // Needed unconditionally through app, so the win32 path does not narrow it
+    assert_eq!(marker("core"), Some(None));
+    assert_eq!(marker("pywin32"), Some(Some("os_name == 'nt'".to_string())));
+    // Unrequested extras are never resolved
+    assert_eq!(marker("pytest"), None);
+    assert!(!index.requests().iter().any(|r| r.contains("pytest")));
+
+    let lockfile = Lockfile::from_packages(&resolved, ResolutionStrategy::Highest);
+    let locked = lockfile.packages.iter().find(|p| p.name == "winhelper").unwrap();
+    assert_eq!(locked.markers.as_deref(), Some("sys_platform == 'win32'"));
+}
+
+#[test]
+fn dependents_are_found_by_normalized_name() {
+    let lockfile: Lockfile = toml::from_str(r#"
+        version = 1
+
+        [[package]]
+        name = "requests"
+        version = "2.31.0"
+        url = ""
+        dependencies = ["charset-normalizer", "urllib3"]
+
+        [[package]]
+        name = "httpx"
+        version = "0.27.0"
+        url = ""
+        dependencies = ["Charset_Normalizer"]
+
+        [[package]]
+        name = "charset-normalizer"
+        version = "3.3.2"
+        url = ""
+    "#).unwrap();
+
+    assert_eq!(lockfile.dependents("charset.normalizer"), vec!["requests", "httpx"]);
+    assert_eq!(lockfile.dependents("urllib3"), vec!["requests"]);
+    assert!(lockfile.dependents("requests").is_empty());
+}
+
+#[tokio::test]
+async fn requested_extras_of_root_packages_are_resolved() {
+    let index = MockIndex::start().await;
+    let uploaded = "2024-01-01T00:00:00Z";
+    for (name, requires) in [
+        ("client", vec!["pysocks; extra == 'socks'", "pytest; extra == 'test'", "winsocks; extra == 'socks' and os_name == 'nt'"]),
+        ("pysocks", vec![]),
+        ("winsocks", vec![]),
+    ] {
+        index.serve_json(
+            &format!("/pypi/{}/json", name),
+            &project_json(&index.url, name, &[("1.0.0", uploaded)], &requires),
+        );
+    }
+
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let resolver = DependencyResolver::new(client, None);
+    let roots = [Dependency { extras: vec!["socks".to_string()], ..dep("client", "*") }];
+    let resolved = resolver.resolve(&roots).await.unwrap();
+
+    let marker = |name: &str| resolved.iter().find(|p| p.name == name).map(|p| p.markers.clone());
+    assert_eq!(marker("pysocks"), Some(None));
+    assert_eq!(marker("winsocks"), Some(Some("os_name == \"nt\"".to_string())));
+    assert_eq!(marker("pytest"), None);
+}
+
+#[tokio::test]
+async fn unresolvable_optional_dependencies_are_skipped() {
+    let index = MockIndex::start().await;
+    index.serve_json("/pypi/demo/json", &project_json(&index.url, "demo", &[("1.0.0", "2024-01-01T00:00:00Z")], &[]));
+
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let resolver = DependencyResolver::new(client, None);
+    let roots = [dep("demo", "*"), Dependency { optional: true, ..dep("missing", "*") }];
+    let resolved = resolver.resolve(&roots).await.unwrap();
+
+    assert_eq!(resolved.len(), 1);
+    let skipped = resolver.skipped_dependencies();
+    assert_eq!(skipped.len(), 1);
+    assert_eq!(skipped[0].package, "missing");
+
+    // Without `optional` the same failure aborts the resolution
+    assert!(resolver.resolve(&[dep("demo", "*"), dep("missing", "*")]).await.is_err());
+    assert!(resolver.skipped_dependencies().is_empty());
+}
+
+#[tokio::test]
+async fn dependency_graph_lists_every_path_to_a_package() {
+    let index = MockIndex::start().await;
+    let uploaded = "2024-01-01T00:00:00Z";
+    for (name, requires) in [
+        ("app", vec!["requests", "botocore; os_name == 'posix'"]),
+        ("requests", vec!["urllib3"]),
+        ("botocore", vec!["urllib3"]),
+        ("urllib3", vec![]),
+        ("other", vec![]),
+    ] {
+        index.serve_json(
+            &format!("/pypi/{}/json", name),
+            &project_json(&index.url, name, &[("1.0.0", uploaded)], &requires),
+        );
+    }
+
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let resolver = DependencyResolver::new(client, None);
+    let graph = resolver.resolve_graph(&[dep("app", "*"), dep("urllib3", "*"), dep("other", "*")]).await.unwrap();
+
+    let paths: Vec<String> = graph.paths_to("URLLib3").iter().map(|path| format_path(path)).collect();
+    assert_eq!(paths, [
+        "app 1.0.0 -> botocore 1.0.0 (os_name == 'posix') -> urllib3 1.0.0",
+        "app 1.0.0 -> requests 1.0.0 -> urllib3 1.0.0",
+        "urllib3 1.0.0",
+    ]);
+    assert_eq!(graph.paths_to("other").len(), 1);
+    assert!(graph.package("missing").is_none());
+    assert!(graph.paths_to("missing").is_empty());
+}
+
+#[tokio::test]
+async fn resolution_deadline_reports_resolved_and_pending_packages() {
+    let index = MockIndex::start().await;
+    let uploaded = "2024-01-01T00:00:00Z";
+    index.serve_json("/pypi/app/json", &project_json(&index.url, "app", &[("1.0.0", uploaded)], &["slowdep", "fastdep"]));
+    index.serve_json("/pypi/fastdep/json", &project_json(&index.url, "fastdep", &[("1.0.0", uploaded)], &[]));
+    index.stall("/pypi/slowdep/json");
+
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let resolver = DependencyResolver::new(client, None)
+        .with_timeout(Some(std::time::Duration::from_millis(300)));
+
+    let started = std::time::Instant::now();
+    let error = resolver.resolve(&[dep("app", "*")]).await.unwrap_err();
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    match error {
+        cobra::CobraError::ResolutionTimeout { resolved, pending, .. } => {
+            assert_eq!(resolved, 2);
+            assert_eq!(pending, vec!["slowdep".to_string()]);
+        }
+        other => panic!("expected a resolution timeout, got {}", other),
+    }
+}
+
+#[tokio::test]
+async fn parallel_downloads_caps_concurrent_metadata_requests() {
+    let index = MockIndex::start().await;
+    index.stall("/pypi/first/json");
+    index.stall("/pypi/second/json");
+
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let resolver = DependencyResolver::new(client, None)
+        .with_parallel_downloads(1)
+        .with_timeout(Some(std::time::Duration::from_millis(300)));
+
+    let error = resolver.resolve(&[dep("first", "*"), dep("second", "*")]).await.unwrap_err();
+    assert!(matches!(error, cobra::CobraError::ResolutionTimeout { .. }));
+    // The second lookup waited for a permit and never reached the index
+    assert_eq!(index.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn simple_api_lists_files_with_digests_and_yanked_flags() {
+    let index = MockIndex::start().await;
+    index.serve_simple("/simple/demo-pkg/", &serde_json::json!({
+        "meta": { "api-version": "1.1" },
+        "name": "demo-pkg",
+        "files": [
+            {
+                "filename": "demo_pkg-1.0.0.tar.gz",
+                "url": "../../files/demo_pkg-1.0.0.tar.gz",
+                "hashes": { "sha256": "aa" },
+                "requires-python": ">=3.8",
+            },
+            {
+                "filename": "demo_pkg-1.0.5-py3-none-any.whl",
+                "url": "/files/demo_pkg-1.0.5-py3-none-any.whl",
+                "hashes": { "sha256": "bb" },
+                "upload-time": "2024-01-01T00:00:00Z",
+            },
+            {
+                "filename": "demo_pkg-1.1.0-py3-none-any.whl",
+                "url": "/files/demo_pkg-1.1.0-py3-none-any.whl",
+                "hashes": { "sha256": "cc" },
+                "yanked": "broken metadata",
+            },
+        ],
+    }));
+    index.serve_json(
+        "/pypi/demo_pkg/1.0.5/json",
+        &version_json(&index.url, "demo_pkg", "1.0.5", "2024-01-01T00:00:00Z", &[]),
+    );
+
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let releases = client.get_releases("demo_pkg").await.unwrap();
+    let versions: Vec<_> = releases.iter().map(|r| r.version.as_str()).collect();
+    assert_eq!(versions, vec!["1.0.0", "1.0.5", "1.1.0"]);
+
+    let sdist = &releases[0].files[0];
+    assert_eq!(sdist.packagetype, "sdist");
+    assert_eq!(sdist.url, format!("{}/files/demo_pkg-1.0.0.tar.gz", index.url));
+    assert_eq!(sdist.sha256.as_deref(), Some("aa"));
+    assert_eq!(sdist.requires_python.as_deref(), Some(">=3.8"));
+    assert!(releases[2].files[0].yanked);
+    assert_eq!(releases[2].files[0].yanked_reason.as_deref(), Some("broken metadata"));
+    assert!(!index.requests().contains(&"/pypi/demo_pkg/json".to_string()));
+
+    // Yanked releases are skipped unless pinned exactly
+    let resolver = DependencyResolver::new(client, None).with_strategy(ResolutionStrategy::Lowest);
+    let spec = cobra::core::version::VersionSpec::parse(">=1.0.5").unwrap();
+    let (release, _) = cobra::core::resolver::select_release(&releases, &spec, None, None, ResolutionStrategy::Highest).unwrap();
+    assert_eq!(release.version, "1.0.5");
+    let pinned = cobra::core::version::VersionSpec::parse("==1.1.0").unwrap();
+    assert!(cobra::core::resolver::select_release(&releases, &pinned, None, None, ResolutionStrategy::Highest).is_some());
+
+    let resolved = resolver.resolve(&[dep("demo_pkg", ">=1.0.5")]).await.unwrap();
+    assert_eq!(resolved[0].version, "1.0.5");
+    assert_eq!(resolved[0].hash.as_deref(), Some("bb"));
+}
+
+#[tokio::test]
+async fn yanked_releases_need_an_exact_pin_and_keep_their_reason() {
+    let index = MockIndex::start().await;
+    let uploaded = "2024-01-01T00:00:00Z";
+    let yank = |file: &mut serde_json::Value| {
+        file["yanked"] = serde_json::json!(true);
+        file["yanked_reason"] = serde_json::json!("leaks credentials");
+    };
+    let mut project = project_json(&index.url, "demo", &[("1.0.0", uploaded), ("1.1.0", uploaded)], &[]);
+    yank(&mut project["urls"][0]);
+    yank(&mut project["releases"]["1.1.0"][0]);
+    let mut yanked = version_json(&index.url, "demo", "1.1.0", uploaded, &[]);
+    yank(&mut yanked["urls"][0]);
+    index.serve_json("/pypi/demo/json", &project);
+    index.serve_json("/pypi/demo/1.0.0/json", &version_json(&index.url, "demo", "1.0.0", uploaded, &[]));
+    index.serve_json("/pypi/demo/1.1.0/json", &yanked);
+
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let resolver = DependencyResolver::new(client, None);
+
+    // The index still reports the yanked release as its latest
+    let resolved = resolver.resolve(&[dep("demo", "*")]).await.unwrap();
+    assert_eq!(resolved[0].version, "1.0.0");
+    assert!(!resolved[0].yanked);
+
+    let resolved = resolver.resolve(&[dep("demo", "==1.1.0")]).await.unwrap();
+    assert_eq!(resolved[0].version, "1.1.0");
+    assert!(resolved[0].yanked);
+    assert_eq!(resolved[0].yanked_reason.as_deref(), Some("leaks credentials"));
+
+    let lockfile = Lockfile::from_packages(&resolved, ResolutionStrategy::Highest);
+    let locked: Package = (&lockfile.packages[0]).into();
+    assert_eq!(locked.yanked_reason.as_deref(), Some("leaks credentials"));
+}
+
+#[tokio::test]
+async fn cached_metadata_is_refetched_once_its_ttl_passes() {
+    let index = MockIndex::start().await;
+    let uploaded = "2024-01-01T00:00:00Z";
+    index.serve_json("/pypi/demo/json", &project_json(&index.url, "demo", &[("1.0.0", uploaded)], &[]));
+
+    let dir = tempfile::tempdir().unwrap();
+    let cache = Arc::new(cobra::MultiLevelCache::open(&dir.path().join("cache")).await.unwrap());
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let resolve = |ttl| {
+        let resolver = DependencyResolver::new(client.clone(), Some(cache.clone())).with_metadata_ttl(ttl);
+        async move { resolver.resolve(&[dep("demo", "*")]).await.unwrap()[0].version.clone() }
+    };
+    let lookups = || index.requests().iter().filter(|r| r.as_str() == "/pypi/demo/json").count();
+
+    assert_eq!(resolve(std::time::Duration::from_secs(600)).await, "1.0.0");
+    assert_eq!(lookups(), 1);
+
+    // A new release appears; fresh metadata still answers from the cache
+    index.serve_json("/pypi/demo/json", &project_json(&index.url, "demo", &[("1.0.0", uploaded), ("1.1.0", uploaded)], &[]));
+    assert_eq!(resolve(std::time::Duration::from_secs(600)).await, "1.0.0");
+    assert_eq!(lookups(), 1);
+
+    assert_eq!(resolve(std::time::Duration::ZERO).await, "1.1.0");
+    assert_eq!(lookups(), 2);
+}
+
+#[tokio::test]
+async fn caret_spec_selects_newest_release_below_next_major() {
+    let index = MockIndex::start().await;
+    let releases = [
+        ("1.0.0", "2023-01-10T12:00:00Z"),
+        ("1.2.0", "2023-08-01T12:00:00Z"),
+        ("2.0.0", "2024-06-01T12:00:00Z"),
+    ];
+    index.serve_json("/pypi/demo/json", &project_json(&index.url, "demo", &releases, &[]));
+    index.serve_json(
+        "/pypi/demo/1.2.0/json",
+        &version_json(&index.url, "demo", "1.2.0", "2023-08-01T12:00:00Z", &[]),
+    );
+
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let resolved = DependencyResolver::new(client, None).resolve(&[dep("demo", "^1.0.0")]).await.unwrap();
+
+    assert_eq!(resolved[0].version, "1.2.0");
+}
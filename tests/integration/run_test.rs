@@ -0,0 +1,28 @@
+use cobra::cli::run::prepare;
+use cobra::core::package_manager::LocalPackageManager;
+
+#[cfg(unix)]
+#[tokio::test]
+async fn commands_see_the_install_dir_and_its_scripts() {
+    let dir = tempfile::tempdir().unwrap();
+    let install_dir = dir.path().join(".cobra_packages");
+    std::fs::create_dir_all(install_dir.join("bin")).unwrap();
+    let package_manager = LocalPackageManager::new(install_dir.clone());
+
+    let script = r#"printf '%s\n%s\n' "$PYTHONPATH" "$PATH"; exit 3"#;
+    let args = ["sh", "-c", script].map(String::from);
+    let output = prepare(&args, &package_manager).await.unwrap().output().unwrap();
+
+    assert_eq!(output.status.code(), Some(3));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let mut lines = stdout.lines();
+    assert!(lines.next().unwrap().starts_with(install_dir.to_str().unwrap()));
+    assert!(lines.next().unwrap().starts_with(install_dir.join("bin").to_str().unwrap()));
+}
+
+#[tokio::test]
+async fn an_empty_command_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let package_manager = LocalPackageManager::new(dir.path().to_path_buf());
+    assert!(prepare(&[], &package_manager).await.is_err());
+}
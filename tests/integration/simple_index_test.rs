@@ -0,0 +1,95 @@
+use crate::support::{dep, MockIndex};
+use cobra::registry::simple::{releases_from_simple_html, SimpleIndex};
+use cobra::{CobraConfig, CobraError, DependencyResolver, RegistryBackend, RegistryClient};
+use std::io::Write;
+use std::sync::Arc;
+
+/// A wheel whose METADATA requires `requires`
+fn wheel(name: &str, version: &str, requires: &[&str]) -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default();
+    writer.start_file(format!("{}/__init__.py", name), options).unwrap();
+    writer.start_file(format!("{}-{}.dist-info/METADATA", name, version), options).unwrap();
+    write!(writer, "Metadata-Version: 2.1\nName: {}\nVersion: {}\n", name, version).unwrap();
+    for requirement in requires {
+        writeln!(writer, "Requires-Dist: {}", requirement).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+#[test]
+fn html_pages_list_versions_hashes_and_file_attributes() {
+    let page = reqwest::Url::parse("https://index.example/simple/demo-pkg/").unwrap();
+    let html = r#"<!DOCTYPE html>
+<html><body><h1>Links for demo-pkg</h1>
+<a href="../../files/demo_pkg-1.0.0-py3-none-any.whl#sha256=ABC123" data-requires-python="&gt;=3.8">demo_pkg-1.0.0-py3-none-any.whl</a><br/>
+<A HREF='https://cdn.example/demo-pkg-1.0.0.tar.gz'>demo-pkg-1.0.0.tar.gz</A>
+<a href="/files/demo_pkg-0.9.0-py3-none-any.whl" data-yanked="broken &amp; withdrawn">demo_pkg-0.9.0-py3-none-any.whl</a>
+<a href="/files/demo_pkg-2.0.0-py3-none-any.whl" data-yanked></a>
+<a href="/files/other-1.0.tar.gz">other-1.0.tar.gz</a>
+</body></html>"#;
+
+    let releases = releases_from_simple_html("demo-pkg", &page, html);
+    let versions: Vec<&str> = releases.iter().map(|r| r.version.as_str()).collect();
+    assert_eq!(versions, vec!["0.9.0", "1.0.0", "2.0.0"]);
+
+    let files = &releases[1].files;
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[0].url, "https://index.example/files/demo_pkg-1.0.0-py3-none-any.whl");
+    assert_eq!(files[0].sha256.as_deref(), Some("abc123"));
+    assert_eq!(files[0].requires_python.as_deref(), Some(">=3.8"));
+    assert_eq!(files[0].packagetype, "bdist_wheel");
+    assert_eq!(files[1].packagetype, "sdist");
+    assert!(!files[0].yanked);
+
+    assert!(releases[0].files[0].yanked);
+    assert_eq!(releases[0].files[0].yanked_reason.as_deref(), Some("broken & withdrawn"));
+    // Anchors without text fall back to the filename in the URL
+    assert_eq!(releases[2].files[0].filename, "demo_pkg-2.0.0-py3-none-any.whl");
+    assert!(releases[2].files[0].yanked && releases[2].files[0].yanked_reason.is_none());
+}
+
+#[tokio::test]
+async fn requirements_come_from_pep658_metadata_or_the_wheel() {
+    let index = MockIndex::start().await;
+    index.serve("/simple/app/", format!(
+        r#"<a href="{0}/files/app-1.0.0-py3-none-any.whl">app-1.0.0-py3-none-any.whl</a>
+<a href="{0}/files/app-1.2.0-py3-none-any.whl">app-1.2.0-py3-none-any.whl</a>
+<a href="{0}/files/app-2.0.0-py3-none-any.whl">app-2.0.0-py3-none-any.whl</a>"#,
+        index.url
+    ));
+    index.serve("/files/app-1.2.0-py3-none-any.whl.metadata", "Metadata-Version: 2.1\nName: app\nVersion: 1.2.0\nRequires-Dist: lib (>=1.0)\n\nDescription\n");
+    index.serve("/simple/lib/", format!(r#"<a href="{}/files/lib-1.1.0-py3-none-any.whl">lib-1.1.0-py3-none-any.whl</a>"#, index.url));
+    index.serve("/files/lib-1.1.0-py3-none-any.whl", wheel("lib", "1.1.0", &["six; python_version < '3'"]));
+
+    let simple = SimpleIndex::new(format!("{}/simple/", index.url), None);
+    let app = simple.get_package_info("app", ">=1.0,<2").await.unwrap();
+    assert_eq!(app.version, "1.2.0");
+    assert_eq!(app.download_url, format!("{}/files/app-1.2.0-py3-none-any.whl", index.url));
+    assert_eq!(app.dependencies.iter().map(|d| d.name.as_str()).collect::<Vec<_>>(), vec!["lib"]);
+
+    // No .metadata file: the wheel itself is read
+    let lib = simple.get_package_version("lib", "1.1.0").await.unwrap();
+    assert_eq!(lib.dependencies.len(), 1);
+    assert_eq!(lib.dependencies[0].markers.as_deref(), Some("python_version < '3'"));
+
+    assert!(matches!(simple.get_package_info("app", ">=3").await, Err(CobraError::PackageNotFound(_))));
+    assert!(matches!(simple.get_releases("missing").await, Err(CobraError::PackageNotFound(_))));
+}
+
+#[tokio::test]
+async fn registries_with_the_simple_api_serve_their_packages() {
+    let index = MockIndex::start().await;
+    index.serve("/gitlab/simple/corp-lib/", format!(r#"<a href="{}/files/corp_lib-0.4.0-py3-none-any.whl">corp_lib-0.4.0-py3-none-any.whl</a>"#, index.url));
+    index.serve("/files/corp_lib-0.4.0-py3-none-any.whl.metadata", "Name: corp-lib\nVersion: 0.4.0\n");
+
+    let config: CobraConfig = toml::from_str(&format!(
+        "[project]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[tool.cobra.registries.gitlab]\napi = \"simple\"\nurl = \"{}/gitlab/simple\"\npackages = [\"corp-*\"]\n",
+        index.url
+    )).unwrap();
+    let resolver = DependencyResolver::new(Arc::new(RegistryClient::with_base_url(index.url.clone())), None)
+        .with_registries(config.private_registries());
+    let resolved = resolver.resolve(&[dep("corp-lib", "^0.4")]).await.unwrap();
+    assert_eq!(resolved[0].version, "0.4.0");
+    assert!(!index.requests().iter().any(|path| path.starts_with("/pypi/")));
+}
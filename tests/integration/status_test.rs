@@ -0,0 +1,93 @@
+use cobra::cli::status::{drift_warnings, lockfile_drift};
+use cobra::core::layout::InstallLayout;
+use cobra::core::lockfile::Lockfile;
+use cobra::core::markers::MarkerEnvironment;
+use cobra::core::package_manager::LocalPackageManager;
+use cobra::core::record::RecordEntry;
+use cobra::core::resolver::ResolutionStrategy;
+use cobra::Package;
+
+fn locked(name: &str, version: &str, markers: Option<&str>) -> Package {
+    Package {
+        name: name.to_string(),
+        version: version.to_string(),
+        dependencies: Vec::new(),
+        download_url: format!("https://files.example/{}-{}.whl", name, version),
+        hash: None,
+        size: None,
+        description: None,
+        author: None,
+        homepage: None,
+        markers: markers.map(str::to_string),
+        yanked: false,
+        yanked_reason: None,
+        requires_python: None,
+    }
+}
+
+#[tokio::test]
+async fn manually_modified_registry_is_reported_as_drift() {
+    let dir = tempfile::tempdir().unwrap();
+    let manager = LocalPackageManager::with_layout(InstallLayout::target(dir.path().join("target")));
+    manager.ensure_install_dir().await.unwrap();
+
+    let packages = [
+        locked("requests", "2.31.0", None),
+        locked("urllib3", "2.0.7", None),
+        locked("certifi", "2024.2.2", None),
+        locked("pywin32", "306", Some("sys_platform == 'never'")),
+    ];
+    for package in &packages[..3] {
+        manager.register_package(package).await.unwrap();
+        std::fs::create_dir_all(manager.layout().package_dir(&package.name)).unwrap();
+    }
+    let lockfile = Lockfile::from_packages(&packages, ResolutionStrategy::Highest);
+    let environment = MarkerEnvironment::current("3.11");
+
+    let installed = manager.list_present().await.unwrap();
+    assert!(lockfile_drift(&lockfile, &installed, &environment).is_empty());
+
+    // Out-of-band changes: a pip upgrade, a new package and a deleted directory
+    let mut registry = manager.load_registry().await.unwrap();
+    registry.packages.get_mut("urllib3").unwrap().version = "1.26.18".to_string();
+    let mut six = registry.packages["requests"].clone();
+    six.name = "six".to_string();
+    six.version = "1.16.0".to_string();
+    registry.packages.insert("six".to_string(), six);
+    manager.save_registry(&registry).await.unwrap();
+    std::fs::remove_dir_all(manager.layout().package_dir("certifi")).unwrap();
+
+    let installed = manager.list_present().await.unwrap();
+    let drift = lockfile_drift(&lockfile, &installed, &environment);
+    assert_eq!(
+        drift_warnings(&drift),
+        vec![
+            "certifi 2024.2.2 is locked but not installed".to_string(),
+            "urllib3 1.26.18 is installed but 2.0.7 is locked".to_string(),
+            "six 1.16.0 is installed but not locked".to_string(),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn packages_without_a_directory_of_their_own_are_not_drift() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("target");
+    let manager = LocalPackageManager::with_layout(InstallLayout::target(target.clone()));
+    manager.ensure_install_dir().await.unwrap();
+
+    // six ships a lone six.py, PyYAML imports as `yaml`
+    let packages = [locked("six", "1.16.0", None), locked("PyYAML", "6.0.1", None)];
+    for (package, file) in packages.iter().zip(["six.py", "yaml/__init__.py"]) {
+        let path = target.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "").unwrap();
+        let files = vec![RecordEntry { path: file.to_string(), sha256: None, size: None }];
+        manager.register_package_files(package, None, files).await.unwrap();
+    }
+    let lockfile = Lockfile::from_packages(&packages, ResolutionStrategy::Highest);
+    let environment = MarkerEnvironment::current("3.11");
+
+    let installed = manager.list_present().await.unwrap();
+    assert!(lockfile_drift(&lockfile, &installed, &environment).is_empty());
+}
@@ -0,0 +1,267 @@
+//! Shared helpers for integration tests: an in-process fake PyPI index and
+//! builders for the JSON documents it serves.
+
+#![allow(dead_code)]
+
+use cobra::{Dependency, DependencySource};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Canned response body and optional `Content-Type`
+type Route = (Option<String>, Vec<u8>);
+
+/// Remaining 502 responses for a path and the `Retry-After` seconds sent with them
+type Fault = (usize, Option<u64>);
+
+/// Minimal HTTP server answering canned responses keyed by request path
+pub struct MockIndex {
+    pub url: String,
+    routes: Arc<Mutex<HashMap<String, Route>>>,
+    stalled: Arc<Mutex<HashSet<String>>>,
+    requests: Arc<Mutex<Vec<String>>>,
+    ranged: Arc<Mutex<HashSet<String>>>,
+    /// Paths whose next response is cut off after this many body bytes
+    cut: Arc<Mutex<HashMap<String, usize>>>,
+    ranges: Arc<Mutex<Vec<String>>>,
+    failing: Arc<Mutex<HashMap<String, Fault>>>,
+    authorizations: Arc<Mutex<Vec<String>>>,
+    latency: Arc<Mutex<Option<std::time::Duration>>>,
+}
+
+impl MockIndex {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let routes: Arc<Mutex<HashMap<String, Route>>> = Arc::default();
+        let stalled: Arc<Mutex<HashSet<String>>> = Arc::default();
+        let ranged: Arc<Mutex<HashSet<String>>> = Arc::default();
+        let cut: Arc<Mutex<HashMap<String, usize>>> = Arc::default();
+        let ranges: Arc<Mutex<Vec<String>>> = Arc::default();
+        let failing: Arc<Mutex<HashMap<String, Fault>>> = Arc::default();
+        let authorizations: Arc<Mutex<Vec<String>>> = Arc::default();
+        let latency: Arc<Mutex<Option<std::time::Duration>>> = Arc::default();
+
+        let table = Arc::clone(&routes);
+        let hang = Arc::clone(&stalled);
+        let log = Arc::clone(&requests);
+        let (seekable, cutoffs, range_log) = (Arc::clone(&ranged), Arc::clone(&cut), Arc::clone(&ranges));
+        let faults = Arc::clone(&failing);
+        let auth_log = Arc::clone(&authorizations);
+        let delay = Arc::clone(&latency);
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut socket, _)) = listener.accept().await else {
+                    break;
+                };
+                let routes = Arc::clone(&table);
+                let hang = Arc::clone(&hang);
+                let log = Arc::clone(&log);
+                let (seekable, cutoffs, range_log) = (Arc::clone(&seekable), Arc::clone(&cutoffs), Arc::clone(&range_log));
+                let faults = Arc::clone(&faults);
+                let auth_log = Arc::clone(&auth_log);
+                let delay = Arc::clone(&delay);
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 1024];
+                    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match socket.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                        }
+                    }
+                    let request = String::from_utf8_lossy(&buf);
+                    let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+                    log.lock().unwrap().push(path.clone());
+                    let authorization = request.lines()
+                        .find_map(|line| line.split_once(':').filter(|(name, _)| name.eq_ignore_ascii_case("authorization")));
+                    if let Some((_, value)) = authorization {
+                        auth_log.lock().unwrap().push(value.trim().to_string());
+                    }
+                    let latency = *delay.lock().unwrap();
+                    if let Some(latency) = latency {
+                        tokio::time::sleep(latency).await;
+                    }
+                    if hang.lock().unwrap().contains(&path) {
+                        // Keep the connection open without ever answering
+                        std::future::pending::<()>().await;
+                    }
+                    let fault = faults.lock().unwrap().get_mut(&path)
+                        .filter(|(remaining, _)| *remaining > 0)
+                        .map(|(remaining, retry_after)| {
+                            *remaining -= 1;
+                            *retry_after
+                        });
+                    if let Some(retry_after) = fault {
+                        let retry_after = retry_after
+                            .map(|secs| format!("Retry-After: {}\r\n", secs))
+                            .unwrap_or_default();
+                        let head = format!("HTTP/1.1 502 Bad Gateway\r\n{}Content-Length: 0\r\nConnection: close\r\n\r\n", retry_after);
+                        let _ = socket.write_all(head.as_bytes()).await;
+                        return;
+                    }
+
+                    // `bytes=N-` or `bytes=N-M`
+                    let range = request.lines().find_map(|line| {
+                        let spec = line.to_ascii_lowercase().strip_prefix("range: bytes=")?.trim().to_string();
+                        let (start, end) = spec.split_once('-')?;
+                        let end = if end.is_empty() { None } else { Some(end.parse::<usize>().ok()?) };
+                        Some((start.parse::<usize>().ok()?, end, spec))
+                    });
+                    if let Some((_, _, spec)) = &range {
+                        range_log.lock().unwrap().push(format!("bytes={}", spec));
+                    }
+
+                    let route = routes.lock().unwrap().get(&path).cloned();
+                    let seekable = seekable.lock().unwrap().contains(&path);
+                    let (mut status, (content_type, mut body)) = match route {
+                        Some(route) => ("200 OK", route),
+                        None => ("404 Not Found", (None, Vec::new())),
+                    };
+                    let mut extra = content_type
+                        .map(|value| format!("Content-Type: {}\r\n", value))
+                        .unwrap_or_default();
+                    if seekable {
+                        extra.push_str("Accept-Ranges: bytes\r\n");
+                        if let Some((start, end, _)) = range.filter(|(start, _, _)| *start < body.len()) {
+                            let end = end.map_or(body.len() - 1, |end| end.min(body.len() - 1));
+                            status = "206 Partial Content";
+                            extra.push_str(&format!("Content-Range: bytes {}-{}/{}\r\n", start, end, body.len()));
+                            body.truncate(end + 1);
+                            body.drain(..start);
+                        }
+                    }
+                    let head = format!(
+                        "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n",
+                        status,
+                        extra,
+                        body.len()
+                    );
+                    let _ = socket.write_all(head.as_bytes()).await;
+                    if let Some(sent) = cutoffs.lock().unwrap().remove(&path) {
+                        body.truncate(sent);
+                    }
+                    let _ = socket.write_all(&body).await;
+                });
+            }
+        });
+
+        Self { url, routes, stalled, requests, ranged, cut, ranges, failing, authorizations, latency }
+    }
+
+    /// Serve `body` for GET requests to `path`
+    pub fn serve(&self, path: &str, body: impl Into<Vec<u8>>) {
+        self.routes.lock().unwrap().insert(path.to_string(), (None, body.into()));
+    }
+
+    /// Serve a PEP 691 JSON Simple API page at `path`
+    pub fn serve_simple(&self, path: &str, document: &Value) {
+        let body = serde_json::to_vec(document).unwrap();
+        let content_type = cobra::registry::client::SIMPLE_JSON_V1.to_string();
+        self.routes.lock().unwrap().insert(path.to_string(), (Some(content_type), body));
+    }
+
+    /// Hold every response for `latency`, like an index far away
+    pub fn set_latency(&self, latency: std::time::Duration) {
+        *self.latency.lock().unwrap() = Some(latency);
+    }
+
+    /// Accept requests to `path` but never respond, like an unresponsive index
+    pub fn stall(&self, path: &str) {
+        self.stalled.lock().unwrap().insert(path.to_string());
+    }
+
+    /// Serve a JSON document at `path`
+    pub fn serve_json(&self, path: &str, document: &Value) {
+        self.serve(path, serde_json::to_vec(document).unwrap());
+    }
+
+    /// Honor `Range: bytes=N-` and `bytes=N-M` requests to `path`, advertising `Accept-Ranges`
+    pub fn accept_ranges(&self, path: &str) {
+        self.ranged.lock().unwrap().insert(path.to_string());
+    }
+
+    /// Close the connection of the next request to `path` after `sent` body bytes
+    pub fn cut_next(&self, path: &str, sent: usize) {
+        self.cut.lock().unwrap().insert(path.to_string(), sent);
+    }
+
+    /// Answer the next `count` requests to `path` with 502, sending `Retry-After` if given
+    pub fn fail_next(&self, path: &str, count: usize, retry_after: Option<u64>) {
+        self.failing.lock().unwrap().insert(path.to_string(), (count, retry_after));
+    }
+
+    /// `Range` headers received so far, in arrival order
+    pub fn ranges(&self) -> Vec<String> {
+        self.ranges.lock().unwrap().clone()
+    }
+
+    /// `Authorization` headers received so far, in arrival order
+    pub fn authorizations(&self) -> Vec<String> {
+        self.authorizations.lock().unwrap().clone()
+    }
+
+    /// Paths requested so far, in arrival order
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// A registry requirement on `name` with no markers, extras or flags
+pub fn dep(name: &str, spec: &str) -> Dependency {
+    Dependency { name: name.to_string(), version_spec: spec.to_string(), markers: None, source: DependencySource::Registry, extras: Vec::new(), optional: false }
+}
+
+/// A release file entry as it appears in PyPI JSON responses
+pub fn file_json(base_url: &str, name: &str, version: &str, uploaded: &str) -> Value {
+    let filename = format!("{}-{}-py3-none-any.whl", name, version);
+    json!({
+        "filename": filename,
+        "url": format!("{}/files/{}", base_url, filename),
+        "packagetype": "bdist_wheel",
+        "size": 1024,
+        "digests": { "sha256": format!("{:0>64}", version.replace('.', "")) },
+        "upload_time": uploaded.trim_end_matches('Z'),
+        "upload_time_iso_8601": uploaded,
+    })
+}
+
+/// `/pypi/<name>/json` document; `releases` is `(version, upload time)` oldest first
+pub fn project_json(base_url: &str, name: &str, releases: &[(&str, &str)], requires: &[&str]) -> Value {
+    let (latest, latest_uploaded) = releases.last().copied().unwrap();
+    let releases_map: serde_json::Map<String, Value> = releases
+        .iter()
+        .map(|(version, uploaded)| {
+            (version.to_string(), json!([file_json(base_url, name, version, uploaded)]))
+        })
+        .collect();
+
+    json!({
+        "info": { "name": name, "version": latest, "requires_dist": requires },
+        "urls": [file_json(base_url, name, latest, latest_uploaded)],
+        "releases": releases_map,
+    })
+}
+
+/// `/pypi/<name>/<version>/json` document
+pub fn version_json(base_url: &str, name: &str, version: &str, uploaded: &str, requires: &[&str]) -> Value {
+    json!({
+        "info": { "name": name, "version": version, "requires_dist": requires },
+        "urls": [file_json(base_url, name, version, uploaded)],
+    })
+}
+
+/// Record the digest and size of `archive` for every file in a project or version document
+pub fn set_archive(document: &mut Value, archive: &[u8]) {
+    let sha256 = cobra::utils::hash::sha256_hex(archive);
+    let record = |file: &mut Value| {
+        file["digests"]["sha256"] = json!(sha256);
+        file["size"] = json!(archive.len());
+    };
+    document["urls"].as_array_mut().into_iter().flatten().for_each(record);
+    let releases = document["releases"].as_object_mut().into_iter().flat_map(|map| map.values_mut());
+    releases.filter_map(Value::as_array_mut).flatten().for_each(record);
+}
@@ -0,0 +1,58 @@
+use cobra::cli::sync::plan_sync;
+use cobra::core::package_manager::InstalledPackage;
+use cobra::Package;
+
+fn resolved(name: &str, version: &str) -> Package {
+    Package {
+        name: name.to_string(),
+        version: version.to_string(),
+        dependencies: Vec::new(),
+        download_url: format!("https://files.example/{}-{}.whl", name, version),
+        hash: None,
+        size: None,
+        description: None,
+        author: None,
+        homepage: None,
+        markers: None,
+        yanked: false,
+        yanked_reason: None,
+        requires_python: None,
+    }
+}
+
+fn installed(name: &str, version: &str) -> InstalledPackage {
+    InstalledPackage {
+        name: name.to_string(),
+        version: version.to_string(),
+        install_path: format!("/site-packages/{}", name).into(),
+        installed_at: chrono::Utc::now(),
+        source_hash: None,
+        files: Vec::new(),
+        store_entry: None,
+        size_on_disk: None,
+    }
+}
+
+#[test]
+fn plan_installs_missing_reinstalls_mismatched_and_removes_extras() {
+    let plan = plan_sync(
+        &[resolved("requests", "2.31.0"), resolved("urllib3", "2.0.7"), resolved("Flask", "3.0.0")],
+        &[installed("urllib3", "1.26.18"), installed("flask", "3.0.0"), installed("six", "1.16.0")],
+    );
+
+    let install: Vec<_> = plan.install.iter().map(|p| p.name.as_str()).collect();
+    let remove: Vec<_> = plan.remove.iter().map(|p| p.name.as_str()).collect();
+    let reinstall: Vec<_> = plan.reinstall.iter()
+        .map(|(current, target)| (current.version.as_str(), target.version.as_str()))
+        .collect();
+
+    assert_eq!(install, vec!["requests"]);
+    assert_eq!(remove, vec!["six"]);
+    assert_eq!(reinstall, vec![("1.26.18", "2.0.7")]);
+}
+
+#[test]
+fn plan_is_empty_when_environment_matches() {
+    let plan = plan_sync(&[resolved("six", "1.16.0")], &[installed("six", "1.16.0")]);
+    assert!(plan.is_empty());
+}
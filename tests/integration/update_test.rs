@@ -0,0 +1,140 @@
+use crate::support::{dep, project_json, version_json, MockIndex};
+use cobra::cli::update::{resolve_within, Bump, VersionChange, VersionDiff};
+use cobra::core::package_manager::InstalledPackage;
+use cobra::{DependencyResolver, Dependency, DependencySource, RegistryClient};
+use std::sync::Arc;
+
+#[test]
+fn diff_classifies_every_package() {
+    let diff = VersionDiff::between(
+        [("requests", "2.30.0"), ("urllib3", "2.0.7"), ("Six", "1.16.0"), ("idna", "3.4"), ("torch", "2.1.0")],
+        [("requests", "2.31.0"), ("urllib3", "1.26.18"), ("six", "1.16"), ("certifi", "2023.7.22"), ("torch", "2.1.0+cu118")],
+    );
+
+    let up = |from: &str, to: &str| VersionChange::Upgraded { from: from.to_string(), to: to.to_string() };
+    assert_eq!(diff.changes, vec![
+        ("certifi".to_string(), VersionChange::Added("2023.7.22".to_string())),
+        ("idna".to_string(), VersionChange::Removed("3.4".to_string())),
+        ("requests".to_string(), up("2.30.0", "2.31.0")),
+        // Equal under PEP 440, named as in the new set
+        ("six".to_string(), VersionChange::Unchanged("1.16".to_string())),
+        ("torch".to_string(), up("2.1.0", "2.1.0+cu118")),
+        ("urllib3".to_string(), VersionChange::Downgraded { from: "2.0.7".to_string(), to: "1.26.18".to_string() }),
+    ]);
+    assert!(diff.has_changes());
+    assert!(diff.has_downgrades());
+}
+
+#[test]
+fn identical_sets_have_no_changes() {
+    let diff = VersionDiff::between([("requests", "2.31.0")], [("Requests", "2.31.0")]);
+
+    assert!(!diff.has_changes());
+    assert!(!diff.has_downgrades());
+}
+
+fn installed(name: &str, version: &str) -> InstalledPackage {
+    InstalledPackage {
+        name: name.to_string(),
+        version: version.to_string(),
+        install_path: format!("/site-packages/{}", name).into(),
+        installed_at: chrono::Utc::now(),
+        source_hash: None,
+        files: Vec::new(),
+        store_entry: None,
+        size_on_disk: None,
+    }
+}
+
+#[test]
+fn upgrades_are_grouped_by_semver_impact() {
+    let diff = VersionDiff::between(
+        [("django", "4.2.7"), ("flask", "2.3.3"), ("idna", "3.4"), ("pydantic", "0.27.1"), ("urllib3", "2.0.7"), ("six", "1.16.0")],
+        [("django", "5.0"), ("flask", "2.3.4"), ("idna", "3.6"), ("pydantic", "0.28.0"), ("urllib3", "2.0.7.post1"), ("six", "1.16.0")],
+    );
+
+    assert_eq!(diff.upgrades(Bump::Major), ["django", "pydantic"]);
+    assert_eq!(diff.upgrades(Bump::Minor), ["idna"]);
+    assert_eq!(diff.upgrades(Bump::Patch), ["flask", "urllib3"]);
+    assert_eq!(Bump::between("1!1.0", "1!1.0.1"), Bump::Patch);
+    assert_eq!(Bump::between("1.0", "1!1.0"), Bump::Major);
+
+    let held = diff.held_back(Bump::Minor);
+    assert_eq!(held.len(), 2);
+    assert_eq!(held["django"], "4.2.7");
+    assert_eq!(held["pydantic"], "0.27.1");
+    assert_eq!(diff.held_back(Bump::Patch).len(), 3);
+    assert!(diff.held_back(Bump::Major).is_empty());
+    assert_eq!(Bump::Patch.range_from("4.2.7"), "~4.2.7");
+    assert_eq!(Bump::Minor.range_from("0.27.1"), "^0.27.1");
+}
+
+#[tokio::test]
+async fn risk_tiers_hold_back_riskier_upgrades() {
+    let index = MockIndex::start().await;
+    let uploaded = "2024-01-01T00:00:00Z";
+    let packages = [
+        ("app", &["1.0.0", "1.0.1", "1.1.0", "2.0.0"][..]),
+        ("lib", &["1.0.0", "1.0.5"][..]),
+    ];
+    for (name, versions) in packages {
+        let releases: Vec<_> = versions.iter().map(|v| (*v, uploaded)).collect();
+        index.serve_json(&format!("/pypi/{}/json", name), &project_json(&index.url, name, &releases, &[]));
+        for version in versions {
+            index.serve_json(
+                &format!("/pypi/{}/{}/json", name, version),
+                &version_json(&index.url, name, version, uploaded, &[]),
+            );
+        }
+    }
+
+    let client = Arc::new(RegistryClient::with_base_url(index.url.clone()));
+    let make_resolver = |overrides| DependencyResolver::new(client.clone(), None).with_overrides(overrides);
+    let roots: Vec<_> = ["app", "lib"].iter()
+        .map(|name| Dependency { name: name.to_string(), version_spec: "*".to_string(), markers: None, source: DependencySource::Registry, extras: Vec::new(), optional: false })
+        .collect();
+    let current = [installed("app", "1.0.0"), installed("lib", "1.0.0")];
+
+    let (resolved, diff) = resolve_within(make_resolver, &roots, &current, Some(Bump::Patch)).await.unwrap();
+
+    let mut versions: Vec<_> = resolved.iter().map(|p| (p.name.as_str(), p.version.as_str())).collect();
+    versions.sort();
+    assert_eq!(versions, [("app", "1.0.1"), ("lib", "1.0.5")]);
+    assert_eq!(diff.upgrades(Bump::Patch), ["app", "lib"]);
+    assert!(diff.upgrades(Bump::Major).is_empty());
+
+    let (resolved, _) = resolve_within(make_resolver, &roots, &current, Some(Bump::Minor)).await.unwrap();
+    assert!(resolved.iter().any(|p| p.name == "app" && p.version == "1.1.0"));
+    let (resolved, _) = resolve_within(make_resolver, &roots, &current, None).await.unwrap();
+    assert!(resolved.iter().any(|p| p.name == "app" && p.version == "2.0.0"));
+}
+
+#[tokio::test]
+async fn dry_run_reports_the_newest_allowed_and_newest_releases() {
+    use cobra::cli::update::{available_upgrades, latest_roots};
+    use cobra::registry::packagecloud::PrivateRegistries;
+
+    let index = MockIndex::start().await;
+    let releases = [("1.0.0", "2023-01-01T00:00:00Z"), ("1.2.0", "2023-06-01T00:00:00Z"), ("2.0.0", "2024-01-01T00:00:00Z")];
+    index.serve_json("/pypi/demo/json", &project_json(&index.url, "demo", &releases, &[]));
+    index.serve_json("/pypi/other/json", &project_json(&index.url, "other", &[("3.1.0", "2024-01-01T00:00:00Z")], &[]));
+
+    let client = RegistryClient::with_base_url(index.url.clone());
+    let roots = [dep("demo", "<2"), dep("other", ">=3")];
+    let upgrades = available_upgrades(&client, &PrivateRegistries::default(), &roots, &[installed("Demo", "1.0.0")], None)
+        .await
+        .unwrap();
+
+    assert_eq!(upgrades.len(), 2);
+    assert_eq!(upgrades[0].installed.as_deref(), Some("1.0.0"));
+    assert_eq!(upgrades[0].wanted.as_deref(), Some("1.2.0"));
+    assert_eq!(upgrades[0].latest.as_deref(), Some("2.0.0"));
+    assert!(upgrades[0].latest_outside_spec());
+    assert_eq!(upgrades[1].installed, None);
+    assert!(!upgrades[1].latest_outside_spec());
+
+    // --latest resolves with only the outdated spec moved
+    let moved = latest_roots(&roots, &upgrades);
+    assert_eq!(moved[0].version_spec, "^2.0.0");
+    assert_eq!(moved[1].version_spec, ">=3");
+}
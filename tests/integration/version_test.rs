@@ -0,0 +1,122 @@
+use cobra::core::version::{satisfies, Version, VersionSpec};
+
+fn v(input: &str) -> Version {
+    Version::parse(input).unwrap()
+}
+
+fn matches(spec: &str, version: &str) -> bool {
+    VersionSpec::parse(spec).unwrap().contains(&v(version))
+}
+
+#[test]
+fn pep440_example_ordering() {
+    // PEP 440 "Summary of permitted suffixes and relative ordering", plus epochs
+    let ordered = [
+        "1.dev0", "1.0.dev456", "1.0a1", "1.0a2.dev456", "1.0a12.dev456", "1.0a12",
+        "1.0b1.dev456", "1.0b2", "1.0b2.post345.dev456", "1.0b2.post345",
+        "1.0rc1.dev456", "1.0rc1", "1.0", "1.0+abc.5", "1.0+abc.7", "1.0+5",
+        "1.0.post456.dev34", "1.0.post456", "1.0.15", "1.1.dev1",
+        "2013.10", "2014.04", "1!1.0", "1!1.1", "2!0.1",
+    ];
+    for pair in ordered.windows(2) {
+        assert!(v(pair[0]) < v(pair[1]), "{} < {}", pair[0], pair[1]);
+    }
+}
+
+#[test]
+fn local_versions_parse_and_normalize() {
+    assert_eq!(v("2.1.0+cu118").to_string(), "2.1.0+cu118");
+    assert_eq!(v("1.0+Ubuntu-1_2").to_string(), "1.0+ubuntu.1.2");
+    assert_eq!(v("1.0+ubuntu.1"), v("1.0+ubuntu-1"));
+    assert_eq!(v("1!2.0").to_string(), "1!2.0");
+    assert!(Version::parse("1.0+").is_err());
+    assert!(Version::parse("1.0+a..b").is_err());
+    assert!(Version::parse("1.0+a@b").is_err());
+}
+
+#[test]
+fn specifier_matrix() {
+    let cases = [
+        // Candidate local labels are ignored unless the specifier has one
+        ("==2.1.0", "2.1.0+cu118", true),
+        ("==2.1.0+cu118", "2.1.0+cu118", true),
+        ("==2.1.0+cu118", "2.1.0+cu121", false),
+        ("==2.1.0+cu118", "2.1.0", false),
+        ("!=2.1.0", "2.1.0+cu118", false),
+        ("==2.1.*", "2.1.0+cu118", true),
+        ("<=2.1.0", "2.1.0+cu118", true),
+        (">=2.1.0", "2.1.0+cu118", true),
+        (">2.1.0", "2.1.0+cu118", false),
+        ("<2.1.0", "2.1.0+cu118", false),
+        // Arbitrary equality compares the strings, local label included
+        ("===2.1.0", "2.1.0", true),
+        ("===2.1.0", "2.1.0+cu118", false),
+        ("===2.1.0+cu118", "2.1.0+cu118", true),
+        // Post-releases follow their base release
+        ("==1.1", "1.1.post1", false),
+        ("==1.1.post1", "1.1.post1", true),
+        ("==1.1.*", "1.1.post1", true),
+        (">1.1", "1.1.post1", false),
+        (">1.1.post1", "1.1.post2", true),
+        (">=1.1", "1.1.post1", true),
+        ("~=2.2.post3", "2.3", true),
+        ("~=2.2.post3", "2.2.post2", false),
+        ("~=1.4.5a4", "1.4.5", true),
+        ("~=1.4.5a4", "1.5", false),
+        // Epochs dominate the release segment
+        (">=2.0", "1!1.0", true),
+        ("<3.0", "1!1.0", false),
+        ("==1!2.*", "1!2.5", true),
+        ("==2.*", "1!2.5", false),
+    ];
+    for (spec, version, expected) in cases {
+        assert_eq!(matches(spec, version), expected, "{} contains {}", spec, version);
+    }
+}
+
+#[test]
+fn installed_versions_satisfy_requirements() {
+    // Skip-if-installed compares against an exact resolved version
+    assert!(satisfies("2.1.0+cu118", "2.1.0+cu118"));
+    assert!(!satisfies("2.1.0+cu118", "2.1.0"));
+    assert!(satisfies("1.0.0", "1.0"));
+    assert!(satisfies("1.0.post1", ">=1.0"));
+    assert!(!satisfies("1.9", ">=2.0"));
+    assert!(satisfies("2.1.0+cu118", "==2.1.0"));
+    assert!(satisfies("legacy-1", "*"));
+    assert!(satisfies("legacy-1", "==legacy-1"));
+}
+
+#[test]
+fn caret_and_tilde_expand_to_ranges() {
+    let cases = [
+        ("^2.31.0", ">=2.31.0,<3.0.0"),
+        ("^0.2.3", ">=0.2.3,<0.3.0"),
+        ("^0.0.3", ">=0.0.3,<0.0.4"),
+        ("^0.0", ">=0.0,<0.1"),
+        ("^3.8", ">=3.8,<4.0"),
+        ("~1.2.3", ">=1.2.3,<1.3.0"),
+        ("~1", ">=1,<2"),
+        ("~=1.2", "~=1.2"),
+        ("^1.2, !=1.4.0", ">=1.2,<2.0,!=1.4.0"),
+    ];
+    for (input, expanded) in cases {
+        assert_eq!(VersionSpec::parse(input).unwrap().to_string(), expanded, "{}", input);
+    }
+
+    assert!(matches("^2.31.0", "2.99"));
+    assert!(!matches("^2.31.0", "3.0.0rc1"));
+    assert!(!matches("~1.2.3", "1.3.0"));
+    assert!(satisfies("2.32.0", "^2.31.0"));
+    assert!(!satisfies("3.0.0", "^2.31.0"));
+    assert!(!satisfies("1.2.2", "~1.2.3"));
+}
+
+#[test]
+fn bare_versions_are_exact_pins() {
+    assert_eq!(VersionSpec::parse("2.31.0").unwrap().to_string(), "==2.31.0");
+    assert_eq!(VersionSpec::parse("2.*").unwrap().to_string(), "==2.*");
+    assert!(VersionSpec::parse("2.31.0").unwrap().is_pinned());
+    assert!(satisfies("2.31.0", "2.31.0"));
+    assert!(!satisfies("2.31.1", "2.31.0"));
+}